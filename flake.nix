@@ -186,7 +186,8 @@
               partOf = [ "graphical-session.target" ];
               
               serviceConfig = {
-                Type = "simple";
+                Type = "notify";
+                WatchdogSec = "30s";
                 ExecStart = "${cfg.package}/bin/blipply-assistant daemon";
                 Restart = "on-failure";
                 RestartSec = "5s";
@@ -0,0 +1,126 @@
+// Blipply Assistant - Screen Reader Announcements
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use tracing::{debug, warn};
+use zbus::zvariant::Value;
+use zbus::{Connection, Message};
+
+const A11Y_BUS_NAME: &str = "org.a11y.Bus";
+const A11Y_BUS_PATH: &str = "/org/a11y/bus";
+const EVENT_INTERFACE: &str = "org.a11y.atspi.Event.Object";
+const ROOT_ACCESSIBLE_PATH: &str = "/org/a11y/atspi/accessible/root";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnouncementPriority {
+    Low,
+    Medium,
+    High,
+}
+
+impl AnnouncementPriority {
+    /// AT-SPI live region politeness: none, polite or assertive
+    fn politeness(self) -> i32 {
+        match self {
+            AnnouncementPriority::Low => 0,
+            AnnouncementPriority::Medium => 1,
+            AnnouncementPriority::High => 2,
+        }
+    }
+}
+
+/// Sends AT-SPI2 announcements so screen readers such as Orca read new messages aloud
+pub struct AnnouncementService {
+    connection: Connection,
+}
+
+impl AnnouncementService {
+    /// Whether the AT-SPI2 accessibility bus is running in this session
+    pub async fn is_available() -> Result<bool> {
+        let connection = Connection::session().await
+            .context("Failed to connect to session bus")?;
+
+        let proxy = zbus::fdo::DBusProxy::new(&connection).await?;
+        let available = proxy.name_has_owner(A11Y_BUS_NAME.try_into()?).await?;
+        Ok(available)
+    }
+
+    /// Connect to the accessibility bus
+    pub async fn connect() -> Result<Self> {
+        let session = Connection::session().await
+            .context("Failed to connect to session bus")?;
+
+        let reply = session
+            .call_method(Some(A11Y_BUS_NAME), A11Y_BUS_PATH, Some(A11Y_BUS_NAME), "GetAddress", &())
+            .await
+            .context("Failed to get accessibility bus address")?;
+        let address: String = reply.body().deserialize()?;
+
+        let connection = zbus::connection::Builder::address(address.as_str())?
+            .build()
+            .await
+            .context("Failed to connect to accessibility bus")?;
+
+        debug!("Connected to accessibility bus at {}", address);
+        Ok(Self { connection })
+    }
+
+    /// Ask the screen reader to read `text`
+    pub fn announce(&self, text: &str, priority: AnnouncementPriority) {
+        let message = match announcement_message(text, priority) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Failed to build announcement: {}", e);
+                return;
+            }
+        };
+
+        let connection = self.connection.clone();
+        tokio::spawn(async move {
+            if let Err(e) = connection.send(&message).await {
+                warn!("Failed to send announcement: {}", e);
+            }
+        });
+    }
+}
+
+/// `Object:Announcement` event signal carrying `text`
+fn announcement_message(text: &str, priority: AnnouncementPriority) -> Result<Message> {
+    let properties: HashMap<&str, Value> = HashMap::new();
+    let body = ("", priority.politeness(), 0i32, Value::from(text), properties);
+
+    let message = Message::signal(ROOT_ACCESSIBLE_PATH, EVENT_INTERFACE, "Announcement")?
+        .build(&body)?;
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::OwnedValue;
+
+    #[test]
+    fn test_announcement_message() {
+        let message = announcement_message("Hello there", AnnouncementPriority::High).unwrap();
+
+        let header = message.header();
+        assert_eq!(header.interface().unwrap().as_str(), EVENT_INTERFACE);
+        assert_eq!(header.member().unwrap().as_str(), "Announcement");
+        assert_eq!(header.path().unwrap().as_str(), ROOT_ACCESSIBLE_PATH);
+
+        let (detail, politeness, detail2, data, properties): (
+            String,
+            i32,
+            i32,
+            OwnedValue,
+            HashMap<String, OwnedValue>,
+        ) = message.body().deserialize().unwrap();
+        assert_eq!(detail, "");
+        assert_eq!(politeness, 2);
+        assert_eq!(detail2, 0);
+        assert_eq!(String::try_from(data).unwrap(), "Hello there");
+        assert!(properties.is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+// Blipply Assistant - Focused Window Detection
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use tokio::process::Command;
+use tracing::debug;
+
+/// Title of the focused window, if the compositor exposes it.
+///
+/// Wayland has no portable way to query this, so it asks Hyprland and Sway
+/// over their IPC tools and gives up on other compositors.
+pub async fn title() -> Option<String> {
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        let json = command_json("hyprctl", &["activewindow", "-j"]).await?;
+        return json["title"].as_str().map(str::to_string);
+    }
+
+    if std::env::var_os("SWAYSOCK").is_some() {
+        let tree = command_json("swaymsg", &["-t", "get_tree"]).await?;
+        return focused_node_name(&tree);
+    }
+
+    None
+}
+
+async fn command_json(program: &str, args: &[&str]) -> Option<serde_json::Value> {
+    let output = match Command::new(program).args(args).output().await {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            debug!("{} exited with {}", program, output.status);
+            return None;
+        }
+        Err(e) => {
+            debug!("Failed to run {}: {}", program, e);
+            return None;
+        }
+    };
+
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+/// Name of the focused node in a `swaymsg -t get_tree` tree
+fn focused_node_name(node: &serde_json::Value) -> Option<String> {
+    if node["focused"].as_bool() == Some(true) {
+        return node["name"].as_str().map(str::to_string);
+    }
+
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+        .find_map(focused_node_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focused_node_name() {
+        let tree = serde_json::json!({
+            "name": "root",
+            "focused": false,
+            "nodes": [{
+                "name": "1",
+                "focused": false,
+                "nodes": [],
+                "floating_nodes": [{ "name": "main.rs - Code", "focused": true }]
+            }]
+        });
+        assert_eq!(focused_node_name(&tree).as_deref(), Some("main.rs - Code"));
+    }
+}
@@ -0,0 +1,319 @@
+// Blipply Assistant - Audio Pipeline
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// How long other apps stay ducked after speech ends, so the gaps between
+/// sentences don't make their volume pump
+const RESTORE_DELAY: Duration = Duration::from_millis(600);
+
+/// An audio stream of another app, as listed by `pactl list sink-inputs`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SinkInput {
+    id: u32,
+    volume_percent: u32,
+    pid: Option<u32>,
+}
+
+#[derive(Debug, Default)]
+struct DuckState {
+    speaking: bool,
+    listening: bool,
+    /// Bumped on every change so a delayed restore can tell it is stale
+    generation: u64,
+    /// Streams lowered by us and their original volumes; None when not ducked
+    ducked: Option<Vec<SinkInput>>,
+}
+
+/// Lowers other apps' audio while the assistant speaks or listens.
+///
+/// Works on PulseAudio and PipeWire (through pipewire-pulse) by calling
+/// `pactl`; without it ducking does nothing.
+pub struct AudioDucker {
+    /// Fraction of their volume other streams are lowered to
+    level: f32,
+    state: Mutex<DuckState>,
+    /// Held while pactl runs, so changes queue up behind it without
+    /// blocking callers on `state`
+    pactl_runs: Mutex<()>,
+    /// Original volumes of the ducked streams, kept on disk so they can be
+    /// restored after a crash
+    state_file: Option<PathBuf>,
+}
+
+impl AudioDucker {
+    pub fn new(level: f32) -> Self {
+        Self {
+            level: level.clamp(0.0, 1.0),
+            state: Mutex::new(DuckState::default()),
+            pactl_runs: Mutex::new(()),
+            state_file: None,
+        }
+    }
+
+    /// Record ducked streams in `path` while they are lowered
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_file = Some(path.into());
+        self
+    }
+
+    /// Restore streams a previous run ducked but never restored, e.g.
+    /// because it crashed
+    pub fn restore_after_crash(&self) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let _running = self.pactl_runs.lock();
+        let Ok(contents) = std::fs::read_to_string(path) else {
+            return;
+        };
+
+        let inputs = parse_state_file(&contents);
+        if !inputs.is_empty() {
+            warn!("Restoring {} audio streams left ducked by a previous run", inputs.len());
+            restore_inputs(&inputs);
+        }
+        self.forget_ducked();
+    }
+
+    pub fn set_speaking(self: &Arc<Self>, speaking: bool) {
+        self.update(|state| state.speaking = speaking);
+    }
+
+    pub fn set_listening(self: &Arc<Self>, listening: bool) {
+        self.update(|state| state.listening = listening);
+    }
+
+    /// Restore other apps' volume right away, e.g. on shutdown
+    pub fn restore(&self) {
+        let _running = self.pactl_runs.lock();
+        let ducked = {
+            let mut state = self.state.lock();
+            state.speaking = false;
+            state.listening = false;
+            state.generation += 1;
+            state.ducked.take()
+        };
+        if let Some(inputs) = ducked {
+            restore_inputs(&inputs);
+            self.forget_ducked();
+        }
+    }
+
+    fn update(self: &Arc<Self>, change: impl FnOnce(&mut DuckState)) {
+        let generation = {
+            let mut state = self.state.lock();
+            change(&mut state);
+            state.generation += 1;
+            state.generation
+        };
+
+        let ducker = self.clone();
+        tokio::task::spawn_blocking(move || ducker.apply(generation));
+    }
+
+    /// Duck or restore to match the state as of `generation`. pactl runs
+    /// without `state` locked; a change arriving meanwhile is applied by its
+    /// own call once this one finishes.
+    fn apply(&self, generation: u64) {
+        let wanted = {
+            let state = self.state.lock();
+            state.speaking || state.listening
+        };
+        if !wanted {
+            std::thread::sleep(RESTORE_DELAY);
+        }
+
+        let _running = self.pactl_runs.lock();
+        let ducked = {
+            let mut state = self.state.lock();
+            if state.generation != generation {
+                return;
+            }
+            match (wanted, state.ducked.is_some()) {
+                (true, true) | (false, false) => return,
+                (true, false) => None,
+                (false, true) => state.ducked.take(),
+            }
+        };
+
+        match ducked {
+            None => {
+                let inputs = self.duck_others();
+                self.remember_ducked(&inputs);
+                self.state.lock().ducked = Some(inputs);
+            }
+            Some(inputs) => {
+                restore_inputs(&inputs);
+                self.forget_ducked();
+            }
+        }
+    }
+
+    fn remember_ducked(&self, inputs: &[SinkInput]) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        if let Err(e) = write_state_file(path, inputs) {
+            warn!("Failed to record ducked audio streams: {}", e);
+        }
+    }
+
+    fn forget_ducked(&self) {
+        if let Some(path) = &self.state_file {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Lower every stream that isn't ours, returning them with their old volumes
+    fn duck_others(&self) -> Vec<SinkInput> {
+        let output = match pactl(&["list", "sink-inputs"]) {
+            Some(output) => output,
+            None => return Vec::new(),
+        };
+
+        let own_pid = std::process::id();
+        let inputs: Vec<SinkInput> = parse_sink_inputs(&output)
+            .into_iter()
+            .filter(|input| input.pid != Some(own_pid))
+            .collect();
+
+        for input in &inputs {
+            let percent = (input.volume_percent as f32 * self.level).round() as u32;
+            pactl(&["set-sink-input-volume", &input.id.to_string(), &format!("{}%", percent)]);
+        }
+
+        debug!("Ducked {} audio streams", inputs.len());
+        inputs
+    }
+}
+
+impl Drop for AudioDucker {
+    fn drop(&mut self) {
+        if let Some(inputs) = self.state.get_mut().ducked.take() {
+            restore_inputs(&inputs);
+            self.forget_ducked();
+        }
+    }
+}
+
+/// One `<id> <volume percent>` line per ducked stream
+fn write_state_file(path: &Path, inputs: &[SinkInput]) -> std::io::Result<()> {
+    let contents: String = inputs.iter()
+        .map(|input| format!("{} {}\n", input.id, input.volume_percent))
+        .collect();
+    std::fs::write(path, contents)
+}
+
+fn parse_state_file(contents: &str) -> Vec<SinkInput> {
+    contents.lines()
+        .filter_map(|line| {
+            let (id, volume) = line.split_once(' ')?;
+            Some(SinkInput {
+                id: id.trim().parse().ok()?,
+                volume_percent: volume.trim().parse().ok()?,
+                pid: None,
+            })
+        })
+        .collect()
+}
+
+fn restore_inputs(inputs: &[SinkInput]) {
+    // Streams that ended while ducked fail here, which is fine
+    for input in inputs {
+        pactl(&["set-sink-input-volume", &input.id.to_string(), &format!("{}%", input.volume_percent)]);
+    }
+    debug!("Restored {} audio streams", inputs.len());
+}
+
+/// Run `pactl` with untranslated output, returning its stdout on success
+fn pactl(args: &[&str]) -> Option<String> {
+    match Command::new("pactl").args(args).env("LC_ALL", "C").output() {
+        Ok(output) if output.status.success() => Some(String::from_utf8_lossy(&output.stdout).into_owned()),
+        Ok(output) => {
+            debug!("pactl {:?} exited with {}", args, output.status);
+            None
+        }
+        Err(e) => {
+            warn!("Audio ducking needs pactl: {}", e);
+            None
+        }
+    }
+}
+
+/// Streams in `pactl list sink-inputs` output, with the first channel's volume
+fn parse_sink_inputs(output: &str) -> Vec<SinkInput> {
+    let mut inputs = Vec::new();
+    let mut current: Option<SinkInput> = None;
+
+    for line in output.lines().map(str::trim) {
+        if let Some(id) = line.strip_prefix("Sink Input #") {
+            inputs.extend(current.take());
+            current = id.trim().parse().ok().map(|id| SinkInput { id, volume_percent: 100, pid: None });
+            continue;
+        }
+        let Some(input) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(volume) = line.strip_prefix("Volume:") {
+            if let Some(percent) = volume.split_whitespace().find_map(|word| word.strip_suffix('%')) {
+                input.volume_percent = percent.parse().unwrap_or(input.volume_percent);
+            }
+        } else if let Some(pid) = line.strip_prefix("application.process.id = ") {
+            input.pid = pid.trim_matches('"').parse().ok();
+        }
+    }
+
+    inputs.extend(current);
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sink_inputs() {
+        let output = "\
+Sink Input #42
+\tDriver: protocol-native.c
+\tMute: no
+\tVolume: front-left: 52429 /  80% / -5.81 dB,   front-right: 52429 /  80% / -5.81 dB
+\t        balance 0.00
+\tProperties:
+\t\tapplication.name = \"Firefox\"
+\t\tapplication.process.id = \"1234\"
+
+Sink Input #57
+\tVolume: mono: 65536 / 100% / 0.00 dB
+";
+        assert_eq!(parse_sink_inputs(output), vec![
+            SinkInput { id: 42, volume_percent: 80, pid: Some(1234) },
+            SinkInput { id: 57, volume_percent: 100, pid: None },
+        ]);
+        assert!(parse_sink_inputs("").is_empty());
+    }
+
+    #[test]
+    fn test_state_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("blipply-ducked-{}", uuid::Uuid::new_v4()));
+        let inputs = vec![
+            SinkInput { id: 42, volume_percent: 80, pid: None },
+            SinkInput { id: 57, volume_percent: 100, pid: None },
+        ];
+
+        write_state_file(&path, &inputs).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(parse_state_file(&contents), inputs);
+        assert!(parse_state_file("garbage\n").is_empty());
+    }
+}
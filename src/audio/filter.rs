@@ -0,0 +1,108 @@
+// Blipply Assistant - Audio Pipeline
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use std::collections::HashSet;
+
+const BUNDLED_PROFANITY_LIST: &str = include_str!("../../data/profanity_list.txt");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterResult {
+    pub text: String,
+    pub blocked: bool,
+}
+
+/// A stage applied to transcripts before they are sent to the LLM
+pub trait TranscriptFilter: Send + Sync {
+    fn filter(&self, text: &str) -> FilterResult;
+}
+
+/// Redacts listed words with `***`, or blocks the whole transcript
+pub struct ProfanityFilter {
+    words: HashSet<String>,
+    block: bool,
+}
+
+impl ProfanityFilter {
+    pub fn new(words: impl IntoIterator<Item = impl AsRef<str>>, block: bool) -> Self {
+        Self {
+            words: words.into_iter().map(|w| w.as_ref().trim().to_lowercase()).collect(),
+            block,
+        }
+    }
+
+    /// Filter using the bundled word list
+    pub fn bundled(block: bool) -> Self {
+        Self::new(parse_word_list(BUNDLED_PROFANITY_LIST), block)
+    }
+
+    fn is_listed(&self, word: &str) -> bool {
+        self.words.contains(&word.to_lowercase())
+    }
+}
+
+impl TranscriptFilter for ProfanityFilter {
+    fn filter(&self, text: &str) -> FilterResult {
+        let mut output = String::with_capacity(text.len());
+        let mut word = String::new();
+        let mut matched = false;
+
+        let flush = |word: &mut String, output: &mut String, matched: &mut bool| {
+            if self.is_listed(word) {
+                output.push_str("***");
+                *matched = true;
+            } else {
+                output.push_str(word);
+            }
+            word.clear();
+        };
+
+        for c in text.chars() {
+            if c.is_alphanumeric() || c == '\'' {
+                word.push(c);
+            } else {
+                flush(&mut word, &mut output, &mut matched);
+                output.push(c);
+            }
+        }
+        flush(&mut word, &mut output, &mut matched);
+
+        FilterResult {
+            text: output,
+            blocked: matched && self.block,
+        }
+    }
+}
+
+fn parse_word_list(list: &str) -> impl Iterator<Item = &str> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_listed_words_only() {
+        let filter = ProfanityFilter::new(["darn"], false);
+        let result = filter.filter("Well, DARN it, the class passed!");
+        assert_eq!(result.text, "Well, *** it, the class passed!");
+        assert!(!result.blocked);
+    }
+
+    #[test]
+    fn test_block_mode() {
+        let filter = ProfanityFilter::new(["darn"], true);
+        assert!(filter.filter("darn").blocked);
+        assert!(!filter.filter("hello there").blocked);
+    }
+
+    #[test]
+    fn test_bundled_list_loads() {
+        let filter = ProfanityFilter::bundled(false);
+        assert!(filter.is_listed("damn"));
+        assert!(!filter.is_listed("hello"));
+    }
+}
@@ -0,0 +1,326 @@
+// Blipply Assistant - Audio Pipeline
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+//! LoRA weight deltas for Whisper's attention projections, merged into a
+//! copy of the ggml model file. whisper.cpp gives no access to the weights
+//! once a model is loaded, so the patched copy is what gets loaded instead.
+//!
+//! A LoRA file holds one already-multiplied delta per patched tensor, all
+//! little-endian:
+//!
+//! ```text
+//! magic    b"WLRA"
+//! version  u32 (1)
+//! count    u32
+//! count x  name_len u32, name (UTF-8), n_elements u64, n_elements x f32
+//! ```
+//!
+//! Names are the model's tensor names, e.g.
+//! `decoder.blocks.0.cross_attn.query.weight`.
+
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::io::{ErrorKind, Read, Write};
+use std::path::Path;
+
+const LORA_MAGIC: &[u8; 4] = b"WLRA";
+const LORA_VERSION: u32 = 1;
+
+/// "ggml", the first word of a whisper.cpp model file
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+/// Model hyperparameters following the magic
+const GGML_HPARAMS: usize = 11;
+
+const GGML_TYPE_F32: i32 = 0;
+const GGML_TYPE_F16: i32 = 1;
+
+/// Weight deltas by tensor name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoraWeights {
+    pub deltas: HashMap<String, Vec<f32>>,
+}
+
+impl LoraWeights {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open LoRA file {:?}", path))?;
+        Self::read(std::io::BufReader::new(file))
+            .with_context(|| format!("Invalid LoRA file {:?}", path))
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != LORA_MAGIC {
+            bail!("Not a LoRA file");
+        }
+        let version = read_u32(&mut reader)?;
+        if version != LORA_VERSION {
+            bail!("Unsupported LoRA version {}", version);
+        }
+
+        let mut deltas = HashMap::new();
+        for _ in 0..read_u32(&mut reader)? {
+            let name = String::from_utf8(read_bytes(&mut reader, read_u32(&mut reader)? as usize)?)
+                .context("Tensor name is not UTF-8")?;
+            if !is_attention_projection(&name) {
+                bail!("{} is not an attention projection", name);
+            }
+            let len = read_u64(&mut reader)? as usize;
+            let delta = read_bytes(&mut reader, len * 4)?
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            deltas.insert(name, delta);
+        }
+
+        Ok(Self { deltas })
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> Result<()> {
+        writer.write_all(LORA_MAGIC)?;
+        writer.write_all(&LORA_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.deltas.len() as u32).to_le_bytes())?;
+        for (name, delta) in &self.deltas {
+            writer.write_all(&(name.len() as u32).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+            writer.write_all(&(delta.len() as u64).to_le_bytes())?;
+            for value in delta {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Query, key, value and output weights of self- and cross-attention
+fn is_attention_projection(name: &str) -> bool {
+    name.contains("attn.") && name.ends_with(".weight")
+}
+
+/// Copy the ggml Whisper model in `model` to `out`, adding each LoRA delta
+/// to the tensor of the same name. Patched tensors must be f32 or f16, and
+/// every delta must match a tensor of the same size.
+pub fn merge_into_model(mut model: impl Read, mut out: impl Write, lora: &LoraWeights) -> Result<()> {
+    let magic = read_u32(&mut model)?;
+    if magic != GGML_MAGIC {
+        bail!("Not a ggml Whisper model");
+    }
+    out.write_all(&magic.to_le_bytes())?;
+    copy_bytes(&mut model, &mut out, GGML_HPARAMS * 4)?;
+
+    // Mel filterbank
+    let n_mel = copy_i32(&mut model, &mut out)?;
+    let n_fft = copy_i32(&mut model, &mut out)?;
+    copy_bytes(&mut model, &mut out, (n_mel * n_fft) as usize * 4)?;
+
+    let n_tokens = copy_i32(&mut model, &mut out)?;
+    for _ in 0..n_tokens {
+        let len = copy_i32(&mut model, &mut out)?;
+        copy_bytes(&mut model, &mut out, len as usize)?;
+    }
+
+    let mut unmatched: HashSet<&str> = lora.deltas.keys().map(String::as_str).collect();
+    loop {
+        let n_dims = match read_i32(&mut model) {
+            Ok(n_dims) => n_dims,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        out.write_all(&n_dims.to_le_bytes())?;
+        let name_len = copy_i32(&mut model, &mut out)?;
+        let ttype = copy_i32(&mut model, &mut out)?;
+        let mut elements = 1usize;
+        for _ in 0..n_dims {
+            elements *= copy_i32(&mut model, &mut out)? as usize;
+        }
+        let name = String::from_utf8_lossy(&copy_bytes(&mut model, &mut out, name_len as usize)?).into_owned();
+
+        let mut data = read_bytes(&mut model, tensor_bytes(ttype, elements).with_context(|| name.clone())?)?;
+        if let Some(delta) = lora.deltas.get(&name) {
+            if delta.len() != elements {
+                bail!("LoRA delta for {} has {} values, the tensor has {}", name, delta.len(), elements);
+            }
+            add_delta(&mut data, ttype, delta).with_context(|| name.clone())?;
+            unmatched.remove(name.as_str());
+        }
+        out.write_all(&data)?;
+    }
+
+    if !unmatched.is_empty() {
+        bail!("LoRA patches tensors the model doesn't have: {:?}", unmatched);
+    }
+    Ok(())
+}
+
+/// Size of a tensor's data for the ggml types Whisper models ship in
+fn tensor_bytes(ttype: i32, elements: usize) -> Result<usize> {
+    // (elements per block, bytes per block)
+    let (block, size) = match ttype {
+        GGML_TYPE_F32 => (1, 4),
+        GGML_TYPE_F16 => (1, 2),
+        2 => (32, 18), // q4_0
+        3 => (32, 20), // q4_1
+        6 => (32, 22), // q5_0
+        7 => (32, 24), // q5_1
+        8 => (32, 34), // q8_0
+        other => bail!("Unsupported ggml tensor type {}", other),
+    };
+    Ok(elements / block * size)
+}
+
+/// Add `delta` to a tensor's data. Zero deltas leave the bytes alone, which
+/// also keeps -0.0 weights from turning into 0.0.
+fn add_delta(data: &mut [u8], ttype: i32, delta: &[f32]) -> Result<()> {
+    match ttype {
+        GGML_TYPE_F32 => {
+            for (bytes, d) in data.chunks_exact_mut(4).zip(delta).filter(|(_, d)| **d != 0.0) {
+                let value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) + d;
+                bytes.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        GGML_TYPE_F16 => {
+            for (bytes, d) in data.chunks_exact_mut(2).zip(delta).filter(|(_, d)| **d != 0.0) {
+                let value = half::f16::from_le_bytes([bytes[0], bytes[1]]).to_f32() + d;
+                bytes.copy_from_slice(&half::f16::from_f32(value).to_le_bytes());
+            }
+        }
+        _ => bail!("LoRA needs an f16 or f32 model, not a quantized one"),
+    }
+    Ok(())
+}
+
+fn read_bytes(reader: &mut impl Read, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_i32(reader: &mut impl Read) -> std::io::Result<i32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn copy_bytes(reader: &mut impl Read, writer: &mut impl Write, len: usize) -> Result<Vec<u8>> {
+    let buf = read_bytes(reader, len)?;
+    writer.write_all(&buf)?;
+    Ok(buf)
+}
+
+fn copy_i32(reader: &mut impl Read, writer: &mut impl Write) -> Result<i32> {
+    let value = read_i32(reader)?;
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUERY: &str = "decoder.blocks.0.attn.query.weight";
+    const KEY: &str = "decoder.blocks.0.cross_attn.key.weight";
+
+    /// A minimal ggml Whisper model: two tokens and two 2x2 attention
+    /// tensors, one f32 and one f16
+    fn tiny_model() -> Vec<u8> {
+        let mut model = Vec::new();
+        model.extend(GGML_MAGIC.to_le_bytes());
+        for hparam in [2i32, 1, 4, 1, 1, 1, 4, 1, 1, 1, 1] {
+            model.extend(hparam.to_le_bytes());
+        }
+        for value in [1i32, 2] {
+            model.extend(value.to_le_bytes());
+        }
+        for value in [0.5f32, 0.25] {
+            model.extend(value.to_le_bytes());
+        }
+        model.extend(2i32.to_le_bytes());
+        for token in ["hi", "there"] {
+            model.extend((token.len() as i32).to_le_bytes());
+            model.extend(token.as_bytes());
+        }
+        for (name, ttype) in [(QUERY, GGML_TYPE_F32), (KEY, GGML_TYPE_F16)] {
+            for value in [2i32, name.len() as i32, ttype, 2, 2] {
+                model.extend(value.to_le_bytes());
+            }
+            model.extend(name.as_bytes());
+            for value in [1.0f32, -2.0, 0.5, 3.0] {
+                if ttype == GGML_TYPE_F32 {
+                    model.extend(value.to_le_bytes());
+                } else {
+                    model.extend(half::f16::from_f32(value).to_le_bytes());
+                }
+            }
+        }
+        model
+    }
+
+    fn merged(model: &[u8], lora: &LoraWeights) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        merge_into_model(model, &mut out, lora)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_zero_lora_leaves_model_unchanged() {
+        let model = tiny_model();
+        let mut lora = LoraWeights::default();
+        lora.deltas.insert(QUERY.to_string(), vec![0.0; 4]);
+        lora.deltas.insert(KEY.to_string(), vec![0.0; 4]);
+
+        // The same bytes load as the same model, so transcripts match the baseline
+        assert_eq!(merged(&model, &lora).unwrap(), model);
+    }
+
+    #[test]
+    fn test_lora_round_trip_and_merge() {
+        let mut lora = LoraWeights::default();
+        lora.deltas.insert(QUERY.to_string(), vec![0.5, 0.0, 0.0, -1.0]);
+        let mut file = Vec::new();
+        lora.write(&mut file).unwrap();
+        assert_eq!(LoraWeights::read(file.as_slice()).unwrap(), lora);
+
+        let model = tiny_model();
+        let patched = merged(&model, &lora).unwrap();
+        assert_eq!(patched.len(), model.len());
+        let start = patched.windows(QUERY.len()).position(|w| w == QUERY.as_bytes()).unwrap() + QUERY.len();
+        let query: Vec<f32> = patched[start..start + 16]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(query, [1.5, -2.0, 0.5, 2.0]);
+    }
+
+    #[test]
+    fn test_lora_rejects_mismatches() {
+        let model = tiny_model();
+
+        let mut wrong_size = LoraWeights::default();
+        wrong_size.deltas.insert(QUERY.to_string(), vec![0.0; 3]);
+        assert!(merged(&model, &wrong_size).is_err());
+
+        let mut unknown = LoraWeights::default();
+        unknown.deltas.insert("encoder.blocks.9.attn.key.weight".to_string(), vec![0.0; 4]);
+        assert!(merged(&model, &unknown).is_err());
+
+        let mut not_attention = LoraWeights::default();
+        not_attention.deltas.insert("encoder.conv1.weight".to_string(), vec![0.0; 4]);
+        let mut file = Vec::new();
+        not_attention.write(&mut file).unwrap();
+        assert!(LoraWeights::read(file.as_slice()).is_err());
+    }
+}
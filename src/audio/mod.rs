@@ -2,25 +2,57 @@
 // Copyright (c) 2026 DeMoD LLC
 // Licensed under the MIT License
 
+pub mod ducking;
+pub mod filter;
+pub mod lora;
+pub mod resample;
+pub mod spectrum;
 pub mod stt;
+pub mod synthesis_queue;
 pub mod tts;
 pub mod vad;
+pub mod wake_word;
 
+pub use ducking::AudioDucker;
 pub use stt::SttPipeline;
-pub use tts::TtsPipeline;
-pub use vad::VoiceActivityDetector;
+pub use tts::{TtsPipeline, SystemTtsBackend};
+pub use vad::{VoiceActivityDetector, VadStats};
+pub use filter::{TranscriptFilter, ProfanityFilter, FilterResult};
+pub use spectrum::SpectrumAnalyzer;
+pub use wake_word::WakeWordDetector;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait};
+use std::path::Path;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+use crate::config::ResampleQuality;
 
 #[derive(Debug, Clone)]
 pub enum AudioEvent {
     SpeechStart,
     SpeechEnd,
+    /// Whisper started on a finished utterance
+    Transcribing,
+    /// Whisper finished, whatever the outcome
+    Transcribed,
     TranscriptPartial(String),
-    TranscriptFinal(String),
+    TranscriptFinal { text: String, confidence: f32 },
+    /// Transcript dropped for falling below the confidence threshold
+    TranscriptDiscarded(f32),
+    TranscriptBlocked,
+    /// Language Whisper detected in the latest transcript, e.g. "de"
+    LanguageDetected(String),
     TtsStarted,
     TtsFinished,
+    LongSilence,
+    Spectrum(Vec<f32>),
+    WakeWordDetected,
+    /// A microphone was plugged in, or the one in use went away. Carries the
+    /// default input device now, if there is one.
+    InputDeviceChanged { new_device: Option<String> },
 }
 
 pub type AudioEventSender = mpsc::UnboundedSender<AudioEvent>;
@@ -30,6 +62,79 @@ pub fn create_audio_channel() -> (AudioEventSender, AudioEventReceiver) {
     mpsc::unbounded_channel()
 }
 
+/// How often `DeviceWatcher` lists the input devices
+pub const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Notices microphones being plugged in or removed after startup. cpal has
+/// no device change notifications, so the device names are polled.
+#[derive(Debug, Default)]
+pub struct DeviceWatcher {
+    /// Input device names as of the last poll
+    devices: Vec<String>,
+    /// The default input device when capture last started
+    current: Option<String>,
+}
+
+impl DeviceWatcher {
+    /// Start from the devices present now
+    pub fn new() -> Self {
+        let (devices, current) = list_input_devices().unwrap_or_else(|e| {
+            debug!("Failed to list input devices: {}", e);
+            (Vec::new(), None)
+        });
+        Self { devices, current }
+    }
+
+    /// Compare a device listing with the last one. Returns the change event
+    /// when a device appeared or the current one disappeared.
+    pub fn update(&mut self, devices: Vec<String>, default: Option<String>) -> Option<AudioEvent> {
+        let added = devices.iter().any(|name| !self.devices.contains(name));
+        let current_removed = self.current.as_ref().is_some_and(|name| !devices.contains(name));
+        self.devices = devices;
+
+        if !added && !current_removed {
+            return None;
+        }
+        self.current = default.clone();
+        Some(AudioEvent::InputDeviceChanged { new_device: default })
+    }
+
+    /// Poll the input devices until the receiver is dropped
+    pub async fn run(mut self, event_tx: AudioEventSender) {
+        let mut interval = tokio::time::interval(DEVICE_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            // Listing devices can block on the sound server
+            // A failed listing says nothing about what changed, so skip it
+            let (devices, default) = match tokio::task::spawn_blocking(list_input_devices).await {
+                Ok(Ok(listing)) => listing,
+                Ok(Err(e)) => {
+                    debug!("Failed to list input devices: {}", e);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            if let Some(event) = self.update(devices, default) {
+                info!("Input devices changed: {:?}", event);
+                if event_tx.send(event).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Names of the input devices, and of the default one
+fn list_input_devices() -> Result<(Vec<String>, Option<String>)> {
+    let host = cpal::default_host();
+    let devices = host.input_devices()?
+        .filter_map(|device| device.name().ok())
+        .collect();
+    let default = host.default_input_device().and_then(|device| device.name().ok());
+    Ok((devices, default))
+}
+
 /// Convert f32 samples [-1.0, 1.0] to i16 samples
 pub fn f32_to_i16(samples: &[f32]) -> Vec<i16> {
     samples.iter()
@@ -44,40 +149,180 @@ pub fn i16_to_f32(samples: &[i16]) -> Vec<f32> {
         .collect()
 }
 
-/// Resample audio from one sample rate to another
-pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Result<Vec<f32>> {
-    use rubato::{Resampler, SincFixedIn, InterpolationType, InterpolationParameters, WindowFunction};
-    
-    if from_rate == to_rate {
-        return Ok(samples.to_vec());
-    }
-    
-    let params = InterpolationParameters {
-        sinc_len: 256,
-        f_cutoff: 0.95,
-        interpolation: InterpolationType::Linear,
-        oversampling_factor: 256,
-        window: WindowFunction::BlackmanHarris2,
+/// Samples above this level are compressed by `soft_limit`
+const LIMITER_KNEE: f32 = 0.8;
+
+/// Scale samples so the loudest one is at `peak_target`. Silence is left alone.
+pub fn normalize_audio(samples: &mut [f32], peak_target: f32) {
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak <= f32::EPSILON {
+        return;
+    }
+
+    let gain = peak_target / peak;
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+}
+
+/// Smoothly compress samples above the knee so they never exceed 1.0,
+/// avoiding the harsh clipping of a hard clamp
+pub fn soft_limit(samples: &mut [f32]) {
+    let headroom = 1.0 - LIMITER_KNEE;
+    for sample in samples.iter_mut() {
+        let level = sample.abs();
+        if level > LIMITER_KNEE {
+            let limited = LIMITER_KNEE + headroom * ((level - LIMITER_KNEE) / headroom).tanh();
+            *sample = limited.copysign(*sample);
+        }
+    }
+}
+
+/// Average interleaved left/right samples into one mono channel
+pub fn downmix_stereo(stereo: &[f32]) -> Vec<f32> {
+    stereo.chunks_exact(2)
+        .map(|frame| (frame[0] + frame[1]) / 2.0)
+        .collect()
+}
+
+/// Average interleaved frames of `channels` samples into one mono channel
+pub fn downmix(samples: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples.chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Load a WAV file as mono samples at `target_rate`, downmixing and
+/// resampling at `quality` as needed
+pub fn read_wav_mono(path: &Path, target_rate: u32, quality: ResampleQuality) -> Result<Vec<f32>> {
+    let mut reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    let spec = reader.spec();
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
     };
-    
-    let mut resampler = SincFixedIn::<f32>::new(
-        to_rate as f64 / from_rate as f64,
-        2.0,
-        params,
-        samples.len(),
-        1,
-    )?;
-    
-    let input = vec![samples.to_vec()];
-    let output = resampler.process(&input, None)?;
-    
-    Ok(output[0].clone())
+
+    let mono = downmix(&samples, spec.channels as usize);
+    if mono.is_empty() {
+        return Ok(mono);
+    }
+    resample_with_quality(&mono, spec.sample_rate, target_rate, quality)
+}
+
+/// Multiply samples by `gain`, clamping to ±1.0. Returns how many samples
+/// had to be clamped.
+pub fn apply_gain(samples: &mut [f32], gain: f32) -> usize {
+    let mut clipped = 0;
+    for sample in samples.iter_mut() {
+        let boosted = *sample * gain;
+        if boosted.abs() > 1.0 {
+            clipped += 1;
+        }
+        *sample = boosted.clamp(-1.0, 1.0);
+    }
+    clipped
+}
+
+/// Drop the near-silent start of `samples`, keeping `keep` samples of lead-in
+/// before the first one louder than `threshold`
+pub fn trim_leading_silence(samples: &[f32], threshold: f32, keep: usize) -> &[f32] {
+    match samples.iter().position(|s| s.abs() > threshold) {
+        Some(start) => &samples[start.saturating_sub(keep)..],
+        None => samples,
+    }
+}
+
+/// Resample audio from one sample rate to another, trading quality for speed
+pub fn resample_with_quality(
+    samples: &[f32],
+    from_rate: u32,
+    to_rate: u32,
+    quality: ResampleQuality,
+) -> Result<Vec<f32>> {
+    let (sinc_len, oversampling_factor) = quality.sinc_params();
+    resample::resample_sinc(samples, from_rate, to_rate, sinc_len, oversampling_factor)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_device_watcher_updates() {
+        let mut watcher = DeviceWatcher {
+            devices: vec!["Built-in Mic".to_string()],
+            current: Some("Built-in Mic".to_string()),
+        };
+        let builtin = || vec!["Built-in Mic".to_string()];
+        let headset = || vec!["Built-in Mic".to_string(), "USB Headset".to_string()];
+
+        assert!(watcher.update(builtin(), Some("Built-in Mic".to_string())).is_none());
+
+        // Plugging in a headset
+        let event = watcher.update(headset(), Some("USB Headset".to_string()));
+        assert!(matches!(event, Some(AudioEvent::InputDeviceChanged { new_device: Some(name) }) if name == "USB Headset"));
+        assert!(watcher.update(headset(), Some("USB Headset".to_string())).is_none());
+
+        // Unplugging it again
+        let event = watcher.update(builtin(), Some("Built-in Mic".to_string()));
+        assert!(matches!(event, Some(AudioEvent::InputDeviceChanged { new_device: Some(name) }) if name == "Built-in Mic"));
+
+        // Removing a device that isn't in use changes nothing
+        let mut watcher = DeviceWatcher { devices: headset(), current: Some("Built-in Mic".to_string()) };
+        assert!(watcher.update(builtin(), Some("Built-in Mic".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_downmix_stereo() {
+        let stereo = vec![0.5, 0.5, 1.0, 0.0, -0.4, 0.2, 0.3];
+        // The trailing half frame is dropped
+        assert_eq!(downmix_stereo(&stereo), vec![0.5, 0.5, -0.1]);
+        assert!(downmix_stereo(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_read_wav_mono() {
+        let path = std::env::temp_dir().join(format!("blipply-read-{}.wav", uuid::Uuid::new_v4()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for (left, right) in [(16384i16, 0i16), (-16384, -16384)] {
+            writer.write_sample(left).unwrap();
+            writer.write_sample(right).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let samples = read_wav_mono(&path, 16000, ResampleQuality::Fast).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(samples, vec![0.25, -0.5]);
+        assert_eq!(downmix(&[0.5, 0.25, 0.75], 3), vec![0.5]);
+    }
+
+    #[test]
+    fn test_apply_gain() {
+        let mut samples = vec![0.1, -0.2, 0.4, -0.6];
+        assert_eq!(apply_gain(&mut samples, 2.0), 1);
+        assert_eq!(samples, vec![0.2, -0.4, 0.8, -1.0]);
+
+        let mut samples = vec![0.5, -0.5];
+        assert_eq!(apply_gain(&mut samples, 1.0), 0);
+        assert_eq!(samples, vec![0.5, -0.5]);
+    }
+
     #[test]
     fn test_f32_to_i16_conversion() {
         let input = vec![0.0, 0.5, -0.5, 1.0, -1.0];
@@ -91,7 +336,53 @@ mod tests {
     #[test]
     fn test_resample_same_rate() {
         let input = vec![0.0, 0.5, -0.5];
-        let output = resample(&input, 16000, 16000).unwrap();
+        let output = resample_with_quality(&input, 16000, 16000, ResampleQuality::HiFi).unwrap();
         assert_eq!(input, output);
     }
+
+    #[test]
+    fn test_normalize_constant_signal() {
+        let mut samples = vec![0.25; 100];
+        normalize_audio(&mut samples, 0.9);
+        assert!(samples.iter().all(|&s| (s - 0.9).abs() < 1e-6));
+
+        let mut samples = vec![0.5, -2.0, 1.0];
+        normalize_audio(&mut samples, 0.9);
+        assert_eq!(samples, vec![0.225, -0.9, 0.45]);
+
+        let mut silence = vec![0.0; 10];
+        normalize_audio(&mut silence, 0.9);
+        assert_eq!(silence, vec![0.0; 10]);
+    }
+
+    #[test]
+    fn test_trim_leading_silence() {
+        let samples = [0.0, 0.001, -0.002, 0.0, 0.3, 0.0, 0.5];
+        assert_eq!(trim_leading_silence(&samples, 0.01, 0), &[0.3, 0.0, 0.5]);
+        assert_eq!(trim_leading_silence(&samples, 0.01, 2), &[-0.002, 0.0, 0.3, 0.0, 0.5]);
+        assert_eq!(trim_leading_silence(&samples, 0.01, 10), &samples);
+        // All-silent audio is left for the caller to deal with
+        assert_eq!(trim_leading_silence(&[0.0; 4], 0.01, 0), &[0.0; 4]);
+    }
+
+    #[test]
+    fn test_soft_limit() {
+        let mut samples = vec![0.5, 0.9, -3.0];
+        soft_limit(&mut samples);
+        assert_eq!(samples[0], 0.5);
+        assert!(samples[1] > 0.8 && samples[1] < 0.9);
+        assert!(samples[2] < -0.99 && samples[2] >= -1.0);
+    }
+
+    #[test]
+    fn test_resample_quality_levels() {
+        let input: Vec<f32> = (0..48000)
+            .map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / 48000.0).sin())
+            .collect();
+
+        for quality in [ResampleQuality::Fast, ResampleQuality::Balanced, ResampleQuality::HiFi] {
+            let output = resample_with_quality(&input, 48000, 16000, quality).unwrap();
+            assert!(output.len().abs_diff(16000) < 100, "{:?} produced {} samples", quality, output.len());
+        }
+    }
 }
@@ -0,0 +1,44 @@
+// Blipply Assistant - Resampling
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+//! Sinc resampling, kept free of other crate modules so the benchmarks can
+//! build it on its own.
+
+use anyhow::Result;
+use rubato::{Resampler, SincFixedIn, InterpolationType, InterpolationParameters, WindowFunction};
+
+/// Resample `samples` from `from_rate` to `to_rate` with a sinc filter of
+/// `sinc_len` taps, interpolated from `oversampling_factor` tables
+pub fn resample_sinc(
+    samples: &[f32],
+    from_rate: u32,
+    to_rate: u32,
+    sinc_len: usize,
+    oversampling_factor: usize,
+) -> Result<Vec<f32>> {
+    if from_rate == to_rate {
+        return Ok(samples.to_vec());
+    }
+
+    let params = InterpolationParameters {
+        sinc_len,
+        f_cutoff: 0.95,
+        interpolation: InterpolationType::Linear,
+        oversampling_factor,
+        window: WindowFunction::BlackmanHarris2,
+    };
+
+    let mut resampler = SincFixedIn::<f32>::new(
+        to_rate as f64 / from_rate as f64,
+        2.0,
+        params,
+        samples.len(),
+        1,
+    )?;
+
+    let input = vec![samples.to_vec()];
+    let output = resampler.process(&input, None)?;
+
+    Ok(output[0].clone())
+}
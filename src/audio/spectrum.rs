@@ -0,0 +1,105 @@
+// Blipply Assistant - Audio Pipeline
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+
+pub const FFT_SIZE: usize = 512;
+pub const NUM_BANDS: usize = 16;
+const MIN_FREQ: f32 = 50.0;
+
+/// Reduces audio frames to log-spaced frequency band levels
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    sample_rate: u32,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(sample_rate: u32) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(FFT_SIZE);
+
+        // Hann window to limit leakage between bands
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+
+        Self { fft, window, sample_rate }
+    }
+
+    /// RMS magnitude per band for one frame, zero-padded or truncated to `FFT_SIZE`
+    pub fn analyze(&self, samples: &[f32]) -> Vec<f32> {
+        let mut buffer: Vec<Complex<f32>> = (0..FFT_SIZE)
+            .map(|i| Complex::new(samples.get(i).copied().unwrap_or(0.0) * self.window[i], 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+
+        let scale = 2.0 / FFT_SIZE as f32;
+        let magnitudes: Vec<f32> = buffer[..FFT_SIZE / 2].iter().map(|c| c.norm() * scale).collect();
+        let bin_width = self.sample_rate as f32 / FFT_SIZE as f32;
+
+        (0..NUM_BANDS)
+            .map(|band| {
+                let (low, high) = self.band_edges(band);
+                let bins: Vec<f32> = magnitudes
+                    .iter()
+                    .enumerate()
+                    .filter(|(k, _)| {
+                        let freq = *k as f32 * bin_width;
+                        freq >= low && freq < high
+                    })
+                    .map(|(_, m)| *m)
+                    .collect();
+
+                if bins.is_empty() {
+                    // Low bands can be narrower than one FFT bin
+                    let center = ((low * high).sqrt() / bin_width).round() as usize;
+                    magnitudes.get(center).copied().unwrap_or(0.0)
+                } else {
+                    (bins.iter().map(|m| m * m).sum::<f32>() / bins.len() as f32).sqrt()
+                }
+            })
+            .collect()
+    }
+
+    fn band_edges(&self, band: usize) -> (f32, f32) {
+        let max_freq = self.sample_rate as f32 / 2.0;
+        let ratio = max_freq / MIN_FREQ;
+        let edge = |b: usize| MIN_FREQ * ratio.powf(b as f32 / NUM_BANDS as f32);
+        (edge(band), edge(band + 1))
+    }
+
+    #[cfg(test)]
+    fn band_for_frequency(&self, freq: f32) -> Option<usize> {
+        (0..NUM_BANDS).find(|&band| {
+            let (low, high) = self.band_edges(band);
+            freq >= low && freq < high
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sine_peaks_in_expected_band() {
+        let analyzer = SpectrumAnalyzer::new(16000);
+        let samples: Vec<f32> = (0..480)
+            .map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16000.0).sin())
+            .collect();
+
+        let bands = analyzer.analyze(&samples);
+        assert_eq!(bands.len(), NUM_BANDS);
+
+        let peak = bands
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .unwrap();
+        assert_eq!(Some(peak), analyzer.band_for_frequency(440.0));
+    }
+}
@@ -2,16 +2,47 @@
 // Copyright (c) 2026 DeMoD LLC
 // Licensed under the MIT License
 
-use anyhow::{Result, Context};
+use anyhow::{Result, Context, bail};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Stream, StreamConfig, SampleRate};
-use parking_lot::Mutex;
+use cpal::{Device, Stream, StreamConfig, SampleRate};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError};
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tracing::{debug, error, warn};
+use tracing::{debug, error, info, warn};
 use whisper_rs::{WhisperContext, FullParams, SamplingStrategy};
 
-use super::{AudioEvent, AudioEventSender, VoiceActivityDetector, f32_to_i16};
+use super::{
+    AudioEvent, AudioEventSender, VoiceActivityDetector, VadStats, TranscriptFilter, SpectrumAnalyzer, WakeWordDetector,
+    apply_gain, downmix_stereo, f32_to_i16,
+};
+use super::lora::{merge_into_model, LoraWeights};
+use crate::config::{ResampleQuality, SttStrategy};
+
+/// Sample rate Whisper models expect
+const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// Seconds of ambient audio sampled to calibrate the VAD noise gate
+const CALIBRATION_SECONDS: usize = 2;
+
+/// Temperature added on each Whisper fallback retry
+const TEMPERATURE_FALLBACK_STEP: f32 = 0.2;
+
+/// Default number of finished utterances waiting for Whisper; more are
+/// dropped, not queued
+const TRANSCRIPTION_QUEUE_SLOTS: usize = 4;
+
+/// Captured buffers waiting for the wake word detector; more are dropped
+/// rather than holding up the capture callback
+const WAKE_WORD_QUEUE_SLOTS: usize = 32;
+
+/// Capture callbacks per clipping check (~15 s of 30 ms buffers)
+const CLIP_WINDOW: u32 = 500;
+/// Warn when more than this share of callbacks in a window clipped
+const CLIP_WARN_FRACTION: f32 = 0.05;
 
 pub struct SttPipeline {
     whisper_ctx: Arc<WhisperContext>,
@@ -20,6 +51,74 @@ pub struct SttPipeline {
     event_tx: AudioEventSender,
     audio_buffer: Arc<Mutex<Vec<f32>>>,
     stream: Option<Stream>,
+    strategy: Arc<RwLock<SttStrategy>>,
+    calibration: Arc<Mutex<Option<Vec<i16>>>>,
+    filter: Arc<RwLock<Option<Arc<dyn TranscriptFilter>>>>,
+    spectrum: Arc<Mutex<Option<SpectrumAnalyzer>>>,
+    min_confidence: Arc<RwLock<f32>>,
+    initial_prompt: Arc<RwLock<Option<String>>>,
+    wake_word: Arc<Mutex<Option<WakeWordDetector>>>,
+    /// Whether captured audio is handed to the wake word worker
+    wake_word_enabled: Arc<AtomicBool>,
+    max_utterance_ms: Arc<RwLock<Option<u64>>>,
+    input_gain: Arc<RwLock<f32>>,
+    clipping: Arc<Mutex<ClipMonitor>>,
+    temperature: Arc<RwLock<f32>>,
+    segment_thresholds: Arc<RwLock<Option<SegmentThresholds>>>,
+    queue_depth: Arc<AtomicUsize>,
+    queue_slots: usize,
+    /// Spoken language to transcribe; Whisper detects it when unset
+    language: Arc<RwLock<Option<String>>>,
+    /// Language Whisper detected in the latest utterance
+    detected_language: Arc<Mutex<Option<String>>>,
+    /// Held while Whisper runs, so a worker left over from a restart can't
+    /// transcribe alongside the new one
+    whisper_lock: Arc<Mutex<()>>,
+    push_to_talk: Arc<Mutex<PushToTalk>>,
+    /// Used when a recording isn't already at Whisper's sample rate
+    resample_quality: Arc<RwLock<ResampleQuality>>,
+}
+
+/// State shared with the cpal capture callback. The callback runs on a
+/// real-time audio thread, so it only hands finished utterances to the
+/// transcription worker and audio to the wake word worker, and never runs
+/// Whisper or the detector itself.
+struct CaptureContext {
+    vad: Arc<Mutex<VoiceActivityDetector>>,
+    audio_buffer: Arc<Mutex<Vec<f32>>>,
+    event_tx: AudioEventSender,
+    sample_rate: u32,
+    calibration: Arc<Mutex<Option<Vec<i16>>>>,
+    spectrum: Arc<Mutex<Option<SpectrumAnalyzer>>>,
+    wake_word_enabled: Arc<AtomicBool>,
+    wake_word_frames: SyncSender<Vec<f32>>,
+    max_utterance_ms: Arc<RwLock<Option<u64>>>,
+    input_gain: Arc<RwLock<f32>>,
+    clipping: Arc<Mutex<ClipMonitor>>,
+    jobs: SyncSender<AudioFrameJob>,
+    queue_depth: Arc<AtomicUsize>,
+    push_to_talk: Arc<Mutex<PushToTalk>>,
+}
+
+/// A finished utterance waiting to be transcribed
+struct AudioFrameJob {
+    audio: Vec<f32>,
+}
+
+/// State used by the transcription worker
+struct TranscriptionWorker {
+    whisper_ctx: Arc<WhisperContext>,
+    event_tx: AudioEventSender,
+    strategy: Arc<RwLock<SttStrategy>>,
+    filter: Arc<RwLock<Option<Arc<dyn TranscriptFilter>>>>,
+    min_confidence: Arc<RwLock<f32>>,
+    initial_prompt: Arc<RwLock<Option<String>>>,
+    temperature: Arc<RwLock<f32>>,
+    segment_thresholds: Arc<RwLock<Option<SegmentThresholds>>>,
+    queue_depth: Arc<AtomicUsize>,
+    language: Arc<RwLock<Option<String>>>,
+    detected_language: Arc<Mutex<Option<String>>>,
+    whisper_lock: Arc<Mutex<()>>,
 }
 
 impl SttPipeline {
@@ -44,6 +143,26 @@ impl SttPipeline {
             event_tx,
             audio_buffer: Arc::new(Mutex::new(Vec::new())),
             stream: None,
+            strategy: Arc::new(RwLock::new(SttStrategy::default())),
+            calibration: Arc::new(Mutex::new(None)),
+            filter: Arc::new(RwLock::new(None)),
+            spectrum: Arc::new(Mutex::new(None)),
+            min_confidence: Arc::new(RwLock::new(0.0)),
+            initial_prompt: Arc::new(RwLock::new(None)),
+            wake_word: Arc::new(Mutex::new(None)),
+            wake_word_enabled: Arc::new(AtomicBool::new(false)),
+            max_utterance_ms: Arc::new(RwLock::new(None)),
+            input_gain: Arc::new(RwLock::new(1.0)),
+            clipping: Arc::new(Mutex::new(ClipMonitor::default())),
+            temperature: Arc::new(RwLock::new(0.0)),
+            segment_thresholds: Arc::new(RwLock::new(None)),
+            queue_depth: Arc::new(AtomicUsize::new(0)),
+            queue_slots: TRANSCRIPTION_QUEUE_SLOTS,
+            language: Arc::new(RwLock::new(None)),
+            detected_language: Arc::new(Mutex::new(None)),
+            whisper_lock: Arc::new(Mutex::new(())),
+            push_to_talk: Arc::new(Mutex::new(PushToTalk::default())),
+            resample_quality: Arc::new(RwLock::new(ResampleQuality::default())),
         })
     }
 
@@ -61,29 +180,62 @@ impl SttPipeline {
 
         debug!("Using input device: {}", device.name()?);
 
-        let config = StreamConfig {
-            channels: 1,
-            sample_rate: SampleRate(self.sample_rate),
-            buffer_size: cpal::BufferSize::Fixed(480), // 30ms at 16kHz
+        let (config, needs_downmix) = build_config_for_device(&device, self.sample_rate)?;
+
+        // Whisper runs on one blocking task, fed through a bounded queue, so
+        // utterances are transcribed one at a time and in order. The worker
+        // exits once the stream, and with it the sender, is dropped.
+        let (jobs_tx, jobs_rx) = std::sync::mpsc::sync_channel(self.queue_slots);
+        // A fresh counter, as the previous worker may still be draining its queue
+        self.queue_depth = Arc::new(AtomicUsize::new(0));
+        let worker = TranscriptionWorker {
+            whisper_ctx: self.whisper_ctx.clone(),
+            event_tx: self.event_tx.clone(),
+            strategy: self.strategy.clone(),
+            filter: self.filter.clone(),
+            min_confidence: self.min_confidence.clone(),
+            initial_prompt: self.initial_prompt.clone(),
+            temperature: self.temperature.clone(),
+            segment_thresholds: self.segment_thresholds.clone(),
+            queue_depth: self.queue_depth.clone(),
+            language: self.language.clone(),
+            detected_language: self.detected_language.clone(),
+            whisper_lock: self.whisper_lock.clone(),
         };
+        tokio::task::spawn_blocking(move || worker.run(jobs_rx));
 
-        let vad = self.vad.clone();
-        let audio_buffer = self.audio_buffer.clone();
+        // Rustpotter allocates as it scores, so it runs on its own thread
+        // rather than the real-time capture callback
+        let (wake_word_tx, wake_word_rx) = std::sync::mpsc::sync_channel(WAKE_WORD_QUEUE_SLOTS);
+        let detector = self.wake_word.clone();
         let event_tx = self.event_tx.clone();
-        let whisper_ctx = self.whisper_ctx.clone();
-        let sample_rate = self.sample_rate;
+        tokio::task::spawn_blocking(move || run_wake_word_worker(&detector, &event_tx, wake_word_rx));
+
+        let capture = CaptureContext {
+            vad: self.vad.clone(),
+            audio_buffer: self.audio_buffer.clone(),
+            event_tx: self.event_tx.clone(),
+            sample_rate: self.sample_rate,
+            calibration: self.calibration.clone(),
+            spectrum: self.spectrum.clone(),
+            wake_word_enabled: self.wake_word_enabled.clone(),
+            wake_word_frames: wake_word_tx,
+            max_utterance_ms: self.max_utterance_ms.clone(),
+            input_gain: self.input_gain.clone(),
+            clipping: self.clipping.clone(),
+            jobs: jobs_tx,
+            queue_depth: self.queue_depth.clone(),
+            push_to_talk: self.push_to_talk.clone(),
+        };
 
         let stream = device.build_input_stream(
             &config,
             move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                Self::audio_callback(
-                    data,
-                    vad.clone(),
-                    audio_buffer.clone(),
-                    event_tx.clone(),
-                    whisper_ctx.clone(),
-                    sample_rate,
-                );
+                if needs_downmix {
+                    Self::audio_callback(&downmix_stereo(data), &capture);
+                } else {
+                    Self::audio_callback(data, &capture);
+                }
             },
             move |err| {
                 error!("Audio stream error: {}", err);
@@ -98,17 +250,77 @@ impl SttPipeline {
         Ok(())
     }
 
-    fn audio_callback(
-        data: &[f32],
-        vad: Arc<Mutex<VoiceActivityDetector>>,
-        audio_buffer: Arc<Mutex<Vec<f32>>>,
-        event_tx: AudioEventSender,
-        whisper_ctx: Arc<WhisperContext>,
-        sample_rate: u32,
-    ) {
+    fn audio_callback(data: &[f32], capture: &CaptureContext) {
+        // Boost quiet microphones before anything listens to them
+        let gain = *capture.input_gain.read();
+        let boosted;
+        let data = if gain == 1.0 {
+            data
+        } else {
+            let mut samples = data.to_vec();
+            let clipped = apply_gain(&mut samples, gain);
+            if let Some(fraction) = capture.clipping.lock().record(clipped > 0) {
+                warn!(
+                    "Microphone input clipped in {:.0}% of recent audio, consider lowering audio.input_gain ({})",
+                    fraction * 100.0,
+                    gain
+                );
+            }
+            boosted = samples;
+            &boosted[..]
+        };
+
+        let CaptureContext { vad, audio_buffer, event_tx, .. } = capture;
+        let sample_rate = capture.sample_rate;
+
+        if let Some(analyzer) = capture.spectrum.lock().as_ref() {
+            event_tx.send(AudioEvent::Spectrum(analyzer.analyze(data))).ok();
+        }
+
+        if capture.wake_word_enabled.load(Ordering::Relaxed) {
+            // A full queue means the detector is behind; skip rather than wait
+            capture.wake_word_frames.try_send(data.to_vec()).ok();
+        }
+
         // Convert to i16 for VAD
         let i16_samples = f32_to_i16(data);
 
+        // Measure ambient noise instead of detecting speech while calibrating
+        {
+            let mut calibration = capture.calibration.lock();
+            if let Some(ambient) = calibration.as_mut() {
+                ambient.extend_from_slice(&i16_samples);
+                if ambient.len() >= sample_rate as usize * CALIBRATION_SECONDS {
+                    let noise_floor = vad.lock().calibrate(ambient);
+                    info!("VAD calibrated, ambient noise floor {:.1} RMS", noise_floor);
+                    *calibration = None;
+                }
+                return;
+            }
+        }
+
+        // With push-to-talk the talk button, not the VAD, bounds utterances
+        {
+            let mut push_to_talk = capture.push_to_talk.lock();
+            if push_to_talk.enabled {
+                match push_to_talk.step() {
+                    TalkStep::Start => {
+                        event_tx.send(AudioEvent::SpeechStart).ok();
+                        let mut buffer = audio_buffer.lock();
+                        buffer.clear();
+                        buffer.extend_from_slice(data);
+                    }
+                    TalkStep::Continue => audio_buffer.lock().extend_from_slice(data),
+                    TalkStep::End => {
+                        event_tx.send(AudioEvent::SpeechEnd).ok();
+                        Self::transcribe_buffered(capture);
+                    }
+                    TalkStep::Idle => {}
+                }
+                return;
+            }
+        }
+
         // Process VAD frame
         let vad_result = {
             let mut vad = vad.lock();
@@ -131,47 +343,31 @@ impl SttPipeline {
                     }
                     VadEvent::Speaking => {
                         // Continue collecting audio
-                        let mut buffer = audio_buffer.lock();
-                        buffer.extend_from_slice(data);
-                    }
-                    VadEvent::SpeechEnd => {
-                        debug!("Speech ended");
-                        event_tx.send(AudioEvent::SpeechEnd).ok();
-
-                        // Transcribe collected audio
-                        let audio = {
+                        let length = {
                             let mut buffer = audio_buffer.lock();
-                            let audio = buffer.clone();
-                            buffer.clear();
-                            audio
+                            buffer.extend_from_slice(data);
+                            buffer.len()
                         };
 
-                        if audio.len() > sample_rate as usize / 2 { // At least 0.5 seconds
-                            let whisper = whisper_ctx.clone();
-                            let tx = event_tx.clone();
-                            
-                            // Spawn blocking task for transcription
-                            tokio::task::spawn_blocking(move || {
-                                match Self::transcribe(&whisper, &audio) {
-                                    Ok(text) if !text.trim().is_empty() => {
-                                        debug!("Transcribed: {}", text);
-                                        tx.send(AudioEvent::TranscriptFinal(text)).ok();
-                                    }
-                                    Ok(_) => {
-                                        debug!("Empty transcription");
-                                    }
-                                    Err(e) => {
-                                        error!("Transcription failed: {}", e);
-                                    }
-                                }
-                            });
-                        } else {
-                            debug!("Audio too short to transcribe");
+                        if utterance_too_long(length, sample_rate, *capture.max_utterance_ms.read()) {
+                            warn!("Utterance exceeded the length limit, transcribing what was heard");
+                            vad.lock().reset();
+                            event_tx.send(AudioEvent::SpeechEnd).ok();
+                            Self::transcribe_buffered(capture);
                         }
                     }
+                    VadEvent::SpeechEnd => {
+                        debug!("Speech ended");
+                        event_tx.send(AudioEvent::SpeechEnd).ok();
+                        Self::transcribe_buffered(capture);
+                    }
                     VadEvent::Silence => {
                         // Do nothing
                     }
+                    VadEvent::LongSilence => {
+                        debug!("Long silence detected");
+                        event_tx.send(AudioEvent::LongSilence).ok();
+                    }
                 }
             }
             Err(e) => {
@@ -180,19 +376,110 @@ impl SttPipeline {
         }
     }
 
-    fn transcribe(ctx: &WhisperContext, samples: &[f32]) -> Result<String> {
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    /// Hand the collected utterance to the transcription worker
+    fn transcribe_buffered(capture: &CaptureContext) {
+        let audio = std::mem::take(&mut *capture.audio_buffer.lock());
+
+        if audio.len() <= capture.sample_rate as usize / 2 { // At least 0.5 seconds
+            debug!("Audio too short to transcribe");
+            return;
+        }
+
+        enqueue_job(&capture.jobs, &capture.queue_depth, AudioFrameJob { audio });
+    }
+
+    /// Utterances waiting for Whisper, not counting the one being transcribed
+    pub fn transcription_queue_depth(&self) -> usize {
+        self.queue_depth.load(Ordering::Relaxed)
+    }
+
+    /// Transcribe a WAV recording through the same gain, decoding and
+    /// filtering as microphone input, without opening an audio device.
+    /// Stereo is downmixed and other sample rates are resampled.
+    pub fn transcribe_file(&self, path: &Path, word_timestamps: bool) -> Result<Transcription> {
+        let mut samples = super::read_wav_mono(path, WHISPER_SAMPLE_RATE, *self.resample_quality.read())?;
+        apply_gain(&mut samples, *self.input_gain.read());
+
+        let options = DecodeOptions {
+            strategy: *self.strategy.read(),
+            initial_prompt: self.initial_prompt.read().clone(),
+            temperature: *self.temperature.read(),
+            thresholds: *self.segment_thresholds.read(),
+            language: self.language.read().clone(),
+            word_timestamps,
+        };
+        let running = self.whisper_lock.lock();
+        let transcription = Self::transcribe(&self.whisper_ctx, &samples, &options)?;
+        drop(running);
+        record_language(&self.detected_language, &transcription);
+
+        let filter = self.filter.read().clone();
+        let min_confidence = *self.min_confidence.read();
+        match transcript_event(transcription.text.clone(), transcription.confidence, min_confidence, filter.as_deref()) {
+            Some(AudioEvent::TranscriptFinal { text, .. }) => Ok(Transcription { text, ..transcription }),
+            Some(AudioEvent::TranscriptDiscarded(confidence)) => {
+                bail!("Transcript discarded, confidence {:.2} is below {:.2}", confidence, min_confidence)
+            }
+            Some(AudioEvent::TranscriptBlocked) => bail!("Transcript blocked by the profanity filter"),
+            _ => Ok(Transcription { text: String::new(), words: Vec::new(), ..transcription }),
+        }
+    }
+
+    fn sampling_strategy(strategy: SttStrategy) -> SamplingStrategy {
+        match strategy {
+            SttStrategy::Greedy { best_of } => SamplingStrategy::Greedy {
+                best_of: best_of as i32,
+            },
+            SttStrategy::BeamSearch { beam_size, patience } => SamplingStrategy::BeamSearch {
+                beam_size: beam_size as i32,
+                patience,
+            },
+        }
+    }
+
+    fn full_params(options: &DecodeOptions) -> FullParams<'_, '_> {
+        let mut params = FullParams::new(Self::sampling_strategy(options.strategy));
         
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
-        params.set_language(Some("en"));
+        params.set_language(Some(options.language.as_deref().unwrap_or("auto")));
         params.set_n_threads(4);
         params.set_translate(false);
         params.set_no_context(false);
         params.set_single_segment(false);
 
+        // Biases recognition towards these words; it doesn't guarantee them
+        if let Some(prompt) = options.initial_prompt.as_deref() {
+            params.set_initial_prompt(prompt);
+        }
+        params.set_token_timestamps(options.word_timestamps);
+
+        params.set_temperature(options.temperature);
+        if let Some((temperature_inc, no_speech_thold, logprob_thold)) = Self::fallback_params(options.thresholds) {
+            params.set_temperature_inc(temperature_inc);
+            params.set_no_speech_thold(no_speech_thold);
+            params.set_logprob_thold(logprob_thold);
+        }
+
+        params
+    }
+
+    /// Temperature step, no-speech and log-probability thresholds for
+    /// Whisper's re-decoding of unsure output. Without the filter this is
+    /// `None` and whisper.cpp's own defaults stay in place.
+    fn fallback_params(thresholds: Option<SegmentThresholds>) -> Option<(f32, f32, f32)> {
+        thresholds.map(|thresholds| {
+            (TEMPERATURE_FALLBACK_STEP, thresholds.max_no_speech_prob, thresholds.min_avg_logprob)
+        })
+    }
+
+    /// Transcribe `samples` of 16 kHz mono audio
+    fn transcribe(ctx: &WhisperContext, samples: &[f32], options: &DecodeOptions) -> Result<Transcription> {
+        let thresholds = options.thresholds;
+        let params = Self::full_params(options);
+
         let mut state = ctx.create_state()
             .context("Failed to create Whisper state")?;
         
@@ -203,14 +490,181 @@ impl SttPipeline {
             .context("Failed to get segment count")?;
 
         let mut text = String::new();
+        let mut words = Vec::new();
+        let mut no_speech_probs = Vec::with_capacity(num_segments as usize);
         for i in 0..num_segments {
             let segment = state.full_get_segment_text(i)
                 .context("Failed to get segment text")?;
+            let no_speech_prob = state.full_get_segment_no_speech_prob(i);
+            let num_tokens = state.full_n_tokens(i)
+                .context("Failed to get token count")?;
+
+            if let Some(thresholds) = thresholds {
+                let mut logprobs = Vec::with_capacity(num_tokens as usize);
+                for j in 0..num_tokens {
+                    let token = state.full_get_token_data(i, j)
+                        .context("Failed to get token data")?;
+                    logprobs.push(token.plog);
+                }
+
+                let avg_logprob = mean(&logprobs);
+                if !thresholds.keep(no_speech_prob, avg_logprob) {
+                    debug!(
+                        "Dropping segment {:?} (no speech {:.2}, avg logprob {:.2})",
+                        segment, no_speech_prob, avg_logprob
+                    );
+                    continue;
+                }
+            }
+
+            if options.word_timestamps {
+                let mut tokens = Vec::with_capacity(num_tokens as usize);
+                for j in 0..num_tokens {
+                    let token_text = state.full_get_token_text(i, j)
+                        .context("Failed to get token text")?;
+                    let token = state.full_get_token_data(i, j)
+                        .context("Failed to get token data")?;
+                    tokens.push((token_text, token.t0, token.t1));
+                }
+                words.extend(group_words(&tokens));
+            }
+
             text.push_str(&segment);
             text.push(' ');
+            no_speech_probs.push(no_speech_prob);
         }
 
-        Ok(text.trim().to_string())
+        // Only worth reporting when Whisper chose the language itself
+        let language = match options.language {
+            Some(_) => None,
+            None => state.full_lang_id_from_state().ok()
+                .and_then(whisper_rs::get_lang_str)
+                .map(str::to_string),
+        };
+
+        Ok(Transcription {
+            text: text.trim().to_string(),
+            confidence: mean_confidence(&no_speech_probs),
+            words,
+            language,
+        })
+    }
+
+    /// Configure the continuous-silence threshold for `AudioEvent::LongSilence`
+    pub fn set_long_silence_ms(&self, long_silence_ms: Option<u64>) {
+        self.vad.lock().set_long_silence_ms(long_silence_ms);
+    }
+
+    /// Restart the inactivity countdown behind `AudioEvent::LongSilence`
+    pub fn reset_inactivity(&self) {
+        self.vad.lock().reset_silence();
+    }
+
+    /// Record only while `set_talking(true)` instead of detecting speech
+    pub fn set_push_to_talk(&self, enabled: bool) {
+        let mut push_to_talk = self.push_to_talk.lock();
+        push_to_talk.enabled = enabled;
+        push_to_talk.held = false;
+    }
+
+    /// Press or release the push-to-talk button; releasing transcribes what
+    /// was said while it was held
+    pub fn set_talking(&self, held: bool) {
+        self.push_to_talk.lock().held = held;
+    }
+
+    /// Multiply captured samples by `gain` before voice detection
+    pub fn set_input_gain(&self, gain: f32) {
+        *self.input_gain.write() = gain;
+    }
+
+    /// Finished utterances allowed to wait for Whisper while it is busy;
+    /// takes effect on the next `start`
+    pub fn set_max_queued_transcriptions(&mut self, slots: usize) {
+        self.queue_slots = slots.max(1);
+    }
+
+    /// Language spoken, e.g. "de"; `None` lets Whisper detect it
+    pub fn set_language(&self, language: Option<String>) {
+        *self.language.write() = language.filter(|l| !l.trim().is_empty());
+    }
+
+    /// Language Whisper detected in the latest utterance, when detecting
+    pub fn get_detected_language(&self) -> Option<String> {
+        self.detected_language.lock().clone()
+    }
+
+    /// Forget the detected language until the next utterance
+    pub fn clear_detected_language(&self) {
+        self.detected_language.lock().take();
+    }
+
+    /// Cut off and transcribe utterances longer than this
+    pub fn set_max_utterance_ms(&self, max_utterance_ms: Option<u64>) {
+        *self.max_utterance_ms.write() = max_utterance_ms;
+    }
+
+    /// Discard transcripts whose mean speech confidence is below `min_confidence` (0.0–1.0)
+    pub fn set_min_confidence(&self, min_confidence: f32) {
+        *self.min_confidence.write() = min_confidence.clamp(0.0, 1.0);
+    }
+
+    /// Text that biases Whisper towards domain vocabulary such as names and jargon
+    pub fn set_initial_prompt(&self, prompt: Option<String>) {
+        *self.initial_prompt.write() = prompt.filter(|p| !p.trim().is_empty());
+    }
+
+    /// Resampler quality for recordings at other sample rates
+    pub fn set_resample_quality(&self, quality: ResampleQuality) {
+        *self.resample_quality.write() = quality;
+    }
+
+    /// Sampling temperature of the first decoding pass
+    pub fn set_temperature(&self, temperature: f32) {
+        *self.temperature.write() = temperature.max(0.0);
+    }
+
+    /// Set the fallback thresholds and drop segments that fail `thresholds`
+    pub fn set_confidence_filter(&self, thresholds: Option<SegmentThresholds>) {
+        *self.segment_thresholds.write() = thresholds;
+    }
+
+    /// Change the Whisper decoding strategy for subsequent transcriptions
+    pub fn set_strategy(&self, strategy: SttStrategy) {
+        *self.strategy.write() = strategy;
+    }
+
+    /// Emit `AudioEvent::Spectrum` band levels for every captured frame
+    pub fn set_spectrum_enabled(&self, enabled: bool) {
+        *self.spectrum.lock() = enabled.then(|| SpectrumAnalyzer::new(self.sample_rate));
+    }
+
+    /// Listen for a wake word in the captured audio
+    pub fn set_wake_word(&self, detector: Option<WakeWordDetector>) {
+        self.wake_word_enabled.store(detector.is_some(), Ordering::Relaxed);
+        *self.wake_word.lock() = detector;
+    }
+
+    /// Filter applied to transcripts before they are emitted
+    pub fn set_transcript_filter(&self, filter: Option<Arc<dyn TranscriptFilter>>) {
+        *self.filter.write() = filter;
+    }
+
+    /// Sample the next few seconds of audio as ambient noise and use it to
+    /// set the VAD noise gate. Speech detection pauses until it completes.
+    pub fn start_calibration(&self) {
+        info!("Calibrating VAD, sampling {}s of ambient audio", CALIBRATION_SECONDS);
+        *self.calibration.lock() = Some(Vec::new());
+    }
+
+    /// Voice detection counters
+    pub fn vad_stats(&self) -> VadStats {
+        self.vad.lock().get_stats()
+    }
+
+    /// Reset VAD state, restarting the silence timer
+    pub fn reset_vad(&self) {
+        self.vad.lock().reset();
     }
 
     pub fn stop(&mut self) {
@@ -221,6 +675,361 @@ impl SttPipeline {
     }
 }
 
+/// Capture config for `device` at `target_rate`: mono where the device
+/// supports it, otherwise stereo. The flag says whether the stereo stream
+/// needs downmixing before use.
+pub fn build_config_for_device(device: &Device, target_rate: u32) -> Result<(StreamConfig, bool)> {
+    let rate = SampleRate(target_rate);
+    let supports = |channels: u16| -> Result<bool> {
+        Ok(device.supported_input_configs()?.any(|range| {
+            range.channels() == channels
+                && range.min_sample_rate() <= rate
+                && rate <= range.max_sample_rate()
+        }))
+    };
+
+    let channels = if supports(1)? {
+        1
+    } else if supports(2)? {
+        debug!("Input device has no mono {} Hz mode, recording stereo", target_rate);
+        2
+    } else {
+        bail!("Input device supports neither mono nor stereo capture at {} Hz", target_rate);
+    };
+
+    let config = StreamConfig {
+        channels,
+        sample_rate: rate,
+        buffer_size: cpal::BufferSize::Fixed(480), // 30ms at 16kHz
+    };
+    Ok((config, channels == 2))
+}
+
+/// Queue `job` without blocking. When the worker is behind and the queue is
+/// full the utterance is dropped. Returns whether it was queued.
+fn enqueue_job(jobs: &SyncSender<AudioFrameJob>, queue_depth: &AtomicUsize, job: AudioFrameJob) -> bool {
+    // Count first so the worker never sees the depth go below zero
+    queue_depth.fetch_add(1, Ordering::Relaxed);
+    match jobs.try_send(job) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            queue_depth.fetch_sub(1, Ordering::Relaxed);
+            warn!("Transcription queue full, dropping utterance");
+            false
+        }
+        Err(TrySendError::Disconnected(_)) => {
+            queue_depth.fetch_sub(1, Ordering::Relaxed);
+            false
+        }
+    }
+}
+
+/// Score captured audio for the wake word until the capture stream, and
+/// with it the sender, is dropped
+fn run_wake_word_worker(
+    detector: &Mutex<Option<WakeWordDetector>>,
+    event_tx: &AudioEventSender,
+    frames: Receiver<Vec<f32>>,
+) {
+    while let Ok(samples) = frames.recv() {
+        let score = detector.lock().as_mut().and_then(|detector| detector.process(&samples));
+        if let Some(score) = score {
+            info!("Wake word detected ({:.2})", score);
+            event_tx.send(AudioEvent::WakeWordDetected).ok();
+        }
+    }
+    debug!("Wake word worker stopped");
+}
+
+impl TranscriptionWorker {
+    fn run(self, jobs: Receiver<AudioFrameJob>) {
+        while let Ok(job) = jobs.recv() {
+            self.queue_depth.fetch_sub(1, Ordering::Relaxed);
+            self.transcribe(job);
+        }
+        debug!("Transcription worker stopped");
+    }
+
+    fn transcribe(&self, job: AudioFrameJob) {
+        let tx = &self.event_tx;
+        let filter = self.filter.read().clone();
+        let min_confidence = *self.min_confidence.read();
+        let options = DecodeOptions {
+            strategy: *self.strategy.read(),
+            initial_prompt: self.initial_prompt.read().clone(),
+            temperature: *self.temperature.read(),
+            thresholds: *self.segment_thresholds.read(),
+            language: self.language.read().clone(),
+            ..Default::default()
+        };
+
+        let running = self.whisper_lock.lock();
+        tx.send(AudioEvent::Transcribing).ok();
+        let result = SttPipeline::transcribe(&self.whisper_ctx, &job.audio, &options);
+        drop(running);
+        // Sent before the transcript so the indicator clears first
+        tx.send(AudioEvent::Transcribed).ok();
+
+        match result {
+            Ok(transcription) => {
+                debug!("Transcribed ({:.2}): {}", transcription.confidence, transcription.text);
+                let language = record_language(&self.detected_language, &transcription);
+                let event = transcript_event(
+                    transcription.text,
+                    transcription.confidence,
+                    min_confidence,
+                    filter.as_deref(),
+                );
+                if let Some(event) = event {
+                    let is_final = matches!(event, AudioEvent::TranscriptFinal { .. });
+                    tx.send(event).ok();
+                    if let Some(language) = language.filter(|_| is_final) {
+                        tx.send(AudioEvent::LanguageDetected(language)).ok();
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Transcription failed: {}", e);
+            }
+        }
+    }
+}
+
+/// Settings for one Whisper run
+#[derive(Debug, Clone, Default)]
+pub struct DecodeOptions {
+    pub strategy: SttStrategy,
+    pub initial_prompt: Option<String>,
+    pub temperature: f32,
+    pub thresholds: Option<SegmentThresholds>,
+    /// Spoken language code such as "de"; detected if unset
+    pub language: Option<String>,
+    /// Collect when each word was spoken
+    pub word_timestamps: bool,
+}
+
+/// Text Whisper heard, with its mean speech confidence
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Transcription {
+    pub text: String,
+    pub confidence: f32,
+    /// Empty unless `DecodeOptions::word_timestamps` was set
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<WordTimestamp>,
+    /// Language Whisper detected; `None` when it was given one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+/// A word and when it was spoken, relative to the start of the audio
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WordTimestamp {
+    pub word: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// Remember the language detected in `transcription`, if any, and return it
+fn record_language(detected: &Mutex<Option<String>>, transcription: &Transcription) -> Option<String> {
+    let language = transcription.language.clone()?;
+    *detected.lock() = Some(language.clone());
+    Some(language)
+}
+
+/// English name of a Whisper language code, e.g. "French" for "fr". Unknown
+/// codes are returned as they are.
+pub fn language_name(code: &str) -> String {
+    let Some(name) = whisper_rs::get_lang_id(code).and_then(whisper_rs::get_lang_str_full) else {
+        return code.to_string();
+    };
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => code.to_string(),
+    }
+}
+
+/// Join Whisper tokens `(text, t0, t1)`, timed in centiseconds, into words.
+/// A token starting with a space starts a new word; special tokens such as
+/// `[_BEG_]` are skipped.
+fn group_words(tokens: &[(String, i64, i64)]) -> Vec<WordTimestamp> {
+    let mut words: Vec<WordTimestamp> = Vec::new();
+    for (text, t0, t1) in tokens {
+        if text.starts_with("[_") || text.starts_with("<|") {
+            continue;
+        }
+        let start_ms = (*t0).max(0) as u64 * 10;
+        let end_ms = (*t1).max(0) as u64 * 10;
+
+        match words.last_mut() {
+            Some(word) if !text.starts_with(' ') => {
+                word.word.push_str(text);
+                word.end_ms = end_ms;
+            }
+            _ if text.trim().is_empty() => {}
+            _ => words.push(WordTimestamp { word: text.trim_start().to_string(), start_ms, end_ms }),
+        }
+    }
+    words
+}
+
+/// A copy of the Whisper model at `model_path` with the LoRA at `lora_path`
+/// merged into its attention projections, written next to the model and
+/// reused while the LoRA is unchanged. Returns the copy's path, to load
+/// instead of the model.
+pub fn apply_lora(model_path: &Path, lora_path: &Path) -> Result<PathBuf> {
+    use sha2::{Digest, Sha256};
+
+    let lora_bytes = std::fs::read(lora_path)
+        .with_context(|| format!("Failed to read LoRA file {:?}", lora_path))?;
+    let digest = Sha256::digest(&lora_bytes);
+    let tag: String = digest.iter().take(6).map(|b| format!("{:02x}", b)).collect();
+    let merged_path = model_path.with_extension(format!("lora-{}.bin", tag));
+    if merged_path.exists() {
+        debug!("Using LoRA-merged Whisper model {:?}", merged_path);
+        return Ok(merged_path);
+    }
+
+    info!("Merging LoRA {:?} into Whisper model {:?}", lora_path, model_path);
+    let lora = LoraWeights::read(lora_bytes.as_slice())
+        .with_context(|| format!("Invalid LoRA file {:?}", lora_path))?;
+    let model = std::fs::File::open(model_path)
+        .with_context(|| format!("Failed to open Whisper model {:?}", model_path))?;
+
+    // Write to a temporary name so an interrupted merge is never reused
+    let partial_path = merged_path.with_extension("partial");
+    let mut out = std::io::BufWriter::new(std::fs::File::create(&partial_path)?);
+    let mut merged = merge_into_model(std::io::BufReader::new(model), &mut out, &lora);
+    if merged.is_ok() {
+        merged = std::io::Write::flush(&mut out).map_err(Into::into);
+    }
+    drop(out);
+    if let Err(e) = merged {
+        std::fs::remove_file(&partial_path).ok();
+        return Err(e.context("Failed to merge LoRA into the Whisper model"));
+    }
+    std::fs::rename(&partial_path, &merged_path)?;
+
+    Ok(merged_path)
+}
+
+/// Limits a Whisper segment must meet to be kept when the confidence
+/// filter is on
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentThresholds {
+    pub max_no_speech_prob: f32,
+    pub min_avg_logprob: f32,
+}
+
+impl SegmentThresholds {
+    /// Like Whisper's own no-speech rule, a segment is only dropped when it
+    /// is both probably silence and decoded with low confidence
+    fn keep(&self, no_speech_prob: f32, avg_logprob: f32) -> bool {
+        no_speech_prob <= self.max_no_speech_prob || avg_logprob >= self.min_avg_logprob
+    }
+}
+
+fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+/// Tracks how often boosted input clips, over windows of `CLIP_WINDOW` callbacks
+#[derive(Debug, Default)]
+struct ClipMonitor {
+    callbacks: u32,
+    clipped: u32,
+}
+
+impl ClipMonitor {
+    /// Count one callback. At the end of a window where clipping was
+    /// frequent, returns the share of callbacks that clipped.
+    fn record(&mut self, clipped: bool) -> Option<f32> {
+        self.callbacks += 1;
+        self.clipped += clipped as u32;
+        if self.callbacks < CLIP_WINDOW {
+            return None;
+        }
+
+        let fraction = self.clipped as f32 / self.callbacks as f32;
+        *self = Self::default();
+        (fraction > CLIP_WARN_FRACTION).then_some(fraction)
+    }
+}
+
+/// Push-to-talk state, advanced once per capture callback
+#[derive(Debug, Default)]
+struct PushToTalk {
+    enabled: bool,
+    /// The talk button is down
+    held: bool,
+    /// An utterance is being collected
+    recording: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TalkStep {
+    Start,
+    Continue,
+    End,
+    Idle,
+}
+
+impl PushToTalk {
+    fn step(&mut self) -> TalkStep {
+        let step = match (self.held, self.recording) {
+            (true, false) => TalkStep::Start,
+            (true, true) => TalkStep::Continue,
+            (false, true) => TalkStep::End,
+            (false, false) => TalkStep::Idle,
+        };
+        self.recording = self.held;
+        step
+    }
+}
+
+/// Whether `samples` of audio at `sample_rate` is longer than `max_ms`
+fn utterance_too_long(samples: usize, sample_rate: u32, max_ms: Option<u64>) -> bool {
+    max_ms.is_some_and(|max_ms| samples as u64 * 1000 >= max_ms * sample_rate as u64)
+}
+
+/// Mean of `1 - no_speech_prob` over all segments; zero when there are none
+fn mean_confidence(no_speech_probs: &[f32]) -> f32 {
+    if no_speech_probs.is_empty() {
+        return 0.0;
+    }
+    no_speech_probs.iter().map(|p| 1.0 - p).sum::<f32>() / no_speech_probs.len() as f32
+}
+
+/// Event to emit for a finished transcription, if any
+fn transcript_event(
+    text: String,
+    confidence: f32,
+    min_confidence: f32,
+    filter: Option<&dyn TranscriptFilter>,
+) -> Option<AudioEvent> {
+    if confidence < min_confidence {
+        debug!("Transcript discarded, confidence {:.2} below {:.2}", confidence, min_confidence);
+        return Some(AudioEvent::TranscriptDiscarded(confidence));
+    }
+
+    if text.trim().is_empty() {
+        debug!("Empty transcription");
+        return None;
+    }
+
+    match filter.map(|f| f.filter(&text)) {
+        Some(result) if result.blocked => {
+            debug!("Transcript blocked by filter");
+            Some(AudioEvent::TranscriptBlocked)
+        }
+        Some(result) => Some(AudioEvent::TranscriptFinal { text: result.text, confidence }),
+        None => Some(AudioEvent::TranscriptFinal { text, confidence }),
+    }
+}
+
 impl Drop for SttPipeline {
     fn drop(&mut self) {
         self.stop();
@@ -231,10 +1040,172 @@ impl Drop for SttPipeline {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_wake_word_worker_stops_with_capture() {
+        let (event_tx, mut event_rx) = super::super::create_audio_channel();
+        let (frames_tx, frames_rx) = std::sync::mpsc::sync_channel(WAKE_WORD_QUEUE_SLOTS);
+        for _ in 0..3 {
+            frames_tx.try_send(vec![0.1; 480]).unwrap();
+        }
+        drop(frames_tx);
+
+        // Without a detector the audio is drained and nothing is reported
+        run_wake_word_worker(&Mutex::new(None), &event_tx, frames_rx);
+        assert!(event_rx.try_recv().is_err());
+    }
+
     #[test]
     fn test_f32_to_i16() {
         let samples = vec![0.0, 0.5, -0.5, 1.0, -1.0];
         let i16_samples = f32_to_i16(&samples);
         assert_eq!(i16_samples.len(), samples.len());
     }
+
+    #[test]
+    fn test_silence_is_discarded() {
+        // Whisper reports silence as segments it is sure contain no speech
+        let confidence = mean_confidence(&[0.97, 0.99]);
+        assert!(confidence < 0.05);
+
+        let event = transcript_event("[BLANK_AUDIO]".to_string(), confidence, 0.5, None);
+        assert!(matches!(event, Some(AudioEvent::TranscriptDiscarded(c)) if c == confidence));
+
+        // No segments at all is silence too
+        let event = transcript_event(String::new(), mean_confidence(&[]), 0.5, None);
+        assert!(matches!(event, Some(AudioEvent::TranscriptDiscarded(_))));
+
+        // The default threshold keeps everything
+        let event = transcript_event("hello".to_string(), confidence, 0.0, None);
+        assert!(matches!(event, Some(AudioEvent::TranscriptFinal { ref text, .. }) if text == "hello"));
+    }
+
+    #[test]
+    fn test_full_transcription_queue_drops_jobs() {
+        let (tx, rx) = std::sync::mpsc::sync_channel(TRANSCRIPTION_QUEUE_SLOTS);
+        let depth = AtomicUsize::new(0);
+
+        let queued = (0..TRANSCRIPTION_QUEUE_SLOTS + 2)
+            .filter(|_| enqueue_job(&tx, &depth, AudioFrameJob { audio: vec![0.0; 16] }))
+            .count();
+        assert_eq!(queued, TRANSCRIPTION_QUEUE_SLOTS);
+        assert_eq!(depth.load(Ordering::Relaxed), TRANSCRIPTION_QUEUE_SLOTS);
+
+        // A stopped worker drops jobs too
+        drop(rx);
+        assert!(!enqueue_job(&tx, &depth, AudioFrameJob { audio: Vec::new() }));
+        assert_eq!(depth.load(Ordering::Relaxed), TRANSCRIPTION_QUEUE_SLOTS);
+    }
+
+    #[test]
+    fn test_clip_monitor() {
+        let mut monitor = ClipMonitor::default();
+        // Occasional clipping is fine
+        let warnings: Vec<f32> = (0..CLIP_WINDOW).filter_map(|i| monitor.record(i % 50 == 0)).collect();
+        assert!(warnings.is_empty());
+
+        let warnings: Vec<f32> = (0..CLIP_WINDOW).filter_map(|i| monitor.record(i % 4 == 0)).collect();
+        assert_eq!(warnings, vec![0.25]);
+    }
+
+    #[test]
+    fn test_utterance_limit() {
+        // 30 s at 16 kHz
+        assert!(!utterance_too_long(479_999, 16000, Some(30_000)));
+        assert!(utterance_too_long(480_000, 16000, Some(30_000)));
+        assert!(!utterance_too_long(usize::MAX / 2000, 16000, None));
+    }
+
+    #[test]
+    fn test_push_to_talk_steps() {
+        let mut push_to_talk = PushToTalk { enabled: true, ..Default::default() };
+        assert_eq!(push_to_talk.step(), TalkStep::Idle);
+        push_to_talk.held = true;
+        assert_eq!(push_to_talk.step(), TalkStep::Start);
+        assert_eq!(push_to_talk.step(), TalkStep::Continue);
+        push_to_talk.held = false;
+        assert_eq!(push_to_talk.step(), TalkStep::End);
+        assert_eq!(push_to_talk.step(), TalkStep::Idle);
+    }
+
+    #[test]
+    fn test_segment_thresholds() {
+        let thresholds = SegmentThresholds { max_no_speech_prob: 0.6, min_avg_logprob: -1.0 };
+        assert!(thresholds.keep(0.1, -2.5));
+        assert!(thresholds.keep(0.9, -0.3));
+        assert!(!thresholds.keep(0.9, -2.5));
+
+        let options = DecodeOptions { temperature: 0.2, thresholds: Some(thresholds), ..Default::default() };
+        let _params = SttPipeline::full_params(&options);
+        assert_eq!(mean(&[-0.5, -1.5]), -1.0);
+    }
+
+    #[test]
+    fn test_record_detected_language() {
+        let detected = Mutex::new(None);
+        let mut transcription = Transcription {
+            text: "Guten Tag".to_string(),
+            confidence: 0.9,
+            words: Vec::new(),
+            language: Some("de".to_string()),
+        };
+        assert_eq!(record_language(&detected, &transcription).as_deref(), Some("de"));
+        assert_eq!(detected.lock().as_deref(), Some("de"));
+
+        // A transcription in a configured language keeps the last detection
+        transcription.language = None;
+        assert_eq!(record_language(&detected, &transcription), None);
+        assert_eq!(detected.lock().as_deref(), Some("de"));
+    }
+
+    #[test]
+    fn test_group_words() {
+        let tokens = [
+            ("[_BEG_]".to_string(), 0, 0),
+            (" Hel".to_string(), 0, 20),
+            ("lo".to_string(), 20, 50),
+            (",".to_string(), 50, 52),
+            (" world".to_string(), 60, 120),
+            ("[_TT_60]".to_string(), 120, 120),
+        ];
+        assert_eq!(group_words(&tokens), vec![
+            WordTimestamp { word: "Hello,".to_string(), start_ms: 0, end_ms: 520 },
+            WordTimestamp { word: "world".to_string(), start_ms: 600, end_ms: 1200 },
+        ]);
+    }
+
+    #[test]
+    fn test_sampling_strategy_params() {
+        let strategies = [
+            SttStrategy::Greedy { best_of: 1 },
+            SttStrategy::BeamSearch { beam_size: 5, patience: 1.0 },
+        ];
+        for strategy in strategies {
+            let options = DecodeOptions {
+                strategy,
+                initial_prompt: Some("NixOS, systemd, Blipply".to_string()),
+                ..Default::default()
+            };
+            let _params = SttPipeline::full_params(&options);
+        }
+
+        assert!(matches!(
+            SttPipeline::sampling_strategy(SttStrategy::Greedy { best_of: 2 }),
+            SamplingStrategy::Greedy { best_of: 2 }
+        ));
+        assert!(matches!(
+            SttPipeline::sampling_strategy(SttStrategy::BeamSearch { beam_size: 5, patience: 1.0 }),
+            SamplingStrategy::BeamSearch { beam_size: 5, .. }
+        ));
+    }
+
+    #[test]
+    fn test_fallback_params_only_with_filter() {
+        assert_eq!(SttPipeline::fallback_params(None), None);
+
+        let thresholds = SegmentThresholds { max_no_speech_prob: 0.6, min_avg_logprob: -1.0 };
+        assert_eq!(
+            SttPipeline::fallback_params(Some(thresholds)),
+            Some((TEMPERATURE_FALLBACK_STEP, 0.6, -1.0))
+        );
+    }
 }
@@ -0,0 +1,210 @@
+// Blipply Assistant - Audio Pipeline
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::Result;
+use std::future::Future;
+use tokio::sync::mpsc;
+
+/// Sentences synthesized ahead of the one playing by default
+pub const DEFAULT_SYNTHESIS_QUEUE_SIZE: usize = 2;
+
+/// Pipelines speech: a worker task synthesizes upcoming sentences while the
+/// caller plays earlier ones, so there is no pause for synthesis between them.
+pub struct SynthesisQueue {
+    /// Most sentences synthesized, or being synthesized, ahead of the one
+    /// playing
+    size: usize,
+}
+
+impl SynthesisQueue {
+    pub fn new(size: usize) -> Self {
+        Self { size: size.max(1) }
+    }
+
+    /// Synthesize each of `sentences` in order and `play` them as they are
+    /// ready. `play` returns false to stop early, dropping the rest and
+    /// abandoning any synthesis in progress. Sentences synthesized before a
+    /// synthesis error are still played.
+    pub async fn run<T, Synth, SynthFut, Play, PlayFut>(
+        &self,
+        mut sentences: mpsc::UnboundedReceiver<String>,
+        mut synthesize: Synth,
+        mut play: Play,
+    ) -> Result<()>
+    where
+        T: Send + 'static,
+        Synth: FnMut(String) -> SynthFut + Send + 'static,
+        SynthFut: Future<Output = Result<T>> + Send,
+        Play: FnMut(T) -> PlayFut,
+        PlayFut: Future<Output = Result<bool>>,
+    {
+        let (ready_tx, mut ready_rx) = mpsc::channel(self.size);
+
+        let worker = tokio::spawn(async move {
+            while let Some(sentence) = sentences.recv().await {
+                // Take a place in the queue before synthesizing, so no more
+                // than `size` sentences are ever ahead. Waits while the queue
+                // is full; fails once playback has ended.
+                let Ok(slot) = ready_tx.reserve().await else {
+                    break;
+                };
+                slot.send(synthesize(sentence).await?);
+            }
+            Ok::<_, anyhow::Error>(())
+        });
+
+        let mut played = Ok(());
+        let mut stopped = false;
+        while let Some(audio) = ready_rx.recv().await {
+            match play(audio).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    stopped = true;
+                    break;
+                }
+                Err(e) => {
+                    played = Err(e);
+                    stopped = true;
+                    break;
+                }
+            }
+        }
+
+        // Nothing more will be played, so don't wait for the next sentence
+        if stopped {
+            worker.abort();
+            return played;
+        }
+        let synthesized = worker.await?;
+        played.and(synthesized)
+    }
+}
+
+impl Default for SynthesisQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_SYNTHESIS_QUEUE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    fn sentences(texts: &[&str]) -> mpsc::UnboundedReceiver<String> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        for text in texts {
+            tx.send(text.to_string()).unwrap();
+        }
+        rx
+    }
+
+    #[tokio::test]
+    async fn test_next_sentence_synthesized_during_playback() {
+        let synth_starts = Arc::new(Mutex::new(Vec::new()));
+        let play_spans = Arc::new(Mutex::new(Vec::new()));
+
+        let starts = synth_starts.clone();
+        let spans = play_spans.clone();
+        SynthesisQueue::default()
+            .run(
+                sentences(&["One.", "Two."]),
+                move |text| {
+                    starts.lock().push(Instant::now());
+                    async move {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        Ok(text)
+                    }
+                },
+                |text| {
+                    let spans = spans.clone();
+                    async move {
+                        let start = Instant::now();
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        spans.lock().push((text, start, Instant::now()));
+                        Ok(true)
+                    }
+                },
+            )
+            .await
+            .unwrap();
+
+        let synth_starts = synth_starts.lock();
+        let play_spans = play_spans.lock();
+        assert_eq!(play_spans.iter().map(|(text, _, _)| text.as_str()).collect::<Vec<_>>(), ["One.", "Two."]);
+        // The second sentence didn't wait for the first to finish playing
+        assert!(synth_starts[1] < play_spans[0].2);
+    }
+
+    #[tokio::test]
+    async fn test_stopping_playback_drops_the_rest() {
+        let played = Arc::new(Mutex::new(Vec::new()));
+
+        let log = played.clone();
+        SynthesisQueue::new(1)
+            .run(
+                sentences(&["One.", "Two.", "Three.", "Four."]),
+                |text| async move { Ok(text) },
+                |text| {
+                    let log = log.clone();
+                    async move {
+                        log.lock().push(text);
+                        Ok(log.lock().len() < 2)
+                    }
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(*played.lock(), ["One.", "Two."]);
+    }
+
+    #[tokio::test]
+    async fn test_stop_doesnt_wait_for_synthesis() {
+        let queue = SynthesisQueue::new(1);
+        let run = queue.run(
+            sentences(&["One.", "Two."]),
+            |text| async move {
+                if text == "Two." {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                }
+                Ok(text)
+            },
+            |_| async { Ok(false) },
+        );
+
+        let result = tokio::time::timeout(Duration::from_secs(5), run).await;
+        assert!(matches!(result, Ok(Ok(()))));
+    }
+
+    #[tokio::test]
+    async fn test_synthesis_error_after_earlier_sentences_play() {
+        let played = Arc::new(Mutex::new(Vec::new()));
+
+        let log = played.clone();
+        let result = SynthesisQueue::default()
+            .run(
+                sentences(&["One.", "Bad.", "Three."]),
+                |text| async move {
+                    if text == "Bad." {
+                        anyhow::bail!("synthesis failed");
+                    }
+                    Ok(text)
+                },
+                |text| {
+                    let log = log.clone();
+                    async move {
+                        log.lock().push(text);
+                        Ok(true)
+                    }
+                },
+            )
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(*played.lock(), ["One."]);
+    }
+}
@@ -2,22 +2,58 @@
 // Copyright (c) 2026 DeMoD LLC
 // Licensed under the MIT License
 
-use anyhow::{Result, Context};
+use anyhow::{Result, Context, bail};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{StreamConfig, SampleRate};
 use ort::{Session, Value, GraphOptimizationLevel, ExecutionProvider};
 use parking_lot::Mutex;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tracing::{debug, error};
+use tokio::sync::{mpsc, Notify};
+use tracing::{debug, error, warn};
 
-use super::AudioEventSender;
+use super::synthesis_queue::{SynthesisQueue, DEFAULT_SYNTHESIS_QUEUE_SIZE};
+use super::{AudioEventSender, f32_to_i16, normalize_audio, soft_limit, trim_leading_silence};
+
+/// Level below which leading Piper output counts as silence
+const SILENCE_THRESHOLD: f32 = 0.01;
+
+/// Lead-in kept before the first sound so consonants aren't clipped
+const LEAD_IN_MS: u64 = 10;
+
+/// Names multi-speaker Piper exports use for the speaker input
+const SPEAKER_INPUT_NAMES: &[&str] = &["sid", "speaker_id"];
 
 pub struct TtsPipeline {
-    session: Arc<Session>,
+    /// None when speaking through espeak-ng only
+    session: Option<Arc<Session>>,
     config: PiperConfig,
     output_sample_rate: u32,
     event_tx: Option<AudioEventSender>,
+    stop_signal: Arc<Notify>,
+    /// Bumped by `stop` so multi-sentence speech can tell it was interrupted
+    stop_count: AtomicU64,
+    system_fallback: Option<SystemTtsBackend>,
+    fallback_voice: String,
+    speed: f32,
+    volume: f32,
+    /// Peak level Piper output is normalised to, if enabled
+    peak_target: Option<f32>,
+    /// Extra playback time after the last sample so the device buffer drains
+    tail_pad: std::time::Duration,
+    trim_leading_silence: bool,
+    /// Speaker of a multi-speaker voice
+    speaker_id: Option<u32>,
+    /// Sentences synthesized ahead of the one playing
+    synthesis_queue_size: usize,
+}
+
+/// A sentence ready to play
+struct Utterance {
+    text: String,
+    /// Piper audio; None to speak `text` through the system synthesizer
+    samples: Option<Vec<f32>>,
 }
 
 #[derive(Debug, Clone)]
@@ -26,6 +62,16 @@ struct PiperConfig {
     sample_rate: u32,
 }
 
+impl PiperConfig {
+    /// Read the `.onnx.json` file Piper ships next to each voice
+    fn parse(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let num_speakers = value["num_speakers"].as_u64().unwrap_or(1).max(1) as usize;
+        let sample_rate = value["audio"]["sample_rate"].as_u64().unwrap_or(22050) as u32;
+        Ok(Self { num_speakers, sample_rate })
+    }
+}
+
 impl TtsPipeline {
     pub fn new(
         model_path: impl AsRef<Path>,
@@ -50,43 +96,226 @@ impl TtsPipeline {
         let config = Self::load_config(config_path)?;
 
         Ok(Self {
-            session: Arc::new(session),
+            session: Some(Arc::new(session)),
             config,
             output_sample_rate: 22050, // Piper default
             event_tx,
+            stop_signal: Arc::new(Notify::new()),
+            stop_count: AtomicU64::new(0),
+            system_fallback: None,
+            fallback_voice: String::new(),
+            speed,
+            volume: 1.0,
+            peak_target: None,
+            tail_pad: std::time::Duration::from_millis(100),
+            trim_leading_silence: false,
+            speaker_id: None,
+            synthesis_queue_size: DEFAULT_SYNTHESIS_QUEUE_SIZE,
         })
     }
 
+    /// Speak only through espeak-ng, for when no Piper voice is available
+    pub fn espeak(voice: impl Into<String>, speed: f32, event_tx: Option<AudioEventSender>) -> Self {
+        Self {
+            session: None,
+            config: PiperConfig { num_speakers: 1, sample_rate: 22050 },
+            output_sample_rate: 22050,
+            event_tx,
+            stop_signal: Arc::new(Notify::new()),
+            stop_count: AtomicU64::new(0),
+            system_fallback: Some(SystemTtsBackend::new()),
+            fallback_voice: voice.into(),
+            speed,
+            volume: 1.0,
+            peak_target: None,
+            tail_pad: std::time::Duration::from_millis(100),
+            trim_leading_silence: false,
+            speaker_id: None,
+            synthesis_queue_size: DEFAULT_SYNTHESIS_QUEUE_SIZE,
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+    }
+
+    /// Normalise each utterance so its loudest sample is at `peak_target`
+    pub fn set_peak_target(&mut self, peak_target: Option<f32>) {
+        self.peak_target = peak_target;
+    }
+
+    /// Playback time added after each sentence before the next one starts
+    pub fn set_tail_pad(&mut self, tail_pad: std::time::Duration) {
+        self.tail_pad = tail_pad;
+    }
+
+    /// Skip the silence some Piper voices put before each sentence
+    pub fn set_trim_leading_silence(&mut self, enabled: bool) {
+        self.trim_leading_silence = enabled;
+    }
+
+    /// Speak as this speaker of a multi-speaker voice
+    pub fn set_speaker_id(&mut self, speaker_id: Option<u32>) {
+        self.speaker_id = speaker_id;
+    }
+
+    /// Sentences synthesized while an earlier one plays
+    pub fn set_synthesis_queue_size(&mut self, size: usize) {
+        self.synthesis_queue_size = size.max(1);
+    }
+
+    /// Speakers the Piper voice at `voice_path` offers, read from the config
+    /// next to it; 1 for single-speaker voices
+    pub fn voice_speakers(voice_path: &Path) -> usize {
+        Self::load_config(voice_path.with_extension("json")).map_or(1, |config| config.num_speakers)
+    }
+
+    /// Name of the synthesizer in use, for logs
+    pub fn backend_name(&self) -> &str {
+        match (&self.session, &self.system_fallback) {
+            (Some(_), _) => "Piper",
+            (None, Some(fallback)) => &fallback.program,
+            (None, None) => "none",
+        }
+    }
+
+    /// Use the system speech synthesizer when Piper fails
+    pub fn enable_system_fallback(&mut self, backend: SystemTtsBackend, voice: impl Into<String>) {
+        self.system_fallback = Some(backend);
+        self.fallback_voice = voice.into();
+    }
+
     fn load_config(config_path: impl AsRef<Path>) -> Result<PiperConfig> {
-        // In a real implementation, parse the Piper config JSON
-        // For now, use defaults
-        Ok(PiperConfig {
-            num_speakers: 1,
-            sample_rate: 22050,
-        })
+        let config_path = config_path.as_ref();
+        match std::fs::read_to_string(config_path) {
+            Ok(json) => PiperConfig::parse(&json)
+                .with_context(|| format!("Invalid Piper voice config {:?}", config_path)),
+            Err(e) => {
+                warn!("Could not read Piper voice config {:?} ({}), assuming one speaker", config_path, e);
+                Ok(PiperConfig { num_speakers: 1, sample_rate: 22050 })
+            }
+        }
     }
 
     pub async fn speak(&self, text: &str) -> Result<()> {
         debug!("Synthesizing speech for: {}", text);
 
+        if self.session.is_none() {
+            return self.speak_system(text).await;
+        }
+        self.reported(self.speak_piper(text)).await
+    }
+
+    /// Run `speech` between TtsStarted and TtsFinished events. The end is
+    /// reported even on failure so listeners like ducking reset.
+    async fn reported(&self, speech: impl std::future::Future<Output = Result<()>>) -> Result<()> {
         if let Some(ref tx) = self.event_tx {
             tx.send(super::AudioEvent::TtsStarted).ok();
         }
+        let result = speech.await;
+        if let Some(ref tx) = self.event_tx {
+            tx.send(super::AudioEvent::TtsFinished).ok();
+        }
+        result
+    }
+
+    async fn speak_piper(&self, text: &str) -> Result<()> {
+        let audio = self.render(text)?;
+        self.play_audio(&audio).await
+    }
 
+    /// Synthesize `text` with Piper, ready for playback
+    fn render(&self, text: &str) -> Result<Vec<f32>> {
         // Prepare input (phonemes from text)
         let phonemes = self.text_to_phonemes(text)?;
         
         // Run inference
+        let mut audio = self.synthesize(&phonemes)?;
+        if self.trim_leading_silence {
+            let keep = (self.output_sample_rate as u64 * LEAD_IN_MS / 1000) as usize;
+            let trimmed = audio.len() - trim_leading_silence(&audio, SILENCE_THRESHOLD, keep).len();
+            audio.drain(..trimmed);
+        }
+        if let Some(peak_target) = self.peak_target {
+            normalize_audio(&mut audio, peak_target);
+        }
+        apply_volume(&mut audio, self.volume);
+
+        Ok(audio)
+    }
+
+    /// Synthesize a sentence for the synthesis queue, leaving it to the
+    /// system synthesizer if there is no Piper voice or Piper fails
+    fn prepare(&self, text: String) -> Result<Utterance> {
+        if self.session.is_none() {
+            return Ok(Utterance { text, samples: None });
+        }
+
+        match self.render(&text) {
+            Ok(samples) => Ok(Utterance { text, samples: Some(samples) }),
+            Err(e) if self.system_fallback.is_some() => {
+                warn!("Piper TTS failed ({}), falling back to the system synthesizer", e);
+                Ok(Utterance { text, samples: None })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Play a prepared sentence, retrying with the system backend if
+    /// playback fails
+    async fn play_utterance(&self, utterance: Utterance) -> Result<()> {
+        match utterance.samples {
+            Some(samples) => self.speak_or_fallback(&utterance.text, self.play_audio(&samples)).await,
+            None => self.speak_system(&utterance.text).await,
+        }
+    }
+
+    /// Synthesize `text` into an audio file instead of playing it. The format
+    /// is chosen from the extension: `.wav` (16-bit PCM) or `.mp3`.
+    pub fn synthesize_to_file(&self, text: &str, path: &Path) -> Result<()> {
+        let phonemes = self.text_to_phonemes(text)?;
         let audio = self.synthesize(&phonemes)?;
 
-        // Play audio
-        self.play_audio(&audio).await?;
+        let extension = path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
 
-        if let Some(ref tx) = self.event_tx {
-            tx.send(super::AudioEvent::TtsFinished).ok();
+        match extension.as_deref() {
+            Some("wav") => write_wav(&audio, self.output_sample_rate, path),
+            Some("mp3") => write_mp3(&audio, self.output_sample_rate, path),
+            _ => bail!("Unsupported audio format {:?}, expected .wav or .mp3", path),
         }
+    }
 
-        Ok(())
+    /// Like `speak`, but retries with the system backend if Piper fails
+    pub async fn speak_with_fallback(&self, text: &str) -> Result<()> {
+        if self.session.is_none() {
+            return self.speak_system(text).await;
+        }
+        self.speak_or_fallback(text, self.speak_piper(text)).await
+    }
+
+    /// Speak with `primary`, or through the system synthesizer if that
+    /// fails. Each attempt is reported with its own start and end events.
+    async fn speak_or_fallback(&self, text: &str, primary: impl std::future::Future<Output = Result<()>>) -> Result<()> {
+        let err = match self.reported(primary).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+
+        let Some(ref fallback) = self.system_fallback else {
+            return Err(err);
+        };
+
+        warn!("Piper TTS failed ({}), falling back to {}", err, fallback.program);
+        self.speak_system(text).await
+    }
+
+    /// Speak through the system synthesizer, reporting start and end
+    async fn speak_system(&self, text: &str) -> Result<()> {
+        let fallback = self.system_fallback.as_ref()
+            .context("No speech synthesizer available")?;
+        self.reported(fallback.speak(text, &self.fallback_voice, self.speed, self.volume)).await
     }
 
     fn text_to_phonemes(&self, text: &str) -> Result<Vec<i64>> {
@@ -120,14 +349,20 @@ impl TtsPipeline {
         )?;
 
         // Create input
-        let inputs = vec![
+        let mut inputs = vec![
             ("input", Value::from_array(input_array)?),
             ("input_lengths", Value::from_array(ndarray::arr1(&[input_len]))?),
             ("scales", Value::from_array(ndarray::arr1(&[0.667, 1.0, 0.8]))?),
         ];
 
+        let session = self.session.as_ref().context("No Piper voice loaded")?;
+        let input_names: Vec<&str> = session.inputs.iter().map(|input| input.name.as_str()).collect();
+        if let Some((name, speaker)) = speaker_input(&input_names, self.config.num_speakers, self.speaker_id) {
+            inputs.push((name, Value::from_array(ndarray::arr1(&[speaker]))?));
+        }
+
         // Run inference
-        let outputs = self.session.run(inputs)?;
+        let outputs = session.run(inputs)?;
 
         // Extract audio
         let audio_tensor = outputs["output"].try_extract_tensor::<f32>()?;
@@ -179,43 +414,314 @@ impl TtsPipeline {
 
         // Calculate playback duration
         let duration_secs = samples.len() as f64 / self.output_sample_rate as f64;
-        let duration = std::time::Duration::from_secs_f64(duration_secs + 0.1);
+        let duration = std::time::Duration::from_secs_f64(duration_secs) + self.tail_pad;
 
-        tokio::time::sleep(duration).await;
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => {}
+            _ = self.stop_signal.notified() => {
+                debug!("TTS playback stopped");
+            }
+        }
 
         Ok(())
     }
 
-    pub async fn speak_streaming<S>(&self, mut text_stream: S) -> Result<()>
+    /// Interrupt any playback in progress
+    pub fn stop(&self) {
+        self.stop_count.fetch_add(1, Ordering::SeqCst);
+        self.stop_signal.notify_waiters();
+    }
+
+    /// Whether `stop` was called since `stop_count` was read
+    fn stopped_since(&self, stop_count: u64) -> bool {
+        self.stop_count.load(Ordering::SeqCst) != stop_count
+    }
+
+    /// Speak `text` one sentence at a time, retrying each with the system
+    /// backend if Piper fails. Remaining sentences are dropped on `stop`.
+    pub async fn speak_sentences(self: &Arc<Self>, text: &str) -> Result<()> {
+        let (sentence_tx, sentence_rx) = mpsc::unbounded_channel();
+        for sentence in split_sentences(text) {
+            sentence_tx.send(sentence).ok();
+        }
+        drop(sentence_tx);
+
+        self.speak_queued(sentence_rx).await
+    }
+
+    pub async fn speak_streaming<S>(self: &Arc<Self>, mut text_stream: S) -> Result<()>
     where
         S: futures::Stream<Item = String> + Unpin,
     {
         use futures::StreamExt;
 
-        let mut buffer = String::new();
+        let (sentence_tx, sentence_rx) = mpsc::unbounded_channel();
+        let split = async move {
+            let mut buffer = String::new();
+
+            while let Some(chunk) = text_stream.next().await {
+                buffer.push_str(&chunk);
 
-        while let Some(chunk) = text_stream.next().await {
-            buffer.push_str(&chunk);
+                // Speak up to the last finished sentence; the rest may still grow
+                if let Some(&end) = sentence_ends(&buffer).last() {
+                    let complete = buffer.drain(..end).collect::<String>();
 
-            // Detect sentence boundaries
-            if let Some(pos) = buffer.rfind(|c| c == '.' || c == '!' || c == '?') {
-                let sentence = buffer.drain(..=pos).collect::<String>();
-                
-                if !sentence.trim().is_empty() {
-                    self.speak(&sentence).await?;
+                    for sentence in split_sentences(&complete) {
+                        if sentence_tx.send(sentence).is_err() {
+                            return;
+                        }
+                    }
                 }
             }
+
+            // Speak remaining text
+            if !buffer.trim().is_empty() {
+                sentence_tx.send(buffer).ok();
+            }
+        };
+
+        let ((), result) = tokio::join!(split, self.speak_queued(sentence_rx));
+        result
+    }
+
+    /// Speak `sentences` through the synthesis queue, so each is synthesized
+    /// while the one before it plays. The rest are dropped on `stop`.
+    async fn speak_queued(self: &Arc<Self>, sentences: mpsc::UnboundedReceiver<String>) -> Result<()> {
+        let stop_count = self.stop_count.load(Ordering::SeqCst);
+        let queue = SynthesisQueue::new(self.synthesis_queue_size);
+
+        let synthesizer = self.clone();
+        let tts: &Self = self;
+        queue.run(
+            sentences,
+            move |text| {
+                let synthesizer = synthesizer.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || synthesizer.prepare(text)).await?
+                }
+            },
+            move |utterance| async move {
+                if tts.stopped_since(stop_count) {
+                    debug!("Speech stopped, skipping remaining sentences");
+                    return Ok(false);
+                }
+                tts.play_utterance(utterance).await?;
+                Ok(!tts.stopped_since(stop_count))
+            },
+        ).await
+    }
+}
+
+/// Speaks through an external synthesizer process (espeak-ng by default)
+#[derive(Debug, Clone)]
+pub struct SystemTtsBackend {
+    program: String,
+}
+
+impl SystemTtsBackend {
+    pub fn new() -> Self {
+        Self::with_program("espeak-ng")
+    }
+
+    pub fn with_program(program: impl Into<String>) -> Self {
+        Self { program: program.into() }
+    }
+
+    /// Map a Piper voice name like `en_US-lessac-medium` to an espeak voice (`en-us`)
+    pub fn voice_for_piper_model(voice_model: &str) -> String {
+        voice_model
+            .split('-')
+            .next()
+            .unwrap_or("en")
+            .replace('_', "-")
+            .to_lowercase()
+    }
+
+    /// espeak-ng arguments; speed and volume are relative to its defaults
+    /// of 175 words per minute and amplitude 100
+    fn command_args(text: &str, voice: &str, speed: f32, volume: f32) -> Vec<String> {
+        let mut args = vec!["-v".to_string(), voice.to_string()];
+        if speed != 1.0 {
+            args.push("-s".to_string());
+            args.push(((175.0 * speed).round() as u32).clamp(80, 500).to_string());
+        }
+        if volume != 1.0 {
+            args.push("-a".to_string());
+            args.push(((100.0 * volume).round() as u32).min(200).to_string());
         }
+        args.push("--".to_string());
+        args.push(text.to_string());
+        args
+    }
+
+    pub async fn speak(&self, text: &str, voice: &str, speed: f32, volume: f32) -> Result<()> {
+        debug!("Speaking via {}: {}", self.program, text);
+
+        let status = tokio::process::Command::new(&self.program)
+            .args(Self::command_args(text, voice, speed, volume))
+            .status()
+            .await
+            .with_context(|| format!("Failed to run {}", self.program))?;
 
-        // Speak remaining text
-        if !buffer.trim().is_empty() {
-            self.speak(&buffer).await?;
+        if !status.success() {
+            bail!("{} exited with {}", self.program, status);
         }
 
         Ok(())
     }
 }
 
+impl Default for SystemTtsBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Abbreviations whose trailing period doesn't end a sentence
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "mt", "vs", "etc", "e.g", "i.e",
+    "cf", "al", "approx", "dept", "est", "fig", "inc", "ltd", "co", "no",
+];
+
+/// Split `text` into trimmed sentences for speech. A `.`, `!` or `?` only
+/// ends a sentence when whitespace follows it, so decimals and URLs stay
+/// whole, and periods after abbreviations, initials or in an ellipsis
+/// don't count.
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for end in sentence_ends(text).into_iter().chain([text.len()]) {
+        let sentence = text[start..end].trim();
+        if !sentence.is_empty() {
+            sentences.push(sentence.to_string());
+        }
+        start = end;
+    }
+    sentences
+}
+
+/// Byte offsets just past each sentence end in `text`: a run of `.!?` plus
+/// any closing quotes or brackets, followed by whitespace
+fn sentence_ends(text: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+
+        let mut end = i + c.len_utf8();
+        let mut periods = usize::from(c == '.');
+        let mut only_periods = c == '.';
+        while let Some(&(j, next)) = chars.peek() {
+            match next {
+                '.' | '!' | '?' => {
+                    periods += usize::from(next == '.');
+                    only_periods &= next == '.';
+                }
+                '"' | '\'' | ')' | ']' | '\u{201d}' | '\u{2019}' => {}
+                _ => break,
+            }
+            end = j + next.len_utf8();
+            chars.next();
+        }
+
+        if !text[end..].starts_with(char::is_whitespace) {
+            continue;
+        }
+        if only_periods && (periods > 1 || is_abbreviation(&text[..i])) {
+            continue;
+        }
+        ends.push(end);
+    }
+
+    ends
+}
+
+/// Whether the word at the end of `before` is an abbreviation or an initial
+fn is_abbreviation(before: &str) -> bool {
+    let word = before
+        .rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches(|c: char| !c.is_alphanumeric());
+
+    let mut chars = word.chars();
+    let is_initial = matches!((chars.next(), chars.next()), (Some(c), None) if c.is_uppercase());
+    is_initial || ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+/// Speaker input node and id to feed a voice with these inputs, if it takes
+/// one. Ids the voice doesn't have fall back to the first speaker.
+fn speaker_input<'a>(input_names: &[&'a str], num_speakers: usize, speaker_id: Option<u32>) -> Option<(&'a str, i64)> {
+    let name = input_names.iter().copied().find(|name| SPEAKER_INPUT_NAMES.contains(name))?;
+
+    let speaker = match speaker_id {
+        Some(id) if (id as usize) < num_speakers => id,
+        Some(id) => {
+            warn!("Voice has {} speakers, no speaker {}; using speaker 0", num_speakers, id);
+            0
+        }
+        None => 0,
+    };
+    Some((name, speaker as i64))
+}
+
+/// Scale samples by `volume`, leaving them untouched at 1.0
+fn apply_volume(samples: &mut [f32], volume: f32) {
+    if volume != 1.0 {
+        for sample in samples.iter_mut() {
+            *sample *= volume;
+        }
+        soft_limit(samples);
+    }
+}
+
+/// Write mono samples as a 16-bit PCM WAV file
+fn write_wav(samples: &[f32], sample_rate: u32, path: &Path) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut writer = hound::WavWriter::create(path, spec)
+        .with_context(|| format!("Failed to create {:?}", path))?;
+    for sample in f32_to_i16(samples) {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+
+    debug!("Wrote {} samples to {:?}", samples.len(), path);
+    Ok(())
+}
+
+/// Encode mono samples as an MP3 file
+fn write_mp3(samples: &[f32], sample_rate: u32, path: &Path) -> Result<()> {
+    use mp3lame_encoder::{Bitrate, Builder, FlushNoGap, MonoPcm, Quality};
+
+    let mut builder = Builder::new().context("Failed to create MP3 encoder")?;
+    builder.set_num_channels(1).map_err(|e| anyhow::anyhow!("MP3 encoder: {:?}", e))?;
+    builder.set_sample_rate(sample_rate).map_err(|e| anyhow::anyhow!("MP3 encoder: {:?}", e))?;
+    builder.set_brate(Bitrate::Kbps128).map_err(|e| anyhow::anyhow!("MP3 encoder: {:?}", e))?;
+    builder.set_quality(Quality::Good).map_err(|e| anyhow::anyhow!("MP3 encoder: {:?}", e))?;
+    let mut encoder = builder.build().map_err(|e| anyhow::anyhow!("MP3 encoder: {:?}", e))?;
+
+    let pcm = f32_to_i16(samples);
+    let mut mp3 = Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(pcm.len()));
+    encoder.encode_to_vec(MonoPcm(&pcm), &mut mp3)
+        .map_err(|e| anyhow::anyhow!("MP3 encoding failed: {:?}", e))?;
+    encoder.flush_to_vec::<FlushNoGap>(&mut mp3)
+        .map_err(|e| anyhow::anyhow!("MP3 encoding failed: {:?}", e))?;
+
+    std::fs::write(path, &mp3).with_context(|| format!("Failed to write {:?}", path))?;
+
+    debug!("Wrote {} samples to {:?}", samples.len(), path);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -223,13 +729,133 @@ mod tests {
     #[test]
     fn test_text_to_phonemes() {
         let tts = TtsPipeline {
-            session: Arc::new(Session::builder().unwrap().commit_from_file("dummy").unwrap()),
+            session: Some(Arc::new(Session::builder().unwrap().commit_from_file("dummy").unwrap())),
             config: PiperConfig { num_speakers: 1, sample_rate: 22050 },
             output_sample_rate: 22050,
             event_tx: None,
+            stop_signal: Arc::new(Notify::new()),
+            stop_count: AtomicU64::new(0),
+            system_fallback: None,
+            fallback_voice: String::new(),
+            speed: 1.0,
+            volume: 1.0,
+            peak_target: None,
+            tail_pad: std::time::Duration::from_millis(100),
+            trim_leading_silence: false,
+            speaker_id: None,
+            synthesis_queue_size: DEFAULT_SYNTHESIS_QUEUE_SIZE,
         };
         
         let phonemes = tts.text_to_phonemes("hello").unwrap();
         assert!(!phonemes.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_fallback_events_are_balanced() {
+        let (event_tx, mut events) = crate::audio::create_audio_channel();
+        let mut tts = TtsPipeline::espeak("en", 1.0, Some(event_tx));
+        tts.enable_system_fallback(SystemTtsBackend::with_program("/nonexistent/blipply-tts"), "en");
+
+        let result = tts.speak_or_fallback("Hi.", async { anyhow::bail!("Piper failed") }).await;
+        assert!(result.is_err());
+
+        let mut started = 0;
+        let mut finished = 0;
+        while let Ok(event) = events.try_recv() {
+            match event {
+                crate::audio::AudioEvent::TtsStarted => started += 1,
+                crate::audio::AudioEvent::TtsFinished => {
+                    finished += 1;
+                    assert!(finished <= started, "TtsFinished without TtsStarted");
+                }
+                _ => {}
+            }
+        }
+        // One pair for Piper and one for the fallback
+        assert_eq!((started, finished), (2, 2));
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(split_sentences("Hi there! How are you? Fine."), vec!["Hi there!", "How are you?", "Fine."]);
+        assert_eq!(split_sentences("Pi is 3.14 or so. Next"), vec!["Pi is 3.14 or so.", "Next"]);
+        assert_eq!(split_sentences("Ask Dr. Smith and Mrs. Jones. Ok"), vec!["Ask Dr. Smith and Mrs. Jones.", "Ok"]);
+        assert_eq!(split_sentences("Fruit, e.g. apples, i.e. food. Yes"), vec!["Fruit, e.g. apples, i.e. food.", "Yes"]);
+        assert_eq!(split_sentences("Written by J. R. Tolkien. Yes"), vec!["Written by J. R. Tolkien.", "Yes"]);
+        assert_eq!(split_sentences("Well... maybe. Or not"), vec!["Well... maybe.", "Or not"]);
+        assert_eq!(split_sentences("See https://nixos.org/manual.html for more. Done"), vec!["See https://nixos.org/manual.html for more.", "Done"]);
+        assert_eq!(split_sentences("Really?! \"Yes.\" Then"), vec!["Really?!", "\"Yes.\"", "Then"]);
+        assert!(split_sentences("  ").is_empty());
+    }
+
+    #[test]
+    fn test_sentence_ends_wait_for_whitespace() {
+        // A trailing period may still turn out to be a decimal point
+        assert!(sentence_ends("It costs 3.").is_empty());
+        assert_eq!(sentence_ends("Hi. It costs 3."), vec![3]);
+    }
+
+    #[test]
+    fn test_voice_speakers() {
+        let dir = std::env::temp_dir().join(format!("blipply-voice-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let voice = dir.join("en_US-libritts-high.onnx");
+        std::fs::write(voice.with_extension("json"), r#"{"num_speakers": 904}"#).unwrap();
+
+        assert_eq!(TtsPipeline::voice_speakers(&voice), 904);
+        assert_eq!(TtsPipeline::voice_speakers(&dir.join("missing.onnx")), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_speaker_id_injection() {
+        let config = PiperConfig::parse(r#"{"num_speakers": 2, "audio": {"sample_rate": 22050}}"#).unwrap();
+        assert_eq!(config.num_speakers, 2);
+
+        let multi_speaker = ["input", "input_lengths", "scales", "sid"];
+        assert_eq!(speaker_input(&multi_speaker, config.num_speakers, Some(1)), Some(("sid", 1)));
+        assert_eq!(speaker_input(&multi_speaker, config.num_speakers, None), Some(("sid", 0)));
+        assert_eq!(speaker_input(&multi_speaker, config.num_speakers, Some(7)), Some(("sid", 0)));
+
+        // Single-speaker voices have no speaker input to fill
+        let single_speaker = ["input", "input_lengths", "scales"];
+        assert_eq!(speaker_input(&single_speaker, 1, Some(1)), None);
+        assert_eq!(PiperConfig::parse("{}").unwrap().num_speakers, 1);
+    }
+
+    #[test]
+    fn test_write_wav_header() {
+        let path = std::env::temp_dir().join(format!("blipply-tts-{}.wav", uuid::Uuid::new_v4()));
+        let samples: Vec<f32> = (0..2205).map(|i| (i as f32 * 0.05).sin()).collect();
+
+        write_wav(&samples, 22050, &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..12], b"WAVE");
+        assert_eq!(&bytes[12..16], b"fmt ");
+        assert_eq!(u16::from_le_bytes([bytes[20], bytes[21]]), 1); // PCM
+        assert_eq!(u16::from_le_bytes([bytes[22], bytes[23]]), 1); // channels
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into().unwrap()), 22050);
+        assert_eq!(u16::from_le_bytes([bytes[34], bytes[35]]), 16); // bits per sample
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 2205 * 2);
+    }
+
+    #[test]
+    fn test_system_tts_args() {
+        let args = SystemTtsBackend::command_args("-hello", "en-us", 1.0, 1.0);
+        assert_eq!(args, vec!["-v", "en-us", "--", "-hello"]);
+
+        let args = SystemTtsBackend::command_args("hi", "en-us", 1.2, 0.5);
+        assert_eq!(args, vec!["-v", "en-us", "-s", "210", "-a", "50", "--", "hi"]);
+        assert_eq!(SystemTtsBackend::voice_for_piper_model("en_US-lessac-medium"), "en-us");
+    }
+
+    #[tokio::test]
+    async fn test_system_tts_reports_failure() {
+        assert!(SystemTtsBackend::with_program("true").speak("hi", "en", 1.0, 1.0).await.is_ok());
+        assert!(SystemTtsBackend::with_program("false").speak("hi", "en", 1.0, 1.0).await.is_err());
+    }
 }
@@ -3,9 +3,13 @@
 // Licensed under the MIT License
 
 use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
 use webrtc_vad::{Vad, SampleRate, Mode};
 use std::time::{Duration, Instant};
 
+/// Noise gate sits this far above the calibrated noise floor (~6 dB)
+const NOISE_GATE_MARGIN: f32 = 2.0;
+
 pub struct VoiceActivityDetector {
     vad: Vad,
     sample_rate: u32,
@@ -13,6 +17,70 @@ pub struct VoiceActivityDetector {
     silence_duration: Duration,
     last_speech_time: Option<Instant>,
     is_speaking: bool,
+    long_silence_ms: Option<u64>,
+    silence_elapsed_ms: u64,
+    long_silence_reported: bool,
+    noise_floor: Option<f32>,
+    min_rms: f32,
+    counters: VadCounters,
+}
+
+/// Detection counters for diagnosing silence thresholds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct VadStats {
+    pub total_frames: u64,
+    pub speech_frames: u64,
+    pub silence_frames: u64,
+    /// Length of the last complete utterance
+    pub last_speech_duration_ms: u64,
+    /// Silence since speech was last detected
+    pub current_silence_ms: u64,
+    /// Share of frames containing speech (0.0–1.0)
+    pub speech_ratio: f32,
+}
+
+/// Running totals behind `VadStats`. The detector is only used behind a
+/// lock, so plain counters are enough.
+#[derive(Debug, Default)]
+struct VadCounters {
+    speech_frames: u64,
+    silence_frames: u64,
+    current_speech_ms: u64,
+    last_speech_duration_ms: u64,
+    current_silence_ms: u64,
+}
+
+impl VadCounters {
+    fn record(&mut self, has_speech: bool, frame_ms: u64) {
+        if has_speech {
+            self.speech_frames += 1;
+            self.current_speech_ms += frame_ms;
+            self.current_silence_ms = 0;
+        } else {
+            self.silence_frames += 1;
+            self.current_silence_ms += frame_ms;
+            if self.current_speech_ms > 0 {
+                self.last_speech_duration_ms = self.current_speech_ms;
+                self.current_speech_ms = 0;
+            }
+        }
+    }
+
+    fn stats(&self) -> VadStats {
+        let total_frames = self.speech_frames + self.silence_frames;
+        VadStats {
+            total_frames,
+            speech_frames: self.speech_frames,
+            silence_frames: self.silence_frames,
+            last_speech_duration_ms: self.last_speech_duration_ms,
+            current_silence_ms: self.current_silence_ms,
+            speech_ratio: if total_frames == 0 {
+                0.0
+            } else {
+                self.speech_frames as f32 / total_frames as f32
+            },
+        }
+    }
 }
 
 impl VoiceActivityDetector {
@@ -45,13 +113,47 @@ impl VoiceActivityDetector {
             silence_duration: Duration::from_millis(silence_duration_ms),
             last_speech_time: None,
             is_speaking: false,
+            long_silence_ms: None,
+            silence_elapsed_ms: 0,
+            long_silence_reported: false,
+            noise_floor: None,
+            min_rms: 0.0,
+            counters: VadCounters::default(),
         })
     }
 
+    /// Emit `VadEvent::LongSilence` once silence has lasted this long.
+    /// `None` disables the event.
+    pub fn set_long_silence_ms(&mut self, long_silence_ms: Option<u64>) {
+        self.long_silence_ms = long_silence_ms;
+        self.silence_elapsed_ms = 0;
+        self.long_silence_reported = false;
+    }
+
+    /// Restart the `LongSilence` countdown, e.g. on activity the
+    /// microphone can't hear
+    pub fn reset_silence(&mut self) {
+        self.silence_elapsed_ms = 0;
+        self.long_silence_reported = false;
+    }
+
     pub fn samples_per_frame(&self) -> usize {
         (self.sample_rate as u32 * self.frame_duration_ms / 1000) as usize
     }
 
+    /// Measure the noise floor of ambient audio and gate out frames that
+    /// aren't louder than it. Returns the measured floor (RMS).
+    pub fn calibrate(&mut self, ambient: &[i16]) -> f32 {
+        let noise_floor = noise_floor_rms(ambient, self.samples_per_frame());
+        self.noise_floor = Some(noise_floor);
+        self.min_rms = noise_floor * NOISE_GATE_MARGIN;
+        noise_floor
+    }
+
+    pub fn noise_floor(&self) -> Option<f32> {
+        self.noise_floor
+    }
+
     pub fn process_frame(&mut self, samples: &[i16]) -> Result<VadEvent> {
         if samples.len() != self.samples_per_frame() {
             return Err(anyhow::anyhow!(
@@ -63,12 +165,16 @@ impl VoiceActivityDetector {
         }
 
         let has_speech = self.vad.is_voice_segment(samples)
-            .context("VAD processing failed")?;
+            .context("VAD processing failed")?
+            && frame_rms(samples) >= self.min_rms;
+        self.counters.record(has_speech, self.frame_duration_ms as u64);
 
         let now = Instant::now();
 
         if has_speech {
             self.last_speech_time = Some(now);
+            self.silence_elapsed_ms = 0;
+            self.long_silence_reported = false;
             
             if !self.is_speaking {
                 self.is_speaking = true;
@@ -86,6 +192,16 @@ impl VoiceActivityDetector {
                     }
                 }
             }
+
+            // Count silence in frames rather than wall-clock time so the
+            // threshold tracks the audio actually processed
+            self.silence_elapsed_ms += self.frame_duration_ms as u64;
+            if let Some(long_silence_ms) = self.long_silence_ms {
+                if !self.long_silence_reported && self.silence_elapsed_ms >= long_silence_ms {
+                    self.long_silence_reported = true;
+                    return Ok(VadEvent::LongSilence);
+                }
+            }
             
             Ok(VadEvent::Silence)
         }
@@ -94,11 +210,36 @@ impl VoiceActivityDetector {
     pub fn reset(&mut self) {
         self.is_speaking = false;
         self.last_speech_time = None;
+        self.silence_elapsed_ms = 0;
+        self.long_silence_reported = false;
     }
 
     pub fn is_speaking(&self) -> bool {
         self.is_speaking
     }
+
+    pub fn get_stats(&self) -> VadStats {
+        self.counters.stats()
+    }
+}
+
+/// Root-mean-square amplitude of a frame
+pub fn frame_rms(samples: &[i16]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / samples.len() as f64).sqrt() as f32
+}
+
+/// Median per-frame RMS, so brief bumps during calibration don't skew the floor
+pub fn noise_floor_rms(samples: &[i16], frame_len: usize) -> f32 {
+    let mut levels: Vec<f32> = samples.chunks_exact(frame_len).map(frame_rms).collect();
+    if levels.is_empty() {
+        return frame_rms(samples);
+    }
+    levels.sort_by(|a, b| a.total_cmp(b));
+    levels[levels.len() / 2]
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -107,6 +248,8 @@ pub enum VadEvent {
     Speaking,
     Silence,
     SpeechEnd,
+    /// Silence has been continuous for longer than the configured threshold
+    LongSilence,
 }
 
 #[cfg(test)]
@@ -133,4 +276,76 @@ mod tests {
         let vad = VoiceActivityDetector::new(16000, 5, 1000);
         assert!(vad.is_err());
     }
+
+    #[test]
+    fn test_long_silence() {
+        let mut vad = VoiceActivityDetector::new(16000, 2, 1000).unwrap();
+        vad.set_long_silence_ms(Some(1000));
+
+        let frame = vec![0i16; vad.samples_per_frame()];
+        let frames = 2000 / 30; // 2 seconds of 30ms frames
+        let events: Vec<VadEvent> = (0..frames)
+            .map(|_| vad.process_frame(&frame).unwrap())
+            .collect();
+
+        let long_silences = events.iter().filter(|e| **e == VadEvent::LongSilence).count();
+        assert_eq!(long_silences, 1);
+        assert_ne!(events[0], VadEvent::LongSilence);
+    }
+
+    #[test]
+    fn test_reset_silence_restarts_countdown() {
+        let mut vad = VoiceActivityDetector::new(16000, 2, 1000).unwrap();
+        vad.set_long_silence_ms(Some(1000));
+
+        let frame = vec![0i16; vad.samples_per_frame()];
+        let mut process = |vad: &mut VoiceActivityDetector, frames: usize| {
+            (0..frames).filter(|_| vad.process_frame(&frame).unwrap() == VadEvent::LongSilence).count()
+        };
+
+        assert_eq!(process(&mut vad, 600 / 30), 0);
+        vad.reset_silence();
+        assert_eq!(process(&mut vad, 600 / 30), 0);
+        assert_eq!(process(&mut vad, 600 / 30), 1);
+    }
+
+    #[test]
+    fn test_stats_speech_ratio() {
+        let mut counters = VadCounters::default();
+        for _ in 0..100 {
+            counters.record(false, 30);
+        }
+        for _ in 0..50 {
+            counters.record(true, 30);
+        }
+
+        let stats = counters.stats();
+        assert_eq!(stats.total_frames, 150);
+        assert_eq!(stats.speech_frames, 50);
+        assert_eq!(stats.silence_frames, 100);
+        assert!((stats.speech_ratio - 0.333).abs() < 0.001);
+        assert_eq!(stats.current_silence_ms, 0);
+
+        counters.record(false, 30);
+        let stats = counters.stats();
+        assert_eq!(stats.last_speech_duration_ms, 1500);
+        assert_eq!(stats.current_silence_ms, 30);
+    }
+
+    #[test]
+    fn test_calibration_noise_floor() {
+        let mut vad = VoiceActivityDetector::new(16000, 2, 1000).unwrap();
+        let frame_len = vad.samples_per_frame();
+
+        // 2 seconds of ±100 noise with one loud 30ms bump
+        let mut ambient: Vec<i16> = (0..32000).map(|i| if i % 2 == 0 { 100 } else { -100 }).collect();
+        for s in &mut ambient[frame_len..frame_len * 2] {
+            *s *= 50;
+        }
+
+        let floor = vad.calibrate(&ambient);
+        assert!((floor - 100.0).abs() < 0.01);
+        assert_eq!(vad.noise_floor(), Some(floor));
+        assert!((vad.min_rms - 200.0).abs() < 0.01);
+    }
 }
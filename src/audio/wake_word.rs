@@ -0,0 +1,108 @@
+// Blipply Assistant - Audio Pipeline
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::{anyhow, Result};
+use rustpotter::{Rustpotter, RustpotterConfig, SampleFormat};
+use std::path::Path;
+use tracing::debug;
+
+/// Frames quieter than this RMS can't contain the wake word and are never
+/// scored, which keeps silence and room noise from triggering it
+const SILENCE_RMS: f32 = 0.005;
+
+/// Listens continuously for a wake word such as "Hey Blipply"
+pub struct WakeWordDetector {
+    rustpotter: Rustpotter,
+    threshold: f32,
+    samples_per_frame: usize,
+    /// Captured audio not yet making up a whole frame
+    pending: Vec<f32>,
+}
+
+impl WakeWordDetector {
+    /// Load a rustpotter wake word model (`.rpw`)
+    pub fn new(model_path: &Path, sample_rate: u32, threshold: f32) -> Result<Self> {
+        debug!("Loading wake word model from {:?}", model_path);
+
+        let mut config = RustpotterConfig::default();
+        config.fmt.sample_rate = sample_rate as usize;
+        config.fmt.sample_format = SampleFormat::F32;
+        config.fmt.channels = 1;
+        config.detector.threshold = threshold;
+
+        let mut rustpotter = Rustpotter::new(&config)
+            .map_err(|e| anyhow!("Failed to start wake word detector: {}", e))?;
+        rustpotter
+            .add_wakeword_from_file("wake_word", &model_path.to_string_lossy())
+            .map_err(|e| anyhow!("Failed to load wake word model {:?}: {}", model_path, e))?;
+
+        let samples_per_frame = rustpotter.get_samples_per_frame();
+        Ok(Self {
+            rustpotter,
+            threshold,
+            samples_per_frame,
+            pending: Vec::with_capacity(samples_per_frame),
+        })
+    }
+
+    /// Feed captured audio. Returns the detection score when the wake word
+    /// was heard with at least the configured confidence.
+    pub fn process(&mut self, samples: &[f32]) -> Option<f32> {
+        self.pending.extend_from_slice(samples);
+
+        let mut detected = None;
+        while self.pending.len() >= self.samples_per_frame {
+            let frame: Vec<f32> = self.pending.drain(..self.samples_per_frame).collect();
+            if is_silent(&frame) {
+                continue;
+            }
+
+            if let Some(detection) = self.rustpotter.process_samples(frame) {
+                debug!("Wake word scored {:.2}", detection.score);
+                if detection.score >= self.threshold {
+                    detected = Some(detection.score);
+                }
+            }
+        }
+        detected
+    }
+}
+
+fn is_silent(frame: &[f32]) -> bool {
+    let energy: f32 = frame.iter().map(|s| s * s).sum();
+    (energy / frame.len().max(1) as f32).sqrt() < SILENCE_RMS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silence_never_scored() {
+        // A minute of digital silence and faint noise, as a quiet room would give
+        let mut seed = 1u32;
+        let frames: Vec<Vec<f32>> = (0..3750)
+            .map(|i| {
+                (0..256)
+                    .map(|_| {
+                        if i % 2 == 0 {
+                            return 0.0;
+                        }
+                        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                        ((seed >> 16) as f32 / 32768.0 - 1.0) * 0.002
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let false_positives = frames.iter().filter(|frame| !is_silent(frame)).count();
+        assert_eq!(false_positives, 0);
+    }
+
+    #[test]
+    fn test_speech_level_is_not_silent() {
+        let tone: Vec<f32> = (0..256).map(|i| (i as f32 * 0.1).sin() * 0.1).collect();
+        assert!(!is_silent(&tone));
+    }
+}
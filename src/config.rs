@@ -3,9 +3,18 @@
 // Licensed under the MIT License
 
 use anyhow::{Result, Context};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::hotkeys::Hotkey;
+use crate::profiles::{ProfileRule, VoiceProfile};
+use crate::model_registry::LocalModelRegistry;
+use crate::presets::GenerationPreset;
+use crate::voice_commands::{self, VoiceCommand};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -13,24 +22,394 @@ pub struct Config {
     pub audio: AudioConfig,
     pub pipewire: PipewireConfig,
     pub profiles: HashMap<String, ProfileConfig>,
+    /// User-defined generation presets, added to the built-in ones
+    #[serde(default)]
+    pub presets: HashMap<String, GenerationPreset>,
+    /// Models found in the models directory, scanned on the first lookup
+    #[serde(skip)]
+    model_registry: Arc<Mutex<Option<(PathBuf, Arc<LocalModelRegistry>)>>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneralConfig {
     pub ollama_url: String,
-    pub hotkey: String,
+    /// Ollama hosts tried in order on connection failure; `ollama_url` is tried last
+    #[serde(default)]
+    pub ollama_urls: Vec<String>,
+    #[serde(deserialize_with = "crate::hotkeys::deserialize_or_default")]
+    pub hotkey: Hotkey,
     pub first_run_complete: bool,
     pub active_profile: String,
+    /// Hide the window after this many seconds without speech
+    #[serde(default)]
+    pub auto_hide_after_seconds: Option<u64>,
+    /// Append every exchange to this JSON Lines file; relative paths are
+    /// inside the data directory
+    #[serde(default)]
+    pub conversation_log_path: Option<String>,
+    /// Where models and other data are kept instead of ~/.local/share/blipply-assistant.
+    /// `~` and environment variables are expanded.
+    #[serde(default)]
+    pub data_dir: Option<String>,
+    /// Set when `data_dir` couldn't be written at load, e.g. an unmounted
+    /// drive. The default is used meanwhile; the setting itself is kept.
+    #[serde(skip)]
+    pub data_dir_unusable: bool,
+    /// Append raw Ollama requests and responses to this file
+    #[serde(default)]
+    pub debug_transcript: Option<PathBuf>,
+    /// Replace system prompts with "[redacted]" in the debug transcript
+    #[serde(default)]
+    pub redact_system_prompt: bool,
+    /// Messages kept in the chat window; the oldest are dropped from the
+    /// view (not the history) past this or `chat_scrollback_limit`,
+    /// whichever comes first. 0 keeps everything
+    #[serde(default = "default_display_history_limit")]
+    pub display_history_limit: usize,
+    /// Most recent messages sent to the model as context
+    #[serde(default = "default_context_history_limit")]
+    pub context_history_limit: usize,
+    /// Announce new messages to screen readers; auto-detected if unset
+    #[serde(default)]
+    pub accessibility_announcements: Option<bool>,
+    /// Switch profiles automatically by focused window or time of day; first match wins
+    #[serde(default)]
+    pub profile_rules: Vec<ProfileRule>,
+    /// Clipboard text longer than this many characters is truncated before it is sent
+    #[serde(default = "default_clipboard_max_chars")]
+    pub clipboard_max_chars: usize,
+    /// Swipe to hide or open settings and pinch to resize on touch screens
+    #[serde(default = "default_swipe_gestures_enabled")]
+    pub swipe_gestures_enabled: bool,
+    /// Keep the window on screen with a one-line status; toggling only focuses it
+    #[serde(default)]
+    pub persistent_hud: bool,
+    /// Save each conversation under sessions/ in the data directory for the history browser
+    #[serde(default = "default_save_sessions")]
+    pub save_sessions: bool,
+    /// Ask the model to title each conversation instead of using its first words
+    #[serde(default)]
+    pub auto_title: bool,
+    /// Spoken once audio is up; `{greeting}` and `{profile_name}` are filled in
+    #[serde(default)]
+    pub startup_message: Option<String>,
+    /// Show a running word and token count while a reply is generated
+    #[serde(default = "default_show_generation_progress")]
+    pub show_generation_progress: bool,
+    /// Milliseconds between streamed chunks shown in the chat for a typewriter
+    /// effect; shown as fast as they arrive if unset
+    #[serde(default)]
+    pub stream_pacing_ms: Option<u32>,
+    /// Phrases that control the app instead of being sent to the model
+    #[serde(default = "voice_commands::default_commands")]
+    pub voice_commands: Vec<VoiceCommand>,
+    /// Whether the chat history panel is expanded; remembered between runs
+    #[serde(default = "default_chat_panel_open")]
+    pub chat_panel_open: bool,
+    /// Lines kept in the chat view; the oldest messages are dropped from the
+    /// view (not the history) past this. 0 keeps everything
+    #[serde(default = "default_chat_scrollback_limit")]
+    pub chat_scrollback_limit: usize,
+    /// Look for a newer release when the daemon starts
+    #[serde(default)]
+    pub update_check_on_startup: bool,
+    /// GitHub "latest release" API URL checked for updates; the project's
+    /// own releases if unset
+    #[serde(default)]
+    pub update_url: Option<String>,
+}
+
+fn default_display_history_limit() -> usize {
+    500
+}
+
+fn default_context_history_limit() -> usize {
+    20
+}
+
+fn default_clipboard_max_chars() -> usize {
+    8000
+}
+
+fn default_swipe_gestures_enabled() -> bool {
+    true
+}
+
+fn default_chat_panel_open() -> bool {
+    true
+}
+
+fn default_chat_scrollback_limit() -> usize {
+    5000
+}
+
+fn default_save_sessions() -> bool {
+    true
+}
+
+fn default_show_generation_progress() -> bool {
+    true
+}
+
+pub(crate) fn default_tts_volume() -> f32 {
+    1.0
+}
+
+pub(crate) fn default_assistant_name() -> String {
+    "Blipply".to_string()
+}
+
+fn default_tts_peak_target() -> f32 {
+    0.9
+}
+
+fn default_wake_word_model() -> String {
+    "hey-blipply.rpw".to_string()
+}
+
+fn default_wake_word_threshold() -> f32 {
+    0.5
+}
+
+/// Accepted `audio.input_gain` values
+const INPUT_GAIN_RANGE: std::ops::RangeInclusive<f32> = 0.1..=20.0;
+
+fn default_stt_queue_slots() -> usize {
+    4
+}
+
+fn default_ducking_level() -> f32 {
+    0.3
+}
+
+fn default_input_gain() -> f32 {
+    1.0
+}
+
+fn default_stt_max_no_speech_prob() -> f32 {
+    0.6
+}
+
+fn default_stt_min_avg_logprob() -> f32 {
+    -1.0
+}
+
+fn default_dedup_window_ms() -> u64 {
+    3_000
+}
+
+fn default_max_utterance_ms() -> u64 {
+    30_000
+}
+
+fn default_tts_tail_pad_ms() -> u64 {
+    100
+}
+
+fn default_tts_trim_leading_silence() -> bool {
+    true
+}
+
+fn default_tts_synthesis_queue_size() -> usize {
+    2
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioConfig {
     pub stt_model: String,
+    /// Refuse to start without a working microphone instead of falling back
+    /// to text-only chat
+    #[serde(default)]
+    pub require_audio: bool,
     pub vad_enabled: bool,
     pub vad_aggressiveness: u8,
     pub sample_rate: u32,
+    /// Deprecated: set a profile's `interaction_mode` to "voice_ptt" instead.
+    /// Profiles without an `interaction_mode` use push-to-talk when this is set.
+    #[serde(default)]
     pub push_to_talk: bool,
     pub silence_duration_ms: u64,
+    /// Microphone boost applied before voice detection and transcription
+    #[serde(default = "default_input_gain")]
+    pub input_gain: f32,
+    /// Utterances longer than this are cut off and transcribed, so constant
+    /// background noise can't keep the VAD listening forever
+    #[serde(default = "default_max_utterance_ms")]
+    pub max_utterance_ms: u64,
+    /// Drop a transcript that repeats the previous one within this many
+    /// milliseconds; 0 turns the check off
+    #[serde(default = "default_dedup_window_ms")]
+    pub dedup_window_ms: u64,
+    /// Fall back to espeak-ng when Piper synthesis fails
+    #[serde(default)]
+    pub tts_system_fallback: bool,
+    /// Speech synthesizer; Piper falls back to espeak-ng if its voice fails to load
+    #[serde(default)]
+    pub tts_backend: TtsBackend,
+    /// Scale Piper output so every voice peaks at `tts_peak_target`
+    #[serde(default)]
+    pub tts_normalize_audio: bool,
+    #[serde(default = "default_tts_peak_target")]
+    pub tts_peak_target: f32,
+    /// Playback time added after each sentence so the end isn't cut off
+    #[serde(default = "default_tts_tail_pad_ms")]
+    pub tts_tail_pad_ms: u64,
+    /// Skip the silence some Piper voices put before each sentence
+    #[serde(default = "default_tts_trim_leading_silence")]
+    pub tts_trim_leading_silence: bool,
+    /// Sentences synthesized ahead while an earlier one plays
+    #[serde(default = "default_tts_synthesis_queue_size")]
+    pub tts_synthesis_queue_size: usize,
+    /// Measure ambient noise on startup and gate the VAD above it
+    #[serde(default)]
+    pub vad_auto_calibrate: bool,
+    /// Redact profanity in transcripts before they reach the LLM
+    #[serde(default)]
+    pub transcript_filter_enabled: bool,
+    /// Drop transcripts containing profanity instead of redacting them
+    #[serde(default)]
+    pub transcript_filter_block: bool,
+    /// Show a live spectrogram of microphone input
+    #[serde(default)]
+    pub show_spectrogram: bool,
+    /// Whisper decoding strategy; beam search is more accurate but slower than greedy
+    #[serde(default, alias = "stt_sampling")]
+    pub stt_strategy: SttStrategy,
+    /// Spoken language code such as "en" or "de"; detected per utterance if
+    /// unset. English-only (`.en`) models always transcribe English.
+    #[serde(default)]
+    pub stt_language: Option<String>,
+    /// LoRA weight deltas merged into the Whisper model's attention layers,
+    /// to adapt it to an accent or jargon. Needs an f16 or f32 model.
+    #[serde(default)]
+    pub stt_lora_path: Option<PathBuf>,
+    /// Short list of names and jargon that biases Whisper towards them
+    #[serde(default)]
+    pub stt_initial_prompt: Option<String>,
+    /// Discard transcripts with mean speech confidence below this (0.0–1.0)
+    #[serde(default)]
+    pub stt_min_confidence: f32,
+    /// Initial Whisper sampling temperature; 0.0 is deterministic
+    #[serde(default)]
+    pub stt_temperature: f32,
+    /// Retry unsure decodes at higher temperatures and drop segments that
+    /// look like noise. Slower, but hallucinates less on noisy input.
+    #[serde(default)]
+    pub stt_confidence_filter: bool,
+    /// With the confidence filter, segments more likely than this to be
+    /// silence are dropped...
+    #[serde(default = "default_stt_max_no_speech_prob")]
+    pub stt_max_no_speech_prob: f32,
+    /// ...when their mean token log probability is also below this
+    #[serde(default = "default_stt_min_avg_logprob")]
+    pub stt_min_avg_logprob: f32,
+    /// Utterances that may wait while Whisper transcribes an earlier one;
+    /// more are dropped. Transcriptions always run one at a time.
+    #[serde(default = "default_stt_queue_slots")]
+    pub stt_queue_slots: usize,
+    /// Resampler quality; lower is cheaper on low-power devices
+    #[serde(default)]
+    pub resample_quality: ResampleQuality,
+    /// Show the window when the wake word is heard
+    #[serde(default)]
+    pub wake_word_enabled: bool,
+    /// rustpotter `.rpw` model; relative paths are under models/wake_word in the data directory
+    #[serde(default = "default_wake_word_model")]
+    pub wake_word_model: String,
+    /// Minimum detection score (0.0–1.0)
+    #[serde(default = "default_wake_word_threshold")]
+    pub wake_word_threshold: f32,
+    /// Lower other apps' audio while the assistant speaks (needs pactl)
+    #[serde(default)]
+    pub audio_ducking: bool,
+    /// Fraction of their volume other apps are lowered to
+    #[serde(default = "default_ducking_level")]
+    pub ducking_level: f32,
+    /// Also duck while the user is talking, so music doesn't reach the mic
+    #[serde(default)]
+    pub duck_while_listening: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SttStrategy {
+    Greedy { best_of: usize },
+    BeamSearch { beam_size: usize, patience: f32 },
+}
+
+impl Default for SttStrategy {
+    fn default() -> Self {
+        SttStrategy::Greedy { best_of: 1 }
+    }
+}
+
+/// How a profile takes questions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InteractionMode {
+    /// Listen all the time and send once the speaker goes quiet
+    #[default]
+    VoiceAuto,
+    /// Listen only while the talk button is held
+    VoicePtt,
+    /// Typing only; the microphone is never opened
+    TextOnly,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TtsBackend {
+    #[default]
+    Piper,
+    Espeak,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResampleQuality {
+    Fast,
+    Balanced,
+    #[default]
+    HiFi,
+}
+
+impl ResampleQuality {
+    /// Sinc interpolation `(sinc_len, oversampling_factor)`
+    pub fn sinc_params(self) -> (usize, usize) {
+        match self {
+            ResampleQuality::Fast => (32, 64),
+            ResampleQuality::Balanced => (128, 128),
+            ResampleQuality::HiFi => (256, 256),
+        }
+    }
+}
+
+/// Output format requested from the model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    #[default]
+    Plain,
+    Json,
+    /// JSON schema the response must match (requires Ollama 0.5+)
+    JsonSchema(String),
+}
+
+impl ResponseFormat {
+    /// Value for the `format` field of an Ollama chat request
+    pub fn request_format(&self) -> Result<Option<serde_json::Value>> {
+        match self {
+            ResponseFormat::Plain => Ok(None),
+            ResponseFormat::Json => Ok(Some(serde_json::Value::String("json".to_string()))),
+            ResponseFormat::JsonSchema(schema) => serde_json::from_str(schema)
+                .map(Some)
+                .context("Invalid response_format JSON schema"),
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        !matches!(self, ResponseFormat::Plain)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,9 +426,72 @@ pub struct ProfileConfig {
     pub personality: String,
     pub avatar_path: String,
     pub avatar_size_px: u32,
+    /// Voice with automatic sending, push-to-talk, or text only. Unset
+    /// follows the old `audio.push_to_talk` flag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interaction_mode: Option<InteractionMode>,
+    /// Window size while this profile is active; the default size if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_height: Option<u32>,
     pub voice_model: String,
     pub tts_speed: f32,
     pub tts_enabled: bool,
+    /// Stop sequences that end the response early, e.g. `["User:"]` to keep
+    /// the model from writing the user's turn
+    #[serde(default, alias = "llm_stop_sequences")]
+    pub stop: Vec<String>,
+    /// Maximum number of tokens to generate
+    #[serde(default)]
+    pub num_predict: Option<i32>,
+    /// Shown (and spoken) the first time the window opens; supports {time_of_day}
+    #[serde(default)]
+    pub greeting: Option<String>,
+    /// Ask the model for plain text, any JSON, or JSON matching a schema
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+    /// Sampling temperature; the preset's or the Ollama client default if unset
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Named sampling preset, e.g. "precise", "balanced" or "creative".
+    /// `temperature` still wins when set.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Custom system prompt used instead of the personality preset
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Whisper vocabulary prompt used instead of audio.stt_initial_prompt
+    #[serde(default)]
+    pub stt_initial_prompt: Option<String>,
+    /// Language every answer is written in, e.g. "French", whatever the
+    /// user speaks
+    #[serde(default)]
+    pub response_language: Option<String>,
+    /// Answer in the language Whisper heard, when audio.stt_language is unset
+    #[serde(default)]
+    pub auto_detect_language: bool,
+    /// Spoken commands checked before the global ones
+    #[serde(default)]
+    pub voice_commands: Vec<VoiceCommand>,
+    /// Playback volume, 1.0 is unchanged
+    #[serde(default = "default_tts_volume")]
+    pub tts_volume: f32,
+    /// Send attached images even if the model name isn't a known vision model
+    #[serde(default)]
+    pub vision_enabled: bool,
+    /// Template for assistant messages in the window, e.g. "[{timestamp}] {role}: {content}"
+    #[serde(default)]
+    pub message_template: Option<String>,
+    /// Speaker of a multi-speaker Piper voice; the first one when unset
+    #[serde(default)]
+    pub tts_speaker_id: Option<u32>,
+    /// What the assistant calls itself, in prompts and chat labels
+    #[serde(default = "default_assistant_name")]
+    pub assistant_name: String,
+    /// The two profiles this one was merged from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derived_from: Option<(String, String)>,
 }
 
 impl Default for Config {
@@ -63,26 +505,102 @@ impl Default for Config {
                 personality: "helpful".to_string(),
                 avatar_path: "/usr/share/blipply/clippy.gif".to_string(),
                 avatar_size_px: 96,
+                interaction_mode: None,
+                window_width: None,
+                window_height: None,
                 voice_model: "en_US-lessac-medium".to_string(),
                 tts_speed: 1.0,
                 tts_enabled: true,
+                stop: Vec::new(),
+                num_predict: None,
+                greeting: None,
+                response_format: ResponseFormat::Plain,
+                temperature: None,
+                preset: None,
+                system_prompt: None,
+                stt_initial_prompt: None,
+                response_language: None,
+                auto_detect_language: false,
+                voice_commands: Vec::new(),
+                tts_volume: 1.0,
+                vision_enabled: false,
+                message_template: None,
+                tts_speaker_id: None,
+                assistant_name: default_assistant_name(),
+                derived_from: None,
             },
         );
 
         Self {
             general: GeneralConfig {
                 ollama_url: "http://127.0.0.1:11434".to_string(),
-                hotkey: "Super+Shift+A".to_string(),
+                ollama_urls: Vec::new(),
+                hotkey: Hotkey::default(),
                 first_run_complete: false,
                 active_profile: "default".to_string(),
+                auto_hide_after_seconds: None,
+                conversation_log_path: None,
+                data_dir: None,
+                data_dir_unusable: false,
+                debug_transcript: None,
+                redact_system_prompt: false,
+                display_history_limit: default_display_history_limit(),
+                context_history_limit: default_context_history_limit(),
+                accessibility_announcements: None,
+                profile_rules: Vec::new(),
+                clipboard_max_chars: default_clipboard_max_chars(),
+                swipe_gestures_enabled: default_swipe_gestures_enabled(),
+                persistent_hud: false,
+                save_sessions: default_save_sessions(),
+                auto_title: false,
+                startup_message: None,
+                show_generation_progress: default_show_generation_progress(),
+                stream_pacing_ms: None,
+                voice_commands: voice_commands::default_commands(),
+                chat_panel_open: default_chat_panel_open(),
+                chat_scrollback_limit: default_chat_scrollback_limit(),
+                update_check_on_startup: false,
+                update_url: None,
             },
             audio: AudioConfig {
                 stt_model: "base.en".to_string(),
+                require_audio: false,
                 vad_enabled: true,
                 vad_aggressiveness: 2,
                 sample_rate: 16000,
                 push_to_talk: false,
                 silence_duration_ms: 1000,
+                input_gain: default_input_gain(),
+                max_utterance_ms: default_max_utterance_ms(),
+                dedup_window_ms: default_dedup_window_ms(),
+                tts_system_fallback: false,
+                tts_backend: TtsBackend::default(),
+                tts_normalize_audio: false,
+                tts_peak_target: default_tts_peak_target(),
+                tts_tail_pad_ms: default_tts_tail_pad_ms(),
+                tts_trim_leading_silence: default_tts_trim_leading_silence(),
+                tts_synthesis_queue_size: default_tts_synthesis_queue_size(),
+                vad_auto_calibrate: false,
+                transcript_filter_enabled: false,
+                transcript_filter_block: false,
+                show_spectrogram: false,
+                stt_strategy: SttStrategy::default(),
+                stt_min_confidence: 0.0,
+                stt_temperature: 0.0,
+                stt_confidence_filter: false,
+                stt_max_no_speech_prob: default_stt_max_no_speech_prob(),
+                stt_min_avg_logprob: default_stt_min_avg_logprob(),
+                stt_language: None,
+                stt_lora_path: None,
+                stt_initial_prompt: None,
+                stt_queue_slots: default_stt_queue_slots(),
+                resample_quality: ResampleQuality::default(),
+                wake_word_enabled: false,
+                wake_word_model: default_wake_word_model(),
+                wake_word_threshold: default_wake_word_threshold(),
+                audio_ducking: false,
+                ducking_level: default_ducking_level(),
+                duck_while_listening: false,
             },
             pipewire: PipewireConfig {
                 input_device: "auto".to_string(),
@@ -90,6 +608,8 @@ impl Default for Config {
                 buffer_size: 480,
             },
             profiles,
+            presets: HashMap::new(),
+            model_registry: Arc::default(),
         }
     }
 }
@@ -102,26 +622,56 @@ impl Config {
     }
 
     pub fn load() -> Result<Self> {
-        let path = Self::config_path()?;
-        
+        Self::load_from(&Self::config_path()?)
+    }
+
+    /// Read the config at `path`, writing the defaults there if there is none
+    pub fn load_from(path: &Path) -> Result<Self> {
         if !path.exists() {
             let config = Self::default();
-            config.save()?;
+            config.save_to(path)?;
             return Ok(config);
         }
 
-        let contents = std::fs::read_to_string(&path)
+        let contents = std::fs::read_to_string(path)
             .context("Failed to read config file")?;
         
-        let config: Config = toml::from_str(&contents)
+        let mut config: Config = toml::from_str(&contents)
             .context("Failed to parse config file")?;
+        config.check_settings();
         
         Ok(config)
     }
 
+    /// Warn about settings that can't be used as written. The fallbacks are
+    /// applied where the settings are read, so saving keeps what the user wrote.
+    fn check_settings(&mut self) {
+        if let Some(dir) = &self.general.data_dir {
+            let path = expand_path(dir);
+            if let Err(e) = check_writable_dir(&path) {
+                warn!("Data directory {:?} is not usable, using the default: {:#}", path, e);
+                self.general.data_dir_unusable = true;
+            }
+        }
+
+        if !INPUT_GAIN_RANGE.contains(&self.audio.input_gain) {
+            warn!(
+                "audio.input_gain {} is outside {:?}, using {}",
+                self.audio.input_gain, INPUT_GAIN_RANGE, self.input_gain()
+            );
+        }
+    }
+
+    /// `audio.input_gain`, limited to the accepted range
+    pub fn input_gain(&self) -> f32 {
+        self.audio.input_gain.clamp(*INPUT_GAIN_RANGE.start(), *INPUT_GAIN_RANGE.end())
+    }
+
     pub fn save(&self) -> Result<()> {
-        let path = Self::config_path()?;
-        
+        self.save_to(&Self::config_path()?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
@@ -129,33 +679,171 @@ impl Config {
         let contents = toml::to_string_pretty(self)
             .context("Failed to serialize config")?;
         
-        std::fs::write(&path, contents)
+        std::fs::write(path, contents)
             .context("Failed to write config file")?;
         
         Ok(())
     }
 
+    /// All configured Ollama hosts in failover order
+    pub fn ollama_hosts(&self) -> Vec<String> {
+        let mut hosts = self.general.ollama_urls.clone();
+        if !hosts.contains(&self.general.ollama_url) {
+            hosts.push(self.general.ollama_url.clone());
+        }
+        hosts
+    }
+
     pub fn active_profile(&self) -> Result<&ProfileConfig> {
         self.profiles
             .get(&self.general.active_profile)
             .context("Active profile not found")
     }
 
-    pub fn data_dir() -> Result<PathBuf> {
+    /// The configured data directory, or the XDG default if it isn't set or
+    /// wasn't usable at load
+    pub fn data_dir(&self) -> Result<PathBuf> {
+        if let Some(dir) = self.general.data_dir.as_ref().filter(|_| !self.general.data_dir_unusable) {
+            return Ok(expand_path(dir));
+        }
+
         let data_dir = dirs::data_local_dir()
             .context("Could not determine data directory")?;
         Ok(data_dir.join("blipply-assistant"))
     }
 
+    /// Conversation log location, resolving relative paths against the data directory
+    pub fn conversation_log_path(&self) -> Result<Option<PathBuf>> {
+        let Some(path) = &self.general.conversation_log_path else {
+            return Ok(None);
+        };
+
+        let path = expand_path(path);
+        if path.is_absolute() {
+            Ok(Some(path))
+        } else {
+            Ok(Some(self.data_dir()?.join(path)))
+        }
+    }
+
+    /// Saved conversations, one file per session
+    pub fn sessions_dir(&self) -> Result<PathBuf> {
+        Ok(self.data_dir()?.join("sessions"))
+    }
+
+    /// Personality prompt files, `<personality>.md`
+    pub fn prompts_dir() -> Result<PathBuf> {
+        let config_path = Self::config_path()?;
+        Ok(config_path.with_file_name("prompts"))
+    }
+
+    /// Unix socket used for daemon IPC
+    pub fn socket_path() -> PathBuf {
+        dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("blipply-assistant.sock")
+    }
+
+    /// Volumes of other apps' audio streams while they are ducked
+    pub fn ducking_state_path() -> PathBuf {
+        dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("blipply-assistant-ducked")
+    }
+
+    pub fn models_dir(&self) -> Result<PathBuf> {
+        Ok(self.data_dir()?.join("models"))
+    }
+
+    /// The installed Whisper model matching `stt_model`, or where it is
+    /// expected (models/whisper/<name>.bin) if none is found
     pub fn whisper_model_path(&self) -> Result<PathBuf> {
-        Ok(Self::data_dir()?.join("models").join("whisper").join(format!("{}.bin", self.audio.stt_model)))
+        let (models_dir, registry) = self.model_registry()?;
+        Ok(registry.find_whisper(&self.audio.stt_model)
+            .unwrap_or_else(|| models_dir.join("whisper").join(format!("{}.bin", self.audio.stt_model))))
+    }
+
+    /// How `profile` takes questions, falling back to `audio.push_to_talk`
+    /// for profiles written before `interaction_mode` existed
+    pub fn interaction_mode(&self, profile: &VoiceProfile) -> InteractionMode {
+        profile.interaction_mode.unwrap_or(if self.audio.push_to_talk {
+            InteractionMode::VoicePtt
+        } else {
+            InteractionMode::VoiceAuto
+        })
+    }
+
+    /// The Whisper model to load: the installed one, with `stt_lora_path`
+    /// merged in if set
+    pub fn whisper_model_path_with_lora(&self) -> Result<PathBuf> {
+        let model_path = self.whisper_model_path()?;
+        match &self.audio.stt_lora_path {
+            Some(lora) => crate::audio::stt::apply_lora(&model_path, &expand_path(&lora.to_string_lossy())),
+            None => Ok(model_path),
+        }
     }
 
+    pub fn wake_word_model_path(&self) -> Result<PathBuf> {
+        let path = expand_path(&self.audio.wake_word_model);
+        if path.is_absolute() {
+            Ok(path)
+        } else {
+            Ok(self.models_dir()?.join("wake_word").join(path))
+        }
+    }
+
+    /// The installed Piper voice matching `voice`, or where it is expected
+    /// (models/piper/<voice>.onnx) if none is found
     pub fn piper_voice_path(&self, voice: &str) -> Result<PathBuf> {
-        Ok(Self::data_dir()?.join("models").join("piper").join(format!("{}.onnx", voice)))
+        let (models_dir, registry) = self.model_registry()?;
+        Ok(registry.find_piper(voice)
+            .unwrap_or_else(|| models_dir.join("piper").join(format!("{}.onnx", voice))))
+    }
+
+    /// The models directory and the models installed in it. The directory
+    /// is scanned once; reloading the config scans it again.
+    fn model_registry(&self) -> Result<(PathBuf, Arc<LocalModelRegistry>)> {
+        let models_dir = self.models_dir()?;
+        let mut cached = self.model_registry.lock();
+        if let Some((dir, registry)) = cached.as_ref() {
+            if *dir == models_dir {
+                return Ok((models_dir, registry.clone()));
+            }
+        }
+
+        let registry = Arc::new(LocalModelRegistry::scan(&models_dir)?);
+        *cached = Some((models_dir.clone(), registry.clone()));
+        Ok((models_dir, registry))
     }
 }
 
+/// Expand a leading `~` and `$VAR` / `${VAR}` references. Unset variables are left as written.
+fn expand_path(path: &str) -> PathBuf {
+    let re = regex::Regex::new(r"\$(?:\{(\w+)\}|(\w+))").unwrap();
+    let expanded = re.replace_all(path, |caps: &regex::Captures| {
+        let name = caps.get(1).or_else(|| caps.get(2)).unwrap().as_str();
+        std::env::var(name).unwrap_or_else(|_| caps[0].to_string())
+    });
+
+    match expanded.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match dirs::home_dir() {
+            Some(home) => home.join(rest.trim_start_matches('/')),
+            None => PathBuf::from(expanded.as_ref()),
+        },
+        _ => PathBuf::from(expanded.as_ref()),
+    }
+}
+
+/// Create `dir` if needed and make sure files can be written in it
+fn check_writable_dir(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).context("Failed to create directory")?;
+
+    let probe = dir.join(".blipply-write-test");
+    std::fs::write(&probe, b"").context("Directory is not writable")?;
+    std::fs::remove_file(&probe).ok();
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -173,5 +861,147 @@ mod tests {
         let serialized = toml::to_string(&config).unwrap();
         let deserialized: Config = toml::from_str(&serialized).unwrap();
         assert_eq!(config.general.ollama_url, deserialized.general.ollama_url);
+        assert!(serialized.contains("hotkey = \"Super+Shift+A\""));
+        assert_eq!(deserialized.general.hotkey, config.general.hotkey);
+
+        // A hotkey that doesn't parse doesn't stop the rest of the config loading
+        let hand_edited = serialized.replace("hotkey = \"Super+Shift+A\"", "hotkey = \"Super+Nope\"");
+        let deserialized: Config = toml::from_str(&hand_edited).unwrap();
+        assert_eq!(deserialized.general.hotkey, Hotkey::default());
+    }
+
+    #[test]
+    fn test_response_format() {
+        assert_eq!(ResponseFormat::Plain.request_format().unwrap(), None);
+        assert_eq!(
+            ResponseFormat::Json.request_format().unwrap(),
+            Some(serde_json::json!("json"))
+        );
+        let schema = ResponseFormat::JsonSchema(r#"{"type":"object"}"#.to_string());
+        assert_eq!(schema.request_format().unwrap(), Some(serde_json::json!({"type": "object"})));
+        assert!(ResponseFormat::JsonSchema("not json".to_string()).request_format().is_err());
+
+        #[derive(Deserialize)]
+        struct Wrapper {
+            response_format: ResponseFormat,
+        }
+        let parsed: Wrapper = toml::from_str(r#"response_format = "json""#).unwrap();
+        assert_eq!(parsed.response_format, ResponseFormat::Json);
+        let parsed: Wrapper = toml::from_str(r#"response_format = { json_schema = "{}" }"#).unwrap();
+        assert_eq!(parsed.response_format, ResponseFormat::JsonSchema("{}".to_string()));
+    }
+
+    #[test]
+    fn test_ollama_hosts() {
+        let mut config = Config::default();
+        assert_eq!(config.ollama_hosts(), vec!["http://127.0.0.1:11434"]);
+
+        config.general.ollama_urls = vec!["http://desktop:11434".to_string()];
+        assert_eq!(config.ollama_hosts(), vec!["http://desktop:11434", "http://127.0.0.1:11434"]);
+    }
+
+    #[test]
+    fn test_chat_panel_state_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("blipply-config-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("config.toml");
+
+        // First start writes the defaults, with the panel open
+        let mut config = Config::load_from(&path).unwrap();
+        assert!(config.general.chat_panel_open);
+        config.general.chat_panel_open = false;
+        config.save_to(&path).unwrap();
+
+        // The next start comes up collapsed
+        assert!(!Config::load_from(&path).unwrap().general.chat_panel_open);
+
+        // Configs written before the panel could collapse open it
+        let contents = std::fs::read_to_string(&path).unwrap().replace("chat_panel_open = false\n", "");
+        std::fs::write(&path, contents).unwrap();
+        assert!(Config::load_from(&path).unwrap().general.chat_panel_open);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_stt_strategy_serialization() {
+        let mut config = Config::default();
+        config.audio.stt_strategy = SttStrategy::BeamSearch { beam_size: 5, patience: 1.0 };
+        let serialized = toml::to_string(&config).unwrap();
+        let deserialized: Config = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.audio.stt_strategy, config.audio.stt_strategy);
+    }
+
+    #[test]
+    fn test_interaction_mode_migration() {
+        let mut config = Config::default();
+        let mut profile: VoiceProfile = config.profiles["default"].clone().into();
+        assert_eq!(config.interaction_mode(&profile), InteractionMode::VoiceAuto);
+
+        // Old configs with the global flag keep push-to-talk
+        config.audio.push_to_talk = true;
+        assert_eq!(config.interaction_mode(&profile), InteractionMode::VoicePtt);
+
+        profile.interaction_mode = Some(InteractionMode::TextOnly);
+        assert_eq!(config.interaction_mode(&profile), InteractionMode::TextOnly);
+
+        let parsed: ProfileConfig = toml::from_str(r#"
+            name = "Walkie-talkie"
+            model = "llama3.2:3b"
+            personality = "helpful"
+            avatar_path = ""
+            avatar_size_px = 96
+            voice_model = "en_US-lessac-medium"
+            tts_speed = 1.0
+            tts_enabled = true
+            interaction_mode = "voice_ptt"
+        "#).unwrap();
+        assert_eq!(parsed.interaction_mode, Some(InteractionMode::VoicePtt));
+    }
+
+    #[test]
+    fn test_expand_path() {
+        std::env::set_var("BLIPPLY_TEST_DRIVE", "/mnt/models");
+        assert_eq!(expand_path("$BLIPPLY_TEST_DRIVE/blipply"), PathBuf::from("/mnt/models/blipply"));
+        assert_eq!(expand_path("${BLIPPLY_TEST_DRIVE}/x"), PathBuf::from("/mnt/models/x"));
+        assert_eq!(expand_path("$BLIPPLY_UNSET_VAR/x"), PathBuf::from("$BLIPPLY_UNSET_VAR/x"));
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(expand_path("~/models"), home.join("models"));
+        }
+        assert_eq!(expand_path("~other/models"), PathBuf::from("~other/models"));
+    }
+
+    #[test]
+    fn test_fallbacks_are_not_saved() {
+        // A file where the data directory should be, like an unmounted drive's mount point
+        let file = std::env::temp_dir().join(format!("blipply-not-a-dir-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&file, b"").unwrap();
+
+        let mut config = Config::default();
+        config.general.data_dir = Some(file.to_string_lossy().into_owned());
+        config.audio.input_gain = 50.0;
+        config.check_settings();
+        std::fs::remove_file(&file).ok();
+
+        assert_eq!(config.data_dir().unwrap(), Config::default().data_dir().unwrap());
+        assert_eq!(config.input_gain(), *INPUT_GAIN_RANGE.end());
+
+        let saved: Config = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(saved.general.data_dir.as_deref(), Some(file.to_string_lossy().as_ref()));
+        assert_eq!(saved.audio.input_gain, 50.0);
+    }
+
+    #[test]
+    fn test_data_dir_override() {
+        let mut config = Config::default();
+        config.general.data_dir = Some("/mnt/shared/blipply".to_string());
+        config.general.conversation_log_path = Some("history.jsonl".to_string());
+
+        assert_eq!(
+            config.whisper_model_path().unwrap(),
+            PathBuf::from("/mnt/shared/blipply/models/whisper/base.en.bin")
+        );
+        assert_eq!(
+            config.conversation_log_path().unwrap(),
+            Some(PathBuf::from("/mnt/shared/blipply/history.jsonl"))
+        );
     }
 }
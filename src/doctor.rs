@@ -0,0 +1,202 @@
+// Blipply Assistant
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::Result;
+use cpal::traits::{DeviceTrait, HostTrait};
+
+use crate::audio::TtsPipeline;
+use crate::config::Config;
+use crate::hotkeys;
+use crate::ollama::{OllamaClient, OllamaClientConfig};
+
+struct Check {
+    name: &'static str,
+    passed: bool,
+    critical: bool,
+    detail: String,
+    hint: &'static str,
+}
+
+impl Check {
+    fn new(name: &'static str, critical: bool, result: Result<String>, hint: &'static str) -> Self {
+        let (passed, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(e) => (false, format!("{:#}", e)),
+        };
+        Self { name, passed, critical, detail, hint }
+    }
+
+    fn print(&self) {
+        let mark = match (self.passed, self.critical) {
+            (true, _) => "✓",
+            (false, true) => "✗",
+            (false, false) => "!",
+        };
+        println!("{} {}: {}", mark, self.name, self.detail);
+        if !self.passed {
+            println!("    → {}", self.hint);
+        }
+    }
+}
+
+/// Run every setup check and print a report. Returns false if a critical check failed.
+pub async fn run() -> Result<bool> {
+    println!("\n=== Blipply Assistant Doctor ===\n");
+
+    let config = Config::load()?;
+    let mut checks = Vec::new();
+
+    checks.push(Check::new(
+        "Ollama",
+        true,
+        check_ollama(&config).await,
+        "Start Ollama (systemctl --user start ollama) or fix general.ollama_url",
+    ));
+    checks.push(Check::new(
+        "Whisper model",
+        false,
+        check_whisper(&config),
+        "Run scripts/download-models.sh or change audio.stt_model",
+    ));
+    checks.push(Check::new(
+        "Piper voice",
+        false,
+        check_piper(&config),
+        "Download the voice .onnx and .json into the models/piper directory",
+    ));
+    checks.push(Check::new(
+        "Input device",
+        false,
+        check_input_device(),
+        "Connect a microphone and check it in pavucontrol / pw-top",
+    ));
+    checks.push(Check::new(
+        "Output device",
+        false,
+        check_output_device(),
+        "Connect speakers or headphones and check PipeWire is running",
+    ));
+    checks.push(Check::new(
+        "Global hotkey",
+        false,
+        check_hotkeys().await,
+        "Add yourself to the 'input' group or bind 'blipply-assistant toggle' in your compositor",
+    ));
+    checks.push(Check::new(
+        "Layer shell",
+        true,
+        check_layer_shell(),
+        "Use a Wayland compositor that supports wlr-layer-shell (KDE Plasma 6, Hyprland, Sway)",
+    ));
+
+    for check in &checks {
+        check.print();
+    }
+
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    let critical_failed = checks.iter().any(|c| !c.passed && c.critical);
+
+    println!();
+    if failed == 0 {
+        println!("All checks passed");
+    } else {
+        println!("{} check(s) failed", failed);
+    }
+
+    Ok(!critical_failed)
+}
+
+async fn check_ollama(config: &Config) -> Result<String> {
+    let client = OllamaClient::with_hosts(config.ollama_hosts(), OllamaClientConfig::default());
+    let models = client.list_models().await?;
+
+    let model = &config.active_profile()?.model;
+    if !models.iter().any(|m| m == model) {
+        anyhow::bail!("connected, but model '{}' is not installed (ollama pull {})", model, model);
+    }
+
+    Ok(format!("{} ({} model(s): {})", client.base_url(), models.len(), models.join(", ")))
+}
+
+fn check_whisper(config: &Config) -> Result<String> {
+    let path = config.whisper_model_path()?;
+    if !path.exists() {
+        anyhow::bail!("{:?} not found", path);
+    }
+
+    whisper_rs::WhisperContext::new(&path)
+        .map_err(|e| anyhow::anyhow!("{:?} failed to load: {}", path, e))?;
+
+    Ok(format!("{:?}", path))
+}
+
+fn check_piper(config: &Config) -> Result<String> {
+    let voice = &config.active_profile()?.voice_model;
+    let voice_path = config.piper_voice_path(voice)?;
+    let config_path = voice_path.with_extension("json");
+
+    if !voice_path.exists() {
+        anyhow::bail!("{:?} not found", voice_path);
+    }
+    if !config_path.exists() {
+        anyhow::bail!("{:?} not found", config_path);
+    }
+
+    TtsPipeline::new(&voice_path, &config_path, 1.0, None)?;
+
+    Ok(format!("{:?}", voice_path))
+}
+
+fn check_input_device() -> Result<String> {
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| anyhow::anyhow!("no input device available"))?;
+    Ok(device.name()?)
+}
+
+fn check_output_device() -> Result<String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| anyhow::anyhow!("no output device available"))?;
+    Ok(device.name()?)
+}
+
+async fn check_hotkeys() -> Result<String> {
+    if hotkeys::portal_available().await.unwrap_or(false) {
+        return Ok("xdg-desktop-portal".to_string());
+    }
+
+    let keyboards = hotkeys::keyboard_devices().len();
+    if keyboards == 0 {
+        anyhow::bail!("no portal and no readable keyboard devices");
+    }
+
+    Ok(format!("evdev ({} keyboard(s))", keyboards))
+}
+
+fn check_layer_shell() -> Result<String> {
+    gtk::init()?;
+
+    if !gtk4_layer_shell::is_supported() {
+        anyhow::bail!("compositor does not support layer shell");
+    }
+
+    Ok("supported".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_result() {
+        let ok = Check::new("ok", true, Ok("fine".to_string()), "");
+        assert!(ok.passed);
+        assert_eq!(ok.detail, "fine");
+
+        let failed = Check::new("bad", true, Err(anyhow::anyhow!("broken")), "fix it");
+        assert!(!failed.passed);
+        assert_eq!(failed.detail, "broken");
+    }
+}
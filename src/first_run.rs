@@ -88,7 +88,10 @@ pub async fn run_interactive_setup() -> Result<()> {
     
     if let Some(hotkey) = prompt_string("Hotkey")? {
         if !hotkey.is_empty() {
-            config.general.hotkey = hotkey;
+            match hotkey.parse() {
+                Ok(hotkey) => config.general.hotkey = hotkey,
+                Err(e) => println!("Invalid hotkey ({}), using {}", e, config.general.hotkey),
+            }
         }
     }
     
@@ -0,0 +1,104 @@
+// Blipply Assistant - History Undo
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use parking_lot::{Mutex, RwLock};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Number of snapshots kept when none is given
+pub const DEFAULT_UNDO_DEPTH: usize = 10;
+
+/// Snapshot-based undo/redo for a shared value such as the chat history
+pub struct UndoStack<T: Clone> {
+    target: Arc<RwLock<T>>,
+    undo: Mutex<VecDeque<T>>,
+    redo: Mutex<Vec<T>>,
+    depth: usize,
+}
+
+impl<T: Clone> UndoStack<T> {
+    pub fn new(target: Arc<RwLock<T>>, depth: usize) -> Self {
+        Self {
+            target,
+            undo: Mutex::new(VecDeque::with_capacity(depth)),
+            redo: Mutex::new(Vec::new()),
+            depth,
+        }
+    }
+
+    /// Snapshot the current value; call before modifying it
+    pub fn push_checkpoint(&self) {
+        let snapshot = self.target.read().clone();
+
+        let mut undo = self.undo.lock();
+        undo.push_back(snapshot);
+        while undo.len() > self.depth {
+            undo.pop_front();
+        }
+        self.redo.lock().clear();
+    }
+
+    /// Restore the last checkpoint. Returns false if there is none.
+    pub fn undo(&self) -> bool {
+        let Some(snapshot) = self.undo.lock().pop_back() else {
+            return false;
+        };
+
+        let current = std::mem::replace(&mut *self.target.write(), snapshot);
+        self.redo.lock().push(current);
+        true
+    }
+
+    /// Reapply the last undone change. Returns false if there is none.
+    pub fn redo(&self) -> bool {
+        let Some(snapshot) = self.redo.lock().pop() else {
+            return false;
+        };
+
+        let current = std::mem::replace(&mut *self.target.write(), snapshot);
+        self.undo.lock().push_back(current);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_undos_restore_original() {
+        let history = Arc::new(RwLock::new(VecDeque::from(vec![1, 2, 3])));
+        let stack = UndoStack::new(history.clone(), DEFAULT_UNDO_DEPTH);
+
+        stack.push_checkpoint();
+        history.write().pop_back();
+        stack.push_checkpoint();
+        history.write().clear();
+
+        assert!(stack.undo());
+        assert_eq!(*history.read(), VecDeque::from(vec![1, 2]));
+        assert!(stack.undo());
+        assert_eq!(*history.read(), VecDeque::from(vec![1, 2, 3]));
+        assert!(!stack.undo());
+
+        assert!(stack.redo());
+        assert_eq!(*history.read(), VecDeque::from(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let value = Arc::new(RwLock::new(0));
+        let stack = UndoStack::new(value.clone(), 2);
+
+        for i in 1..=3 {
+            stack.push_checkpoint();
+            *value.write() = i;
+        }
+
+        assert!(stack.undo());
+        assert!(stack.undo());
+        assert!(!stack.undo());
+        assert_eq!(*value.read(), 1);
+    }
+}
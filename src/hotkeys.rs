@@ -3,6 +3,9 @@
 // Licensed under the MIT License
 
 use anyhow::{Result, Context};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
 use std::sync::Arc;
 use tracing::{debug, info, warn};
 
@@ -20,19 +23,32 @@ pub async fn run_listener(state: Arc<AppState>) -> Result<()> {
     try_evdev_backend(state).await
 }
 
-async fn try_portal_backend(state: Arc<AppState>) -> Result<()> {
+/// Whether xdg-desktop-portal is running on the session bus
+pub async fn portal_available() -> Result<bool> {
     use zbus::Connection;
 
-    debug!("Attempting to use xdg-desktop-portal GlobalShortcuts");
-
     let connection = Connection::session().await
         .context("Failed to connect to session bus")?;
 
-    // Check if portal is available
     let proxy = zbus::fdo::DBusProxy::new(&connection).await?;
     let has_portal = proxy.name_has_owner("org.freedesktop.portal.Desktop").await?;
+    Ok(has_portal)
+}
+
+/// Keyboard devices readable by the current user
+pub fn keyboard_devices() -> Vec<(std::path::PathBuf, evdev::Device)> {
+    evdev::enumerate()
+        .filter(|(_, device)| {
+            device.supported_keys()
+                .map_or(false, |keys| keys.contains(evdev::Key::KEY_A))
+        })
+        .collect()
+}
+
+async fn try_portal_backend(state: Arc<AppState>) -> Result<()> {
+    debug!("Attempting to use xdg-desktop-portal GlobalShortcuts");
 
-    if !has_portal {
+    if !portal_available().await? {
         return Err(anyhow::anyhow!("GlobalShortcuts portal not available"));
     }
 
@@ -57,12 +73,7 @@ async fn try_evdev_backend(state: Arc<AppState>) -> Result<()> {
     debug!("Attempting to use evdev for hotkeys");
 
     // Find keyboard devices
-    let devices = evdev::enumerate()
-        .filter(|(_, device)| {
-            device.supported_keys()
-                .map_or(false, |keys| keys.contains(Key::KEY_A))
-        })
-        .collect::<Vec<_>>();
+    let devices = keyboard_devices();
 
     if devices.is_empty() {
         return Err(anyhow::anyhow!("No keyboard devices found. Are you in the 'input' group?"));
@@ -70,11 +81,7 @@ async fn try_evdev_backend(state: Arc<AppState>) -> Result<()> {
 
     info!("Monitoring {} keyboard device(s)", devices.len());
 
-    // Parse hotkey configuration
-    let hotkey = {
-        let config = state.config.read();
-        parse_hotkey(&config.general.hotkey)?
-    };
+    let hotkey = state.config.read().general.hotkey.clone();
 
     debug!("Listening for hotkey: {:?}", hotkey);
 
@@ -134,8 +141,9 @@ async fn try_evdev_backend(state: Arc<AppState>) -> Result<()> {
     Ok(())
 }
 
-#[derive(Debug, Clone)]
-struct Hotkey {
+/// A key combination such as "Super+Shift+A", stored in the config in that form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
     super_mod: bool,
     shift_mod: bool,
     ctrl_mod: bool,
@@ -143,81 +151,190 @@ struct Hotkey {
     key: evdev::Key,
 }
 
-fn parse_hotkey(hotkey_str: &str) -> Result<Hotkey> {
-    let parts: Vec<&str> = hotkey_str.split('+').collect();
-    
-    if parts.is_empty() {
-        return Err(anyhow::anyhow!("Empty hotkey string"));
+impl Default for Hotkey {
+    fn default() -> Self {
+        Self {
+            super_mod: true,
+            shift_mod: true,
+            ctrl_mod: false,
+            alt_mod: false,
+            key: evdev::Key::KEY_A,
+        }
     }
+}
+
+impl FromStr for Hotkey {
+    type Err = anyhow::Error;
 
-    let mut super_mod = false;
-    let mut shift_mod = false;
-    let mut ctrl_mod = false;
-    let mut alt_mod = false;
-    let mut key = None;
-
-    for (i, part) in parts.iter().enumerate() {
-        let part = part.trim();
-        let is_last = i == parts.len() - 1;
-
-        match part.to_lowercase().as_str() {
-            "super" | "meta" | "win" => super_mod = true,
-            "shift" => shift_mod = true,
-            "ctrl" | "control" => ctrl_mod = true,
-            "alt" => alt_mod = true,
-            _ if is_last => {
-                key = Some(parse_key_name(part)?);
+    fn from_str(hotkey_str: &str) -> Result<Self> {
+        let parts: Vec<&str> = hotkey_str.split('+').collect();
+
+        if hotkey_str.trim().is_empty() {
+            return Err(anyhow::anyhow!("Empty hotkey string"));
+        }
+
+        let mut super_mod = false;
+        let mut shift_mod = false;
+        let mut ctrl_mod = false;
+        let mut alt_mod = false;
+        let mut key = None;
+
+        for (i, part) in parts.iter().enumerate() {
+            let part = part.trim();
+            let is_last = i == parts.len() - 1;
+
+            match part.to_lowercase().as_str() {
+                "super" | "meta" | "win" => super_mod = true,
+                "shift" => shift_mod = true,
+                "ctrl" | "control" => ctrl_mod = true,
+                "alt" => alt_mod = true,
+                _ if is_last => {
+                    key = Some(parse_key_name(part)?);
+                }
+                _ => return Err(anyhow::anyhow!("Unknown modifier: {}", part)),
             }
-            _ => return Err(anyhow::anyhow!("Unknown modifier: {}", part)),
         }
+
+        Ok(Hotkey {
+            super_mod,
+            shift_mod,
+            ctrl_mod,
+            alt_mod,
+            key: key.ok_or_else(|| anyhow::anyhow!("No key specified"))?,
+        })
     }
+}
 
-    Ok(Hotkey {
-        super_mod,
-        shift_mod,
-        ctrl_mod,
-        alt_mod,
-        key: key.ok_or_else(|| anyhow::anyhow!("No key specified"))?,
-    })
+/// Canonical form, with modifiers in the order the hotkey recorder writes them
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.super_mod, "Super"),
+            (self.ctrl_mod, "Ctrl"),
+            (self.alt_mod, "Alt"),
+            (self.shift_mod, "Shift"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        f.write_str(key_name(self.key))
+    }
 }
 
-fn parse_key_name(name: &str) -> Result<evdev::Key> {
+impl Serialize for Hotkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hotkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct HotkeyVisitor;
+
+        impl de::Visitor<'_> for HotkeyVisitor {
+            type Value = Hotkey;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a hotkey such as \"Super+Shift+A\"")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Hotkey, E> {
+                value.parse().map_err(|e: anyhow::Error| E::custom(format!("{:#}", e)))
+            }
+        }
+
+        deserializer.deserialize_str(HotkeyVisitor)
+    }
+}
+
+/// Read a config hotkey, falling back to the default for one that doesn't
+/// parse, so a hand-edited typo only affects the hotkey and not the whole config
+pub fn deserialize_or_default<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Hotkey, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    Ok(text.parse().unwrap_or_else(|e: anyhow::Error| {
+        let hotkey = Hotkey::default();
+        warn!("Hotkey {:?} is not valid ({:#}), using {}", text, e, hotkey);
+        hotkey
+    }))
+}
+
+/// Keys a hotkey can end with, by their canonical names
+const KEY_NAMES: &[(&str, evdev::Key)] = {
     use evdev::Key;
-    
-    let key = match name.to_lowercase().as_str() {
-        "a" => Key::KEY_A,
-        "b" => Key::KEY_B,
-        "c" => Key::KEY_C,
-        "d" => Key::KEY_D,
-        "e" => Key::KEY_E,
-        "f" => Key::KEY_F,
-        "g" => Key::KEY_G,
-        "h" => Key::KEY_H,
-        "i" => Key::KEY_I,
-        "j" => Key::KEY_J,
-        "k" => Key::KEY_K,
-        "l" => Key::KEY_L,
-        "m" => Key::KEY_M,
-        "n" => Key::KEY_N,
-        "o" => Key::KEY_O,
-        "p" => Key::KEY_P,
-        "q" => Key::KEY_Q,
-        "r" => Key::KEY_R,
-        "s" => Key::KEY_S,
-        "t" => Key::KEY_T,
-        "u" => Key::KEY_U,
-        "v" => Key::KEY_V,
-        "w" => Key::KEY_W,
-        "x" => Key::KEY_X,
-        "y" => Key::KEY_Y,
-        "z" => Key::KEY_Z,
-        "space" => Key::KEY_SPACE,
-        "enter" | "return" => Key::KEY_ENTER,
-        "esc" | "escape" => Key::KEY_ESC,
-        _ => return Err(anyhow::anyhow!("Unknown key: {}", name)),
+    &[
+        ("A", Key::KEY_A),
+        ("B", Key::KEY_B),
+        ("C", Key::KEY_C),
+        ("D", Key::KEY_D),
+        ("E", Key::KEY_E),
+        ("F", Key::KEY_F),
+        ("G", Key::KEY_G),
+        ("H", Key::KEY_H),
+        ("I", Key::KEY_I),
+        ("J", Key::KEY_J),
+        ("K", Key::KEY_K),
+        ("L", Key::KEY_L),
+        ("M", Key::KEY_M),
+        ("N", Key::KEY_N),
+        ("O", Key::KEY_O),
+        ("P", Key::KEY_P),
+        ("Q", Key::KEY_Q),
+        ("R", Key::KEY_R),
+        ("S", Key::KEY_S),
+        ("T", Key::KEY_T),
+        ("U", Key::KEY_U),
+        ("V", Key::KEY_V),
+        ("W", Key::KEY_W),
+        ("X", Key::KEY_X),
+        ("Y", Key::KEY_Y),
+        ("Z", Key::KEY_Z),
+        ("0", Key::KEY_0),
+        ("1", Key::KEY_1),
+        ("2", Key::KEY_2),
+        ("3", Key::KEY_3),
+        ("4", Key::KEY_4),
+        ("5", Key::KEY_5),
+        ("6", Key::KEY_6),
+        ("7", Key::KEY_7),
+        ("8", Key::KEY_8),
+        ("9", Key::KEY_9),
+        ("F1", Key::KEY_F1),
+        ("F2", Key::KEY_F2),
+        ("F3", Key::KEY_F3),
+        ("F4", Key::KEY_F4),
+        ("F5", Key::KEY_F5),
+        ("F6", Key::KEY_F6),
+        ("F7", Key::KEY_F7),
+        ("F8", Key::KEY_F8),
+        ("F9", Key::KEY_F9),
+        ("F10", Key::KEY_F10),
+        ("F11", Key::KEY_F11),
+        ("F12", Key::KEY_F12),
+        ("Space", Key::KEY_SPACE),
+        ("Enter", Key::KEY_ENTER),
+        ("Esc", Key::KEY_ESC),
+    ]
+};
+
+fn parse_key_name(name: &str) -> Result<evdev::Key> {
+    let canonical = match name.to_lowercase().as_str() {
+        "return" => "Enter",
+        "escape" => "Esc",
+        _ => name,
     };
 
-    Ok(key)
+    KEY_NAMES.iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(canonical.trim()))
+        .map(|(_, key)| *key)
+        .ok_or_else(|| anyhow::anyhow!("Unknown key: {}", name))
+}
+
+fn key_name(key: evdev::Key) -> &'static str {
+    KEY_NAMES.iter()
+        .find(|(_, known)| *known == key)
+        .map(|(name, _)| *name)
+        .expect("hotkeys only hold keys from KEY_NAMES")
 }
 
 #[cfg(test)]
@@ -226,15 +343,48 @@ mod tests {
 
     #[test]
     fn test_parse_hotkey() {
-        let hotkey = parse_hotkey("Super+Shift+A").unwrap();
+        let hotkey: Hotkey = "Super+Shift+A".parse().unwrap();
         assert!(hotkey.super_mod);
         assert!(hotkey.shift_mod);
         assert!(!hotkey.ctrl_mod);
+        assert_eq!(hotkey, Hotkey::default());
+
+        assert!("".parse::<Hotkey>().is_err());
+        assert!("Super+Shift".parse::<Hotkey>().is_err());
+        assert!("Hyper+A".parse::<Hotkey>().is_err());
+    }
+
+    #[test]
+    fn test_hotkey_round_trip() {
+        let hotkey: Hotkey = "Ctrl+Alt+F4".parse().unwrap();
+        assert_eq!(hotkey.to_string(), "Ctrl+Alt+F4");
+        assert_eq!(hotkey.to_string().parse::<Hotkey>().unwrap(), hotkey);
+
+        // Spelling is normalised to the canonical form
+        let hotkey: Hotkey = "shift+control+escape".parse().unwrap();
+        assert_eq!(hotkey.to_string(), "Ctrl+Shift+Esc");
+    }
+
+    #[test]
+    fn test_hotkey_serde() {
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper {
+            hotkey: Hotkey,
+        }
+
+        let wrapper = Wrapper { hotkey: "Ctrl+Alt+F4".parse().unwrap() };
+        let serialized = toml::to_string(&wrapper).unwrap();
+        assert_eq!(serialized.trim(), "hotkey = \"Ctrl+Alt+F4\"");
+
+        let deserialized: Wrapper = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.hotkey, wrapper.hotkey);
+        assert!(toml::from_str::<Wrapper>("hotkey = \"Super+Nope\"").is_err());
     }
 
     #[test]
     fn test_parse_key_name() {
         assert!(matches!(parse_key_name("A").unwrap(), evdev::Key::KEY_A));
         assert!(matches!(parse_key_name("space").unwrap(), evdev::Key::KEY_SPACE));
+        assert!(matches!(parse_key_name("F12").unwrap(), evdev::Key::KEY_F12));
     }
 }
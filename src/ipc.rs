@@ -0,0 +1,361 @@
+// Blipply Assistant
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::{Result, Context, bail};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::profiles::ProfileOverrides;
+use crate::state::AppState;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpcCommand {
+    Toggle,
+    Calibrate,
+    Status,
+    VadStats,
+    /// Reply with the `ContextSnapshot` as JSON
+    GetContext,
+    AskAboutClipboard,
+    SetOverrides(ProfileOverrides),
+    ClearOverrides,
+    /// Switch the generation preset, keeping other overrides
+    SetPreset(String),
+    /// Re-read the config and rebuild the profiles and speech pipelines.
+    /// Replies `ok` or `error: <reason>`.
+    Reload,
+    /// Replace the last user message and regenerate the reply. Replies `ok`
+    /// or `error: <reason>`.
+    EditLast(String),
+}
+
+impl IpcCommand {
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+
+        // SET_OVERRIDES carries a JSON payload after the command name
+        if let Some(json) = line.strip_prefix("SET_OVERRIDES ") {
+            return serde_json::from_str(json).ok().map(Self::SetOverrides);
+        }
+        if let Some(name) = line.strip_prefix("SET preset ") {
+            return Some(Self::SetPreset(name.trim().to_string()));
+        }
+        // JSON, so the new text can span lines
+        if let Some(json) = line.strip_prefix("EDIT_LAST ") {
+            return serde_json::from_str(json).ok().map(Self::EditLast);
+        }
+
+        match line {
+            "TOGGLE" => Some(Self::Toggle),
+            "CALIBRATE" => Some(Self::Calibrate),
+            "STATUS" => Some(Self::Status),
+            "VAD_STATS" => Some(Self::VadStats),
+            "GET_CONTEXT" => Some(Self::GetContext),
+            "ASK_CLIPBOARD" => Some(Self::AskAboutClipboard),
+            "CLEAR_OVERRIDES" => Some(Self::ClearOverrides),
+            "RELOAD" => Some(Self::Reload),
+            _ => None,
+        }
+    }
+
+    /// The line sent over the socket for this command
+    pub fn to_line(&self) -> String {
+        match self {
+            Self::Toggle => "TOGGLE".to_string(),
+            Self::Calibrate => "CALIBRATE".to_string(),
+            Self::Status => "STATUS".to_string(),
+            Self::VadStats => "VAD_STATS".to_string(),
+            Self::GetContext => "GET_CONTEXT".to_string(),
+            Self::AskAboutClipboard => "ASK_CLIPBOARD".to_string(),
+            Self::SetOverrides(overrides) => format!(
+                "SET_OVERRIDES {}",
+                serde_json::to_string(overrides).unwrap_or_default()
+            ),
+            Self::ClearOverrides => "CLEAR_OVERRIDES".to_string(),
+            Self::SetPreset(name) => format!("SET preset {}", name),
+            Self::Reload => "RELOAD".to_string(),
+            Self::EditLast(text) => format!("EDIT_LAST {}", serde_json::to_string(text).unwrap_or_default()),
+        }
+    }
+}
+
+/// How often `IpcClient::connect` retries while waiting for the daemon
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Why a CLI command couldn't talk to the daemon. Each kind exits with its
+/// own status so scripts can tell them apart.
+#[derive(Debug)]
+pub enum ClientError {
+    /// No daemon is listening on the socket
+    NotRunning,
+    /// The socket exists but reading or writing it failed
+    Socket(std::io::Error),
+    /// The daemon answered with something unexpected, or nothing
+    BadResponse(String),
+}
+
+impl ClientError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NotRunning => 3,
+            Self::Socket(_) => 4,
+            Self::BadResponse(_) => 5,
+        }
+    }
+
+    fn from_connect(e: std::io::Error) -> Self {
+        match e.kind() {
+            // No socket file, or a stale one left by a daemon that died
+            std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused => Self::NotRunning,
+            _ => Self::Socket(e),
+        }
+    }
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotRunning => write!(f, "Could not connect to daemon. Is it running?"),
+            Self::Socket(e) => write!(f, "Daemon socket error: {}", e),
+            Self::BadResponse(reply) => write!(f, "Unexpected reply from daemon: {:?}", reply),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<std::io::Error> for ClientError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Socket(e)
+    }
+}
+
+/// CLI side of the daemon socket
+pub struct IpcClient {
+    reader: std::io::BufReader<std::os::unix::net::UnixStream>,
+    writer: std::os::unix::net::UnixStream,
+}
+
+impl IpcClient {
+    /// Connect to the daemon, retrying for up to `wait` while it starts
+    pub fn connect(wait: Option<Duration>) -> Result<Self, ClientError> {
+        Self::connect_to(&Config::socket_path(), wait)
+    }
+
+    fn connect_to(path: &Path, wait: Option<Duration>) -> Result<Self, ClientError> {
+        let deadline = wait.map(|wait| Instant::now() + wait);
+        loop {
+            match std::os::unix::net::UnixStream::connect(path).map_err(ClientError::from_connect) {
+                Ok(stream) => {
+                    let writer = stream.try_clone()?;
+                    return Ok(Self { reader: std::io::BufReader::new(stream), writer });
+                }
+                Err(ClientError::NotRunning) if deadline.is_some_and(|d| Instant::now() < d) => {
+                    std::thread::sleep(CONNECT_RETRY_INTERVAL);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Send a command that has no reply
+    pub fn send(&mut self, command: &IpcCommand) -> Result<(), ClientError> {
+        self.writer.write_all(format!("{}\n", command.to_line()).as_bytes())?;
+        Ok(())
+    }
+
+    /// Send a command and return the daemon's one-line reply
+    pub fn request(&mut self, command: &IpcCommand) -> Result<String, ClientError> {
+        self.send(command)?;
+        let mut reply = String::new();
+        if self.reader.read_line(&mut reply)? == 0 {
+            return Err(ClientError::BadResponse(String::new()));
+        }
+        Ok(reply.trim_end().to_string())
+    }
+}
+
+/// Bind the daemon socket, replacing a stale one left by a previous run
+pub fn bind() -> Result<UnixListener> {
+    let path = Config::socket_path();
+
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            bail!("Another instance is already listening on {:?}", path);
+        }
+        debug!("Removing stale socket {:?}", path);
+        std::fs::remove_file(&path).context("Failed to remove stale socket")?;
+    }
+
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to bind IPC socket {:?}", path))?;
+
+    info!("IPC socket listening on {:?}", path);
+    Ok(listener)
+}
+
+pub async fn run_server(listener: UnixListener, state: Arc<AppState>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    handle_client(stream, state).await;
+                });
+            }
+            Err(e) => {
+                warn!("IPC accept error: {}", e);
+            }
+        }
+    }
+}
+
+async fn handle_client(stream: UnixStream, state: Arc<AppState>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        match IpcCommand::parse(&line) {
+            Some(IpcCommand::Toggle) => state.toggle_visibility(),
+            Some(IpcCommand::Calibrate) => state.calibrate_vad(),
+            Some(IpcCommand::AskAboutClipboard) => state.ask_about_clipboard(),
+            Some(IpcCommand::SetOverrides(overrides)) => state.set_overrides(overrides),
+            Some(IpcCommand::ClearOverrides) => state.clear_overrides(),
+            Some(IpcCommand::SetPreset(name)) => {
+                if let Err(e) = state.set_preset(Some(&name)) {
+                    warn!("{}", e);
+                }
+            }
+            Some(IpcCommand::Status) => {
+                let report = format!("{}\n", state.status_report());
+                if let Err(e) = writer.write_all(report.as_bytes()).await {
+                    warn!("Failed to send IPC status: {}", e);
+                }
+            }
+            Some(IpcCommand::Reload) => {
+                let reply = match state.reload().await {
+                    Ok(()) => "ok".to_string(),
+                    Err(e) => format!("error: {:#}", e),
+                };
+                if let Err(e) = writer.write_all(format!("{}\n", reply).as_bytes()).await {
+                    warn!("Failed to send reload result: {}", e);
+                }
+            }
+            Some(IpcCommand::EditLast(text)) => {
+                // Reply once the old turn is gone, not after the new answer
+                let reply = if state.remove_last_user_message() {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = state.send_edited_message(&text).await {
+                            warn!("Failed to send edited message: {}", e);
+                        }
+                    });
+                    "ok".to_string()
+                } else {
+                    "error: No user message to edit".to_string()
+                };
+                if let Err(e) = writer.write_all(format!("{}\n", reply).as_bytes()).await {
+                    warn!("Failed to send edit result: {}", e);
+                }
+            }
+            Some(IpcCommand::VadStats) => {
+                // JSON, or `null` while audio capture isn't running
+                let stats = serde_json::to_string(&state.vad_stats()).unwrap_or_default();
+                if let Err(e) = writer.write_all(format!("{}\n", stats).as_bytes()).await {
+                    warn!("Failed to send VAD stats: {}", e);
+                }
+            }
+            Some(IpcCommand::GetContext) => {
+                let snapshot = serde_json::to_string(&state.get_context_snapshot()).unwrap_or_default();
+                if let Err(e) = writer.write_all(format!("{}\n", snapshot).as_bytes()).await {
+                    warn!("Failed to send context snapshot: {}", e);
+                }
+            }
+            None => warn!("Unknown IPC command: {}", line.trim()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_errors() {
+        let dir = std::env::temp_dir().join(format!("blipply-ipc-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("daemon.sock");
+
+        let missing = IpcClient::connect_to(&path, Some(Duration::from_millis(250)));
+        assert!(matches!(missing, Err(ClientError::NotRunning)));
+
+        // Reply to STATUS, then hang up without answering the next request
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "STATUS\n");
+            (&stream).write_all(b"idle\n").unwrap();
+        });
+
+        let mut client = IpcClient::connect_to(&path, None).unwrap();
+        assert_eq!(client.request(&IpcCommand::Status).unwrap(), "idle");
+        server.join().unwrap();
+        let error = client.request(&IpcCommand::Status).unwrap_err();
+        assert!(matches!(error, ClientError::BadResponse(_) | ClientError::Socket(_)));
+        assert_ne!(error.exit_code(), ClientError::NotRunning.exit_code());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(IpcCommand::parse("TOGGLE\n"), Some(IpcCommand::Toggle));
+        assert_eq!(IpcCommand::parse("STATUS"), Some(IpcCommand::Status));
+        assert_eq!(IpcCommand::parse(&IpcCommand::VadStats.to_line()), Some(IpcCommand::VadStats));
+        assert_eq!(IpcCommand::parse(&IpcCommand::Reload.to_line()), Some(IpcCommand::Reload));
+        assert_eq!(IpcCommand::parse("NOPE"), None);
+        assert_eq!(IpcCommand::parse("SET preset creative\n"), Some(IpcCommand::SetPreset("creative".to_string())));
+
+        let overrides = ProfileOverrides {
+            model: Some("llama3.1".to_string()),
+            temperature: Some(0.2),
+            ..Default::default()
+        };
+        let command = IpcCommand::SetOverrides(overrides);
+        assert_eq!(IpcCommand::parse(&command.to_line()), Some(command));
+
+        let command = IpcCommand::EditLast("first line\nsecond line".to_string());
+        assert_eq!(IpcCommand::parse(&command.to_line()), Some(command));
+    }
+
+    #[tokio::test]
+    async fn test_edit_last_over_ipc() {
+        use crate::ollama::Message;
+
+        let state = Arc::new(AppState::new(crate::config::Config::default()).await.unwrap());
+        let (client, server) = UnixStream::pair().unwrap();
+        tokio::spawn(handle_client(server, state.clone()));
+        let (reader, mut writer) = client.into_split();
+        let mut replies = BufReader::new(reader).lines();
+
+        // Nothing to edit yet
+        let command = IpcCommand::EditLast("hello".to_string()).to_line();
+        writer.write_all(format!("{}\n", command).as_bytes()).await.unwrap();
+        assert_eq!(replies.next_line().await.unwrap().unwrap(), "error: No user message to edit");
+
+        state.chat_history.write().extend([Message::user("helo"), Message::assistant("Pardon?")]);
+        writer.write_all(format!("{}\n", command).as_bytes()).await.unwrap();
+        assert_eq!(replies.next_line().await.unwrap().unwrap(), "ok");
+        assert!(!state.chat_messages().iter().any(|m| m.content == "helo" || m.content == "Pardon?"));
+    }
+}
@@ -0,0 +1,237 @@
+// Blipply Assistant
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::{Result, Context};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+use crate::ollama::Message;
+
+/// A single line of the conversation log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub ts: DateTime<Utc>,
+    pub profile: String,
+    pub role: String,
+    pub content: String,
+    pub model: String,
+    pub duration_ms: u64,
+    /// Title of the conversation the message belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl LogEntry {
+    pub fn new(
+        ts: DateTime<Utc>,
+        profile: impl Into<String>,
+        message: &Message,
+        model: impl Into<String>,
+        duration_ms: u64,
+    ) -> Self {
+        Self {
+            ts,
+            profile: profile.into(),
+            role: message.role.clone(),
+            content: message.content.clone(),
+            model: model.into(),
+            duration_ms,
+            title: None,
+        }
+    }
+
+    pub fn with_title(mut self, title: Option<String>) -> Self {
+        self.title = title;
+        self
+    }
+}
+
+/// Appends conversation exchanges to a JSON Lines file
+pub struct ConversationLogger {
+    path: PathBuf,
+}
+
+impl ConversationLogger {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn append(&self, entries: &[LogEntry]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .context("Failed to open conversation log")?;
+
+        for entry in entries {
+            let mut line = serde_json::to_string(entry)?;
+            line.push('\n');
+            file.write_all(line.as_bytes()).await
+                .context("Failed to write conversation log")?;
+        }
+
+        file.flush().await?;
+        Ok(())
+    }
+
+    pub fn read_entries(path: impl AsRef<Path>) -> Result<Vec<LogEntry>> {
+        let contents = std::fs::read_to_string(path.as_ref())
+            .context("Failed to read conversation log")?;
+
+        let mut entries = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(line)
+                .with_context(|| format!("Invalid log entry on line {}", i + 1))?;
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    /// Write the entries for `profile` (all profiles if `None`) to a CSV file
+    /// with one row per message, for spreadsheet tools
+    pub fn export_to_csv(&self, profile: Option<&str>, path: &Path) -> Result<()> {
+        let entries = filter_entries(Self::read_entries(&self.path)?, None, profile);
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create {:?}", path))?;
+        write_csv(&entries, file)
+    }
+
+    /// Write the entries for `profile` (all profiles if `None`) as a single
+    /// JSON array, for tools that don't read JSON Lines
+    pub fn export_to_json_array(&self, profile: Option<&str>, path: &Path) -> Result<()> {
+        let entries = filter_entries(Self::read_entries(&self.path)?, None, profile);
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create {:?}", path))?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &entries)
+            .context("Failed to write JSON export")?;
+        Ok(())
+    }
+}
+
+/// Title of the most recent titled conversation in `entries`
+pub fn latest_title(entries: &[LogEntry]) -> Option<&str> {
+    entries.iter().rev().find_map(|entry| entry.title.as_deref())
+}
+
+/// CSV with columns `timestamp,profile,role,content_length,word_count,content`
+fn write_csv(entries: &[LogEntry], writer: impl std::io::Write) -> Result<()> {
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(["timestamp", "profile", "role", "content_length", "word_count", "content"])?;
+
+    for entry in entries {
+        csv.write_record([
+            entry.ts.to_rfc3339(),
+            entry.profile.clone(),
+            entry.role.clone(),
+            entry.content.chars().count().to_string(),
+            entry.content.split_whitespace().count().to_string(),
+            entry.content.clone(),
+        ])?;
+    }
+
+    csv.flush().context("Failed to write CSV export")?;
+    Ok(())
+}
+
+/// Parse a `--since` argument, either a date (`2026-01-31`) or an RFC 3339 timestamp
+pub fn parse_since(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date '{}', expected YYYY-MM-DD", value))?;
+    Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
+pub fn filter_entries(
+    entries: Vec<LogEntry>,
+    since: Option<DateTime<Utc>>,
+    profile: Option<&str>,
+) -> Vec<LogEntry> {
+    entries
+        .into_iter()
+        .filter(|e| since.map_or(true, |since| e.ts >= since))
+        .filter(|e| profile.map_or(true, |p| e.profile == p))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn fixed_time() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap()
+    }
+
+    #[test]
+    fn test_log_entry_format() {
+        let entry = LogEntry::new(fixed_time(), "default", &Message::user("Hi"), "llama3.2:3b", 42);
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            json,
+            r#"{"ts":"2026-01-02T03:04:05Z","profile":"default","role":"user","content":"Hi","model":"llama3.2:3b","duration_ms":42}"#
+        );
+    }
+
+    #[test]
+    fn test_latest_title() {
+        let untitled = LogEntry::new(fixed_time(), "default", &Message::user("hi"), "m", 0);
+        let titled = untitled.clone().with_title(Some("Mounting NFS".to_string()));
+
+        assert_eq!(latest_title(&[titled.clone(), untitled.clone()]), Some("Mounting NFS"));
+        assert_eq!(latest_title(&[untitled]), None);
+        assert!(serde_json::to_string(&titled).unwrap().ends_with(r#""title":"Mounting NFS"}"#));
+    }
+
+    #[test]
+    fn test_filter_entries() {
+        let old = LogEntry::new(fixed_time(), "default", &Message::user("old"), "m", 0);
+        let new = LogEntry {
+            ts: Utc.with_ymd_and_hms(2026, 2, 1, 0, 0, 0).unwrap(),
+            profile: "sassy".to_string(),
+            ..old.clone()
+        };
+
+        let since = parse_since("2026-01-15").unwrap();
+        assert_eq!(filter_entries(vec![old.clone(), new.clone()], Some(since), None).len(), 1);
+        assert_eq!(filter_entries(vec![old, new], None, Some("default")).len(), 1);
+    }
+
+    #[test]
+    fn test_csv_quoting() {
+        let entry = LogEntry::new(
+            fixed_time(),
+            "default",
+            &Message::assistant("Yes, \"really\",\nsee below"),
+            "m",
+            0,
+        );
+
+        let mut out = Vec::new();
+        write_csv(&[entry], &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "timestamp,profile,role,content_length,word_count,content\n\
+             2026-01-02T03:04:05+00:00,default,assistant,24,4,\"Yes, \"\"really\"\",\nsee below\"\n"
+        );
+    }
+}
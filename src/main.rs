@@ -16,6 +16,24 @@ mod ui;
 mod hotkeys;
 mod state;
 mod first_run;
+mod logger;
+mod ipc;
+mod systemd;
+mod doctor;
+mod pipeline;
+mod pipe;
+mod accessibility;
+mod active_window;
+mod history;
+mod screenshot;
+mod voice_commands;
+mod title;
+mod sessions;
+mod model_registry;
+mod prompts;
+mod transcript;
+mod presets;
+mod updater;
 
 use crate::config::Config;
 use crate::profiles::ProfileManager;
@@ -30,8 +48,15 @@ struct Cli {
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
+
+    /// Keep trying to reach the daemon for a few seconds, e.g. right after starting it
+    #[arg(long, global = true)]
+    wait: bool,
 }
 
+/// How long `--wait` retries connecting to the daemon
+const DAEMON_WAIT: std::time::Duration = std::time::Duration::from_secs(5);
+
 #[derive(Subcommand)]
 enum Commands {
     /// Run the assistant daemon
@@ -40,11 +65,52 @@ enum Commands {
     /// Toggle assistant visibility
     Toggle,
     
+    /// Re-measure ambient noise for voice detection
+    Calibrate,
+    
+    /// Show daemon status and stats of the last response
+    Status,
+    
+    /// Re-read the config and reload the active profile's speech models
+    Reload,
+    
+    /// Watch voice detection counters live, to tune silence thresholds
+    VadStats,
+    
+    /// Print what the next request to the model would contain, as JSON
+    DebugContext {
+        /// Indent the JSON
+        #[arg(long)]
+        pretty: bool,
+    },
+    
+    /// Use the clipboard text as context for the next question
+    AskClipboard,
+    
+    /// Override profile settings in the running daemon until it restarts
+    Chat {
+        /// key=value, e.g. model=llama3.1 or temperature=0.2 (repeatable)
+        #[arg(long = "override", value_name = "KEY=VALUE", required_unless_present = "clear")]
+        overrides: Vec<String>,
+
+        /// Remove all session overrides
+        #[arg(long, conflicts_with = "overrides")]
+        clear: bool,
+    },
+    
     /// Run first-time setup
     Setup,
     
     /// List available profiles
-    Profiles,
+    Profiles {
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: ProfilesFormat,
+
+        /// Show each profile's effective system prompt instead
+        #[arg(long)]
+        prompts: bool,
+    },
     
     /// Create a new profile
     CreateProfile {
@@ -55,6 +121,158 @@ enum Commands {
         #[arg(short, long)]
         base: Option<String>,
     },
+
+    /// Create a profile combining two existing ones
+    MergeProfiles {
+        /// First profile
+        a: String,
+
+        /// Second profile
+        b: String,
+
+        /// Name of the new profile
+        new_name: String,
+
+        /// Which profile's settings win
+        #[arg(short, long, value_enum, default_value = "prefer-a")]
+        strategy: profiles::MergeStrategy,
+    },
+
+    /// Switch to a profile automatically when a trigger matches
+    AddRule {
+        /// window=<title regex> or time=<start>-<end>
+        #[arg(long)]
+        trigger: profiles::RuleTrigger,
+
+        /// Profile to switch to
+        #[arg(long)]
+        profile: String,
+    },
+
+    /// Export all profiles to a zip profile pack
+    ExportProfiles {
+        /// Output .zip file
+        file: std::path::PathBuf,
+    },
+    
+    /// Import profiles from a zip profile pack
+    ImportProfiles {
+        /// Profile pack .zip file
+        file: std::path::PathBuf,
+    },
+    
+    /// Check the setup and report problems
+    Doctor,
+    
+    /// List models installed in Ollama
+    ListModels {
+        /// List the Whisper, Piper and wake word models on disk instead
+        #[arg(long)]
+        local: bool,
+    },
+    
+    /// Send stdin to the model and print the response
+    Pipe {
+        /// Profile to use instead of the active one
+        #[arg(short, long)]
+        profile: Option<String>,
+
+        /// System prompt to use instead of the profile's
+        #[arg(short, long)]
+        system: Option<String>,
+
+        /// Print the response as it is generated
+        #[arg(long)]
+        stream: bool,
+    },
+    
+    /// Synthesize speech to an audio file
+    Say {
+        /// Text to speak
+        text: String,
+
+        /// Output file (.wav or .mp3)
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+
+        /// Profile whose voice to use instead of the active one
+        #[arg(short, long)]
+        profile: Option<String>,
+    },
+    
+    /// Transcribe a WAV recording through the live speech recognition path,
+    /// without starting the assistant or opening a microphone
+    #[command(visible_alias = "transcribe")]
+    Transcript {
+        /// Input .wav file
+        input: std::path::PathBuf,
+
+        /// Output file; stdout if omitted
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+
+        /// Spoken language code such as "de"; audio.stt_language or detected if omitted
+        #[arg(short, long)]
+        language: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "plain")]
+        format: transcript::TranscriptFormat,
+    },
+    
+    /// Show the conversation log
+    Logs {
+        /// Only show entries on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only show entries for this profile
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    
+    /// List saved conversations, or print one
+    History {
+        /// ID of the conversation to print, as shown in the list
+        id: Option<String>,
+    },
+    
+    /// Check for a newer release
+    UpdateCheck {
+        /// Download the new release and replace this executable with it
+        #[arg(long)]
+        apply: bool,
+    },
+    
+    /// Export the conversation log for spreadsheets and other tools
+    Export {
+        /// Output file (named after the latest conversation title if omitted)
+        output: Option<std::path::PathBuf>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+
+        /// Only export entries for this profile
+        #[arg(long)]
+        profile: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ProfilesFormat {
+    /// Aligned columns for reading
+    Table,
+    /// A JSON array of profiles, for scripts and status bars
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ExportFormat {
+    /// One row per message
+    Csv,
+    /// A single JSON array of log entries
+    Json,
 }
 
 #[tokio::main]
@@ -63,20 +281,62 @@ async fn main() -> Result<()> {
     
     // Initialize logging
     let log_level = if cli.verbose { "debug" } else { "info" };
+    // Keep stdout clean for the response in pipe mode and for transcripts
+    let log_to_stderr = matches!(
+        cli.command,
+        Some(Commands::Pipe { .. } | Commands::Transcript { .. })
+    );
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| format!("blipply_assistant={}", log_level).into())
         )
+        .with_writer(move || -> Box<dyn std::io::Write> {
+            if log_to_stderr {
+                Box::new(std::io::stderr())
+            } else {
+                Box::new(std::io::stdout())
+            }
+        })
         .init();
 
-    match cli.command {
+    let wait = cli.wait.then_some(DAEMON_WAIT);
+    let result = match cli.command {
         Some(Commands::Daemon) | None => run_daemon().await,
-        Some(Commands::Toggle) => toggle_assistant().await,
+        Some(Commands::Toggle) => toggle_assistant(wait).await,
+        Some(Commands::Calibrate) => send_daemon_command(ipc::IpcCommand::Calibrate, wait).await,
+        Some(Commands::Status) => show_status(wait).await,
+        Some(Commands::Reload) => reload_daemon(wait).await,
+        Some(Commands::VadStats) => watch_vad_stats(wait).await,
+        Some(Commands::DebugContext { pretty }) => show_context(pretty, wait).await,
+        Some(Commands::AskClipboard) => send_daemon_command(ipc::IpcCommand::AskAboutClipboard, wait).await,
+        Some(Commands::Chat { overrides, clear }) => set_chat_overrides(&overrides, clear, wait).await,
         Some(Commands::Setup) => run_setup().await,
-        Some(Commands::Profiles) => list_profiles().await,
+        Some(Commands::Profiles { format, prompts }) => list_profiles(format, prompts).await,
         Some(Commands::CreateProfile { name, base }) => create_profile(&name, base.as_deref()).await,
+        Some(Commands::MergeProfiles { a, b, new_name, strategy }) => merge_profiles(&a, &b, &new_name, strategy).await,
+        Some(Commands::AddRule { trigger, profile }) => add_rule(trigger, profile).await,
+        Some(Commands::ExportProfiles { file }) => export_profiles(&file).await,
+        Some(Commands::Export { output, format, profile }) => export_log(output, format, profile.as_deref()),
+        Some(Commands::ImportProfiles { file }) => import_profiles(&file).await,
+        Some(Commands::Doctor) => run_doctor().await,
+        Some(Commands::ListModels { local }) => list_models(local).await,
+        Some(Commands::Pipe { profile, system, stream }) => run_pipe(profile.as_deref(), system, stream).await,
+        Some(Commands::Say { text, output, profile }) => run_say(&text, &output, profile.as_deref()).await,
+        Some(Commands::Transcript { input, output, language, format }) => {
+            run_transcript(&input, output.as_deref(), language, format)
+        }
+        Some(Commands::Logs { since, profile }) => show_logs(since.as_deref(), profile.as_deref()).await,
+        Some(Commands::History { id }) => show_history(id.as_deref()),
+        Some(Commands::UpdateCheck { apply }) => run_update_check(apply).await,
+    };
+
+    // Daemon connection failures get their own exit codes for scripts
+    if let Some(e) = result.as_ref().err().and_then(|e| e.downcast_ref::<ipc::ClientError>()) {
+        error!("{}", e);
+        std::process::exit(e.exit_code());
     }
+    result
 }
 
 async fn run_daemon() -> Result<()> {
@@ -106,10 +366,55 @@ async fn run_daemon() -> Result<()> {
         }
     });
     
+    // Start audio pipelines
+    if let Err(e) = state.initialize_audio().await {
+        if state.config.read().audio.require_audio {
+            return Err(e.context("Failed to initialize audio (audio.require_audio is set)"));
+        }
+        error!("Failed to initialize audio: {}", e);
+    }
+    
     // Create UI
     let window = ui::create_window(state.clone())?;
     window.present();
     
+    if state.config.read().general.update_check_on_startup {
+        let update_url = state.config.read().general.update_url.clone();
+        let update_state = state.clone();
+        tokio::spawn(async move {
+            match updater::UpdateChecker::new(update_url).check().await {
+                Ok(Some(update)) => {
+                    info!("Blipply {} is available", update.version);
+                    update_state.send_ui_command(state::UiCommand::ShowToast(format!(
+                        "Blipply {} is available",
+                        update.version
+                    )));
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Update check failed: {:#}", e),
+            }
+        });
+    }
+    
+    // Listen for IPC commands
+    let listener = ipc::bind()?;
+    tokio::spawn(ipc::run_server(listener, state.clone()));
+    
+    // Shut down cleanly on SIGINT/SIGTERM
+    let main_loop = glib::MainLoop::new(None, false);
+    let shutdown_state = state.clone();
+    let shutdown_loop = main_loop.clone();
+    tokio::spawn(async move {
+        if let Err(e) = wait_for_shutdown_signal().await {
+            error!("Failed to install signal handler: {}", e);
+            return;
+        }
+        systemd::notify_stopping();
+        shutdown_state.shutdown();
+        remove_socket();
+        glib::MainContext::default().invoke(move || shutdown_loop.quit());
+    });
+    
     // Run GTK main loop
     let main_context = glib::MainContext::default();
     main_context.spawn_local(async move {
@@ -117,46 +422,254 @@ async fn run_daemon() -> Result<()> {
     });
     
     info!("Assistant ready");
-    gtk::main();
+    systemd::notify_ready();
+    systemd::start_watchdog();
+    main_loop.run();
     
+    info!("Daemon stopped");
     Ok(())
 }
 
-async fn toggle_assistant() -> Result<()> {
-    // Send IPC message to daemon to toggle visibility
-    use std::os::unix::net::UnixStream;
-    use std::io::Write;
-    
-    let socket_path = dirs::runtime_dir()
-        .unwrap_or_else(|| std::env::temp_dir())
-        .join("blipply-assistant.sock");
-    
-    if let Ok(mut stream) = UnixStream::connect(&socket_path) {
-        stream.write_all(b"TOGGLE\n")?;
-        info!("Toggle command sent");
+async fn wait_for_shutdown_signal() -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+
+    tokio::select! {
+        _ = sigterm.recv() => info!("Received SIGTERM, shutting down"),
+        _ = sigint.recv() => info!("Received SIGINT, shutting down"),
+    }
+
+    Ok(())
+}
+
+fn remove_socket() {
+    let socket_path = Config::socket_path();
+    if socket_path.exists() {
+        if let Err(e) = std::fs::remove_file(&socket_path) {
+            error!("Failed to remove socket {:?}: {}", socket_path, e);
+        }
+    }
+}
+
+async fn toggle_assistant(wait: Option<std::time::Duration>) -> Result<()> {
+    send_daemon_command(ipc::IpcCommand::Toggle, wait).await
+}
+
+async fn send_daemon_command(command: ipc::IpcCommand, wait: Option<std::time::Duration>) -> Result<()> {
+    ipc::IpcClient::connect(wait)?.send(&command)?;
+    info!("{} command sent", command.to_line());
+    Ok(())
+}
+
+async fn set_chat_overrides(overrides: &[String], clear: bool, wait: Option<std::time::Duration>) -> Result<()> {
+    let command = if clear {
+        ipc::IpcCommand::ClearOverrides
     } else {
-        error!("Could not connect to daemon. Is it running?");
+        ipc::IpcCommand::SetOverrides(profiles::ProfileOverrides::from_pairs(overrides)?)
+    };
+
+    send_daemon_command(command, wait).await
+}
+
+async fn show_status(wait: Option<std::time::Duration>) -> Result<()> {
+    let reply = ipc::IpcClient::connect(wait)?.request(&ipc::IpcCommand::Status)?;
+    println!("{}", reply);
+    Ok(())
+}
+
+async fn reload_daemon(wait: Option<std::time::Duration>) -> Result<()> {
+    let reply = ipc::IpcClient::connect(wait)?.request(&ipc::IpcCommand::Reload)?;
+    match reply.strip_prefix("error: ") {
+        Some(reason) => anyhow::bail!("Reload failed: {}", reason),
+        None if reply == "ok" => {
+            info!("Configuration reloaded");
+            Ok(())
+        }
+        None => Err(ipc::ClientError::BadResponse(reply).into()),
+    }
+}
+
+async fn run_update_check(apply: bool) -> Result<()> {
+    let update_url = Config::load()?.general.update_url;
+    let checker = updater::UpdateChecker::new(update_url);
+
+    let Some(update) = checker.check().await? else {
+        println!("Blipply {} is up to date", updater::CURRENT_VERSION);
+        return Ok(());
+    };
+
+    println!("Blipply {} is available (running {})", update.version, updater::CURRENT_VERSION);
+    if let Some(url) = &update.url {
+        println!("{}", url);
+    }
+    if apply {
+        let exe = checker.apply(&update).await?;
+        println!("Installed {} to {}; restart the daemon to use it", update.version, exe.display());
+    } else {
+        println!("Run with --apply to install it");
     }
-    
     Ok(())
 }
 
+async fn show_context(pretty: bool, wait: Option<std::time::Duration>) -> Result<()> {
+    let reply = ipc::IpcClient::connect(wait)?.request(&ipc::IpcCommand::GetContext)?;
+    let snapshot: state::ContextSnapshot = serde_json::from_str(&reply)
+        .map_err(|_| ipc::ClientError::BadResponse(reply))?;
+
+    if pretty {
+        println!("{}", serde_json::to_string_pretty(&snapshot)?);
+    } else {
+        println!("{}", serde_json::to_string(&snapshot)?);
+    }
+    Ok(())
+}
+
+/// Print the daemon's VAD counters every second until interrupted
+async fn watch_vad_stats(wait: Option<std::time::Duration>) -> Result<()> {
+    use std::io::Write;
+
+    let mut client = ipc::IpcClient::connect(wait)?;
+
+    println!(
+        "{:>8} {:>8} {:>8} {:>7} {:>12} {:>10}",
+        "frames", "speech", "silence", "ratio", "last speech", "silence"
+    );
+    loop {
+        let reply = client.request(&ipc::IpcCommand::VadStats)?;
+        let stats = serde_json::from_str::<Option<audio::VadStats>>(&reply)
+            .map_err(|_| ipc::ClientError::BadResponse(reply))?;
+
+        match stats {
+            Some(stats) => print!(
+                "\r{:>8} {:>8} {:>8} {:>6.1}% {:>10}ms {:>8}ms",
+                stats.total_frames,
+                stats.speech_frames,
+                stats.silence_frames,
+                stats.speech_ratio * 100.0,
+                stats.last_speech_duration_ms,
+                stats.current_silence_ms,
+            ),
+            None => print!("\rAudio capture is not running{:30}", ""),
+        }
+        std::io::stdout().flush()?;
+
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
 async fn run_setup() -> Result<()> {
     info!("Running first-time setup");
     first_run::run_interactive_setup().await?;
     Ok(())
 }
 
-async fn list_profiles() -> Result<()> {
+async fn run_doctor() -> Result<()> {
+    if !doctor::run().await? {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn run_pipe(profile: Option<&str>, system: Option<String>, stream: bool) -> Result<()> {
+    let config = Config::load()?;
+    let manager = ProfileManager::from_config(&config);
+
+    let profile = select_profile(&manager, profile)?;
+    let system_prompt = system.unwrap_or_else(|| manager.get_system_prompt(profile));
+
+    let client = ollama::OllamaClient::with_hosts(
+        config.ollama_hosts(),
+        ollama::OllamaClientConfig::default(),
+    );
+
+    pipe::run(
+        &client,
+        &profile.model,
+        system_prompt,
+        stream,
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+    )
+    .await
+}
+
+async fn run_say(text: &str, output: &std::path::Path, profile: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+    let manager = ProfileManager::from_config(&config);
+    let profile = select_profile(&manager, profile)?;
+
+    let voice_path = config.piper_voice_path(&profile.voice_model)?;
+    let config_path = voice_path.with_extension("json");
+    let tts = audio::TtsPipeline::new(voice_path, config_path, profile.tts_speed, None)?;
+
+    tts.synthesize_to_file(text, output)?;
+
+    println!("Wrote {:?}", output);
+    Ok(())
+}
+
+fn run_transcript(
+    input: &std::path::Path,
+    output: Option<&std::path::Path>,
+    language: Option<String>,
+    format: transcript::TranscriptFormat,
+) -> Result<()> {
+    let config = Config::load()?;
+    let manager = ProfileManager::from_config(&config);
+    let (event_tx, _) = audio::create_audio_channel();
+    let stt = state::build_stt(&config, manager.active_profile()?, event_tx)?;
+    if language.is_some() {
+        stt.set_language(language);
+    }
+
+    let transcription = stt.transcribe_file(input, format.needs_timestamps())?;
+    let rendered = transcript::render(&transcription, format)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, rendered)
+                .map_err(|e| anyhow::anyhow!("Failed to write {:?}: {}", path, e))?;
+            println!("Wrote {:?}", path);
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+/// The named profile, or the active one
+fn select_profile<'a>(manager: &'a ProfileManager, id: Option<&str>) -> Result<&'a profiles::VoiceProfile> {
+    match id {
+        Some(id) => manager.profiles.get(id)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", id)),
+        None => manager.active_profile(),
+    }
+}
+
+async fn list_profiles(format: ProfilesFormat, prompts: bool) -> Result<()> {
     let config = Config::load()?;
     let manager = ProfileManager::from_config(&config);
+
+    if prompts {
+        let prompts: std::collections::BTreeMap<String, String> =
+            manager.get_all_system_prompts().into_iter().collect();
+        match format {
+            ProfilesFormat::Table => {
+                for (id, prompt) in &prompts {
+                    println!("[{}]\n{}\n", id, prompt);
+                }
+            }
+            ProfilesFormat::Json => println!("{}", serde_json::to_string_pretty(&prompts)?),
+        }
+        return Ok(());
+    }
+
+    let summaries = manager.summaries();
     
-    println!("Available profiles:");
-    for (id, profile) in &manager.profiles {
-        let active = if id == &manager.active { " (active)" } else { "" };
-        println!("  {} - {}{}", id, profile.name, active);
-        println!("    Model: {}", profile.model);
-        println!("    Voice: {}", profile.voice_model);
+    match format {
+        ProfilesFormat::Table => print!("{}", profiles::format_profile_table(&summaries)),
+        ProfilesFormat::Json => println!("{}", serde_json::to_string_pretty(&summaries)?),
     }
     
     Ok(())
@@ -175,3 +688,181 @@ async fn create_profile(name: &str, base: Option<&str>) -> Result<()> {
     println!("Profile '{}' created successfully", name);
     Ok(())
 }
+
+async fn merge_profiles(a: &str, b: &str, new_name: &str, strategy: profiles::MergeStrategy) -> Result<()> {
+    let mut config = Config::load()?;
+    let mut manager = ProfileManager::from_config(&config);
+
+    manager.merge_profiles(a, b, new_name, strategy)?;
+
+    config.profiles = manager.into_config_map();
+    config.save()?;
+
+    println!("Profile '{}' created from '{}' and '{}'", new_name, a, b);
+    Ok(())
+}
+
+async fn add_rule(trigger: profiles::RuleTrigger, profile: String) -> Result<()> {
+    let mut config = Config::load()?;
+    
+    if !config.profiles.contains_key(&profile) {
+        anyhow::bail!("Profile '{}' not found", profile);
+    }
+    
+    config.general.profile_rules.push(profiles::ProfileRule { trigger, profile: profile.clone() });
+    config.save()?;
+    
+    println!("Rule added; will switch to '{}' when it matches", profile);
+    Ok(())
+}
+
+async fn export_profiles(file: &std::path::Path) -> Result<()> {
+    let config = Config::load()?;
+    let manager = ProfileManager::from_config(&config);
+    
+    manager.export_all(file)?;
+    
+    println!("Exported {} profile(s) to {:?}", manager.profiles.len(), file);
+    Ok(())
+}
+
+async fn import_profiles(file: &std::path::Path) -> Result<()> {
+    let mut config = Config::load()?;
+    
+    let imported = ProfileManager::import_pack(file, &mut config)?;
+    config.save()?;
+    
+    println!("Imported profile(s): {}", imported.join(", "));
+    Ok(())
+}
+
+fn export_log(output: Option<std::path::PathBuf>, format: ExportFormat, profile: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+
+    let Some(path) = config.conversation_log_path()? else {
+        println!("Conversation logging is disabled. Set general.conversation_log_path to enable it.");
+        return Ok(());
+    };
+
+    // e.g. "mounting-nfs-on-nixos.csv"
+    let output = match output {
+        Some(output) => output,
+        None => {
+            let entries = logger::filter_entries(logger::ConversationLogger::read_entries(&path)?, None, profile);
+            let name = logger::latest_title(&entries)
+                .map(profiles::profile_id)
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "conversation".to_string());
+            let extension = match format {
+                ExportFormat::Csv => "csv",
+                ExportFormat::Json => "json",
+            };
+            std::path::PathBuf::from(format!("{}.{}", name, extension))
+        }
+    };
+    let output = output.as_path();
+
+    let log = logger::ConversationLogger::new(path);
+    match format {
+        ExportFormat::Csv => log.export_to_csv(profile, output)?,
+        ExportFormat::Json => log.export_to_json_array(profile, output)?,
+    }
+
+    println!("Exported conversation log to {:?}", output);
+    Ok(())
+}
+
+async fn list_models(local: bool) -> Result<()> {
+    let config = Config::load()?;
+
+    if !local {
+        let client = ollama::OllamaClient::with_hosts(config.ollama_hosts(), ollama::OllamaClientConfig::default());
+        for model in client.list_models().await? {
+            println!("{}", model);
+        }
+        return Ok(());
+    }
+
+    let models_dir = config.models_dir()?;
+    let mut registry = model_registry::LocalModelRegistry::scan(&models_dir)?;
+    registry.hash_all()?;
+
+    let mut models: Vec<_> = registry.models().iter().collect();
+    if models.is_empty() {
+        println!("No models found in {:?}", models_dir);
+    }
+    models.sort_by(|a, b| a.0.cmp(b.0));
+    for (id, entry) in models {
+        println!(
+            "{:<10} {:>8.1} MB  {}  {}",
+            entry.model_type.name(),
+            entry.size_bytes as f64 / 1_000_000.0,
+            entry.sha256.as_deref().unwrap_or("-"),
+            id,
+        );
+    }
+    Ok(())
+}
+
+fn show_history(id: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+    let store = sessions::SessionStore::new(config.sessions_dir()?);
+
+    let Some(id) = id else {
+        let sessions = store.list()?;
+        if sessions.is_empty() {
+            println!("No saved conversations");
+        }
+        for session in sessions {
+            println!(
+                "{}  {}  {:>3} turns  {}",
+                session.id,
+                session.updated.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                session.turn_count(),
+                session.display_title(),
+            );
+        }
+        return Ok(());
+    };
+
+    let session = store.load(id)?;
+    println!("{} ({})", session.display_title(), session.profile);
+    for message in &session.messages {
+        println!();
+        println!("{}:", message.role);
+        for line in message.content.lines() {
+            println!("    {}", line);
+        }
+    }
+    Ok(())
+}
+
+async fn show_logs(since: Option<&str>, profile: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+
+    let Some(path) = config.conversation_log_path()? else {
+        println!("Conversation logging is disabled. Set general.conversation_log_path to enable it.");
+        return Ok(());
+    };
+
+    let since = since.map(logger::parse_since).transpose()?;
+    let entries = logger::ConversationLogger::read_entries(&path)?;
+
+    for entry in logger::filter_entries(entries, since, profile) {
+        println!(
+            "[{}] {} ({}) {}:",
+            entry.ts.format("%Y-%m-%d %H:%M:%S"),
+            entry.profile,
+            entry.model,
+            entry.role,
+        );
+        for line in entry.content.lines() {
+            println!("    {}", line);
+        }
+        if entry.role == "assistant" {
+            println!("    ({} ms)", entry.duration_ms);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+// Blipply Assistant - Local Models
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::{Result, Context};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Path of a model relative to the models directory, e.g. "whisper/ggml-base.en.bin"
+pub type ModelId = String;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelType {
+    Whisper,
+    Piper,
+    WakeWord,
+}
+
+impl ModelType {
+    /// Recognise a model file by its extension
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "bin" => Some(Self::Whisper),
+            "onnx" => Some(Self::Piper),
+            "rpw" => Some(Self::WakeWord),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Whisper => "whisper",
+            Self::Piper => "piper",
+            Self::WakeWord => "wake word",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModelEntry {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// Only computed by `hash_all`, since hashing large Whisper models is slow
+    pub sha256: Option<String>,
+    pub model_type: ModelType,
+}
+
+/// Whisper, Piper and wake word models found under the models directory,
+/// wherever they sit in it
+#[derive(Debug, Default)]
+pub struct LocalModelRegistry {
+    models: HashMap<ModelId, ModelEntry>,
+}
+
+impl LocalModelRegistry {
+    /// Scan `models_dir` recursively. A missing directory gives an empty registry.
+    pub fn scan(models_dir: &Path) -> Result<Self> {
+        let mut models = HashMap::new();
+        if models_dir.is_dir() {
+            scan_dir(models_dir, models_dir, &mut models)?;
+        }
+        debug!("Found {} local model(s) in {:?}", models.len(), models_dir);
+        Ok(Self { models })
+    }
+
+    pub fn models(&self) -> &HashMap<ModelId, ModelEntry> {
+        &self.models
+    }
+
+    /// Fill in `sha256` for every model
+    pub fn hash_all(&mut self) -> Result<()> {
+        for entry in self.models.values_mut() {
+            entry.sha256 = Some(sha256_file(&entry.path)?);
+        }
+        Ok(())
+    }
+
+    /// Whisper model called `name`, e.g. "base.en" for "ggml-base.en.bin"
+    pub fn find_whisper(&self, name: &str) -> Option<PathBuf> {
+        self.find(ModelType::Whisper, name)
+    }
+
+    /// Piper voice called `name`, e.g. "en_US-lessac-medium"
+    pub fn find_piper(&self, name: &str) -> Option<PathBuf> {
+        self.find(ModelType::Piper, name)
+    }
+
+    /// Best match for `name`: an exact file name or stem first, then a stem
+    /// without the "ggml-" prefix, then the shortest stem starting with it
+    fn find(&self, model_type: ModelType, name: &str) -> Option<PathBuf> {
+        self.models.iter()
+            .filter(|(_, entry)| entry.model_type == model_type)
+            .filter_map(|(id, entry)| {
+                let stem = entry.path.file_stem()?.to_str()?;
+                let file_name = entry.path.file_name()?.to_str()?;
+                match_rank(name, file_name, stem).map(|rank| ((rank, stem.len(), id), entry))
+            })
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, entry)| entry.path.clone())
+    }
+}
+
+fn match_rank(name: &str, file_name: &str, stem: &str) -> Option<u8> {
+    let bare = stem.strip_prefix("ggml-").unwrap_or(stem);
+    if name == file_name || name == stem {
+        Some(0)
+    } else if name == bare {
+        Some(1)
+    } else if bare.starts_with(name) || stem.starts_with(name) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn scan_dir(root: &Path, dir: &Path, models: &mut HashMap<ModelId, ModelEntry>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))?;
+
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            if let Err(e) = scan_dir(root, &path, models) {
+                warn!("Skipping {:?}: {:#}", path, e);
+            }
+            continue;
+        }
+
+        let Some(model_type) = ModelType::from_path(&path) else {
+            continue;
+        };
+        let id = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().into_owned();
+        models.insert(id, ModelEntry {
+            size_bytes: entry.metadata()?.len(),
+            path,
+            sha256: None,
+            model_type,
+        });
+    }
+
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_matching() {
+        let dir = std::env::temp_dir().join(format!("blipply-models-{}", uuid::Uuid::new_v4()));
+        for file in [
+            "whisper/ggml-base.en.bin",
+            "whisper/ggml-base.en-q5_1.bin",
+            "whisper/extra/small.en.bin",
+            "voices/en_US-lessac-medium.onnx",
+            "voices/en_US-lessac-medium.onnx.json",
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"model").unwrap();
+        }
+
+        let registry = LocalModelRegistry::scan(&dir).unwrap();
+        assert_eq!(registry.models().len(), 4);
+
+        assert_eq!(registry.find_whisper("base.en"), Some(dir.join("whisper/ggml-base.en.bin")));
+        assert_eq!(registry.find_whisper("small.en"), Some(dir.join("whisper/extra/small.en.bin")));
+        assert_eq!(registry.find_whisper("base"), Some(dir.join("whisper/ggml-base.en.bin")));
+        assert_eq!(registry.find_whisper("medium.en"), None);
+        assert_eq!(registry.find_piper("en_US-lessac"), Some(dir.join("voices/en_US-lessac-medium.onnx")));
+        // Whisper names don't match Piper voices
+        assert_eq!(registry.find_piper("base.en"), None);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_sha256() {
+        let path = std::env::temp_dir().join(format!("blipply-model-{}.bin", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -2,19 +2,31 @@
 // Copyright (c) 2026 DeMoD LLC
 // Licensed under the MIT License
 
-use anyhow::{Result, Context};
+use anyhow::{bail, Result, Context};
+use chrono::{DateTime, Utc};
 use futures::Stream;
 use pin_project::pin_project;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task::{Context as TaskContext, Poll};
-use tracing::{debug, error};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
     pub content: String,
+    /// Base64-encoded images for vision models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    /// When the message was written; absent in older saved messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
 }
 
 impl Message {
@@ -22,6 +34,8 @@ impl Message {
         Self {
             role: "user".to_string(),
             content: content.into(),
+            images: Vec::new(),
+            timestamp: Some(Utc::now()),
         }
     }
 
@@ -29,6 +43,8 @@ impl Message {
         Self {
             role: "assistant".to_string(),
             content: content.into(),
+            images: Vec::new(),
+            timestamp: Some(Utc::now()),
         }
     }
 
@@ -36,8 +52,16 @@ impl Message {
         Self {
             role: "system".to_string(),
             content: content.into(),
+            images: Vec::new(),
+            timestamp: None,
         }
     }
+
+    /// Attach base64-encoded images
+    pub fn with_images(mut self, images: Vec<String>) -> Self {
+        self.images = images;
+        self
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -47,36 +71,311 @@ struct ChatRequest {
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<GenerationOptions>,
+    /// `"json"` or a JSON schema constraining the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Serialize)]
-struct GenerationOptions {
-    temperature: f32,
-    num_ctx: u32,
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationOptions {
+    pub temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    pub num_ctx: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    /// Sent as the request's top-level `format`, not as a model option
+    #[serde(skip)]
+    pub format: Option<serde_json::Value>,
+}
+
+impl Default for GenerationOptions {
+    fn default() -> Self {
+        Self {
+            temperature: 0.7,
+            top_p: None,
+            top_k: None,
+            num_ctx: 4096,
+            stop: Vec::new(),
+            num_predict: None,
+            format: None,
+        }
+    }
+}
+
+/// How often `watch_models` polls `/api/tags`
+const MODEL_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Model families known to accept images
+const VISION_MODELS: &[&str] = &[
+    "llava", "bakllava", "moondream", "minicpm-v", "vision", "gemma3", "qwen2.5vl", "granite3.2-vision",
+];
+
+/// Whether `model` accepts images, judged by its name
+pub fn is_vision_model(model: &str) -> bool {
+    let model = model.to_ascii_lowercase();
+    VISION_MODELS.iter().any(|family| model.contains(family))
+}
+
+/// Base64 encoding Ollama expects for `images`
+pub fn encode_image(bytes: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Read and encode an image file for a multimodal request
+pub async fn encode_image_file(path: &Path) -> Result<String> {
+    let bytes = tokio::fs::read(path).await
+        .with_context(|| format!("Failed to read image {:?}", path))?;
+    Ok(encode_image(&bytes))
+}
+
+/// Byte offset of the earliest stop sequence in `text`, if any
+pub fn find_stop_sequence(text: &str, stop: &[String]) -> Option<usize> {
+    stop.iter()
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| text.find(s.as_str()))
+        .min()
+}
+
+/// Byte length of the longest end of `text` that a stop sequence starts
+/// with. That much has to be held back until the next chunk shows whether
+/// the stop sequence completes.
+pub fn partial_stop_len(text: &str, stop: &[String]) -> usize {
+    stop.iter()
+        .filter_map(|s| {
+            (1..s.len()).rev()
+                .filter(|&n| s.is_char_boundary(n))
+                .find(|&n| text.ends_with(&s[..n]))
+        })
+        .max()
+        .unwrap_or(0)
 }
 
 #[derive(Debug, Deserialize)]
 struct ChatResponse {
     message: Message,
     done: bool,
+    /// Request ID, sent by Ollama builds that support cancelling
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(flatten)]
+    stats: GenerationStats,
+}
+
+/// Timing and token counts Ollama reports on the final message of a response.
+/// Durations are in nanoseconds; any field may be missing.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct GenerationStats {
+    #[serde(default)]
+    pub total_duration: Option<u64>,
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    pub prompt_eval_duration: Option<u64>,
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+    #[serde(default)]
+    pub eval_duration: Option<u64>,
+}
+
+impl GenerationStats {
+    /// Generation speed as measured by the server
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        match (self.eval_count, self.eval_duration) {
+            (Some(count), Some(duration)) if duration > 0 => {
+                Some(count as f64 / (duration as f64 / 1_000_000_000.0))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A model Ollama currently holds in memory, as listed by `/api/ps`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunningModel {
+    pub name: String,
+    #[serde(rename = "size_vram", default)]
+    pub size_vram_bytes: u64,
+    /// When Ollama unloads the model unless it's used again
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl RunningModel {
+    /// Name and memory use, e.g. "llama3.2:3b (2.1 GB)"
+    pub fn label(&self) -> String {
+        format!("{} ({:.1} GB)", self.name, self.size_vram_bytes as f64 / 1_000_000_000.0)
+    }
+}
+
+/// Appends every request sent to Ollama and the reassembled response to a
+/// file, for attaching to bug reports
+pub struct DebugTranscript {
+    path: PathBuf,
+    redact_system_prompt: bool,
+    /// Keeps concurrent requests from interleaving their entries
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl DebugTranscript {
+    pub fn new(path: impl Into<PathBuf>, redact_system_prompt: bool) -> Self {
+        Self {
+            path: path.into(),
+            redact_system_prompt,
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn render_request(&self, request: &ChatRequest) -> Result<String> {
+        let mut value = serde_json::to_value(request)?;
+        if self.redact_system_prompt {
+            if let Some(messages) = value["messages"].as_array_mut() {
+                for message in messages.iter_mut().filter(|m| m["role"] == "system") {
+                    message["content"] = serde_json::Value::String("[redacted]".to_string());
+                }
+            }
+        }
+        Ok(serde_json::to_string_pretty(&value)?)
+    }
+
+    async fn append(&self, label: &str, body: &str) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let entry = format!("=== {} {} ===\n{}\n\n", chrono::Utc::now().to_rfc3339(), label, body);
+
+        let _guard = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open debug transcript {:?}", self.path))?;
+        file.write_all(entry.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn log_request(&self, request: &ChatRequest) {
+        let result = match self.render_request(request) {
+            Ok(body) => self.append("request", &body).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("Failed to write debug transcript: {}", e);
+        }
+    }
+
+    async fn log_response(&self, response: &str) {
+        if let Err(e) = self.append("response", response).await {
+            warn!("Failed to write debug transcript: {}", e);
+        }
+    }
 }
 
+/// The response reassembled from a stream, logged when the stream ends or,
+/// if it is dropped first (cancel, stop sequence), from `Drop`
+struct ResponseLog {
+    transcript: Option<Arc<DebugTranscript>>,
+    response: String,
+}
+
+impl ResponseLog {
+    async fn finish(mut self) {
+        if let Some(transcript) = self.transcript.take() {
+            transcript.log_response(&self.response).await;
+        }
+    }
+}
+
+impl Drop for ResponseLog {
+    fn drop(&mut self) {
+        if let Some(transcript) = self.transcript.take() {
+            let response = std::mem::take(&mut self.response);
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(async move { transcript.log_response(&response).await });
+            }
+        }
+    }
+}
+
+/// HTTP connection settings for `OllamaClient`
+#[derive(Debug, Clone)]
+pub struct OllamaClientConfig {
+    pub max_idle_connections: usize,
+    pub connect_timeout_ms: u64,
+    /// Covers the whole response including streamed output; 0 disables it
+    pub request_timeout_ms: u64,
+}
+
+impl Default for OllamaClientConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_connections: 4,
+            connect_timeout_ms: 5000,
+            request_timeout_ms: 0,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct OllamaClient {
     client: Client,
-    base_url: String,
+    hosts: Arc<Vec<String>>,
+    /// Index into `hosts` of the last host that accepted a connection
+    preferred: Arc<AtomicUsize>,
+    transcript: Option<Arc<DebugTranscript>>,
 }
 
 impl OllamaClient {
     pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_config(base_url, OllamaClientConfig::default())
+    }
+
+    /// Create a client whose connections are kept alive and reused across requests
+    pub fn with_config(base_url: impl Into<String>, config: OllamaClientConfig) -> Self {
+        Self::with_hosts(vec![base_url.into()], config)
+    }
+
+    /// Create a client that fails over between hosts, trying them in order
+    /// starting from the last one that worked
+    pub fn with_hosts(hosts: Vec<String>, config: OllamaClientConfig) -> Self {
+        assert!(!hosts.is_empty(), "OllamaClient needs at least one host");
+
+        let mut builder = Client::builder()
+            .tcp_keepalive(Duration::from_secs(60))
+            .pool_max_idle_per_host(config.max_idle_connections)
+            .connection_verbose(false)
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms));
+
+        if config.request_timeout_ms > 0 {
+            builder = builder.timeout(Duration::from_millis(config.request_timeout_ms));
+        }
+
         Self {
-            client: Client::new(),
-            base_url: base_url.into(),
+            client: builder.build().expect("Failed to build HTTP client"),
+            hosts: Arc::new(hosts),
+            preferred: Arc::new(AtomicUsize::new(0)),
+            transcript: None,
         }
     }
 
+    /// Record all chat traffic to a debug transcript
+    pub fn with_debug_transcript(mut self, transcript: DebugTranscript) -> Self {
+        self.transcript = Some(Arc::new(transcript));
+        self
+    }
+
+    /// The host requests currently go to first
+    pub fn base_url(&self) -> &str {
+        &self.hosts[self.preferred.load(Ordering::Relaxed)]
+    }
+
     pub async fn list_models(&self) -> Result<Vec<String>> {
-        let url = format!("{}/api/tags", self.base_url);
-        
         #[derive(Deserialize)]
         struct TagsResponse {
             models: Vec<ModelInfo>,
@@ -87,43 +386,80 @@ impl OllamaClient {
             name: String,
         }
         
-        let response = self.client
-            .get(&url)
-            .send()
-            .await
-            .context("Failed to fetch models")?;
+        let response = send_with_failover(&self.client, &self.hosts, &self.preferred, |client, host| {
+            client.get(format!("{}/api/tags", host))
+        })
+        .await
+        .context("Failed to fetch models")?;
         
         let tags: TagsResponse = response.json().await?;
         Ok(tags.models.into_iter().map(|m| m.name).collect())
     }
 
+    /// Models currently loaded into memory on the preferred host. Only that
+    /// host is asked, so a background poll never moves the failover
+    /// preference away from the host chats are using.
+    pub async fn list_running_models(&self) -> Result<Vec<RunningModel>> {
+        #[derive(Deserialize)]
+        struct PsResponse {
+            models: Vec<RunningModel>,
+        }
+
+        let response = self.client
+            .get(format!("{}/api/ps", self.base_url()))
+            .send()
+            .await
+            .context("Failed to fetch running models")?;
+
+        let ps: PsResponse = response.json().await?;
+        Ok(ps.models)
+    }
+
+    /// Poll the installed models every few seconds, yielding the list
+    /// whenever it changes (and once at the start)
+    pub fn watch_models(&self) -> impl Stream<Item = Result<Vec<String>>> + Send + 'static {
+        let client = self.clone();
+        let polls = async_stream::stream! {
+            let mut interval = tokio::time::interval(MODEL_POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                yield client.list_models().await;
+            }
+        };
+        changed_model_lists(polls)
+    }
+
     pub async fn chat(
         &self,
         model: &str,
         messages: Vec<Message>,
     ) -> Result<String> {
-        let url = format!("{}/api/chat", self.base_url);
-        
         let request = ChatRequest {
             model: model.to_string(),
             messages,
             stream: false,
-            options: Some(GenerationOptions {
-                temperature: 0.7,
-                num_ctx: 4096,
-            }),
+            options: Some(GenerationOptions::default()),
+            format: None,
         };
 
         debug!("Sending chat request to Ollama");
+
+        if let Some(transcript) = &self.transcript {
+            transcript.log_request(&request).await;
+        }
         
-        let response = self.client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send chat request")?;
+        let response = send_with_failover(&self.client, &self.hosts, &self.preferred, |client, host| {
+            client.post(format!("{}/api/chat", host)).json(&request)
+        })
+        .await
+        .context("Failed to send chat request")?;
 
         let chat_response: ChatResponse = response.json().await?;
+
+        if let Some(transcript) = &self.transcript {
+            transcript.log_response(&chat_response.message.content).await;
+        }
+
         Ok(chat_response.message.content)
     }
 
@@ -132,7 +468,157 @@ impl OllamaClient {
         model: String,
         messages: Vec<Message>,
     ) -> impl Stream<Item = Result<String>> + '_ {
-        ChatStream::new(self, model, messages)
+        self.chat_stream_with_options(model, messages, GenerationOptions::default()).0
+    }
+
+    /// Stream a response, along with a handle for cancelling it on the
+    /// server that generates it
+    pub fn chat_stream_with_options(
+        &self,
+        model: String,
+        messages: Vec<Message>,
+        options: GenerationOptions,
+    ) -> (impl Stream<Item = Result<String>> + '_, StreamHandle) {
+        let transcript = self.transcript.clone();
+        let handle = StreamHandle::new(self.client.clone());
+        let mut inner = ChatStream::new(self, model, messages, options, &handle);
+
+        let stream = Box::pin(async_stream::stream! {
+            let mut log = ResponseLog { transcript, response: String::new() };
+            while let Some(item) = futures::StreamExt::next(&mut inner).await {
+                if let Ok(chunk) = &item {
+                    log.response.push_str(chunk);
+                }
+                yield item;
+            }
+            log.finish().await;
+        });
+        (stream, handle)
+    }
+}
+
+/// One streamed response, identified on the host that accepted it, so it
+/// can be cancelled without touching other requests in flight
+#[derive(Clone)]
+pub struct StreamHandle {
+    client: Client,
+    /// Host the request went to
+    host: Arc<parking_lot::Mutex<Option<String>>>,
+    /// Filled in from the first message that carries an `id`
+    request_id: Arc<parking_lot::Mutex<Option<String>>>,
+    /// Filled in from the terminal `done: true` message
+    stats: Arc<parking_lot::Mutex<Option<GenerationStats>>>,
+}
+
+impl StreamHandle {
+    fn new(client: Client) -> Self {
+        Self {
+            client,
+            host: Default::default(),
+            request_id: Default::default(),
+            stats: Default::default(),
+        }
+    }
+
+    /// Server-side ID of the response, once a message carrying one arrived
+    pub fn request_id(&self) -> Option<String> {
+        self.request_id.lock().clone()
+    }
+
+    /// Server-reported stats, once the response finished
+    pub fn stats(&self) -> Option<GenerationStats> {
+        self.stats.lock().clone()
+    }
+
+    /// Ask the server to stop generating the response and free its
+    /// resources. Servers without the endpoint answer 404, which is not an
+    /// error: the response then stops once its stream is dropped.
+    pub async fn cancel(&self) -> Result<()> {
+        let host = self.host.lock().clone();
+        let (Some(host), Some(request_id)) = (host, self.request_id()) else {
+            debug!("No request ID to cancel yet");
+            return Ok(());
+        };
+
+        let url = format!("{}/api/chat/{}", host, request_id);
+        let response = self.client.delete(&url).send().await
+            .context("Failed to send cancel request")?;
+
+        match response.status() {
+            status if status.is_success() => {
+                debug!("Cancelled request {}", request_id);
+                Ok(())
+            }
+            reqwest::StatusCode::NOT_FOUND => {
+                debug!("Ollama does not support cancelling requests");
+                Ok(())
+            }
+            status => bail!("Ollama refused to cancel request {}: {}", request_id, status),
+        }
+    }
+}
+
+/// Send a request to each host in turn, starting from the preferred one,
+/// until one accepts the connection. Remembers the host that worked.
+async fn send_with_failover<F>(
+    client: &Client,
+    hosts: &[String],
+    preferred: &AtomicUsize,
+    build: F,
+) -> Result<reqwest::Response>
+where
+    F: Fn(&Client, &str) -> reqwest::RequestBuilder,
+{
+    let start = preferred.load(Ordering::Relaxed);
+    let mut last_error = None;
+
+    for offset in 0..hosts.len() {
+        let index = (start + offset) % hosts.len();
+        let host = &hosts[index];
+
+        match build(client, host).send().await {
+            Ok(response) => {
+                if index != start {
+                    info!("Failed over to Ollama host {}", host);
+                    preferred.store(index, Ordering::Relaxed);
+                }
+                return Ok(response);
+            }
+            Err(e) if e.is_connect() || e.is_timeout() => {
+                warn!("Ollama host {} unreachable: {}", host, e);
+                last_error = Some(e);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    match last_error {
+        Some(e) => Err(anyhow::anyhow!("No Ollama host reachable: {}", e)),
+        None => Err(anyhow::anyhow!("No Ollama hosts configured")),
+    }
+}
+
+/// Pass through only the model lists that differ, as sets, from the last one
+/// seen. Errors are passed through without resetting the last list.
+fn changed_model_lists<S>(lists: S) -> impl Stream<Item = Result<Vec<String>>> + Send + 'static
+where
+    S: Stream<Item = Result<Vec<String>>> + Send + 'static,
+{
+    async_stream::stream! {
+        let mut lists = Box::pin(lists);
+        let mut last: Option<HashSet<String>> = None;
+        while let Some(item) = futures::StreamExt::next(&mut lists).await {
+            match item {
+                Ok(models) => {
+                    let current: HashSet<String> = models.iter().cloned().collect();
+                    if last.as_ref() != Some(&current) {
+                        last = Some(current);
+                        yield Ok(models);
+                    }
+                }
+                Err(e) => yield Err(e),
+            }
+        }
     }
 }
 
@@ -140,26 +626,51 @@ impl OllamaClient {
 struct ChatStream {
     #[pin]
     inner: Pin<Box<dyn Stream<Item = Result<bytes::Bytes>> + Send>>,
-    buffer: String,
+    /// Raw bytes of the current, possibly incomplete, line. Decoded only once
+    /// the line is complete so multi-byte characters split across chunks survive.
+    buffer: Vec<u8>,
+    done: bool,
+    /// Filled in from the terminal `done: true` message
+    stats: Arc<parking_lot::Mutex<Option<GenerationStats>>>,
+    /// Filled in from the first message that carries an `id`
+    request_id: Arc<parking_lot::Mutex<Option<String>>>,
 }
 
 impl ChatStream {
-    fn new(client: &OllamaClient, model: String, messages: Vec<Message>) -> Self {
-        let url = format!("{}/api/chat", client.base_url);
+    fn new(
+        client: &OllamaClient,
+        model: String,
+        messages: Vec<Message>,
+        options: GenerationOptions,
+        handle: &StreamHandle,
+    ) -> Self {
         let http_client = client.client.clone();
+        let hosts = client.hosts.clone();
+        let preferred = client.preferred.clone();
+        let transcript = client.transcript.clone();
+        let host = handle.host.clone();
         
         let request = ChatRequest {
             model,
             messages,
             stream: true,
-            options: Some(GenerationOptions {
-                temperature: 0.7,
-                num_ctx: 4096,
-            }),
+            format: options.format.clone(),
+            options: Some(options),
         };
 
         let stream = Box::pin(async_stream::stream! {
-            match http_client.post(&url).json(&request).send().await {
+            if let Some(transcript) = &transcript {
+                transcript.log_request(&request).await;
+            }
+
+            // The last host tried is the one that accepted the request
+            let response = send_with_failover(&http_client, &hosts, &preferred, |client, attempted| {
+                *host.lock() = Some(attempted.to_string());
+                client.post(format!("{}/api/chat", attempted)).json(&request)
+            })
+            .await;
+
+            match response {
                 Ok(response) => {
                     let mut stream = response.bytes_stream();
                     while let Some(chunk) = futures::StreamExt::next(&mut stream).await {
@@ -179,9 +690,57 @@ impl ChatStream {
             }
         });
 
+        Self::from_bytes(stream, handle.stats.clone(), handle.request_id.clone())
+    }
+
+    fn from_bytes(
+        inner: Pin<Box<dyn Stream<Item = Result<bytes::Bytes>> + Send>>,
+        stats: Arc<parking_lot::Mutex<Option<GenerationStats>>>,
+        request_id: Arc<parking_lot::Mutex<Option<String>>>,
+    ) -> Self {
         Self {
-            inner: stream,
-            buffer: String::new(),
+            inner,
+            buffer: Vec::new(),
+            done: false,
+            stats,
+            request_id,
+        }
+    }
+}
+
+/// Take the next newline-terminated line out of `buffer`, or the remaining
+/// tail once the stream has ended
+fn take_line(buffer: &mut Vec<u8>, at_end: bool) -> Option<Vec<u8>> {
+    match buffer.iter().position(|&b| b == b'\n') {
+        Some(pos) => {
+            let mut line: Vec<u8> = buffer.drain(..=pos).collect();
+            line.pop();
+            Some(line)
+        }
+        None if at_end && !buffer.is_empty() => Some(std::mem::take(buffer)),
+        None => None,
+    }
+}
+
+/// One message of Ollama's newline-delimited JSON
+fn parse_line(line: &[u8]) -> Option<ChatResponse> {
+    let line = match std::str::from_utf8(line) {
+        Ok(line) => line.trim(),
+        Err(e) => {
+            error!("Invalid UTF-8 in stream: {}", e);
+            return None;
+        }
+    };
+
+    if line.is_empty() {
+        return None;
+    }
+
+    match serde_json::from_str::<ChatResponse>(line) {
+        Ok(response) => Some(response),
+        Err(e) => {
+            error!("Failed to parse JSON: {} - Line: {}", e, line);
+            None
         }
     }
 }
@@ -191,52 +750,159 @@ impl Stream for ChatStream {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
-        
-        match this.inner.as_mut().poll_next(cx) {
-            Poll::Ready(Some(Ok(bytes))) => {
-                this.buffer.push_str(&String::from_utf8_lossy(&bytes));
-                
-                // Process complete JSON objects (newline-delimited)
-                if let Some(newline_pos) = this.buffer.find('\n') {
-                    let line = this.buffer.drain(..=newline_pos).collect::<String>();
-                    let line = line.trim();
-                    
-                    if line.is_empty() {
-                        return Poll::Pending;
-                    }
-                    
-                    match serde_json::from_str::<ChatResponse>(line) {
-                        Ok(response) => {
-                            if !response.message.content.is_empty() {
-                                Poll::Ready(Some(Ok(response.message.content)))
-                            } else {
-                                Poll::Pending
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed to parse JSON: {} - Line: {}", e, line);
-                            Poll::Pending
-                        }
-                    }
-                } else {
-                    Poll::Pending
+
+        loop {
+            // Drain every complete line before asking for more bytes
+            while let Some(line) = take_line(this.buffer, *this.done) {
+                let Some(response) = parse_line(&line) else {
+                    continue;
+                };
+
+                if let Some(id) = response.id {
+                    this.request_id.lock().get_or_insert(id);
+                }
+
+                // The terminal message carries the stats; nothing useful follows it
+                if response.done {
+                    debug!("Generation finished: {:?}", response.stats);
+                    *this.stats.lock() = Some(response.stats);
+                    *this.done = true;
+                    this.buffer.clear();
+                }
+
+                if !response.message.content.is_empty() {
+                    return Poll::Ready(Some(Ok(response.message.content)));
                 }
             }
-            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => Poll::Pending,
+
+            if *this.done {
+                return Poll::Ready(None);
+            }
+
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) => this.buffer.extend_from_slice(&bytes),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => *this.done = true,
+                Poll::Pending => return Poll::Pending,
+            }
         }
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Minimal keep-alive HTTP server answering every request with a chat
+    /// response. Returns its URL and the number of accepted connections.
+    pub(crate) async fn spawn_mock_server() -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    loop {
+                        let n = match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => n,
+                        };
+                        buf.extend_from_slice(&chunk[..n]);
+
+                        let Some(header_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                            continue;
+                        };
+                        let headers = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+                        let content_length = headers
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length:"))
+                            .and_then(|v| v.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if buf.len() < header_end + 4 + content_length {
+                            continue;
+                        }
+                        buf.drain(..header_end + 4 + content_length);
+
+                        let body = r#"{"message":{"role":"assistant","content":"hi"},"done":true}"#;
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        );
+                        if socket.write_all(response.as_bytes()).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        (format!("http://{}", addr), connections)
+    }
+
+    #[tokio::test]
+    async fn test_failover_order() {
+        // A port nothing listens on, so connections are refused
+        let dead = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let (first, first_connections) = spawn_mock_server().await;
+        let (second, second_connections) = spawn_mock_server().await;
+
+        let client = OllamaClient::with_hosts(
+            vec![dead, first.clone(), second],
+            OllamaClientConfig::default(),
+        );
+
+        client.chat("llama3.2:3b", vec![Message::user("one")]).await.unwrap();
+        assert_eq!(client.base_url(), first);
+
+        // The last-good host is tried first from now on
+        client.chat("llama3.2:3b", vec![Message::user("two")]).await.unwrap();
+        assert_eq!(first_connections.load(Ordering::SeqCst), 1);
+        assert_eq!(second_connections.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_running_models_poll_keeps_preferred_host() {
+        let dead = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let (live, connections) = spawn_mock_server().await;
+        let client = OllamaClient::with_hosts(
+            vec![dead.clone(), live],
+            OllamaClientConfig::default(),
+        );
+
+        assert!(client.list_running_models().await.is_err());
+        assert_eq!(client.base_url(), dead);
+        assert_eq!(connections.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_reuse() {
+        let (url, connections) = spawn_mock_server().await;
+        let client = OllamaClient::new(url);
+
+        assert_eq!(client.chat("llama3.2:3b", vec![Message::user("one")]).await.unwrap(), "hi");
+        assert_eq!(client.chat("llama3.2:3b", vec![Message::user("two")]).await.unwrap(), "hi");
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
 
     #[tokio::test]
     async fn test_ollama_client_creation() {
         let client = OllamaClient::new("http://localhost:11434");
-        assert_eq!(client.base_url, "http://localhost:11434");
+        assert_eq!(client.base_url(), "http://localhost:11434");
     }
 
     #[test]
@@ -245,4 +911,364 @@ mod tests {
         assert_eq!(msg.role, "user");
         assert_eq!(msg.content, "Hello");
     }
+
+    #[test]
+    fn test_generation_options_only_when_configured() {
+        let request = ChatRequest {
+            model: "llama3.2:3b".to_string(),
+            messages: vec![],
+            stream: true,
+            options: Some(GenerationOptions::default()),
+            format: None,
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json["options"].get("stop").is_none());
+        assert!(json["options"].get("num_predict").is_none());
+        assert!(json["options"].get("top_p").is_none());
+
+        let request = ChatRequest {
+            options: Some(GenerationOptions {
+                stop: vec!["User:".to_string()],
+                num_predict: Some(128),
+                ..Default::default()
+            }),
+            ..request
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["options"]["stop"][0], "User:");
+        assert_eq!(json["options"]["num_predict"], 128);
+    }
+
+    #[test]
+    fn test_format_only_when_enabled() {
+        let request = ChatRequest {
+            model: "llama3.2:3b".to_string(),
+            messages: vec![],
+            stream: true,
+            options: None,
+            format: None,
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("format").is_none());
+
+        let request = ChatRequest {
+            format: Some(serde_json::json!("json")),
+            ..request
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["format"], "json");
+
+        let schema = serde_json::json!({"type": "object", "required": ["answer"]});
+        let request = ChatRequest {
+            format: Some(schema.clone()),
+            ..request
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["format"], schema);
+    }
+
+    #[tokio::test]
+    async fn test_debug_transcript() {
+        let path = std::env::temp_dir().join(format!("blipply-transcript-{}.log", uuid::Uuid::new_v4()));
+        let (url, _) = spawn_mock_server().await;
+        let client = OllamaClient::new(url)
+            .with_debug_transcript(DebugTranscript::new(&path, true));
+
+        let messages = vec![Message::system("secret prompt"), Message::user("hello")];
+        let requests = (0..4).map(|_| client.chat("llama3.2:3b", messages.clone()));
+        for result in futures::future::join_all(requests).await {
+            result.unwrap();
+        }
+
+        let transcript = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(transcript.matches("request ===").count(), 4);
+        assert_eq!(transcript.matches("response ===\nhi\n").count(), 4);
+        assert!(transcript.contains("[redacted]"));
+        assert!(!transcript.contains("secret prompt"));
+        assert!(transcript.contains("\"content\": \"hello\""));
+    }
+
+    #[tokio::test]
+    async fn test_debug_transcript_logs_dropped_stream() {
+        let path = std::env::temp_dir().join(format!("blipply-transcript-{}.log", uuid::Uuid::new_v4()));
+        let (url, _) = spawn_mock_server().await;
+        let client = OllamaClient::new(url)
+            .with_debug_transcript(DebugTranscript::new(&path, false));
+
+        let mut stream = client.chat_stream("llama3.2:3b".to_string(), vec![Message::user("hello")]);
+        assert_eq!(futures::StreamExt::next(&mut stream).await.unwrap().unwrap(), "hi");
+        drop(stream);
+
+        let mut transcript = String::new();
+        for _ in 0..50 {
+            transcript = std::fs::read_to_string(&path).unwrap_or_default();
+            if transcript.contains("response ===") {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(transcript.matches("response ===\nhi\n").count(), 1);
+    }
+
+    fn chunked_stream(chunks: Vec<Vec<u8>>) -> ChatStream {
+        let chunks = chunks.into_iter().map(|c| Ok(bytes::Bytes::from(c)));
+        ChatStream::from_bytes(Box::pin(futures::stream::iter(chunks)), Default::default(), Default::default())
+    }
+
+    #[tokio::test]
+    async fn test_stream_captures_request_id() {
+        use futures::StreamExt;
+
+        let body = concat!(
+            "{\"id\":\"req-42\",\"message\":{\"role\":\"assistant\",\"content\":\"hi\"},\"done\":false}\n",
+            "{\"id\":\"req-other\",\"message\":{\"role\":\"assistant\",\"content\":\"!\"},\"done\":false}\n",
+        );
+        let handle = StreamHandle::new(Client::new());
+        let chunks = futures::stream::iter([Ok(bytes::Bytes::from(body))]);
+        let mut stream = ChatStream::from_bytes(Box::pin(chunks), Default::default(), handle.request_id.clone());
+        assert_eq!(handle.request_id(), None);
+        assert_eq!(stream.next().await.unwrap().unwrap(), "hi");
+        assert_eq!(handle.request_id().as_deref(), Some("req-42"));
+        stream.next().await;
+        assert_eq!(handle.request_id().as_deref(), Some("req-42"));
+    }
+
+    /// Answer one HTTP request with `status` and return its request line
+    async fn spawn_status_server(status: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 1024];
+            while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+            }
+            let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf).lines().next().unwrap_or_default().to_string()
+        });
+        (url, handle)
+    }
+
+    fn handle_for(host: &str, request_id: &str) -> StreamHandle {
+        let handle = StreamHandle::new(Client::new());
+        *handle.host.lock() = Some(host.to_string());
+        *handle.request_id.lock() = Some(request_id.to_string());
+        handle
+    }
+
+    #[tokio::test]
+    async fn test_cancel_request() {
+        let (url, request) = spawn_status_server("200 OK").await;
+        handle_for(&url, "req-42").cancel().await.unwrap();
+        assert_eq!(request.await.unwrap(), "DELETE /api/chat/req-42 HTTP/1.1");
+
+        // Servers without the endpoint are fine
+        let (url, _) = spawn_status_server("404 Not Found").await;
+        handle_for(&url, "req-42").cancel().await.unwrap();
+
+        let (url, _) = spawn_status_server("500 Internal Server Error").await;
+        assert!(handle_for(&url, "req-42").cancel().await.is_err());
+
+        // Nothing to cancel before the server sent an ID
+        assert!(StreamHandle::new(Client::new()).cancel().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_stream_handle_records_its_host() {
+        use futures::StreamExt;
+
+        let dead = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let (url, _) = spawn_mock_server().await;
+        let client = OllamaClient::with_hosts(
+            vec![dead, url.clone()],
+            OllamaClientConfig::default(),
+        );
+        let (stream, handle) = client.chat_stream_with_options(
+            "llama3.2:3b".to_string(),
+            vec![Message::user("hello")],
+            GenerationOptions::default(),
+        );
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(handle.host.lock().as_deref(), Some(url.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_stream_stats_are_per_handle() {
+        use futures::StreamExt;
+
+        let (url, _) = spawn_mock_server().await;
+        let client = OllamaClient::new(url);
+        let (finished, finished_handle) = client.chat_stream_with_options(
+            "llama3.2:3b".to_string(),
+            vec![Message::user("one")],
+            GenerationOptions::default(),
+        );
+        let (_pending, pending_handle) = client.chat_stream_with_options(
+            "llama3.2:3b".to_string(),
+            vec![Message::user("two")],
+            GenerationOptions::default(),
+        );
+
+        let _: Vec<_> = finished.collect().await;
+        assert!(finished_handle.stats().is_some());
+        assert!(pending_handle.stats().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_split_multibyte_sequence() {
+        use futures::StreamExt;
+
+        let line = "{\"message\":{\"role\":\"assistant\",\"content\":\"héllo 👋 世界\"},\"done\":false}\n";
+        let bytes = line.as_bytes();
+        // Split inside the four-byte emoji
+        let split = line.find('👋').unwrap() + 2;
+
+        let mut stream = chunked_stream(vec![bytes[..split].to_vec(), bytes[split..].to_vec()]);
+        assert_eq!(stream.next().await.unwrap().unwrap(), "héllo 👋 世界");
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_several_objects_in_one_chunk() {
+        use futures::StreamExt;
+
+        let chunk: String = ["Hel", "lo", ", ", "world"]
+            .iter()
+            .map(|token| format!("{{\"message\":{{\"role\":\"assistant\",\"content\":\"{}\"}},\"done\":false}}\n", token))
+            .chain(std::iter::once("{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}".to_string()))
+            .collect();
+
+        let stream = chunked_stream(vec![chunk.into_bytes()]);
+        let tokens: Vec<String> = stream.map(|t| t.unwrap()).collect().await;
+        assert_eq!(tokens, vec!["Hel", "lo", ", ", "world"]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_captures_final_stats() {
+        use futures::StreamExt;
+
+        let body = concat!(
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"hi\"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,",
+            "\"total_duration\":2000000000,\"eval_count\":40,\"eval_duration\":2000000000}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"ignored\"},\"done\":false}\n",
+        );
+        let mut stream = chunked_stream(vec![body.as_bytes().to_vec()]);
+        let stats = stream.stats.clone();
+
+        let tokens: Vec<String> = (&mut stream).map(|t| t.unwrap()).collect().await;
+        assert_eq!(tokens, vec!["hi"]);
+
+        let stats = stats.lock().clone().unwrap();
+        assert_eq!(stats.eval_count, Some(40));
+        assert_eq!(stats.prompt_eval_count, None);
+        assert_eq!(stats.tokens_per_second(), Some(20.0));
+    }
+
+    #[test]
+    fn test_message_timestamp_optional() {
+        let message: Message = serde_json::from_str(r#"{"role":"user","content":"hi"}"#).unwrap();
+        assert!(message.timestamp.is_none());
+
+        let json = serde_json::to_string(&Message::user("hi")).unwrap();
+        let message: Message = serde_json::from_str(&json).unwrap();
+        assert!(message.timestamp.is_some());
+    }
+
+    #[test]
+    fn test_message_images() {
+        let json = serde_json::to_value(Message::user("hi")).unwrap();
+        assert!(json.get("images").is_none());
+
+        let json = serde_json::to_value(Message::user("what is this?").with_images(vec!["aGk=".to_string()])).unwrap();
+        assert_eq!(json["images"][0], "aGk=");
+
+        assert!(is_vision_model("llava:7b"));
+        assert!(is_vision_model("llama3.2-vision:11b"));
+        assert!(!is_vision_model("llama3.2:3b"));
+    }
+
+    #[test]
+    fn test_encode_image() {
+        // A transparent 1x1 PNG
+        let png = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+            0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x60, 0x00, 0x02, 0x00,
+            0x00, 0x05, 0x00, 0x01, 0x7a, 0x5e, 0xab, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+            0xae, 0x42, 0x60, 0x82,
+        ];
+
+        assert_eq!(
+            encode_image(&png),
+            "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAAC0lEQVR4nGNgAAIAAAUAAXpeqz8AAAAASUVORK5CYII="
+        );
+    }
+
+    #[test]
+    fn test_find_stop_sequence() {
+        let stop = vec!["###".to_string(), "User:".to_string()];
+        assert_eq!(find_stop_sequence("Hi there User: ###", &stop), Some(9));
+        assert_eq!(find_stop_sequence("Hi there", &stop), None);
+    }
+
+    #[test]
+    fn test_partial_stop_len() {
+        let stop = vec!["###".to_string(), "User:".to_string()];
+        assert_eq!(partial_stop_len("Hi there\nUse", &stop), 3);
+        assert_eq!(partial_stop_len("Hi ##", &stop), 2);
+        assert_eq!(partial_stop_len("Hi there", &stop), 0);
+        // A complete stop sequence is find_stop_sequence's job
+        assert_eq!(partial_stop_len("Hi User:", &stop), 0);
+        assert_eq!(partial_stop_len("Hi", &[]), 0);
+    }
+
+    #[test]
+    fn test_running_models_deserialize() {
+        let body = r#"{"models":[{"name":"llama3.2:3b","model":"llama3.2:3b","size":2600000000,
+            "digest":"a80c4f17acd5","details":{"family":"llama","parameter_size":"3.2B"},
+            "expires_at":"2026-06-04T14:38:31.83753-07:00","size_vram":2100000000}]}"#;
+
+        #[derive(Deserialize)]
+        struct PsResponse {
+            models: Vec<RunningModel>,
+        }
+        let ps: PsResponse = serde_json::from_str(body).unwrap();
+
+        assert_eq!(ps.models.len(), 1);
+        let model = &ps.models[0];
+        assert_eq!(model.name, "llama3.2:3b");
+        assert_eq!(model.size_vram_bytes, 2_100_000_000);
+        assert_eq!(model.expires_at.unwrap().to_rfc3339(), "2026-06-04T21:38:31.837530+00:00");
+        assert_eq!(model.label(), "llama3.2:3b (2.1 GB)");
+    }
+
+    #[tokio::test]
+    async fn test_watch_models_skips_unchanged() {
+        use futures::StreamExt;
+
+        let lists = futures::stream::iter(vec![
+            Ok(vec!["llama3.2:3b".to_string()]),
+            Ok(vec!["llama3.2:3b".to_string()]),
+            Ok(vec!["llama3.2:3b".to_string(), "mistral:7b".to_string()]),
+            Ok(vec!["mistral:7b".to_string(), "llama3.2:3b".to_string()]),
+        ]);
+
+        let yielded: Vec<Vec<String>> = changed_model_lists(lists).map(|l| l.unwrap()).collect().await;
+        assert_eq!(yielded, vec![
+            vec!["llama3.2:3b".to_string()],
+            vec!["llama3.2:3b".to_string(), "mistral:7b".to_string()],
+        ]);
+    }
 }
@@ -0,0 +1,83 @@
+// Blipply Assistant - Pipe Mode
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use std::io::{Read, Write};
+
+use crate::ollama::{Message, OllamaClient};
+
+/// Read a prompt from `input`, send it to the model as a single turn and
+/// write the reply to `output`. With `stream`, chunks are written as they arrive.
+pub async fn run(
+    client: &OllamaClient,
+    model: &str,
+    system_prompt: String,
+    stream: bool,
+    mut input: impl Read,
+    mut output: impl Write,
+) -> Result<()> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes).context("Failed to read stdin")?;
+    let prompt = String::from_utf8(bytes)
+        .map_err(|_| anyhow::anyhow!("stdin is not valid UTF-8 text"))?;
+
+    if prompt.trim().is_empty() {
+        anyhow::bail!("No input on stdin");
+    }
+
+    let messages = vec![Message::system(system_prompt), Message::user(prompt)];
+
+    if stream {
+        let mut chunks = client.chat_stream(model.to_string(), messages);
+        while let Some(chunk) = chunks.next().await {
+            output.write_all(chunk?.as_bytes())?;
+            output.flush()?;
+        }
+        writeln!(output)?;
+    } else {
+        let response = client.chat(model, messages).await?;
+        writeln!(output, "{}", response.trim())?;
+    }
+
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ollama::tests::spawn_mock_server;
+
+    #[tokio::test]
+    async fn test_pipe_through_mock_server() {
+        let (url, _) = spawn_mock_server().await;
+        let client = OllamaClient::new(url);
+
+        let mut output = Vec::new();
+        run(
+            &client,
+            "llama3.2:3b",
+            "You are a test.".to_string(),
+            false,
+            "Say hi".as_bytes(),
+            &mut output,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "hi\n");
+    }
+
+    #[tokio::test]
+    async fn test_pipe_rejects_binary_input() {
+        let client = OllamaClient::new("http://127.0.0.1:9");
+        let input: &[u8] = &[0xff, 0xfe, 0x00, 0x80];
+
+        let err = run(&client, "llama3.2:3b", String::new(), false, input, Vec::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("UTF-8"));
+    }
+}
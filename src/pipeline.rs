@@ -0,0 +1,273 @@
+// Blipply Assistant
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::audio::TtsPipeline;
+use crate::history::UndoStack;
+use crate::ollama::Message;
+use crate::profiles::{MessageFormatter, VoiceProfile};
+use crate::state::UiCommand;
+
+/// A fenced code block found in a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    pub language: Option<String>,
+    pub code: String,
+}
+
+/// State shared between the stages of one response
+pub struct PipelineContext {
+    pub profile: VoiceProfile,
+    pub model: String,
+    pub code_blocks: Vec<CodeBlock>,
+}
+
+impl PipelineContext {
+    pub fn new(profile: VoiceProfile, model: impl Into<String>) -> Self {
+        Self {
+            profile,
+            model: model.into(),
+            code_blocks: Vec::new(),
+        }
+    }
+}
+
+/// One step applied to a complete LLM response
+#[async_trait]
+pub trait ResponseStage: Send + Sync {
+    async fn process(&self, text: &mut String, ctx: &mut PipelineContext) -> Result<()>;
+}
+
+/// Runs response stages in order
+#[derive(Default)]
+pub struct ResponsePipeline {
+    stages: Vec<Box<dyn ResponseStage>>,
+}
+
+impl ResponsePipeline {
+    pub fn new(stages: Vec<Box<dyn ResponseStage>>) -> Self {
+        Self { stages }
+    }
+
+    pub fn push(&mut self, stage: Box<dyn ResponseStage>) {
+        self.stages.push(stage);
+    }
+
+    pub async fn run(&self, text: &mut String, ctx: &mut PipelineContext) -> Result<()> {
+        for stage in &self.stages {
+            stage.process(text, ctx).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Trims surrounding whitespace
+pub struct TrimStage;
+
+#[async_trait]
+impl ResponseStage for TrimStage {
+    async fn process(&self, text: &mut String, _ctx: &mut PipelineContext) -> Result<()> {
+        let trimmed = text.trim();
+        if trimmed.len() != text.len() {
+            *text = trimmed.to_string();
+        }
+        Ok(())
+    }
+}
+
+/// Collects fenced code blocks into the context
+pub struct CodeBlockExtractStage;
+
+#[async_trait]
+impl ResponseStage for CodeBlockExtractStage {
+    async fn process(&self, text: &mut String, ctx: &mut PipelineContext) -> Result<()> {
+        ctx.code_blocks.extend(extract_code_blocks(text));
+        Ok(())
+    }
+}
+
+fn extract_code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(Option<String>, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        let fence = line.trim_start().strip_prefix("```");
+        match (fence, current.take()) {
+            (Some(info), None) => {
+                let language = Some(info.trim().to_string()).filter(|l| !l.is_empty());
+                current = Some((language, Vec::new()));
+            }
+            (Some(_), Some((language, lines))) => {
+                blocks.push(CodeBlock { language, code: lines.join("\n") });
+            }
+            (None, Some((language, mut lines))) => {
+                lines.push(line);
+                current = Some((language, lines));
+            }
+            (None, None) => {}
+        }
+    }
+
+    blocks
+}
+
+/// Appends the response to the chat history
+pub struct HistoryAppendStage {
+    history: Arc<RwLock<VecDeque<Message>>>,
+    undo: Arc<UndoStack<VecDeque<Message>>>,
+}
+
+impl HistoryAppendStage {
+    pub fn new(history: Arc<RwLock<VecDeque<Message>>>, undo: Arc<UndoStack<VecDeque<Message>>>) -> Self {
+        Self { history, undo }
+    }
+}
+
+#[async_trait]
+impl ResponseStage for HistoryAppendStage {
+    async fn process(&self, text: &mut String, _ctx: &mut PipelineContext) -> Result<()> {
+        self.undo.push_checkpoint();
+        self.history.write().push_back(Message::assistant(text.as_str()));
+        Ok(())
+    }
+}
+
+/// Shows a reply in the profile's message template before it is spoken.
+/// Untemplated replies were already streamed into the window.
+pub struct TemplatedReplyStage {
+    ui_tx: mpsc::UnboundedSender<UiCommand>,
+}
+
+impl TemplatedReplyStage {
+    pub fn new(ui_tx: mpsc::UnboundedSender<UiCommand>) -> Self {
+        Self { ui_tx }
+    }
+}
+
+#[async_trait]
+impl ResponseStage for TemplatedReplyStage {
+    async fn process(&self, text: &mut String, ctx: &mut PipelineContext) -> Result<()> {
+        let Some(template) = &ctx.profile.message_template else {
+            return Ok(());
+        };
+
+        let reply = Message::assistant(text.as_str());
+        let content = MessageFormatter::format(template, &reply, &ctx.model);
+        self.ui_tx.send(UiCommand::AppendMessage(Message { content, ..reply })).ok();
+        Ok(())
+    }
+}
+
+/// Speaks the response if the profile has TTS enabled
+pub struct TtsStage {
+    tts: Arc<RwLock<Option<Arc<TtsPipeline>>>>,
+}
+
+impl TtsStage {
+    pub fn new(tts: Arc<RwLock<Option<Arc<TtsPipeline>>>>) -> Self {
+        Self { tts }
+    }
+}
+
+#[async_trait]
+impl ResponseStage for TtsStage {
+    async fn process(&self, text: &mut String, ctx: &mut PipelineContext) -> Result<()> {
+        if !ctx.profile.tts_enabled || text.is_empty() {
+            return Ok(());
+        }
+
+        let tts = self.tts.read().clone();
+        match tts {
+            Some(tts) => tts.speak_sentences(text).await,
+            None => {
+                debug!("TTS not initialized, skipping speech");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::profiles::ProfileManager;
+
+    fn context() -> PipelineContext {
+        let manager = ProfileManager::from_config(&Config::default());
+        PipelineContext::new(manager.active_profile().unwrap().clone(), "llama3.2:3b")
+    }
+
+    #[tokio::test]
+    async fn test_trim_stage() {
+        let mut text = "  hello \n".to_string();
+        TrimStage.process(&mut text, &mut context()).await.unwrap();
+        assert_eq!(text, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_code_block_extract_stage() {
+        let mut text = "Try:\n```nix\n{ pkgs }:\npkgs.hello\n```\nand\n```\nls\n```".to_string();
+        let mut ctx = context();
+        CodeBlockExtractStage.process(&mut text, &mut ctx).await.unwrap();
+
+        assert_eq!(ctx.code_blocks, vec![
+            CodeBlock { language: Some("nix".to_string()), code: "{ pkgs }:\npkgs.hello".to_string() },
+            CodeBlock { language: None, code: "ls".to_string() },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_history_append_stage() {
+        let history = Arc::new(RwLock::new(VecDeque::from(vec![Message::user("a"), Message::user("b")])));
+        let undo = Arc::new(UndoStack::new(history.clone(), 10));
+        let stage = HistoryAppendStage::new(history.clone(), undo.clone());
+
+        let mut text = "reply".to_string();
+        stage.process(&mut text, &mut context()).await.unwrap();
+
+        {
+            let history = history.read();
+            assert_eq!(history.len(), 3);
+            assert_eq!(history[2].role, "assistant");
+            assert_eq!(history[2].content, "reply");
+        }
+
+        assert!(undo.undo());
+        assert_eq!(history.read().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_templated_reply_stage() {
+        let (ui_tx, mut ui_rx) = mpsc::unbounded_channel();
+        let stage = TemplatedReplyStage::new(ui_tx);
+        let mut text = "reply".to_string();
+
+        // Without a template the streamed text already shows the reply
+        stage.process(&mut text, &mut context()).await.unwrap();
+        assert!(ui_rx.try_recv().is_err());
+
+        let mut ctx = context();
+        ctx.profile.message_template = Some("> {content} ({model})".to_string());
+        stage.process(&mut text, &mut ctx).await.unwrap();
+        match ui_rx.try_recv() {
+            Ok(UiCommand::AppendMessage(message)) => assert_eq!(message.content, "> reply (llama3.2:3b)"),
+            _ => panic!("expected the templated reply"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tts_stage_without_pipeline() {
+        let stage = TtsStage::new(Arc::new(RwLock::new(None)));
+        let mut text = "hello".to_string();
+        assert!(stage.process(&mut text, &mut context()).await.is_ok());
+    }
+}
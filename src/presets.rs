@@ -0,0 +1,96 @@
+// Blipply Assistant - Generation Presets
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+//! Named bundles of sampling settings, so a profile can ask for `precise` or
+//! `creative` answers without tuning temperature and top_p by hand. Presets
+//! defined under `[presets.<name>]` in the config add to the built-in ones,
+//! or replace one with the same name:
+//!
+//! ```toml
+//! [presets.brainstorm]
+//! temperature = 1.2
+//! top_p = 0.98
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::ollama::GenerationOptions;
+
+/// Sampling settings applied by a preset; unset fields keep the defaults
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationPreset {
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub top_k: Option<u32>,
+}
+
+impl GenerationPreset {
+    const fn new(temperature: f32, top_p: f32, top_k: u32) -> Self {
+        Self {
+            temperature: Some(temperature),
+            top_p: Some(top_p),
+            top_k: Some(top_k),
+        }
+    }
+
+    /// Fill in the preset's settings. Call before applying the profile's own
+    /// values so those take precedence.
+    pub fn apply(&self, options: &mut GenerationOptions) {
+        if let Some(temperature) = self.temperature {
+            options.temperature = temperature;
+        }
+        if self.top_p.is_some() {
+            options.top_p = self.top_p;
+        }
+        if self.top_k.is_some() {
+            options.top_k = self.top_k;
+        }
+    }
+}
+
+pub const BUILTIN_PRESETS: &[(&str, GenerationPreset)] = &[
+    ("precise", GenerationPreset::new(0.2, 0.5, 20)),
+    ("balanced", GenerationPreset::new(0.7, 0.9, 40)),
+    ("creative", GenerationPreset::new(1.0, 0.95, 100)),
+];
+
+/// The preset called `name`, looking at the user's presets first
+pub fn find_preset(name: &str, custom: &HashMap<String, GenerationPreset>) -> Option<GenerationPreset> {
+    custom.get(name).copied().or_else(|| {
+        BUILTIN_PRESETS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, preset)| *preset)
+    })
+}
+
+/// Every preset name, built-in ones first and then the user's, sorted
+pub fn preset_names(custom: &HashMap<String, GenerationPreset>) -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_PRESETS.iter().map(|(name, _)| name.to_string()).collect();
+    let mut extra: Vec<&String> = custom.keys().filter(|name| !names.contains(name)).collect();
+    extra.sort();
+    names.extend(extra.into_iter().cloned());
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_presets_replace_builtins() {
+        let mut custom = HashMap::new();
+        custom.insert("precise".to_string(), GenerationPreset { temperature: Some(0.0), ..Default::default() });
+        custom.insert("brainstorm".to_string(), GenerationPreset::new(1.2, 0.98, 200));
+
+        assert_eq!(find_preset("precise", &custom).unwrap().temperature, Some(0.0));
+        assert_eq!(find_preset("creative", &custom), Some(GenerationPreset::new(1.0, 0.95, 100)));
+        assert_eq!(find_preset("missing", &custom), None);
+        assert_eq!(preset_names(&custom), ["precise", "balanced", "creative", "brainstorm"]);
+    }
+}
@@ -2,21 +2,105 @@
 // Copyright (c) 2026 DeMoD LLC
 // Licensed under the MIT License
 
-use anyhow::{Result, bail};
+use anyhow::{Result, Context, bail};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use crate::config::{Config, ProfileConfig};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use crate::config::{Config, InteractionMode, ProfileConfig, ResponseFormat};
+use crate::ollama::Message;
+use crate::prompts::PromptLibrary;
+use crate::voice_commands::VoiceCommand;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+const PACK_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VoiceProfile {
     pub name: String,
     pub model: String,
     pub personality: String,
     pub avatar_path: String,
     pub avatar_size_px: u32,
+    #[serde(default)]
+    pub interaction_mode: Option<InteractionMode>,
+    #[serde(default)]
+    pub window_width: Option<u32>,
+    #[serde(default)]
+    pub window_height: Option<u32>,
     pub voice_model: String,
     pub tts_speed: f32,
     pub tts_enabled: bool,
+    #[serde(default, alias = "llm_stop_sequences")]
+    pub stop: Vec<String>,
+    #[serde(default)]
+    pub num_predict: Option<i32>,
+    #[serde(default)]
+    pub greeting: Option<String>,
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub stt_initial_prompt: Option<String>,
+    #[serde(default)]
+    pub response_language: Option<String>,
+    #[serde(default)]
+    pub auto_detect_language: bool,
+    #[serde(default)]
+    pub voice_commands: Vec<VoiceCommand>,
+    #[serde(default = "crate::config::default_tts_volume")]
+    pub tts_volume: f32,
+    #[serde(default)]
+    pub vision_enabled: bool,
+    #[serde(default)]
+    pub message_template: Option<String>,
+    #[serde(default)]
+    pub tts_speaker_id: Option<u32>,
+    #[serde(default = "crate::config::default_assistant_name")]
+    pub assistant_name: String,
+    #[serde(default)]
+    pub derived_from: Option<(String, String)>,
+}
+
+impl VoiceProfile {
+    /// Profile with the stock settings, used when creating one from scratch
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            model: "llama3.2:3b".to_string(),
+            personality: "helpful".to_string(),
+            avatar_path: "/usr/share/blipply/clippy.gif".to_string(),
+            avatar_size_px: 96,
+            interaction_mode: None,
+            window_width: None,
+            window_height: None,
+            voice_model: "en_US-lessac-medium".to_string(),
+            tts_speed: 1.0,
+            tts_enabled: true,
+            stop: Vec::new(),
+            num_predict: None,
+            greeting: None,
+            response_format: ResponseFormat::Plain,
+            temperature: None,
+            preset: None,
+            system_prompt: None,
+            stt_initial_prompt: None,
+            response_language: None,
+            auto_detect_language: false,
+            voice_commands: Vec::new(),
+            tts_volume: 1.0,
+            vision_enabled: false,
+            message_template: None,
+            tts_speaker_id: None,
+            assistant_name: crate::config::default_assistant_name(),
+            derived_from: None,
+        }
+    }
 }
 
 impl From<ProfileConfig> for VoiceProfile {
@@ -27,9 +111,29 @@ impl From<ProfileConfig> for VoiceProfile {
             personality: config.personality,
             avatar_path: config.avatar_path,
             avatar_size_px: config.avatar_size_px,
+            interaction_mode: config.interaction_mode,
+            window_width: config.window_width,
+            window_height: config.window_height,
             voice_model: config.voice_model,
             tts_speed: config.tts_speed,
             tts_enabled: config.tts_enabled,
+            stop: config.stop,
+            num_predict: config.num_predict,
+            greeting: config.greeting,
+            response_format: config.response_format,
+            temperature: config.temperature,
+            preset: config.preset,
+            system_prompt: config.system_prompt,
+            stt_initial_prompt: config.stt_initial_prompt,
+            response_language: config.response_language,
+            auto_detect_language: config.auto_detect_language,
+            voice_commands: config.voice_commands,
+            tts_volume: config.tts_volume,
+            vision_enabled: config.vision_enabled,
+            message_template: config.message_template,
+            tts_speaker_id: config.tts_speaker_id,
+            assistant_name: config.assistant_name,
+            derived_from: config.derived_from,
         }
     }
 }
@@ -42,16 +146,297 @@ impl From<VoiceProfile> for ProfileConfig {
             personality: profile.personality,
             avatar_path: profile.avatar_path,
             avatar_size_px: profile.avatar_size_px,
+            interaction_mode: profile.interaction_mode,
+            window_width: profile.window_width,
+            window_height: profile.window_height,
             voice_model: profile.voice_model,
             tts_speed: profile.tts_speed,
             tts_enabled: profile.tts_enabled,
+            stop: profile.stop,
+            num_predict: profile.num_predict,
+            greeting: profile.greeting,
+            response_format: profile.response_format,
+            temperature: profile.temperature,
+            preset: profile.preset,
+            system_prompt: profile.system_prompt,
+            stt_initial_prompt: profile.stt_initial_prompt,
+            response_language: profile.response_language,
+            auto_detect_language: profile.auto_detect_language,
+            voice_commands: profile.voice_commands,
+            tts_volume: profile.tts_volume,
+            vision_enabled: profile.vision_enabled,
+            message_template: profile.message_template,
+            tts_speaker_id: profile.tts_speaker_id,
+            assistant_name: profile.assistant_name,
+            derived_from: profile.derived_from,
         }
     }
 }
 
+/// How `ProfileManager::merge_profiles` combines two profiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum MergeStrategy {
+    /// Settings of the first profile
+    #[default]
+    PreferA,
+    /// Settings of the second profile
+    PreferB,
+    /// Settings of the first profile with both system prompts, stop
+    /// sequences and voice commands
+    #[value(alias = "concat")]
+    Concatenate,
+}
+
+/// Between the two system prompts of a concatenated profile
+const MERGED_PROMPT_SEPARATOR: &str = "\n\n";
+
+/// `manifest.json` at the root of a profile pack
+#[derive(Debug, Serialize, Deserialize)]
+struct PackManifest {
+    version: u32,
+    profiles: BTreeMap<String, VoiceProfile>,
+}
+
+/// Switches to `profile` automatically while `trigger` holds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileRule {
+    pub trigger: RuleTrigger,
+    pub profile: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleTrigger {
+    /// Regex matched against the focused window's title
+    ActiveWindow(String),
+    /// Hours `start..end` (24h clock), wrapping past midnight if `start > end`
+    TimeOfDay { start: u8, end: u8 },
+}
+
+impl RuleTrigger {
+    /// Whether a time of day trigger holds at `hour`; window triggers never do
+    pub fn covers_hour(&self, hour: u8) -> bool {
+        match self {
+            RuleTrigger::ActiveWindow(_) => false,
+            RuleTrigger::TimeOfDay { start, end } => {
+                if start <= end {
+                    (*start..*end).contains(&hour)
+                } else {
+                    hour >= *start || hour < *end
+                }
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for RuleTrigger {
+    type Err = anyhow::Error;
+
+    /// Parses `window=<regex>` or `time=<start>-<end>`
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, value) = s.split_once('=')
+            .context("Trigger must look like window=<regex> or time=<start>-<end>")?;
+
+        match kind {
+            "window" => {
+                regex::Regex::new(value).context("Invalid window title pattern")?;
+                Ok(RuleTrigger::ActiveWindow(value.to_string()))
+            }
+            "time" => {
+                let (start, end) = value.split_once('-')
+                    .context("Time trigger must look like time=9-17")?;
+                let start: u8 = start.trim().parse().context("Invalid start hour")?;
+                let end: u8 = end.trim().parse().context("Invalid end hour")?;
+                if start > 23 || end > 24 {
+                    bail!("Hours must be between 0 and 24");
+                }
+                Ok(RuleTrigger::TimeOfDay { start, end })
+            }
+            _ => bail!("Unknown trigger type '{}', expected window or time", kind),
+        }
+    }
+}
+
+/// Config key for a new profile called `name`, e.g. "Night Owl" -> "night-owl"
+pub fn profile_id(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Profile rules with their window title patterns compiled once
+pub struct CompiledRules {
+    rules: Vec<(ProfileRule, Option<regex::Regex>)>,
+}
+
+impl CompiledRules {
+    /// Rules whose pattern doesn't compile are kept but never match
+    pub fn new(rules: &[ProfileRule]) -> Self {
+        let rules = rules.iter()
+            .map(|rule| {
+                let pattern = match &rule.trigger {
+                    RuleTrigger::ActiveWindow(pattern) => match regex::Regex::new(pattern) {
+                        Ok(re) => Some(re),
+                        Err(e) => {
+                            tracing::warn!("Invalid window rule pattern {:?}: {}", pattern, e);
+                            None
+                        }
+                    },
+                    RuleTrigger::TimeOfDay { .. } => None,
+                };
+                (rule.clone(), pattern)
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Whether these were compiled from `rules`
+    pub fn compiled_from(&self, rules: &[ProfileRule]) -> bool {
+        self.rules.len() == rules.len()
+            && self.rules.iter().zip(rules).all(|((compiled, _), rule)| compiled == rule)
+    }
+
+    /// Profile of the first rule whose trigger holds
+    pub fn matching_profile(&self, window_title: Option<&str>, hour: u8) -> Option<&str> {
+        self.rules.iter()
+            .find(|(rule, pattern)| match pattern {
+                Some(re) => window_title.is_some_and(|title| re.is_match(title)),
+                None => rule.trigger.covers_hour(hour),
+            })
+            .map(|(rule, _)| rule.profile.as_str())
+    }
+}
+
+/// Placeholders a message template can use
+static TEMPLATE_PLACEHOLDERS: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r"\{(role|content|timestamp|model)\}").expect("valid regex"));
+
+/// Fills a profile's `message_template` in for messages shown in the window
+pub struct MessageFormatter;
+
+impl MessageFormatter {
+    /// Replace `{role}`, `{content}`, `{timestamp}` (local time) and `{model}`
+    /// in `template`. Unknown placeholders are left as they are.
+    pub fn format(template: &str, message: &Message, model: &str) -> String {
+        Self::format_in(template, message, model, &chrono::Local)
+    }
+
+    fn format_in<Tz: chrono::TimeZone>(template: &str, message: &Message, model: &str, tz: &Tz) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let timestamp = message.timestamp
+            .unwrap_or_else(chrono::Utc::now)
+            .with_timezone(tz)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+
+        // One pass, so placeholders inside the content are left alone
+        TEMPLATE_PLACEHOLDERS.replace_all(template, |captures: &regex::Captures| match &captures[1] {
+            "role" => message.role.clone(),
+            "content" => message.content.clone(),
+            "timestamp" => timestamp.clone(),
+            _ => model.to_string(),
+        }).into_owned()
+    }
+}
+
+/// Session-only changes applied on top of the active profile, e.g. for A/B testing prompts
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileOverrides {
+    pub model: Option<String>,
+    pub personality: Option<String>,
+    pub temperature: Option<f32>,
+    pub preset: Option<String>,
+    pub system_prompt: Option<String>,
+}
+
+impl ProfileOverrides {
+    /// Parse `key=value` pairs such as `model=llama3.1` or `temperature=0.2`
+    pub fn from_pairs(pairs: &[String]) -> Result<Self> {
+        let mut overrides = Self::default();
+
+        for pair in pairs {
+            let (key, value) = pair.split_once('=')
+                .with_context(|| format!("Override '{}' must look like key=value", pair))?;
+            match key.trim() {
+                "model" => overrides.model = Some(value.to_string()),
+                "personality" => overrides.personality = Some(value.to_string()),
+                "temperature" => {
+                    overrides.temperature = Some(value.parse().context("Invalid temperature")?)
+                }
+                "preset" => overrides.preset = Some(value.to_string()),
+                "system_prompt" => overrides.system_prompt = Some(value.to_string()),
+                other => bail!("Unknown override '{}'", other),
+            }
+        }
+
+        Ok(overrides)
+    }
+}
+
+/// A profile as listed by `blipply-assistant profiles`. The field names are
+/// part of the JSON output, so keep them stable.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProfileSummary {
+    pub id: String,
+    pub name: String,
+    pub model: String,
+    pub voice: String,
+    pub active: bool,
+    /// From the personality's prompt file, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Model the personality's prompt file was written for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommended_model: Option<String>,
+}
+
+/// Summaries as aligned columns with a header, the active profile starred
+pub fn format_profile_table(summaries: &[ProfileSummary]) -> String {
+    let header = ProfileSummary {
+        id: "ID".to_string(),
+        name: "NAME".to_string(),
+        model: "MODEL".to_string(),
+        voice: "VOICE".to_string(),
+        active: false,
+        // The column is only shown when a profile has a description
+        description: summaries.iter().any(|s| s.description.is_some()).then(|| "DESCRIPTION".to_string()),
+        recommended_model: None,
+    };
+    let rows: Vec<&ProfileSummary> = std::iter::once(&header).chain(summaries).collect();
+    let width = |field: fn(&ProfileSummary) -> &str| rows.iter().map(|row| field(row).chars().count()).max().unwrap_or(0);
+    let (id_width, name_width, model_width) = (width(|s| &s.id), width(|s| &s.name), width(|s| &s.model));
+    let voice_width = width(|s| &s.voice);
+
+    rows.iter()
+        .map(|row| {
+            let marker = if row.active { '*' } else { ' ' };
+            let line = format!(
+                "{} {:id_width$}  {:name_width$}  {:model_width$}  {:voice_width$}  {}",
+                marker, row.id, row.name, row.model, row.voice, row.description.as_deref().unwrap_or(""),
+            );
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+/// "morning", "afternoon" or "evening" for an hour of the day
+fn time_of_day(hour: u32) -> &'static str {
+    match hour {
+        5..=11 => "morning",
+        12..=17 => "afternoon",
+        _ => "evening",
+    }
+}
+
 pub struct ProfileManager {
     pub active: String,
     pub profiles: HashMap<String, VoiceProfile>,
+    /// Personalities defined by files in the prompts directory
+    pub prompts: PromptLibrary,
 }
 
 impl ProfileManager {
@@ -64,6 +449,7 @@ impl ProfileManager {
         Self {
             active: config.general.active_profile.clone(),
             profiles,
+            prompts: PromptLibrary::new(Config::prompts_dir().ok()),
         }
     }
 
@@ -85,16 +471,7 @@ impl ProfileManager {
                 .cloned()
                 .ok_or_else(|| anyhow::anyhow!("Base profile '{}' not found", base_name))?
         } else {
-            VoiceProfile {
-                name: name.clone(),
-                model: "llama3.2:3b".to_string(),
-                personality: "helpful".to_string(),
-                avatar_path: "/usr/share/blipply/clippy.gif".to_string(),
-                avatar_size_px: 96,
-                voice_model: "en_US-lessac-medium".to_string(),
-                tts_speed: 1.0,
-                tts_enabled: true,
-            }
+            VoiceProfile::new(name.clone())
         };
 
         self.profiles.insert(name.clone(), VoiceProfile {
@@ -105,6 +482,44 @@ impl ProfileManager {
         Ok(())
     }
 
+    /// Create `new_name` by combining profiles `a` and `b`, remembering both
+    /// as its lineage
+    pub fn merge_profiles(&mut self, a: &str, b: &str, new_name: &str, strategy: MergeStrategy) -> Result<()> {
+        if self.profiles.contains_key(new_name) {
+            bail!("Profile '{}' already exists", new_name);
+        }
+        let profile_a = self.profiles.get(a)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", a))?;
+        let profile_b = self.profiles.get(b)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", b))?;
+
+        let mut merged = match strategy {
+            MergeStrategy::PreferA => profile_a.clone(),
+            MergeStrategy::PreferB => profile_b.clone(),
+            MergeStrategy::Concatenate => {
+                let mut merged = profile_a.clone();
+                merged.system_prompt = Some(format!(
+                    "{}{}{}",
+                    self.get_system_prompt(profile_a),
+                    MERGED_PROMPT_SEPARATOR,
+                    self.get_system_prompt(profile_b),
+                ));
+                for stop in &profile_b.stop {
+                    if !merged.stop.contains(stop) {
+                        merged.stop.push(stop.clone());
+                    }
+                }
+                merged.voice_commands.extend(profile_b.voice_commands.iter().cloned());
+                merged
+            }
+        };
+        merged.name = new_name.to_string();
+        merged.derived_from = Some((a.to_string(), b.to_string()));
+
+        self.profiles.insert(new_name.to_string(), merged);
+        Ok(())
+    }
+
     pub fn switch_profile(&mut self, name: &str) -> Result<&VoiceProfile> {
         if !self.profiles.contains_key(name) {
             bail!("Profile '{}' not found", name);
@@ -141,29 +556,226 @@ impl ProfileManager {
         Ok(())
     }
 
-    pub fn get_system_prompt(&self, profile: &VoiceProfile) -> String {
-        match profile.personality.as_str() {
-            "helpful" => {
-                "You are Blipply – a friendly, concise desktop assistant for NixOS. \
-                 Be accurate, use markdown for formatting, and keep answers short unless \
-                 asked for detail. You have access to the user's desktop context.".to_string()
-            }
-            "sassy" => {
-                "You are a sassy, witty desktop assistant. Be helpful but don't be afraid \
-                 to add some personality. Keep it fun but professional.".to_string()
-            }
-            "technical" => {
-                "You are a technical assistant specializing in NixOS, Linux systems, and \
-                 programming. Provide detailed, accurate technical information with code \
-                 examples when relevant.".to_string()
+    /// Write every profile and its avatar into a zip profile pack
+    pub fn export_all(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create {:?}", path))?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default();
+
+        let mut profiles = BTreeMap::new();
+        for (id, profile) in &self.profiles {
+            let mut profile = profile.clone();
+            let avatar = Path::new(&profile.avatar_path);
+
+            // Bundle avatars that exist locally; keep other paths as-is
+            if let (Ok(bytes), Some(file_name)) = (std::fs::read(avatar), avatar.file_name()) {
+                let asset = format!("assets/{}-{}", id, file_name.to_string_lossy());
+                zip.start_file(asset.as_str(), options)?;
+                zip.write_all(&bytes)?;
+                profile.avatar_path = asset;
             }
-            "concise" => {
-                "You are a minimalist assistant. Provide the most direct, concise answers \
-                 possible. No fluff, just facts.".to_string()
+
+            profiles.insert(id.clone(), profile);
+        }
+
+        let manifest = PackManifest { version: PACK_VERSION, profiles };
+        zip.start_file("manifest.json", options)?;
+        zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+        zip.finish()?;
+
+        Ok(())
+    }
+
+    /// Import every profile from a pack into `config`, extracting avatars into
+    /// the data directory. Returns the ids of the imported profiles.
+    pub fn import_pack(path: &Path, config: &mut Config) -> Result<Vec<String>> {
+        let assets_dir = config.data_dir()?.join("avatars");
+        Self::import_pack_into(path, config, &assets_dir)
+    }
+
+    fn import_pack_into(path: &Path, config: &mut Config, assets_dir: &Path) -> Result<Vec<String>> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {:?}", path))?;
+        let mut archive = zip::ZipArchive::new(file).context("Not a valid profile pack")?;
+
+        let manifest: PackManifest = {
+            let mut contents = String::new();
+            archive.by_name("manifest.json")
+                .context("Profile pack has no manifest.json")?
+                .read_to_string(&mut contents)?;
+            serde_json::from_str(&contents).context("Invalid profile pack manifest")?
+        };
+
+        if manifest.version > PACK_VERSION {
+            bail!("Profile pack version {} is newer than supported ({})", manifest.version, PACK_VERSION);
+        }
+
+        let mut imported = Vec::new();
+        for (id, mut profile) in manifest.profiles {
+            if let Some(asset) = profile.avatar_path.strip_prefix("assets/") {
+                let mut entry = archive.by_name(&profile.avatar_path)
+                    .with_context(|| format!("Missing asset {}", profile.avatar_path))?;
+                let file_name = Path::new(asset)
+                    .file_name()
+                    .context("Invalid asset name")?;
+                let target: PathBuf = assets_dir.join(file_name);
+
+                std::fs::create_dir_all(assets_dir)?;
+                let mut out = std::fs::File::create(&target)?;
+                std::io::copy(&mut entry, &mut out)?;
+                profile.avatar_path = target.to_string_lossy().into_owned();
             }
-            _ => {
-                "You are a helpful desktop assistant.".to_string()
+
+            // Never clobber an existing profile
+            let mut new_id = id.clone();
+            let mut n = 2;
+            while config.profiles.contains_key(&new_id) {
+                new_id = format!("{}-{}", id, n);
+                n += 1;
             }
+
+            config.profiles.insert(new_id.clone(), profile.into());
+            imported.push(new_id);
+        }
+
+        Ok(imported)
+    }
+
+    /// A copy of the active profile with the set override fields applied
+    pub fn apply_overrides(&self, overrides: &ProfileOverrides) -> Result<VoiceProfile> {
+        let mut profile = self.active_profile()?.clone();
+
+        if let Some(model) = &overrides.model {
+            profile.model = model.clone();
+        }
+        if let Some(personality) = &overrides.personality {
+            profile.personality = personality.clone();
+        }
+        if let Some(temperature) = overrides.temperature {
+            profile.temperature = Some(temperature);
+        }
+        if let Some(preset) = &overrides.preset {
+            profile.preset = Some(preset.clone());
+        }
+        if let Some(system_prompt) = &overrides.system_prompt {
+            profile.system_prompt = Some(system_prompt.clone());
+        }
+
+        Ok(profile)
+    }
+
+    /// The profile greeting with `{time_of_day}` filled in, or `None` if unset
+    pub fn render_greeting(profile: &VoiceProfile, hour: u32) -> Option<String> {
+        let template = profile.greeting.as_deref()?.trim();
+        if template.is_empty() {
+            return None;
+        }
+
+        Some(template.replace("{time_of_day}", time_of_day(hour)))
+    }
+
+    /// `general.startup_message` with `{greeting}` and `{profile_name}` filled in
+    pub fn render_startup_message(template: &str, profile: &VoiceProfile, hour: u32) -> String {
+        template
+            .replace("{greeting}", &format!("Good {}", time_of_day(hour)))
+            .replace("{profile_name}", &profile.name)
+    }
+
+    /// Every profile for listing, sorted by id
+    pub fn summaries(&self) -> Vec<ProfileSummary> {
+        let mut summaries: Vec<ProfileSummary> = self.profiles
+            .iter()
+            .map(|(id, profile)| ProfileSummary {
+                id: id.clone(),
+                name: profile.name.clone(),
+                model: profile.model.clone(),
+                voice: profile.voice_model.clone(),
+                active: *id == self.active,
+                description: self.prompt_file(profile).and_then(|prompt| prompt.description.clone()),
+                recommended_model: self.prompt_file(profile).and_then(|prompt| prompt.recommended_model.clone()),
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.id.cmp(&b.id));
+        summaries
+    }
+
+    /// The prompt file a profile's personality comes from, unless it has its
+    /// own system prompt
+    fn prompt_file(&self, profile: &VoiceProfile) -> Option<std::sync::Arc<crate::prompts::PromptFile>> {
+        match profile.system_prompt {
+            Some(_) => None,
+            None => self.prompts.get(&profile.personality),
+        }
+    }
+
+    /// Effective system prompt of every profile, keyed by profile id
+    pub fn get_all_system_prompts(&self) -> HashMap<String, String> {
+        self.profiles
+            .iter()
+            .map(|(id, profile)| (id.clone(), self.get_system_prompt(profile)))
+            .collect()
+    }
+
+    /// The profile's system prompt with `{assistant_name}` filled in, and
+    /// its response language if it has one
+    pub fn get_system_prompt(&self, profile: &VoiceProfile) -> String {
+        let name = profile.assistant_name.as_str();
+
+        // Written prompts say what they want; presets are told a custom name
+        let prompt = match &profile.system_prompt {
+            Some(prompt) => prompt.clone(),
+            None => match self.prompts.get(&profile.personality) {
+                // A prompt file wins over a built-in personality of the same name
+                Some(prompt) => prompt.body.clone(),
+                None => {
+                    let preset = preset_prompt(&profile.personality);
+                    if preset.contains(ASSISTANT_NAME_TOKEN) || name == crate::config::default_assistant_name() {
+                        preset.to_string()
+                    } else {
+                        format!("{} Your name is {}.", preset, ASSISTANT_NAME_TOKEN)
+                    }
+                }
+            },
+        };
+
+        let prompt = prompt.replace(ASSISTANT_NAME_TOKEN, name);
+        match &profile.response_language {
+            Some(language) => format!(
+                "{} Always respond in {}, regardless of the user's input language.",
+                prompt, language
+            ),
+            None => prompt,
+        }
+    }
+}
+
+/// Replaced by the profile's assistant name in system prompts
+const ASSISTANT_NAME_TOKEN: &str = "{assistant_name}";
+
+/// System prompt of a built-in personality
+fn preset_prompt(personality: &str) -> &'static str {
+    match personality {
+        "helpful" => {
+            "You are {assistant_name} – a friendly, concise desktop assistant for NixOS. \
+             Be accurate, use markdown for formatting, and keep answers short unless \
+             asked for detail. You have access to the user's desktop context."
+        }
+        "sassy" => {
+            "You are a sassy, witty desktop assistant. Be helpful but don't be afraid \
+             to add some personality. Keep it fun but professional."
+        }
+        "technical" => {
+            "You are a technical assistant specializing in NixOS, Linux systems, and \
+             programming. Provide detailed, accurate technical information with code \
+             examples when relevant."
+        }
+        "concise" => {
+            "You are a minimalist assistant. Provide the most direct, concise answers \
+             possible. No fluff, just facts."
+        }
+        _ => {
+            "You are a helpful desktop assistant."
         }
     }
 }
@@ -172,6 +784,145 @@ impl ProfileManager {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_message_template() {
+        use chrono::TimeZone;
+
+        let mut message = Message::assistant("Hi {model}!");
+        message.timestamp = Some(chrono::Utc.with_ymd_and_hms(2026, 3, 5, 9, 30, 0).unwrap());
+
+        assert_eq!(
+            MessageFormatter::format_in("[{timestamp}] {role}: {content}", &message, "llama3.2:3b", &chrono::Utc),
+            "[2026-03-05 09:30:00] assistant: Hi {model}!"
+        );
+        assert_eq!(
+            MessageFormatter::format_in("{model} {unknown}", &message, "llama3.2:3b", &chrono::Utc),
+            "llama3.2:3b {unknown}"
+        );
+    }
+
+    fn merge_manager() -> ProfileManager {
+        let mut manager = ProfileManager::from_config(&Config::default());
+        manager.create_profile("technical".to_string(), None).unwrap();
+        let mut technical = manager.profiles["technical"].clone();
+        technical.personality = "technical".to_string();
+        technical.model = "qwen2.5-coder:7b".to_string();
+        technical.stop = vec!["User:".to_string()];
+        manager.update_profile("technical", technical).unwrap();
+
+        manager.create_profile("sassy".to_string(), None).unwrap();
+        let mut sassy = manager.profiles["sassy"].clone();
+        sassy.personality = "sassy".to_string();
+        sassy.model = "llama3.2:3b".to_string();
+        sassy.stop = vec!["User:".to_string(), "###".to_string()];
+        sassy.tts_speed = 1.2;
+        manager.update_profile("sassy", sassy).unwrap();
+        manager
+    }
+
+    #[test]
+    fn test_merge_profiles_prefer_a_and_b() {
+        let mut manager = merge_manager();
+
+        manager.merge_profiles("technical", "sassy", "tech-a", MergeStrategy::PreferA).unwrap();
+        let merged = &manager.profiles["tech-a"];
+        assert_eq!(merged.name, "tech-a");
+        assert_eq!(merged.model, "qwen2.5-coder:7b");
+        assert_eq!(merged.personality, "technical");
+        assert_eq!(merged.tts_speed, 1.0);
+        assert_eq!(merged.derived_from, Some(("technical".to_string(), "sassy".to_string())));
+
+        manager.merge_profiles("technical", "sassy", "tech-b", MergeStrategy::PreferB).unwrap();
+        let merged = &manager.profiles["tech-b"];
+        assert_eq!(merged.name, "tech-b");
+        assert_eq!(merged.model, "llama3.2:3b");
+        assert_eq!(merged.personality, "sassy");
+        assert_eq!(merged.tts_speed, 1.2);
+
+        assert!(manager.merge_profiles("technical", "sassy", "tech-a", MergeStrategy::PreferA).is_err());
+        assert!(manager.merge_profiles("technical", "missing", "other", MergeStrategy::PreferA).is_err());
+    }
+
+    #[test]
+    fn test_merge_profiles_concatenate() {
+        let mut manager = merge_manager();
+        manager.merge_profiles("technical", "sassy", "technical-sassy", MergeStrategy::Concatenate).unwrap();
+
+        let merged = manager.profiles["technical-sassy"].clone();
+        assert_eq!(merged.model, "qwen2.5-coder:7b");
+        assert_eq!(merged.stop, vec!["User:".to_string(), "###".to_string()]);
+
+        let prompt = manager.get_system_prompt(&merged);
+        let (first, second) = prompt.split_once(MERGED_PROMPT_SEPARATOR).unwrap();
+        assert!(first.starts_with("You are a technical assistant"));
+        assert!(second.starts_with("You are a sassy"));
+
+        // The lineage survives a round trip through the config
+        let config: ProfileConfig = merged.into();
+        let profile: VoiceProfile = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(profile.derived_from, Some(("technical".to_string(), "sassy".to_string())));
+    }
+
+    #[test]
+    fn test_all_system_prompts() {
+        let config = Config::default();
+        let mut manager = ProfileManager::from_config(&config);
+        manager.create_profile("terse".to_string(), None).unwrap();
+        let mut terse = manager.profiles["terse"].clone();
+        terse.personality = "concise".to_string();
+        manager.update_profile("terse", terse).unwrap();
+        manager.create_profile("custom".to_string(), None).unwrap();
+        let mut custom = manager.profiles["custom"].clone();
+        custom.system_prompt = Some("Answer in haiku.".to_string());
+        manager.update_profile("custom", custom).unwrap();
+
+        let prompts = manager.get_all_system_prompts();
+        assert_eq!(prompts.len(), manager.profiles.len());
+        assert!(prompts["terse"].starts_with("You are a minimalist assistant"));
+        assert_eq!(prompts["custom"], "Answer in haiku.");
+    }
+
+    #[test]
+    fn test_assistant_name_in_prompts() {
+        let manager = ProfileManager::from_config(&Config::default());
+        let mut profile = VoiceProfile::new("Work".to_string());
+        assert!(manager.get_system_prompt(&profile).starts_with("You are Blipply –"));
+
+        profile.assistant_name = "Alice".to_string();
+        assert!(manager.get_system_prompt(&profile).starts_with("You are Alice –"));
+
+        // Presets without a name are told it
+        profile.personality = "concise".to_string();
+        assert!(manager.get_system_prompt(&profile).ends_with("No fluff, just facts. Your name is Alice."));
+
+        profile.system_prompt = Some("You are {assistant_name}, a pirate.".to_string());
+        assert_eq!(manager.get_system_prompt(&profile), "You are Alice, a pirate.");
+
+        profile.response_language = Some("French".to_string());
+        assert_eq!(
+            manager.get_system_prompt(&profile),
+            "You are Alice, a pirate. Always respond in French, regardless of the user's input language."
+        );
+    }
+
+    #[test]
+    fn test_personality_from_prompt_file() {
+        let dir = std::env::temp_dir().join(format!("blipply-prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("pirate.md"), "---\ndescription: Arr\n---\nYou are a pirate.\n").unwrap();
+
+        let mut manager = ProfileManager::from_config(&Config::default());
+        manager.prompts = PromptLibrary::new(Some(dir.clone()));
+        let mut profile = VoiceProfile::new("Pirate".to_string());
+        profile.personality = "pirate".to_string();
+        assert_eq!(manager.get_system_prompt(&profile), "You are a pirate.");
+
+        profile.personality = "concise".to_string();
+        assert!(manager.get_system_prompt(&profile).starts_with("You are a minimalist assistant"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     #[test]
     fn test_create_profile() {
         let config = Config::default();
@@ -191,4 +942,172 @@ mod tests {
         assert!(manager.switch_profile("test").is_ok());
         assert_eq!(manager.active, "test");
     }
+
+    #[test]
+    fn test_time_of_day_rule() {
+        let trigger = RuleTrigger::TimeOfDay { start: 9, end: 17 };
+        assert!(!trigger.covers_hour(8));
+        assert!(trigger.covers_hour(9));
+        assert!(trigger.covers_hour(16));
+        assert!(!trigger.covers_hour(17));
+
+        let overnight = RuleTrigger::TimeOfDay { start: 22, end: 6 };
+        assert!(overnight.covers_hour(23));
+        assert!(overnight.covers_hour(2));
+        assert!(!overnight.covers_hour(12));
+    }
+
+    #[test]
+    fn test_rule_order_and_parsing() {
+        let rules = vec![
+            ProfileRule { trigger: "window=Code".parse().unwrap(), profile: "technical".to_string() },
+            ProfileRule { trigger: "time=9-17".parse().unwrap(), profile: "concise".to_string() },
+        ];
+
+        let compiled = CompiledRules::new(&rules);
+        assert_eq!(compiled.matching_profile(Some("main.rs - Visual Studio Code"), 10), Some("technical"));
+        assert_eq!(compiled.matching_profile(Some("Firefox"), 10), Some("concise"));
+        assert_eq!(compiled.matching_profile(None, 20), None);
+        assert!(compiled.compiled_from(&rules));
+        assert!(!compiled.compiled_from(&rules[..1]));
+        assert!("size=3".parse::<RuleTrigger>().is_err());
+    }
+
+    #[test]
+    fn test_apply_partial_overrides() {
+        let config = Config::default();
+        let manager = ProfileManager::from_config(&config);
+        let active = manager.active_profile().unwrap().clone();
+
+        let overrides = ProfileOverrides::from_pairs(&[
+            "model=llama3.1".to_string(),
+            "temperature=0.2".to_string(),
+            "preset=creative".to_string(),
+        ])
+        .unwrap();
+        let profile = manager.apply_overrides(&overrides).unwrap();
+
+        assert_eq!(profile.model, "llama3.1");
+        assert_eq!(profile.temperature, Some(0.2));
+        assert_eq!(profile.preset.as_deref(), Some("creative"));
+        assert_eq!(profile.personality, active.personality);
+        assert_eq!(profile.system_prompt, None);
+        assert_eq!(manager.active_profile().unwrap(), &active);
+
+        assert_eq!(manager.apply_overrides(&ProfileOverrides::default()).unwrap(), active);
+        assert!(ProfileOverrides::from_pairs(&["colour=blue".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_render_greeting() {
+        let config = Config::default();
+        let manager = ProfileManager::from_config(&config);
+        let mut profile = manager.active_profile().unwrap().clone();
+
+        assert_eq!(ProfileManager::render_greeting(&profile, 9), None);
+
+        profile.greeting = Some("  ".to_string());
+        assert_eq!(ProfileManager::render_greeting(&profile, 9), None);
+
+        profile.greeting = Some("Good {time_of_day}!".to_string());
+        assert_eq!(ProfileManager::render_greeting(&profile, 9).unwrap(), "Good morning!");
+        assert_eq!(ProfileManager::render_greeting(&profile, 14).unwrap(), "Good afternoon!");
+        assert_eq!(ProfileManager::render_greeting(&profile, 22).unwrap(), "Good evening!");
+    }
+
+    #[test]
+    fn test_profile_listing() {
+        let mut manager = ProfileManager::from_config(&Config::default());
+        manager.create_profile("work".to_string(), None).unwrap();
+        let summaries = manager.summaries();
+        assert!(summaries.windows(2).all(|pair| pair[0].id < pair[1].id));
+        assert_eq!(summaries.iter().filter(|s| s.active).count(), 1);
+
+        let json = serde_json::to_value(&summaries[0]).unwrap();
+        let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["active", "id", "model", "name", "voice"]);
+
+        let table = format_profile_table(&[
+            ProfileSummary {
+                id: "default".to_string(),
+                name: "Blipply".to_string(),
+                model: "llama3.2:3b".to_string(),
+                voice: "en_US-lessac-medium".to_string(),
+                active: true,
+                description: None,
+                recommended_model: None,
+            },
+            ProfileSummary {
+                id: "w".to_string(),
+                name: "Work".to_string(),
+                model: "qwen".to_string(),
+                voice: "en_US-amy-medium".to_string(),
+                active: false,
+                description: None,
+                recommended_model: None,
+            },
+        ]);
+        assert_eq!(
+            table,
+            "  ID       NAME     MODEL        VOICE\n\
+             * default  Blipply  llama3.2:3b  en_US-lessac-medium\n\
+             \x20 w        Work     qwen         en_US-amy-medium\n"
+        );
+
+        let pirate = ProfileSummary {
+            id: "p".to_string(),
+            name: "Pirate".to_string(),
+            model: "qwen".to_string(),
+            voice: "en_US-amy-medium".to_string(),
+            active: false,
+            description: Some("Talks like a pirate".to_string()),
+            recommended_model: Some("llama3.2:3b".to_string()),
+        };
+        assert!(format_profile_table(&[pirate.clone()]).starts_with("  ID  NAME    MODEL  VOICE             DESCRIPTION\n"));
+        assert!(format_profile_table(&[pirate.clone()]).ends_with("en_US-amy-medium  Talks like a pirate\n"));
+        let json = serde_json::to_value(&pirate).unwrap();
+        assert_eq!(json["recommended_model"], "llama3.2:3b");
+    }
+
+    #[test]
+    fn test_render_startup_message() {
+        let profile = VoiceProfile::new("Blipply".to_string());
+        let template = "{greeting}, {profile_name} is ready";
+        assert_eq!(ProfileManager::render_startup_message(template, &profile, 14), "Good afternoon, Blipply is ready");
+        assert_eq!(ProfileManager::render_startup_message(template, &profile, 7), "Good morning, Blipply is ready");
+        assert_eq!(ProfileManager::render_startup_message("{greeting}", &profile, 23), "Good evening");
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let dir = std::env::temp_dir().join(format!("blipply-pack-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pack = dir.join("profiles.zip");
+
+        let config = Config::default();
+        let mut manager = ProfileManager::from_config(&config);
+        manager.create_profile("custom".to_string(), None).unwrap();
+        manager.profiles.get_mut("custom").unwrap().tts_speed = 1.23;
+        manager.export_all(&pack).unwrap();
+
+        let mut target = Config::default();
+        target.profiles.clear();
+        let mut imported = ProfileManager::import_pack_into(&pack, &mut target, &dir.join("assets")).unwrap();
+        imported.sort();
+        assert_eq!(imported, vec!["custom", "default"]);
+
+        let restored = ProfileManager::from_config(&target);
+        assert_eq!(restored.profiles["custom"], manager.profiles["custom"]);
+        assert_eq!(restored.profiles["custom"].tts_speed, 1.23);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_profile_id() {
+        assert_eq!(profile_id("Night Owl"), "night-owl");
+        assert_eq!(profile_id("  C++ helper! "), "c-helper");
+        assert_eq!(profile_id("!!!"), "");
+    }
 }
@@ -0,0 +1,159 @@
+// Blipply Assistant - Prompt Files
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+//! Personalities can be plain Markdown files in the prompts directory:
+//!
+//! ```text
+//! <config_dir>/blipply-assistant/prompts/<personality>.md
+//! ```
+//!
+//! The file body is the system prompt. An optional front-matter block adds
+//! metadata:
+//!
+//! ```text
+//! ---
+//! description: Talks like a pirate
+//! model: llama3.2:3b
+//! ---
+//! You are a pirate...
+//! ```
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptFile {
+    pub body: String,
+    pub description: Option<String>,
+    pub recommended_model: Option<String>,
+}
+
+impl PromptFile {
+    pub fn parse(contents: &str) -> Self {
+        let mut prompt = Self::default();
+
+        let body = match split_front_matter(contents) {
+            Some((front_matter, body)) => {
+                for line in front_matter.lines() {
+                    let Some((key, value)) = line.split_once(':') else {
+                        continue;
+                    };
+                    let value = value.trim().trim_matches('"').to_string();
+                    match key.trim() {
+                        "description" => prompt.description = Some(value),
+                        "model" | "recommended_model" => prompt.recommended_model = Some(value),
+                        other => debug!("Ignoring prompt metadata {:?}", other),
+                    }
+                }
+                body
+            }
+            None => contents,
+        };
+
+        prompt.body = body.trim().to_string();
+        prompt
+    }
+}
+
+/// `(front matter, body)` if `contents` starts with a `---` block
+fn split_front_matter(contents: &str) -> Option<(&str, &str)> {
+    let rest = contents.strip_prefix("---")?.strip_prefix('\n')
+        .or_else(|| contents.strip_prefix("---\r\n"))?;
+    let end = rest.find("\n---")?;
+    let body = &rest[end + 4..];
+    Some((&rest[..end], body.split_once('\n').map_or("", |(_, body)| body)))
+}
+
+/// Prompt files in the prompts directory, read once and then cached
+#[derive(Debug, Default)]
+pub struct PromptLibrary {
+    dir: Option<PathBuf>,
+    cache: Mutex<HashMap<String, Arc<PromptFile>>>,
+}
+
+impl PromptLibrary {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir, cache: Mutex::default() }
+    }
+
+    /// The prompt file for `personality`, if there is one
+    pub fn get(&self, personality: &str) -> Option<Arc<PromptFile>> {
+        // Personalities are file names, never paths
+        if personality.is_empty() || personality.contains(['/', '\\']) || personality.starts_with('.') {
+            return None;
+        }
+        if let Some(prompt) = self.cache.lock().get(personality) {
+            return Some(prompt.clone());
+        }
+
+        let path = self.dir.as_ref()?.join(format!("{}.md", personality));
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("Failed to read prompt file {:?}: {}", path, e);
+                return None;
+            }
+        };
+
+        debug!("Loaded personality {:?} from {:?}", personality, path);
+        let prompt = Arc::new(PromptFile::parse(&contents));
+        self.cache.lock().insert(personality.to_string(), prompt.clone());
+        Some(prompt)
+    }
+
+    /// Names of the available prompt files, sorted
+    pub fn names(&self) -> Vec<String> {
+        let Some(entries) = self.dir.as_ref().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+            return Vec::new();
+        };
+
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .filter_map(|path| path.file_stem()?.to_str().map(str::to_string))
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_front_matter() {
+        let prompt = PromptFile::parse("---\ndescription: Talks like a pirate\nmodel: \"llama3.2:3b\"\n---\n\nArr, ye be helpful.\n");
+        assert_eq!(prompt.description.as_deref(), Some("Talks like a pirate"));
+        assert_eq!(prompt.recommended_model.as_deref(), Some("llama3.2:3b"));
+        assert_eq!(prompt.body, "Arr, ye be helpful.");
+
+        let plain = PromptFile::parse("Just a prompt --- with dashes.");
+        assert_eq!(plain, PromptFile { body: "Just a prompt --- with dashes.".to_string(), ..Default::default() });
+    }
+
+    #[test]
+    fn test_library_caches_files() {
+        let dir = std::env::temp_dir().join(format!("blipply-prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("pirate.md"), "Arr.").unwrap();
+        std::fs::write(dir.join("notes.txt"), "Not a prompt").unwrap();
+
+        let library = PromptLibrary::new(Some(dir.clone()));
+        assert_eq!(library.names(), vec!["pirate"]);
+        assert_eq!(library.get("pirate").unwrap().body, "Arr.");
+        assert!(library.get("notes").is_none());
+        assert!(library.get("../pirate").is_none());
+
+        // Edits are picked up on restart, not mid-session
+        std::fs::write(dir.join("pirate.md"), "Changed.").unwrap();
+        assert_eq!(library.get("pirate").unwrap().body, "Arr.");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,70 @@
+// Blipply Assistant - Screenshot Portal
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::{Context, Result, bail};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::debug;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::Connection;
+
+const PORTAL_NAME: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+
+/// Capture the screen through xdg-desktop-portal and return the saved file
+pub async fn capture() -> Result<PathBuf> {
+    let connection = Connection::session().await
+        .context("Failed to connect to session bus")?;
+
+    let sender = connection.unique_name()
+        .context("Session bus connection has no unique name")?;
+    let token = format!("blipply{}", uuid::Uuid::new_v4().simple());
+    let path = request_path(sender.as_str(), &token);
+
+    // Subscribe before calling so the response can't be missed
+    let request = zbus::Proxy::new(&connection, PORTAL_NAME, path.as_str(), "org.freedesktop.portal.Request").await?;
+    let mut responses = request.receive_signal("Response").await?;
+
+    let mut options: HashMap<&str, Value> = HashMap::new();
+    options.insert("handle_token", Value::from(token.as_str()));
+    options.insert("interactive", Value::from(false));
+
+    connection
+        .call_method(Some(PORTAL_NAME), PORTAL_PATH, Some("org.freedesktop.portal.Screenshot"), "Screenshot", &("", options))
+        .await
+        .context("Screenshot portal not available")?;
+
+    let message = responses.next().await.context("Screenshot portal closed the request")?;
+    let (response, mut results): (u32, HashMap<String, OwnedValue>) = message.body().deserialize()?;
+    if response != 0 {
+        bail!("Screenshot was cancelled");
+    }
+
+    let uri = String::try_from(results.remove("uri").context("Screenshot portal returned no image")?)?;
+    debug!("Screenshot saved to {}", uri);
+
+    reqwest::Url::parse(&uri)?
+        .to_file_path()
+        .map_err(|_| anyhow::anyhow!("Screenshot is not a local file: {}", uri))
+}
+
+/// Object path the portal uses for the request identified by `token`
+fn request_path(unique_name: &str, token: &str) -> String {
+    let sender = unique_name.trim_start_matches(':').replace('.', "_");
+    format!("{}/request/{}/{}", PORTAL_PATH, sender, token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_path() {
+        assert_eq!(
+            request_path(":1.42", "blipply1"),
+            "/org/freedesktop/portal/desktop/request/1_42/blipply1"
+        );
+    }
+}
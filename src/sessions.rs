@@ -0,0 +1,154 @@
+// Blipply Assistant - Saved Conversations
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+//! Conversations are saved one file per session:
+//!
+//! ```text
+//! <data_dir>/sessions/<id>.json
+//! ```
+//!
+//! where `<id>` is the UTC start time (`20260305-093000`), so file names
+//! sort oldest to newest. Each file holds a `Session`: metadata plus the
+//! full message list, rewritten after every exchange.
+
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::ollama::Message;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub profile: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub messages: Vec<Message>,
+}
+
+impl Session {
+    pub fn new(profile: impl Into<String>, created: DateTime<Utc>) -> Self {
+        Self {
+            id: created.format("%Y%m%d-%H%M%S").to_string(),
+            profile: profile.into(),
+            title: None,
+            created,
+            updated: created,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Number of user messages
+    pub fn turn_count(&self) -> usize {
+        self.messages.iter().filter(|m| m.role == "user").count()
+    }
+
+    /// Title, or the start time for untitled sessions
+    pub fn display_title(&self) -> String {
+        match &self.title {
+            Some(title) => title.clone(),
+            None => format!("Conversation of {}", self.created.format("%Y-%m-%d %H:%M")),
+        }
+    }
+}
+
+/// Reads and writes sessions in a directory
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    pub fn save(&self, session: &Session) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {:?}", self.dir))?;
+
+        // Write then rename, so a crash never leaves half a session
+        let path = self.path(&session.id);
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_vec_pretty(session)?)
+            .with_context(|| format!("Failed to write {:?}", temp))?;
+        std::fs::rename(&temp, &path)
+            .with_context(|| format!("Failed to save session {:?}", path))?;
+        Ok(())
+    }
+
+    pub fn load(&self, id: &str) -> Result<Session> {
+        read_session(&self.path(id))
+    }
+
+    /// All saved sessions, newest first. Unreadable files are skipped.
+    pub fn list(&self) -> Result<Vec<Session>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", self.dir)),
+        };
+
+        let mut sessions: Vec<Session> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| match read_session(&path) {
+                Ok(session) => Some(session),
+                Err(e) => {
+                    warn!("Skipping session {:?}: {:#}", path, e);
+                    None
+                }
+            })
+            .collect();
+
+        sessions.sort_by(|a, b| b.updated.cmp(&a.updated));
+        Ok(sessions)
+    }
+}
+
+fn read_session(path: &Path) -> Result<Session> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    serde_json::from_str(&contents).with_context(|| format!("Invalid session file {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_save_and_list_sessions() {
+        let dir = std::env::temp_dir().join(format!("blipply-sessions-{}", uuid::Uuid::new_v4()));
+        let store = SessionStore::new(&dir);
+        assert!(store.list().unwrap().is_empty());
+
+        let mut older = Session::new("default", Utc.with_ymd_and_hms(2026, 3, 5, 9, 30, 0).unwrap());
+        older.messages = vec![Message::user("hi"), Message::assistant("hello"), Message::user("bye")];
+        let mut newer = Session::new("sassy", Utc.with_ymd_and_hms(2026, 3, 6, 8, 0, 0).unwrap());
+        newer.title = Some("Mounting NFS".to_string());
+
+        store.save(&older).unwrap();
+        store.save(&newer).unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let sessions = store.list().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id, "20260306-080000");
+        assert_eq!(sessions[1].turn_count(), 2);
+        assert_eq!(sessions[1].display_title(), "Conversation of 2026-03-05 09:30");
+        let loaded = store.load("20260305-093000").unwrap();
+        assert_eq!(loaded.profile, "default");
+        assert_eq!(loaded.messages[2].content, "bye");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -2,62 +2,277 @@
 // Copyright (c) 2026 DeMoD LLC
 // Licensed under the MIT License
 
-use anyhow::Result;
+use anyhow::{Result, Context, bail};
 use parking_lot::RwLock;
-use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing::{debug, info};
 
-use crate::audio::{SttPipeline, TtsPipeline, AudioEvent, create_audio_channel};
-use crate::config::Config;
-use crate::ollama::{OllamaClient, Message};
-use crate::profiles::{ProfileManager, VoiceProfile};
+use crate::audio::{
+    AudioDucker, DeviceWatcher, SttPipeline, TtsPipeline, VadStats, SystemTtsBackend, ProfanityFilter, WakeWordDetector, AudioEvent, AudioEventSender,
+    create_audio_channel, stt::{language_name, SegmentThresholds},
+};
+use crate::config::{Config, InteractionMode, TtsBackend};
+use crate::logger::{ConversationLogger, LogEntry};
+use crate::pipeline::{
+    ResponsePipeline, PipelineContext, TrimStage, CodeBlockExtractStage, HistoryAppendStage, TemplatedReplyStage, TtsStage,
+};
+use crate::accessibility::{AnnouncementPriority, AnnouncementService};
+use crate::ollama::{
+    DebugTranscript, OllamaClient, OllamaClientConfig, Message, GenerationOptions, GenerationStats,
+    RunningModel, StreamHandle, encode_image_file, find_stop_sequence, is_vision_model, partial_stop_len,
+};
+use crate::profiles::{CompiledRules, MessageFormatter, profile_id, ProfileManager, ProfileOverrides, VoiceProfile};
+use crate::active_window;
+use crate::history::{UndoStack, DEFAULT_UNDO_DEPTH};
+use crate::hotkeys::Hotkey;
+use crate::voice_commands::{match_command, VoiceAction};
+use crate::title::{generate_title, heuristic_title};
+use crate::sessions::{Session, SessionStore};
+use crate::presets::{find_preset, preset_names, GenerationPreset};
 
-const MAX_HISTORY_LENGTH: usize = 20;
+/// How often profile rules are re-evaluated
+const PROFILE_RULE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often the models loaded into Ollama's memory are polled
+const LOADED_MODEL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
 
 pub struct AppState {
-    config: Arc<RwLock<Config>>,
-    profiles: Arc<RwLock<ProfileManager>>,
+    pub(crate) config: Arc<RwLock<Config>>,
+    pub(crate) profiles: Arc<RwLock<ProfileManager>>,
     ollama: Arc<OllamaClient>,
     stt: Arc<RwLock<Option<SttPipeline>>>,
-    tts: Arc<RwLock<Option<TtsPipeline>>>,
-    chat_history: Arc<RwLock<VecDeque<Message>>>,
+    tts: Arc<RwLock<Option<Arc<TtsPipeline>>>>,
+    /// The whole conversation; the window shows the last `display_history_limit`
+    /// messages and the model gets the last `context_history_limit`
+    pub(crate) chat_history: Arc<RwLock<VecDeque<Message>>>,
+    history_undo: Arc<UndoStack<VecDeque<Message>>>,
     ui_command_tx: mpsc::UnboundedSender<UiCommand>,
     ui_command_rx: Arc<RwLock<Option<mpsc::UnboundedReceiver<UiCommand>>>>,
     visible: Arc<RwLock<bool>>,
+    generating: Arc<RwLock<bool>>,
+    /// Set by the Stop button to end the response being streamed
+    cancelled: Arc<CancelFlag>,
+    /// The response being streamed, for cancelling it on the server
+    current_stream: Arc<RwLock<Option<StreamHandle>>>,
+    /// Server-reported stats of the last reply that finished
+    last_stats: Arc<RwLock<Option<GenerationStats>>>,
+    /// Inactivity reported while a response was generating, acted on
+    /// once it finishes
+    auto_hide_pending: Arc<AtomicBool>,
+    rule_gate: Arc<RwLock<RuleGate>>,
+    logger: Option<Arc<ConversationLogger>>,
+    pipeline: Arc<ResponsePipeline>,
+    greeted: Arc<RwLock<bool>>,
+    announcer: Option<Arc<AnnouncementService>>,
+    /// Clipboard text prepended to the next user message
+    pending_context: Arc<RwLock<Option<String>>>,
+    /// Session-only profile overrides, never saved
+    overrides: Arc<RwLock<ProfileOverrides>>,
+    /// Models installed in Ollama, kept current by `watch_models`
+    models: Arc<RwLock<Vec<String>>>,
+    /// Models Ollama holds in memory, kept current by `watch_loaded_models`
+    loaded_models: Arc<RwLock<Vec<RunningModel>>>,
+    /// Title of the current conversation, set after its first exchange
+    conversation_title: Arc<RwLock<Option<String>>>,
+    /// Where conversations are saved, unless `save_sessions` is off
+    sessions: Option<Arc<SessionStore>>,
+    /// The conversation being saved; started by its first message
+    session: Arc<RwLock<Option<Session>>>,
+    /// Last accepted voice transcript and when it arrived, for `dedup_window_ms`
+    last_transcript: Arc<RwLock<Option<(String, std::time::Instant)>>>,
+    /// Lowers other apps' audio while speaking, if `audio_ducking` is on
+    ducker: Option<Arc<AudioDucker>>,
+    /// Where the speech pipelines send their events, kept for `reload`
+    audio_tx: Arc<RwLock<Option<AudioEventSender>>>,
+    /// Set while the last user message is in the input box being edited
+    editing_last: Arc<AtomicBool>,
+}
+
+/// What the next request to Ollama would contain, for debugging poor answers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextSnapshot {
+    pub system_prompt: String,
+    /// Sent after the system prompt to answer in the language last heard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_instruction: Option<String>,
+    /// The history messages that fit in the context window
+    pub history: Vec<Message>,
+    /// Clipboard text waiting to be prepended to the next question
+    pub injected_context: Option<String>,
+    /// Always empty for now; Blipply doesn't offer the model any tools
+    pub tool_definitions: Vec<String>,
+    /// Rough count at about four characters per token
+    pub total_estimated_tokens: usize,
 }
 
+/// Commands for the GTK window.
+///
+/// The channel is unbounded so control commands (show, hide, profile
+/// switches) are never dropped. The window drains everything queued each
+/// time it wakes and runs it through `coalesce_ui_commands`, so a burst of
+/// high-frequency commands costs one update rather than one per message.
 #[derive(Debug, Clone)]
 pub enum UiCommand {
     Show,
     Hide,
     Toggle,
     AppendMessage(Message),
-    StreamChunk(String),
+    SetHistory(Vec<Message>),
+    /// Streamed reply text, and how many of the model's chunks it holds
+    /// once `coalesce_ui_commands` has merged some
+    StreamChunk { text: String, chunks: usize },
     SetListening(bool),
+    /// Language detected in the latest voice input
+    SetLanguage(String),
+    /// Whisper is turning speech into text
+    SetTranscribing(bool),
+    /// Waiting for the model to answer
+    SetThinking(bool),
+    /// Show the Stop button while a response streams in
+    SetStopButtonVisible(bool),
+    /// Stop the response being streamed
+    CancelResponse,
     SetSpeaking(bool),
+    SpectrogramFrame(Vec<f32>),
+    JsonParseError(String),
+    /// Read the clipboard and attach it to the next question
+    AskAboutClipboard,
+    /// Open the settings, optionally at a named section
+    OpenSettings(Option<String>),
+    /// Briefly show a short notice, e.g. the outcome of a reload
+    ShowToast(String),
+    /// Show the print dialog for the conversation
+    Print,
+    /// The installed Ollama models changed
+    ModelsUpdated(Vec<String>),
+    /// The models loaded into Ollama's memory changed
+    LoadedModelsUpdated(Vec<RunningModel>),
+    Undo,
+    Redo,
     SwitchProfile(String),
     UpdateAvatar(String),
 }
 
+impl UiCommand {
+    /// A single chunk of a streamed reply
+    pub fn stream_chunk(text: impl Into<String>) -> Self {
+        UiCommand::StreamChunk { text: text.into(), chunks: 1 }
+    }
+}
+
+/// Merge runs of high-frequency commands in a queued batch: consecutive
+/// `StreamChunk`s become one append, unless `merge_chunks` is false because
+/// they are paced, and only the newest of consecutive `SpectrogramFrame`s is
+/// kept. Everything else passes through in order.
+pub fn coalesce_ui_commands(commands: Vec<UiCommand>, merge_chunks: bool) -> Vec<UiCommand> {
+    let mut coalesced: Vec<UiCommand> = Vec::with_capacity(commands.len());
+    for command in commands {
+        match (coalesced.last_mut(), command) {
+            (
+                Some(UiCommand::StreamChunk { text, chunks }),
+                UiCommand::StreamChunk { text: more, chunks: more_chunks },
+            ) if merge_chunks => {
+                text.push_str(&more);
+                *chunks += more_chunks;
+            }
+            (Some(UiCommand::SpectrogramFrame(bands)), UiCommand::SpectrogramFrame(newer)) => *bands = newer,
+            (_, command) => coalesced.push(command),
+        }
+    }
+    coalesced
+}
+
 impl AppState {
     pub async fn new(config: Config) -> Result<Self> {
         let profiles = ProfileManager::from_config(&config);
-        let ollama = OllamaClient::new(config.general.ollama_url.clone());
+        let mut ollama = OllamaClient::with_hosts(config.ollama_hosts(), OllamaClientConfig::default());
+        if let Some(path) = &config.general.debug_transcript {
+            info!("Writing Ollama debug transcript to {:?}", path);
+            ollama = ollama.with_debug_transcript(DebugTranscript::new(
+                path,
+                config.general.redact_system_prompt,
+            ));
+        }
+        let logger = config.conversation_log_path()?
+            .map(|path| Arc::new(ConversationLogger::new(path)));
+        let sessions = if config.general.save_sessions {
+            Some(Arc::new(SessionStore::new(config.sessions_dir()?)))
+        } else {
+            None
+        };
+
+        let announcements = match config.general.accessibility_announcements {
+            Some(enabled) => enabled,
+            None => AnnouncementService::is_available().await.unwrap_or(false),
+        };
+        let announcer = if announcements {
+            match AnnouncementService::connect().await {
+                Ok(service) => Some(Arc::new(service)),
+                Err(e) => {
+                    tracing::warn!("Screen reader announcements unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
 
         let (ui_tx, ui_rx) = mpsc::unbounded_channel();
+        let ducker = config.audio.audio_ducking.then(|| {
+            Arc::new(AudioDucker::new(config.audio.ducking_level).with_state_file(Config::ducking_state_path()))
+        });
+        if let Some(ducker) = ducker.clone() {
+            tokio::task::spawn_blocking(move || ducker.restore_after_crash());
+        }
+
+        let tts = Arc::new(RwLock::new(None));
+        let chat_history = Arc::new(RwLock::new(VecDeque::new()));
+        let history_undo = Arc::new(UndoStack::new(chat_history.clone(), DEFAULT_UNDO_DEPTH));
+        let pipeline = ResponsePipeline::new(vec![
+            Box::new(TrimStage),
+            Box::new(CodeBlockExtractStage),
+            Box::new(HistoryAppendStage::new(chat_history.clone(), history_undo.clone())),
+            Box::new(TemplatedReplyStage::new(ui_tx.clone())),
+            Box::new(TtsStage::new(tts.clone())),
+        ]);
 
         Ok(Self {
             config: Arc::new(RwLock::new(config)),
             profiles: Arc::new(RwLock::new(profiles)),
             ollama: Arc::new(ollama),
             stt: Arc::new(RwLock::new(None)),
-            tts: Arc::new(RwLock::new(None)),
-            chat_history: Arc::new(RwLock::new(VecDeque::new())),
+            tts,
+            history_undo,
+            chat_history,
             ui_command_tx: ui_tx,
             ui_command_rx: Arc::new(RwLock::new(Some(ui_rx))),
             visible: Arc::new(RwLock::new(false)),
+            generating: Arc::new(RwLock::new(false)),
+            cancelled: Arc::new(CancelFlag::default()),
+            current_stream: Arc::new(RwLock::new(None)),
+            last_stats: Arc::new(RwLock::new(None)),
+            auto_hide_pending: Arc::new(AtomicBool::new(false)),
+            rule_gate: Arc::new(RwLock::new(RuleGate::default())),
+            logger,
+            pipeline: Arc::new(pipeline),
+            greeted: Arc::new(RwLock::new(false)),
+            announcer,
+            pending_context: Arc::new(RwLock::new(None)),
+            overrides: Arc::new(RwLock::new(ProfileOverrides::default())),
+            models: Arc::new(RwLock::new(Vec::new())),
+            loaded_models: Arc::new(RwLock::new(Vec::new())),
+            conversation_title: Arc::new(RwLock::new(None)),
+            sessions,
+            session: Arc::new(RwLock::new(None)),
+            last_transcript: Arc::new(RwLock::new(None)),
+            ducker,
+            audio_tx: Arc::new(RwLock::new(None)),
+            editing_last: Arc::new(AtomicBool::new(false)),
         })
     }
 
@@ -72,33 +287,18 @@ impl AppState {
     pub async fn initialize_audio(&self) -> Result<()> {
         let config = self.config.read();
         let (audio_tx, mut audio_rx) = create_audio_channel();
+        *self.audio_tx.write() = Some(audio_tx.clone());
 
-        // Initialize STT
-        let model_path = config.whisper_model_path()?;
-        let mut stt = SttPipeline::new(
-            model_path,
-            config.audio.sample_rate,
-            config.audio.vad_aggressiveness,
-            config.audio.silence_duration_ms,
-            audio_tx.clone(),
-        )?;
-
-        stt.start()?;
-        *self.stt.write() = Some(stt);
+        // Initialize STT unless the profile is text-only
+        let profile = self.profiles.read().active_profile()?.clone();
+        self.apply_interaction_mode(&config, &profile)?;
 
         // Initialize TTS
-        let profile = self.profiles.read().active_profile()?.clone();
-        let voice_path = config.piper_voice_path(&profile.voice_model)?;
-        let config_path = voice_path.with_extension("json");
-        
-        let tts = TtsPipeline::new(
-            voice_path,
-            config_path,
-            profile.tts_speed,
-            Some(audio_tx),
-        )?;
+        let tts = build_tts(&config, &profile, Some(audio_tx.clone()))?;
+        *self.tts.write() = Some(Arc::new(tts));
 
-        *self.tts.write() = Some(tts);
+        // Follow microphones being plugged in and removed
+        tokio::spawn(DeviceWatcher::new().run(audio_tx.clone()));
 
         // Spawn audio event handler
         let state = Arc::new(self.clone());
@@ -111,161 +311,1216 @@ impl AppState {
         });
 
         info!("Audio pipelines initialized");
+        self.speak_startup_message();
+        Ok(())
+    }
+
+    /// Start, stop or reconfigure voice input for `profile`'s interaction
+    /// mode. Without a microphone typing still works, unless audio is required.
+    fn apply_interaction_mode(&self, config: &Config, profile: &VoiceProfile) -> Result<()> {
+        let mode = config.interaction_mode(profile);
+        if mode == InteractionMode::TextOnly {
+            if let Some(mut stt) = self.stt.write().take() {
+                info!("Text-only profile, closing the microphone");
+                stt.stop();
+            }
+            return Ok(());
+        }
+
+        if let Some(stt) = self.stt.read().as_ref() {
+            stt.set_push_to_talk(mode == InteractionMode::VoicePtt);
+            return Ok(());
+        }
+
+        // Audio isn't initialized yet
+        let Some(audio_tx) = self.audio_tx.read().clone() else {
+            return Ok(());
+        };
+        match start_stt(config, profile, audio_tx) {
+            Ok(stt) => *self.stt.write() = Some(stt),
+            Err(e) if config.audio.require_audio => return Err(e),
+            Err(e) => tracing::warn!("Voice input unavailable, continuing text-only: {:#}", e),
+        }
         Ok(())
     }
 
+    /// Reopen the microphone on the default input device after devices
+    /// were plugged in or removed
+    async fn restart_capture(&self, new_device: Option<&str>) {
+        let config = self.config.read().clone();
+        let Ok(profile) = self.profiles.read().active_profile().cloned() else {
+            return;
+        };
+        if config.interaction_mode(&profile) == InteractionMode::TextOnly {
+            return;
+        }
+
+        let reopened = self.stt.write().as_mut().map(|stt| {
+            stt.stop();
+            stt.start()
+        });
+        let restarted = match reopened {
+            Some(restarted) => restarted,
+            // Voice input may have been unavailable for lack of a microphone.
+            // Loading Whisper blocks, so it happens off the async runtime.
+            None => {
+                let Some(audio_tx) = self.audio_tx.read().clone() else {
+                    return;
+                };
+                let started = tokio::task::spawn_blocking(move || start_stt(&config, &profile, audio_tx)).await;
+                match started {
+                    Ok(Ok(started)) => {
+                        let mut stt = self.stt.write();
+                        match stt.as_ref() {
+                            // Started elsewhere meanwhile, e.g. by a reload
+                            Some(_) => drop(started),
+                            None => *stt = Some(started),
+                        }
+                        Ok(())
+                    }
+                    Ok(Err(e)) => Err(e),
+                    Err(e) => Err(anyhow::anyhow!("Microphone task failed: {}", e)),
+                }
+            }
+        };
+
+        let message = match (restarted, new_device) {
+            (Err(e), _) => {
+                tracing::warn!("Failed to reopen the microphone: {:#}", e);
+                format!("Microphone unavailable: {}", e)
+            }
+            (Ok(()), Some(device)) => format!("Microphone: {}", device),
+            (Ok(()), None) => "No microphone connected".to_string(),
+        };
+        self.send_ui_command(UiCommand::ShowToast(message));
+    }
+
+    /// Press or release push-to-talk
+    pub fn set_talking(&self, held: bool) {
+        if let Some(stt) = self.stt.read().as_ref() {
+            stt.set_talking(held);
+        }
+    }
+
+    /// How the active profile takes questions
+    pub fn interaction_mode(&self) -> InteractionMode {
+        let config = self.config.read();
+        self.profiles.read().active_profile().map_or(InteractionMode::default(), |p| config.interaction_mode(p))
+    }
+
+    /// Speak `general.startup_message`, if set
+    fn speak_startup_message(&self) {
+        let Some(template) = self.config.read().general.startup_message.clone() else {
+            return;
+        };
+        let Ok(profile) = self.profiles.read().active_profile().cloned() else {
+            return;
+        };
+        let Some(tts) = self.tts.read().clone() else {
+            return;
+        };
+
+        use chrono::Timelike;
+        let message = ProfileManager::render_startup_message(&template, &profile, chrono::Local::now().hour());
+        tokio::spawn(async move {
+            if let Err(e) = tts.speak_with_fallback(&message).await {
+                tracing::warn!("Failed to speak startup message: {}", e);
+            }
+        });
+    }
+
     async fn handle_audio_event(&self, event: AudioEvent) -> Result<()> {
         match event {
             AudioEvent::SpeechStart => {
                 debug!("Speech started");
                 self.send_ui_command(UiCommand::SetListening(true));
+                self.duck_while_listening(true);
             }
             AudioEvent::SpeechEnd => {
                 debug!("Speech ended");
                 self.send_ui_command(UiCommand::SetListening(false));
+                self.duck_while_listening(false);
+            }
+            AudioEvent::Transcribing => {
+                self.send_ui_command(UiCommand::SetTranscribing(true));
             }
-            AudioEvent::TranscriptFinal(text) => {
-                info!("Transcript: {}", text);
+            AudioEvent::Transcribed => {
+                self.send_ui_command(UiCommand::SetTranscribing(false));
+            }
+            AudioEvent::TranscriptFinal { text, confidence } => {
+                info!("Transcript ({:.2}): {}", confidence, text);
+
+                // A double trigger can transcribe the same question twice
+                let window = std::time::Duration::from_millis(self.config.read().audio.dedup_window_ms);
+                let now = std::time::Instant::now();
+                {
+                    let mut last = self.last_transcript.write();
+                    if let Some((previous, at)) = last.as_ref() {
+                        if is_duplicate_transcript(previous, now.duration_since(*at), &text, window) {
+                            info!("Ignoring repeated transcript");
+                            return Ok(());
+                        }
+                    }
+                    *last = Some((text.clone(), now));
+                }
+
+                // Commands win over chat and never reach the model
+                let action = {
+                    let config = self.config.read();
+                    let profiles = self.profiles.read();
+                    let profile_commands = profiles.active_profile()
+                        .map(|p| p.voice_commands.as_slice())
+                        .unwrap_or_default();
+                    match_command(profile_commands, &config.general.voice_commands, &text)
+                };
+                if let Some(action) = action {
+                    info!("Voice command: {:?}", action);
+                    self.run_voice_action(action);
+                    return Ok(());
+                }
+
+                if !self.begin_generating() {
+                    info!("Still answering, ignoring transcript");
+                    return Ok(());
+                }
                 self.send_ui_command(UiCommand::AppendMessage(Message::user(&text)));
-                
-                // Process with Ollama
-                self.process_user_message(&text).await?;
+
+                // Answer off this loop so speech, stop commands and silence
+                // keep being handled while the reply streams and plays
+                let state = self.clone();
+                tokio::spawn(async move {
+                    let result = state.run_exchange(&text, Vec::new(), None).await;
+                    state.set_generating(false);
+                    if let Err(e) = result {
+                        tracing::error!("Failed to answer transcript: {}", e);
+                    }
+                });
+            }
+            AudioEvent::TranscriptDiscarded(confidence) => {
+                info!("Transcript discarded, confidence {:.2}", confidence);
+            }
+            AudioEvent::TranscriptBlocked => {
+                info!("Transcript blocked by profanity filter");
+            }
+            AudioEvent::LanguageDetected(language) => {
+                debug!("Detected language: {}", language);
+                self.send_ui_command(UiCommand::SetLanguage(language));
+            }
+            AudioEvent::WakeWordDetected => {
+                if !self.is_visible() {
+                    self.toggle_visibility();
+                }
             }
             AudioEvent::TtsStarted => {
                 self.send_ui_command(UiCommand::SetSpeaking(true));
+                if let Some(ducker) = &self.ducker {
+                    ducker.set_speaking(true);
+                }
             }
             AudioEvent::TtsFinished => {
                 self.send_ui_command(UiCommand::SetSpeaking(false));
+                if let Some(ducker) = &self.ducker {
+                    ducker.set_speaking(false);
+                }
+            }
+            AudioEvent::Spectrum(bands) => {
+                self.send_ui_command(UiCommand::SpectrogramFrame(bands));
+            }
+            AudioEvent::InputDeviceChanged { new_device } => {
+                self.restart_capture(new_device.as_deref()).await;
+            }
+            AudioEvent::LongSilence => {
+                if self.is_generating() {
+                    // Hide once the response is done rather than dropping it
+                    self.auto_hide_pending.store(true, Ordering::SeqCst);
+                } else {
+                    self.auto_hide();
+                }
             }
             _ => {}
         }
         Ok(())
     }
 
-    async fn process_user_message(&self, text: &str) -> Result<()> {
-        // Add user message to history
-        {
-            let mut history = self.chat_history.write();
-            history.push_back(Message::user(text));
-            if history.len() > MAX_HISTORY_LENGTH {
-                history.pop_front();
+    fn duck_while_listening(&self, listening: bool) {
+        if let Some(ducker) = &self.ducker {
+            if self.config.read().audio.duck_while_listening {
+                ducker.set_listening(listening);
             }
         }
+    }
 
-        // Get system prompt
-        let system_prompt = {
-            let profiles = self.profiles.read();
-            let profile = profiles.active_profile()?;
-            profiles.get_system_prompt(profile)
-        };
+    /// Send `text` to the model, using `model_override` instead of the
+    /// profile's model for this message only
+    pub async fn process_user_message(&self, text: &str, model_override: Option<&str>) -> Result<()> {
+        self.set_generating(true);
+        let result = self.run_exchange(text, Vec::new(), model_override).await;
+        self.set_generating(false);
+        result
+    }
 
-        // Build messages for Ollama
-        let mut messages = vec![Message::system(system_prompt)];
+    /// Mark a response as generating unless one already is
+    fn begin_generating(&self) -> bool {
         {
-            let history = self.chat_history.read();
-            messages.extend(history.iter().cloned());
+            let mut generating = self.generating.write();
+            if *generating {
+                return false;
+            }
+            *generating = true;
         }
+        self.send_ui_command(UiCommand::SetThinking(true));
+        true
+    }
 
-        // Get model name
-        let model = {
-            let profiles = self.profiles.read();
-            profiles.active_profile()?.model.clone()
+    fn set_generating(&self, generating: bool) {
+        *self.generating.write() = generating;
+        self.send_ui_command(UiCommand::SetThinking(generating));
+        if !generating {
+            self.send_ui_command(UiCommand::SetStopButtonVisible(false));
+            if self.auto_hide_pending.swap(false, Ordering::SeqCst) {
+                self.auto_hide();
+            }
+        }
+    }
+
+    fn auto_hide(&self) {
+        if self.is_visible() {
+            debug!("Auto-hiding after inactivity");
+            self.hide();
+        }
+    }
+
+    /// Typing counts as activity, so the window isn't auto-hidden
+    /// mid-message. A typed message isn't answered in the language last
+    /// heard, either.
+    pub fn note_input_activity(&self) {
+        self.auto_hide_pending.store(false, Ordering::SeqCst);
+        if let Some(stt) = self.stt.read().as_ref() {
+            stt.reset_inactivity();
+            stt.clear_detected_language();
+        }
+    }
+
+    /// Stop the response being streamed, keeping what has arrived so far
+    pub fn cancel_response(&self) {
+        if self.is_generating() {
+            debug!("Cancelling response");
+            // Taken before the flag, which lets the stream end and clear it
+            let stream = self.current_stream.read().clone();
+            self.cancelled.set();
+
+            // Free the server as well; the stream is dropped straight away,
+            // so a stalled model doesn't hold up the Stop button
+            if let Some(stream) = stream {
+                tokio::spawn(async move {
+                    if let Err(e) = stream.cancel().await {
+                        tracing::warn!("Failed to cancel Ollama request: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Ask about an image. The image is sent with this message only and is
+    /// never stored in the chat history.
+    pub async fn submit_with_image(&self, text: &str, image_path: &Path) -> Result<()> {
+        let image = encode_image_file(image_path).await?;
+
+        self.set_generating(true);
+        let result = self.run_exchange(text, vec![image], None).await;
+        self.set_generating(false);
+        result
+    }
+
+    async fn run_exchange(&self, text: &str, images: Vec<String>, model_override: Option<&str>) -> Result<()> {
+        let pending_context = self.pending_context.write().take();
+        let text = match pending_context {
+            Some(context) => with_clipboard_context(&context, text),
+            None => text.to_string(),
         };
+        let text = text.as_str();
 
-        // Stream response
-        use futures::StreamExt;
-        let mut stream = self.ollama.chat_stream(model, messages);
-        let mut full_response = String::new();
+        // The first user message starts a new conversation
+        let first_turn = !self.chat_history.read().iter().any(|m| m.role == "user");
+
+        // Add user message to history
+        self.history_undo.push_checkpoint();
+        self.chat_history.write().push_back(Message::user(text));
 
-        // Check if TTS is enabled
-        let tts_enabled = {
+        // Active profile with any session overrides applied
+        let profile = {
             let profiles = self.profiles.read();
-            profiles.active_profile()?.tts_enabled
+            let mut profile = profiles.apply_overrides(&self.overrides.read())?;
+            override_model(&mut profile, model_override);
+            profile
         };
 
-        while let Some(chunk_result) = stream.next().await {
-            match chunk_result {
-                Ok(chunk) => {
-                    full_response.push_str(&chunk);
-                    self.send_ui_command(UiCommand::StreamChunk(chunk));
-                }
-                Err(e) => {
-                    tracing::error!("Streaming error: {}", e);
-                    break;
+        // Build messages for Ollama
+        let (system_prompt, addendum, history) = self.request_parts(&profile);
+        let mut messages = assemble_messages(system_prompt, addendum, history);
+
+        // Get model name and generation options
+        let model = profile.model.clone();
+        let options = generation_options(&profile, &self.config.read().presets)?;
+        let stop = options.stop.clone();
+
+        // Images go on the outgoing copy of the newest message only
+        if !images.is_empty() {
+            if profile.vision_enabled || is_vision_model(&model) {
+                if let Some(last) = messages.last_mut() {
+                    last.images = images;
                 }
+            } else {
+                tracing::warn!("Model {} does not accept images, sending text only", model);
             }
         }
 
-        // Add assistant response to history
-        {
-            let mut history = self.chat_history.write();
-            history.push_back(Message::assistant(&full_response));
-            if history.len() > MAX_HISTORY_LENGTH {
-                history.pop_front();
+        // Stream response
+        let started_at = chrono::Utc::now();
+        let started = std::time::Instant::now();
+        let (stream, handle) = self.ollama.chat_stream_with_options(model.clone(), messages, options);
+        *self.current_stream.write() = Some(handle.clone());
+        self.cancelled.reset();
+        // A templated reply is shown once it's complete instead of streaming in
+        let template = profile.message_template.clone();
+        let mut first_chunk = true;
+        let (mut full_response, cancelled) = collect_response(stream, &stop, &self.cancelled, |chunk| {
+            if std::mem::take(&mut first_chunk) {
+                self.send_ui_command(UiCommand::SetStopButtonVisible(true));
+            }
+            if template.is_none() {
+                self.send_ui_command(UiCommand::stream_chunk(chunk));
+            }
+        }).await;
+        self.current_stream.write().take();
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        if cancelled {
+            info!("Response cancelled after {} ms", duration_ms);
+            full_response = format!("{} {}", full_response.trim(), CANCELLED_SUFFIX);
+            if template.is_none() {
+                // Not model output, so it doesn't count as a chunk
+                self.send_ui_command(UiCommand::StreamChunk { text: format!(" {}", CANCELLED_SUFFIX), chunks: 0 });
+            }
+        }
+
+        if let Some(stats) = handle.stats() {
+            info!(
+                "Generated {} tokens in {} ms ({:.1} tokens/s, prompt {} tokens)",
+                stats.eval_count.unwrap_or(0),
+                stats.total_duration.unwrap_or(0) / 1_000_000,
+                stats.tokens_per_second().unwrap_or(0.0),
+                stats.prompt_eval_count.unwrap_or(0),
+            );
+            *self.last_stats.write() = Some(stats);
+        }
+
+        // The raw response has already been streamed to the window; just flag bad JSON
+        if profile.response_format.is_json() {
+            if let Err(e) = serde_json::from_str::<serde_json::Value>(&full_response) {
+                tracing::warn!("Response is not valid JSON: {}", e);
+                self.send_ui_command(UiCommand::JsonParseError(e.to_string()));
+            }
+        }
+
+        if first_turn {
+            let profile_id = self.profiles.read().active.clone();
+            *self.session.write() = Some(Session::new(profile_id, started_at));
+            self.title_conversation(text, &model);
+        }
+
+        if let Some(logger) = &self.logger {
+            let profile_id = self.profiles.read().active.clone();
+            let title = self.conversation_title();
+            let entries = [
+                LogEntry::new(started_at, &profile_id, &Message::user(text), &model, 0)
+                    .with_title(title.clone()),
+                LogEntry::new(
+                    chrono::Utc::now(),
+                    &profile_id,
+                    &Message::assistant(full_response.trim()),
+                    &model,
+                    duration_ms,
+                )
+                .with_title(title),
+            ];
+            if let Err(e) = logger.append(&entries).await {
+                tracing::warn!("Failed to write conversation log: {}", e);
+            }
+        }
+
+        // A cancelled reply is kept but not spoken
+        if cancelled {
+            let reply = Message::assistant(full_response);
+            if let Some(template) = &template {
+                self.send_ui_command(UiCommand::AppendMessage(formatted(template, &reply, &model)));
             }
+            self.history_undo.push_checkpoint();
+            self.chat_history.write().push_back(reply);
+            self.save_session();
+            return Ok(());
+        }
+
+        // Trim, record, show a templated reply and speak the response
+        let assistant_name = profile.assistant_name.clone();
+        let mut ctx = PipelineContext::new(profile, model.clone());
+        self.pipeline.run(&mut full_response, &mut ctx).await?;
+
+        // Streamed text isn't picked up by screen readers, so announce the
+        // whole reply. The window announces appended templated ones itself.
+        if template.is_none() {
+            self.announce(&format!("{}: {}", assistant_name, full_response), AnnouncementPriority::Medium);
+        }
+
+        self.save_session();
+        Ok(())
+    }
+
+    pub fn conversation_title(&self) -> Option<String> {
+        self.conversation_title.read().clone()
+    }
+
+    /// Title a new conversation from its first message. The heuristic title
+    /// is set straight away; with `auto_title` the model's title replaces it
+    /// once it arrives, so the reply isn't held up.
+    fn title_conversation(&self, first_message: &str, model: &str) {
+        let title = heuristic_title(first_message);
+        debug!("Conversation title: {:?}", title);
+        let heuristic = (!title.is_empty()).then_some(title);
+        *self.conversation_title.write() = heuristic.clone();
+
+        if self.config.read().general.auto_title {
+            let ollama = self.ollama.clone();
+            let conversation_title = self.conversation_title.clone();
+            let first_message = first_message.to_string();
+            let model = model.to_string();
+            tokio::spawn(async move {
+                let title = generate_title(&ollama, &model, &first_message).await;
+                apply_generated_title(&conversation_title, heuristic.as_deref(), title);
+            });
+        }
+    }
+
+    /// Write the current conversation to its session file
+    fn save_session(&self) {
+        let Some(store) = &self.sessions else {
+            return;
+        };
+        let Some(mut session) = self.session.read().clone() else {
+            return;
+        };
+
+        session.title = self.conversation_title();
+        session.updated = chrono::Utc::now();
+        session.messages = self.chat_history.read().iter().cloned().collect();
+        if let Err(e) = store.save(&session) {
+            tracing::warn!("Failed to save conversation: {:#}", e);
+        }
+    }
+
+    /// Saved conversations, newest first
+    pub fn list_sessions(&self) -> Result<Vec<Session>> {
+        match &self.sessions {
+            Some(store) => store.list(),
+            None => Ok(Vec::new()),
         }
+    }
+
+    /// Reopen a saved conversation; new messages continue it
+    pub fn load_session(&self, id: &str) -> Result<()> {
+        let store = self.sessions.as_ref().context("Saving conversations is disabled")?;
+        let session = store.load(id)?;
+        info!("Reopening conversation {}", session.id);
+
+        // Continue it with the profile it was held with, if that still exists
+        let active = self.profiles.read().active.clone();
+        if session.profile != active && self.profiles.read().profiles.contains_key(&session.profile) {
+            self.switch_profile(&session.profile)?;
+        }
+
+        self.history_undo.push_checkpoint();
+        *self.chat_history.write() = session.messages.iter().cloned().collect();
+        *self.conversation_title.write() = session.title.clone();
+        *self.session.write() = Some(session);
+        self.send_history();
+        Ok(())
+    }
+
+    /// Replace the last user message, drop the reply that followed it and regenerate
+    pub async fn edit_last_user_message(&self, new_text: &str) -> Result<()> {
+        if !self.remove_last_user_message() {
+            bail!("No user message to edit");
+        }
+        self.send_edited_message(new_text).await
+    }
+
+    /// Show and send `text` in place of a user message just removed
+    pub async fn send_edited_message(&self, text: &str) -> Result<()> {
+        self.send_ui_command(UiCommand::AppendMessage(Message::user(text)));
+        self.process_user_message(text, None).await
+    }
+
+    /// The last user message, marking the next message sent from the input
+    /// box as its replacement
+    pub fn start_editing_last(&self) -> Option<String> {
+        let text = self.chat_history.read().iter().rev().find(|m| m.role == "user")?.content.clone();
+        self.editing_last.store(true, Ordering::SeqCst);
+        Some(text)
+    }
+
+    /// Whether the message being sent replaces the last one, clearing the mark
+    pub fn take_editing_last(&self) -> bool {
+        self.editing_last.swap(false, Ordering::SeqCst)
+    }
 
-        // Speak response if TTS enabled
-        if tts_enabled && !full_response.is_empty() {
-            if let Some(tts) = self.tts.read().as_ref() {
-                tts.speak(&full_response).await?;
+    /// Drop the last user message and everything after it. Returns false if
+    /// there was no user message.
+    pub fn remove_last_user_message(&self) -> bool {
+        if !self.chat_history.read().iter().any(|m| m.role == "user") {
+            return false;
+        }
+
+        self.history_undo.push_checkpoint();
+        truncate_last_user_turn(&mut self.chat_history.write());
+
+        self.send_history();
+        self.save_session();
+        true
+    }
+
+    /// Revert the last history modification
+    pub fn undo_history(&self) -> bool {
+        let undone = self.history_undo.undo();
+        if undone {
+            self.send_history();
+            self.save_session();
+        }
+        undone
+    }
+
+    /// Reapply the last reverted history modification
+    pub fn redo_history(&self) -> bool {
+        let redone = self.history_undo.redo();
+        if redone {
+            self.send_history();
+            self.save_session();
+        }
+        redone
+    }
+
+    fn run_voice_action(&self, action: VoiceAction) {
+        match action {
+            VoiceAction::SwitchProfile { profile: Some(spoken) } => {
+                let id = self.profiles.read().profiles.iter()
+                    .find(|(id, profile)| id.eq_ignore_ascii_case(&spoken) || profile.name.eq_ignore_ascii_case(&spoken))
+                    .map(|(id, _)| id.clone());
+                match id {
+                    Some(id) => {
+                        if let Err(e) = self.switch_profile(&id) {
+                            tracing::warn!("Failed to switch profile: {}", e);
+                        }
+                    }
+                    None => self.send_ui_command(UiCommand::AppendMessage(
+                        Message::system(format!("No profile called \"{}\"", spoken)),
+                    )),
+                }
+            }
+            VoiceAction::SwitchProfile { profile: None } => {}
+            VoiceAction::ClearConversation => {
+                self.history_undo.push_checkpoint();
+                self.chat_history.write().clear();
+                self.send_history();
+            }
+            VoiceAction::StopSpeaking => {
+                if let Some(tts) = self.tts.read().as_ref() {
+                    tts.stop();
+                }
+            }
+            VoiceAction::Hide => self.hide(),
+            VoiceAction::Undo => {
+                self.undo_history();
             }
         }
+    }
+
+    /// The messages shown in the chat, oldest first
+    pub fn chat_messages(&self) -> Vec<Message> {
+        self.chat_history.read().iter().cloned().collect()
+    }
+
+    /// Re-render the chat history in the window, up to `display_history_limit` messages
+    fn send_history(&self) {
+        let display_limit = display_limit(&self.config.read());
+        let history = recent_messages(&self.chat_history.read(), display_limit);
+        self.send_ui_command(UiCommand::SetHistory(history));
+    }
+
+    /// Override profile fields for the rest of this session
+    pub fn set_overrides(&self, overrides: ProfileOverrides) {
+        info!("Session profile overrides: {:?}", overrides);
+        *self.overrides.write() = overrides;
+    }
 
+    /// Switch the generation preset for this session, keeping other
+    /// overrides. `None` goes back to the profile's preset.
+    pub fn set_preset(&self, name: Option<&str>) -> Result<()> {
+        if let Some(name) = name {
+            if find_preset(name, &self.config.read().presets).is_none() {
+                bail!("Unknown generation preset '{}'", name);
+            }
+        }
+        self.overrides.write().preset = name.map(str::to_string);
+        info!("Session generation preset: {:?}", name);
         Ok(())
     }
 
+    /// Names of the built-in and user-defined generation presets
+    pub fn preset_names(&self) -> Vec<String> {
+        preset_names(&self.config.read().presets)
+    }
+
+    pub fn clear_overrides(&self) {
+        info!("Session profile overrides cleared");
+        *self.overrides.write() = ProfileOverrides::default();
+    }
+
+    /// Show the window and have it read the clipboard into the next question
+    pub fn ask_about_clipboard(&self) {
+        if !*self.visible.read() {
+            self.toggle_visibility();
+        }
+        self.send_ui_command(UiCommand::AskAboutClipboard);
+    }
+
+    /// Prepend `text` to the next user message, truncated to the configured limit
+    pub fn set_clipboard_context(&self, text: &str) -> usize {
+        let max_chars = self.config.read().general.clipboard_max_chars;
+        let context = truncate_chars(text, max_chars);
+        let len = context.chars().count();
+        *self.pending_context.write() = Some(context);
+        len
+    }
+
+    /// What the active profile's assistant calls itself
+    pub fn assistant_name(&self) -> String {
+        self.profiles.read().active_profile()
+            .map(|profile| profile.assistant_name.clone())
+            .unwrap_or_else(|_| crate::config::default_assistant_name())
+    }
+
+    /// Read `text` out through the screen reader, if announcements are enabled
+    pub fn announce(&self, text: &str, priority: AnnouncementPriority) {
+        if let Some(announcer) = &self.announcer {
+            if !text.is_empty() {
+                announcer.announce(text, priority);
+            }
+        }
+    }
+
     pub fn toggle_visibility(&self) {
+        // The HUD never hides; toggling just brings it to the front
+        if self.config.read().general.persistent_hud {
+            *self.visible.write() = true;
+            self.send_ui_command(UiCommand::Show);
+            self.greet_once();
+            return;
+        }
+
         let mut visible = self.visible.write();
         *visible = !*visible;
         
         if *visible {
+            // Restart the inactivity timer so auto-hide counts from now
+            if let Some(stt) = self.stt.read().as_ref() {
+                stt.reset_vad();
+            }
             self.send_ui_command(UiCommand::Show);
+            drop(visible);
+            self.greet_once();
         } else {
             self.send_ui_command(UiCommand::Hide);
         }
     }
 
-    pub fn is_visible(&self) -> bool {
-        *self.visible.read()
+    /// Show and speak the profile greeting on the first show of the session
+    fn greet_once(&self) {
+        {
+            let mut greeted = self.greeted.write();
+            if *greeted {
+                return;
+            }
+            *greeted = true;
+        }
+
+        let Ok(profile) = self.profiles.read().active_profile().cloned() else {
+            return;
+        };
+
+        use chrono::Timelike;
+        let Some(greeting) = ProfileManager::render_greeting(&profile, chrono::Local::now().hour()) else {
+            return;
+        };
+
+        let message = Message::assistant(&greeting);
+        let message = match &profile.message_template {
+            Some(template) => formatted(template, &message, &profile.model),
+            None => message,
+        };
+        self.send_ui_command(UiCommand::AppendMessage(message));
+
+        if profile.tts_enabled {
+            if let Some(tts) = self.tts.read().clone() {
+                tokio::spawn(async move {
+                    if let Err(e) = tts.speak_with_fallback(&greeting).await {
+                        tracing::warn!("Failed to speak greeting: {}", e);
+                    }
+                });
+            }
+        }
     }
 
-    pub fn switch_profile(&self, profile_name: &str) -> Result<()> {
-        let mut profiles = self.profiles.write();
-        profiles.switch_profile(profile_name)?;
-        
-        self.send_ui_command(UiCommand::SwitchProfile(profile_name.to_string()));
-        
-        // Update TTS with new voice
-        let profile = profiles.active_profile()?.clone();
-        drop(profiles);
+    pub fn hide(&self) {
+        if self.config.read().general.persistent_hud {
+            debug!("Not hiding, persistent HUD is enabled");
+            return;
+        }
 
-        let config = self.config.read();
-        let voice_path = config.piper_voice_path(&profile.voice_model)?;
-        let config_path = voice_path.with_extension("json");
-
-        let (audio_tx, _) = create_audio_channel();
-        let tts = TtsPipeline::new(
-            voice_path,
-            config_path,
-            profile.tts_speed,
-            Some(audio_tx),
-        )?;
-
-        *self.tts.write() = Some(tts);
-        
-        info!("Switched to profile: {}", profile_name);
-        Ok(())
+        *self.visible.write() = false;
+        self.send_ui_command(UiCommand::Hide);
     }
 
-    pub async fn run(&self) {
-        // Main event loop - handles IPC, timers, etc.
-        info!("Application state running");
+    pub fn is_visible(&self) -> bool {
+        *self.visible.read()
     }
-}
 
-// Make AppState Clone-safe by only cloning Arc pointers
-impl Clone for AppState {
+    /// The system prompt, history and pending context the next exchange
+    /// would send, read from memory without contacting Ollama
+    pub fn get_context_snapshot(&self) -> ContextSnapshot {
+        let profile = {
+            let profiles = self.profiles.read();
+            profiles.apply_overrides(&self.overrides.read())
+        };
+        let (system_prompt, language_instruction, history) = match profile {
+            Ok(profile) => self.request_parts(&profile),
+            Err(e) => {
+                tracing::warn!("No active profile for the context snapshot: {}", e);
+                let context_limit = self.config.read().general.context_history_limit;
+                (String::new(), None, recent_messages(&self.chat_history.read(), context_limit))
+            }
+        };
+        let injected_context = self.pending_context.read().clone();
+
+        // Counted over the messages exactly as a request would send them
+        let messages = assemble_messages(system_prompt.clone(), language_instruction.clone(), history.clone());
+        let total_estimated_tokens = messages.iter().map(|m| estimate_tokens(&m.content)).sum::<usize>()
+            + injected_context.as_deref().map_or(0, estimate_tokens);
+
+        ContextSnapshot {
+            system_prompt,
+            language_instruction,
+            history,
+            injected_context,
+            tool_definitions: Vec::new(),
+            total_estimated_tokens,
+        }
+    }
+
+    /// The system prompt, per-request addendum and history a request for
+    /// `profile` sends, for `assemble_messages`
+    fn request_parts(&self, profile: &VoiceProfile) -> (String, Option<String>, Vec<Message>) {
+        let system_prompt = self.profiles.read().get_system_prompt(profile);
+        let addendum = {
+            let detected = self.stt.read().as_ref().and_then(|stt| stt.get_detected_language());
+            language_addendum(profile, &self.config.read(), detected.as_deref())
+        };
+        let context_limit = self.config.read().general.context_history_limit;
+        let history = recent_messages(&self.chat_history.read(), context_limit);
+        (system_prompt, addendum, history)
+    }
+
+    /// One-line summary for the STATUS IPC command
+    pub fn status_report(&self) -> String {
+        let mut report = format!(
+            "profile={} visible={} generating={}",
+            self.profiles.read().active,
+            *self.visible.read(),
+            self.is_generating(),
+        );
+
+        if let Some(stats) = self.last_stats.read().clone() {
+            if let Some(count) = stats.eval_count {
+                report.push_str(&format!(" eval_count={}", count));
+            }
+            if let Some(count) = stats.prompt_eval_count {
+                report.push_str(&format!(" prompt_eval_count={}", count));
+            }
+            if let Some(duration) = stats.total_duration {
+                report.push_str(&format!(" total_ms={}", duration / 1_000_000));
+            }
+            if let Some(rate) = stats.tokens_per_second() {
+                report.push_str(&format!(" tokens_per_sec={:.1}", rate));
+            }
+        }
+
+        if let Some(depth) = self.transcription_queue_depth() {
+            report.push_str(&format!(" transcription_queue={}", depth));
+        }
+
+        let loaded = self.loaded_models.read();
+        if !loaded.is_empty() {
+            let models: Vec<String> = loaded
+                .iter()
+                .map(|m| format!("{}@{:.1}GB", m.name, m.size_vram_bytes as f64 / 1_000_000_000.0))
+                .collect();
+            report.push_str(&format!(" loaded_models={}", models.join(",")));
+        }
+
+        report
+    }
+
+    /// Utterances waiting for Whisper behind the one being transcribed, or
+    /// None while the microphone is off
+    pub fn transcription_queue_depth(&self) -> Option<usize> {
+        self.stt.read().as_ref().map(|stt| stt.transcription_queue_depth())
+    }
+
+    /// Whether an LLM request is currently in flight
+    pub fn is_generating(&self) -> bool {
+        *self.generating.read()
+    }
+
+    /// Switch profiles on the user's behalf. Profile rules leave the choice
+    /// alone until a different rule starts to hold.
+    pub fn switch_profile(&self, profile_name: &str) -> Result<()> {
+        self.rule_gate.write().suspend();
+        self.activate_profile(profile_name)
+    }
+
+    fn activate_profile(&self, profile_name: &str) -> Result<()> {
+        let mut profiles = self.profiles.write();
+        profiles.switch_profile(profile_name)?;
+        
+        self.send_ui_command(UiCommand::SwitchProfile(profile_name.to_string()));
+        
+        // Update TTS with new voice
+        let profile = profiles.active_profile()?.clone();
+        drop(profiles);
+
+        // A copy, since starting the microphone can load the Whisper model
+        let config = self.config.read().clone();
+        if let Some(stt) = self.stt.read().as_ref() {
+            stt.set_initial_prompt(initial_prompt(&config, &profile));
+        }
+        self.apply_interaction_mode(&config, &profile)?;
+        let tts = build_tts(&config, &profile, self.audio_tx.read().clone())?;
+        *self.tts.write() = Some(Arc::new(tts));
+        
+        info!("Switched to profile: {}", profile_name);
+        Ok(())
+    }
+
+    /// Re-read the config file and rebuild the profiles and speech pipelines
+    /// for the active profile, showing the outcome in the UI. A response being
+    /// generated carries on unless the active profile's model changed.
+    pub async fn reload(&self) -> Result<()> {
+        // Loading the Whisper and Piper models blocks for seconds
+        let state = self.clone();
+        let result = match tokio::task::spawn_blocking(move || state.reload_config()).await {
+            Ok(result) => result,
+            Err(e) => Err(anyhow::anyhow!("Reload task failed: {}", e)),
+        };
+        match &result {
+            Ok(()) => self.send_ui_command(UiCommand::ShowToast("Configuration reloaded".to_string())),
+            Err(e) => {
+                tracing::warn!("Reload failed: {:#}", e);
+                self.send_ui_command(UiCommand::ShowToast(format!("Reload failed: {}", e)));
+            }
+        }
+        result
+    }
+
+    fn reload_config(&self) -> Result<()> {
+        let config = Config::load()?;
+        let mut profiles = ProfileManager::from_config(&config);
+
+        // Stay on the profile in use if it still exists
+        let active = self.profiles.read().active.clone();
+        if profiles.switch_profile(&active).is_err() {
+            info!("Profile {} no longer exists, using {}", active, profiles.active);
+        }
+        let profile = profiles.active_profile()?.clone();
+        let old_model = self.profiles.read().active_profile().ok().map(|p| p.model.clone());
+
+        // Load the voice and start the new microphone before replacing
+        // anything, so a bad one leaves the running pipelines untouched
+        let audio_tx = self.audio_tx.read().clone();
+        let tts = build_tts(&config, &profile, audio_tx.clone())?;
+        let stt = match audio_tx {
+            Some(audio_tx) if config.interaction_mode(&profile) != InteractionMode::TextOnly => {
+                match start_stt(&config, &profile, audio_tx) {
+                    Ok(stt) => Some(stt),
+                    Err(e) if config.audio.require_audio => return Err(e),
+                    Err(e) => {
+                        tracing::warn!("Voice input unavailable, continuing text-only: {:#}", e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        if old_model.as_deref() != Some(profile.model.as_str()) {
+            self.cancel_response();
+        }
+
+        // Before audio is initialized there is no microphone to replace
+        if self.audio_tx.read().is_some() {
+            let old_stt = std::mem::replace(&mut *self.stt.write(), stt);
+            if let Some(mut old_stt) = old_stt {
+                old_stt.stop();
+            }
+        }
+        *self.tts.write() = Some(Arc::new(tts));
+
+        *self.config.write() = config;
+        *self.profiles.write() = profiles;
+
+        info!("Reloaded configuration, active profile {}", profile.name);
+        Ok(())
+    }
+
+    /// Change the show/hide hotkey in the saved config. The listener keeps
+    /// the old binding until restart.
+    pub fn set_hotkey(&self, hotkey: &str) -> Result<()> {
+        let hotkey: Hotkey = hotkey.parse()?;
+        let mut config = self.config.write();
+        config.general.hotkey = hotkey.clone();
+        config.save()?;
+
+        info!("Hotkey set to {}", hotkey);
+        Ok(())
+    }
+
+    /// Remember whether the chat history panel is expanded
+    pub fn set_chat_panel_open(&self, open: bool) -> Result<()> {
+        let mut config = self.config.write();
+        if config.general.chat_panel_open != open {
+            config.general.chat_panel_open = open;
+            config.save()?;
+        }
+        Ok(())
+    }
+
+    /// Create or replace a profile and write it to the config file.
+    /// New profiles get an id derived from their name. Returns the id.
+    pub fn save_profile(&self, id: Option<&str>, profile: VoiceProfile) -> Result<String> {
+        let id = {
+            let mut profiles = self.profiles.write();
+            let id = match id {
+                Some(id) => id.to_string(),
+                None => {
+                    let id = profile_id(&profile.name);
+                    if id.is_empty() {
+                        bail!("Profile name needs at least one letter or digit");
+                    }
+                    profiles.create_profile(id.clone(), None)?;
+                    id
+                }
+            };
+            profiles.update_profile(&id, profile.clone())?;
+            id
+        };
+
+        let mut config = self.config.write();
+        config.profiles.insert(id.clone(), profile.into());
+        config.save()?;
+
+        info!("Saved profile {}", id);
+        Ok(id)
+    }
+
+    /// Voice detection counters, if audio capture is running
+    pub fn vad_stats(&self) -> Option<VadStats> {
+        self.stt.read().as_ref().map(SttPipeline::vad_stats)
+    }
+
+    /// Re-measure ambient noise for the VAD noise gate
+    pub fn calibrate_vad(&self) {
+        match self.stt.read().as_ref() {
+            Some(stt) => stt.start_calibration(),
+            None => tracing::warn!("Cannot calibrate, audio capture is not running"),
+        }
+    }
+
+    /// Stop audio capture and playback ahead of process exit
+    pub fn shutdown(&self) {
+        info!("Shutting down audio pipelines");
+
+        if let Some(tts) = self.tts.read().as_ref() {
+            tts.stop();
+        }
+
+        if let Some(mut stt) = self.stt.write().take() {
+            stt.stop();
+        }
+
+        if let Some(ducker) = &self.ducker {
+            ducker.restore();
+        }
+    }
+
+    pub async fn run(&self) {
+        // Main event loop - handles IPC, timers, etc.
+        info!("Application state running");
+
+        // Always running, so rules added later and reloaded take effect
+        let state = self.clone();
+        tokio::spawn(async move { state.run_profile_rules().await });
+
+        let state = self.clone();
+        tokio::spawn(async move { state.watch_models().await });
+
+        let state = self.clone();
+        tokio::spawn(async move { state.watch_loaded_models().await });
+    }
+
+    /// Speakers offered by the Piper voice `voice`, which needn't be the one
+    /// loaded; 1 when it has just one or speech goes through espeak-ng
+    pub fn voice_speakers(&self, voice: &str) -> usize {
+        let config = self.config.read();
+        if matches!(config.audio.tts_backend, TtsBackend::Espeak) {
+            return 1;
+        }
+        config.piper_voice_path(voice).map_or(1, |path| TtsPipeline::voice_speakers(&path))
+    }
+
+    /// Models installed in Ollama as of the last poll
+    pub fn models(&self) -> Vec<String> {
+        self.models.read().clone()
+    }
+
+    /// Keep the model list current so models pulled while running show up
+    async fn watch_models(&self) {
+        use futures::StreamExt;
+
+        let mut updates = Box::pin(self.ollama.watch_models());
+        while let Some(update) = updates.next().await {
+            match update {
+                Ok(models) => {
+                    debug!("Ollama models changed: {:?}", models);
+                    *self.models.write() = models.clone();
+                    self.send_ui_command(UiCommand::ModelsUpdated(models));
+                }
+                Err(e) => debug!("Failed to poll Ollama models: {}", e),
+            }
+        }
+    }
+
+    /// Models Ollama held in memory as of the last poll
+    pub fn loaded_models(&self) -> Vec<RunningModel> {
+        self.loaded_models.read().clone()
+    }
+
+    /// Poll the models Ollama has loaded, which change as models are used
+    /// and expire
+    async fn watch_loaded_models(&self) {
+        let mut interval = tokio::time::interval(LOADED_MODEL_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            match self.ollama.list_running_models().await {
+                Ok(models) => {
+                    if *self.loaded_models.read() == models {
+                        continue;
+                    }
+                    debug!("Ollama loaded models changed: {:?}", models);
+                    *self.loaded_models.write() = models.clone();
+                    self.send_ui_command(UiCommand::LoadedModelsUpdated(models));
+                }
+                Err(e) => debug!("Failed to poll Ollama loaded models: {}", e),
+            }
+        }
+    }
+
+    /// Check the profile rules every few seconds and switch to the first match
+    async fn run_profile_rules(&self) {
+        use chrono::Timelike;
+
+        let mut interval = tokio::time::interval(PROFILE_RULE_INTERVAL);
+        let mut rules: Option<CompiledRules> = None;
+        loop {
+            interval.tick().await;
+            if self.config.read().general.profile_rules.is_empty() {
+                continue;
+            }
+
+            let title = active_window::title().await;
+            let hour = chrono::Local::now().hour() as u8;
+
+            let matched = {
+                let config = self.config.read();
+                let configured = &config.general.profile_rules;
+                // Recompiled only when the rules were edited or reloaded
+                let rules = match rules.take() {
+                    Some(compiled) if compiled.compiled_from(configured) => rules.insert(compiled),
+                    _ => rules.insert(CompiledRules::new(configured)),
+                };
+                rules.matching_profile(title.as_deref(), hour).map(str::to_string)
+            };
+            let Some(target) = self.rule_gate.write().admit(matched) else {
+                continue;
+            };
+
+            if self.profiles.read().active == target {
+                continue;
+            }
+
+            info!("Profile rule matched, switching to {}", target);
+            if let Err(e) = self.activate_profile(&target) {
+                tracing::warn!("Failed to switch to rule profile {}: {}", target, e);
+            }
+        }
+    }
+}
+
+/// Replace the heuristic title with a generated one, unless the title was
+/// changed while it was being generated, e.g. by reopening another session
+fn apply_generated_title(current: &RwLock<Option<String>>, heuristic: Option<&str>, generated: String) {
+    let mut current = current.write();
+    if current.as_deref() != heuristic {
+        debug!("Conversation title changed meanwhile, keeping {:?}", *current);
+        return;
+    }
+    if !generated.is_empty() {
+        *current = Some(generated);
+    }
+}
+
+/// Lets profile rules switch profiles, except while a manual switch holds.
+/// A manual switch holds until the rules match something new.
+#[derive(Default)]
+struct RuleGate {
+    last_match: Option<String>,
+    suspended: bool,
+}
+
+impl RuleGate {
+    fn suspend(&mut self) {
+        self.suspended = true;
+    }
+
+    /// The profile this tick's match may switch to
+    fn admit(&mut self, matched: Option<String>) -> Option<String> {
+        if matched != self.last_match {
+            self.suspended = false;
+            self.last_match = matched.clone();
+        }
+        if self.suspended { None } else { matched }
+    }
+}
+
+// Make AppState Clone-safe by only cloning Arc pointers
+impl Clone for AppState {
     fn clone(&self) -> Self {
         Self {
             config: self.config.clone(),
@@ -274,9 +1529,797 @@ impl Clone for AppState {
             stt: self.stt.clone(),
             tts: self.tts.clone(),
             chat_history: self.chat_history.clone(),
+            history_undo: self.history_undo.clone(),
             ui_command_tx: self.ui_command_tx.clone(),
             ui_command_rx: self.ui_command_rx.clone(),
             visible: self.visible.clone(),
+            generating: self.generating.clone(),
+            cancelled: self.cancelled.clone(),
+            current_stream: self.current_stream.clone(),
+            last_stats: self.last_stats.clone(),
+            auto_hide_pending: self.auto_hide_pending.clone(),
+            rule_gate: self.rule_gate.clone(),
+            logger: self.logger.clone(),
+            pipeline: self.pipeline.clone(),
+            greeted: self.greeted.clone(),
+            announcer: self.announcer.clone(),
+            pending_context: self.pending_context.clone(),
+            overrides: self.overrides.clone(),
+            models: self.models.clone(),
+            loaded_models: self.loaded_models.clone(),
+            conversation_title: self.conversation_title.clone(),
+            sessions: self.sessions.clone(),
+            session: self.session.clone(),
+            last_transcript: self.last_transcript.clone(),
+            ducker: self.ducker.clone(),
+            audio_tx: self.audio_tx.clone(),
+            editing_last: self.editing_last.clone(),
+        }
+    }
+}
+
+/// `message` with its content filled into a profile's message template
+fn formatted(template: &str, message: &Message, model: &str) -> Message {
+    Message {
+        content: MessageFormatter::format(template, message, model),
+        ..message.clone()
+    }
+}
+
+/// Ollama options for a request made with `profile`. The profile's preset
+/// is applied first, so its own explicit settings win.
+fn generation_options(
+    profile: &VoiceProfile,
+    presets: &HashMap<String, GenerationPreset>,
+) -> Result<GenerationOptions> {
+    let mut options = GenerationOptions::default();
+    if let Some(name) = &profile.preset {
+        match find_preset(name, presets) {
+            Some(preset) => preset.apply(&mut options),
+            None => tracing::warn!("Unknown generation preset {:?}, using defaults", name),
+        }
+    }
+    if let Some(temperature) = profile.temperature {
+        options.temperature = temperature;
+    }
+    Ok(GenerationOptions {
+        stop: profile.stop.clone(),
+        num_predict: profile.num_predict,
+        format: profile.response_format.request_format()?,
+        ..options
+    })
+}
+
+/// Marks a reply the user stopped early
+const CANCELLED_SUFFIX: &str = "[cancelled]";
+
+/// Stop request for the response being streamed, which also wakes the
+/// reader waiting on the next chunk
+#[derive(Default)]
+struct CancelFlag {
+    set: AtomicBool,
+    notify: tokio::sync::Notify,
+}
+
+impl CancelFlag {
+    fn set(&self) {
+        self.set.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    fn reset(&self) {
+        self.set.store(false, Ordering::SeqCst);
+    }
+
+    fn is_set(&self) -> bool {
+        self.set.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the flag is set
+    async fn wait(&self) {
+        loop {
+            // Registered before the check so a `set` in between isn't missed
+            let notified = self.notify.notified();
+            if self.is_set() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Read a streamed response, passing each new piece to `on_chunk`. Text that
+/// could be the start of a stop sequence is held back until it's clear it
+/// isn't one. Ends at the first stop sequence, a stream error, or as soon as
+/// `cancelled` is set, without waiting for another chunk. Returns the text
+/// and whether it was cancelled.
+async fn collect_response<S>(
+    stream: S,
+    stop: &[String],
+    cancelled: &CancelFlag,
+    mut on_chunk: impl FnMut(String),
+) -> (String, bool)
+where
+    S: futures::Stream<Item = Result<String>>,
+{
+    use futures::StreamExt;
+
+    let mut stream = std::pin::pin!(stream);
+    let mut full_response = String::new();
+    // Bytes of `full_response` passed to `on_chunk` so far
+    let mut sent_len = 0;
+    let mut send_until = |full_response: &str, end: usize, sent_len: &mut usize| {
+        if end > *sent_len {
+            on_chunk(full_response[*sent_len..end].to_string());
+            *sent_len = end;
+        }
+    };
+
+    loop {
+        if cancelled.is_set() {
+            debug!("Response cancelled, ending stream");
+            send_until(&full_response, full_response.len(), &mut sent_len);
+            return (full_response, true);
+        }
+
+        let chunk_result = tokio::select! {
+            chunk = stream.next() => match chunk {
+                Some(chunk) => chunk,
+                None => break,
+            },
+            _ = cancelled.wait() => continue,
+        };
+
+        match chunk_result {
+            Ok(chunk) => {
+                full_response.push_str(&chunk);
+
+                // Commit what we have and drop the stream once a stop sequence appears
+                if let Some(pos) = find_stop_sequence(&full_response, stop) {
+                    full_response.truncate(pos);
+                    send_until(&full_response, pos, &mut sent_len);
+                    debug!("Stop sequence reached, ending stream");
+                    return (full_response, false);
+                }
+
+                let held = partial_stop_len(&full_response, stop);
+                send_until(&full_response, full_response.len() - held, &mut sent_len);
+            }
+            Err(e) => {
+                tracing::error!("Streaming error: {}", e);
+                break;
+            }
+        }
+    }
+
+    // Whatever was held back didn't turn into a stop sequence
+    send_until(&full_response, full_response.len(), &mut sent_len);
+    (full_response, false)
+}
+
+/// Build the STT pipeline with the daemon's extras and start capturing
+fn start_stt(config: &Config, profile: &VoiceProfile, event_tx: AudioEventSender) -> Result<SttPipeline> {
+    let mut stt = build_stt(config, profile, event_tx)?;
+    stt.set_long_silence_ms(config.general.auto_hide_after_seconds.map(|s| s * 1000));
+    stt.set_spectrum_enabled(config.audio.show_spectrogram);
+    if config.audio.wake_word_enabled {
+        let detector = config.wake_word_model_path().and_then(|path| {
+            WakeWordDetector::new(&path, config.audio.sample_rate, config.audio.wake_word_threshold)
+        });
+        match detector {
+            Ok(detector) => stt.set_wake_word(Some(detector)),
+            Err(e) => tracing::warn!("Wake word disabled: {:#}", e),
+        }
+    }
+    if config.audio.vad_auto_calibrate {
+        stt.start_calibration();
+    }
+    stt.start()?;
+    Ok(stt)
+}
+
+/// Speech recognizer with the transcription settings of `config` and
+/// `profile`. Capture isn't started.
+pub(crate) fn build_stt(config: &Config, profile: &VoiceProfile, event_tx: AudioEventSender) -> Result<SttPipeline> {
+    let model_path = config.whisper_model_path_with_lora()?;
+    let mut stt = SttPipeline::new(
+        model_path,
+        config.audio.sample_rate,
+        config.audio.vad_aggressiveness,
+        config.audio.silence_duration_ms,
+        event_tx,
+    )?;
+
+    stt.set_strategy(config.audio.stt_strategy);
+    stt.set_min_confidence(config.audio.stt_min_confidence);
+    stt.set_temperature(config.audio.stt_temperature);
+    stt.set_resample_quality(config.audio.resample_quality);
+    stt.set_confidence_filter(config.audio.stt_confidence_filter.then_some(SegmentThresholds {
+        max_no_speech_prob: config.audio.stt_max_no_speech_prob,
+        min_avg_logprob: config.audio.stt_min_avg_logprob,
+    }));
+    stt.set_max_utterance_ms(Some(config.audio.max_utterance_ms));
+    stt.set_max_queued_transcriptions(config.audio.stt_queue_slots);
+    stt.set_input_gain(config.input_gain());
+    stt.set_initial_prompt(initial_prompt(config, profile));
+    stt.set_language(config.audio.stt_language.clone());
+    stt.set_push_to_talk(config.interaction_mode(profile) == InteractionMode::VoicePtt);
+    if config.audio.transcript_filter_enabled {
+        stt.set_transcript_filter(Some(Arc::new(ProfanityFilter::bundled(
+            config.audio.transcript_filter_block,
+        ))));
+    }
+
+    Ok(stt)
+}
+
+/// Speech synthesizer for `profile`. Piper is used unless espeak-ng is
+/// configured, and espeak-ng stands in if the Piper voice fails to load.
+fn build_tts(config: &Config, profile: &VoiceProfile, event_tx: Option<AudioEventSender>) -> Result<TtsPipeline> {
+    let espeak_voice = SystemTtsBackend::voice_for_piper_model(&profile.voice_model);
+
+    let mut tts = match config.audio.tts_backend {
+        TtsBackend::Espeak => TtsPipeline::espeak(espeak_voice, profile.tts_speed, event_tx),
+        TtsBackend::Piper => {
+            let voice_path = config.piper_voice_path(&profile.voice_model)?;
+            let config_path = voice_path.with_extension("json");
+
+            match TtsPipeline::new(voice_path, config_path, profile.tts_speed, event_tx.clone()) {
+                Ok(mut tts) => {
+                    if config.audio.tts_system_fallback {
+                        tts.enable_system_fallback(SystemTtsBackend::new(), espeak_voice);
+                    }
+                    tts
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to load Piper voice {} ({:#}), falling back to espeak-ng", profile.voice_model, e);
+                    TtsPipeline::espeak(espeak_voice, profile.tts_speed, event_tx)
+                }
+            }
+        }
+    };
+    tts.set_volume(profile.tts_volume);
+    tts.set_speaker_id(profile.tts_speaker_id);
+    if config.audio.tts_normalize_audio {
+        tts.set_peak_target(Some(config.audio.tts_peak_target));
+    }
+    tts.set_tail_pad(std::time::Duration::from_millis(config.audio.tts_tail_pad_ms));
+    tts.set_trim_leading_silence(config.audio.tts_trim_leading_silence);
+    tts.set_synthesis_queue_size(config.audio.tts_synthesis_queue_size);
+
+    info!("Text-to-speech backend: {}", tts.backend_name());
+    Ok(tts)
+}
+
+/// Use `model_override` for a single message, if one was chosen
+fn override_model(profile: &mut VoiceProfile, model_override: Option<&str>) {
+    if let Some(model) = model_override.map(str::trim).filter(|m| !m.is_empty()) {
+        profile.model = model.to_string();
+    }
+}
+
+/// Whisper initial prompt, preferring the profile's over the global one
+fn initial_prompt(config: &Config, profile: &VoiceProfile) -> Option<String> {
+    profile.stt_initial_prompt.clone()
+        .or_else(|| config.audio.stt_initial_prompt.clone())
+}
+
+/// Number of messages shown in the chat window
+fn display_limit(config: &Config) -> usize {
+    match config.general.display_history_limit {
+        0 => usize::MAX,
+        limit => limit,
+    }
+}
+
+/// The most recent `limit` messages of the history
+fn recent_messages(history: &VecDeque<Message>, limit: usize) -> Vec<Message> {
+    history.iter()
+        .skip(history.len().saturating_sub(limit))
+        .cloned()
+        .collect()
+}
+
+/// Per-request instruction to answer in the language Whisper heard, for
+/// profiles with `auto_detect_language` while Whisper picks the language.
+/// A fixed `response_language` is already in the system prompt.
+fn language_addendum(profile: &VoiceProfile, config: &Config, detected: Option<&str>) -> Option<String> {
+    if !profile.auto_detect_language || profile.response_language.is_some() || config.audio.stt_language.is_some() {
+        return None;
+    }
+    let language = language_name(detected?);
+    Some(format!("The user is speaking {}, please respond in the same language.", language))
+}
+
+/// The messages sent to Ollama: the system prompt, any per-request
+/// addendum, then the conversation
+fn assemble_messages(system_prompt: String, addendum: Option<String>, history: Vec<Message>) -> Vec<Message> {
+    let mut messages = vec![Message::system(system_prompt)];
+    messages.extend(addendum.map(Message::system));
+    messages.extend(history);
+    messages
+}
+
+/// Lowest similarity at which two transcripts count as the same question
+const DUPLICATE_SIMILARITY: f32 = 0.9;
+
+/// Whether `text` repeats `previous`, heard `elapsed` ago, closely enough
+/// to be dropped
+fn is_duplicate_transcript(previous: &str, elapsed: std::time::Duration, text: &str, window: std::time::Duration) -> bool {
+    if elapsed > window {
+        return false;
+    }
+    similarity(&normalize_transcript(previous), &normalize_transcript(text)) >= DUPLICATE_SIMILARITY
+}
+
+/// Lower case words without punctuation, so "Hello?" matches "hello"
+fn normalize_transcript(text: &str) -> Vec<char> {
+    text.split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect::<String>())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect()
+}
+
+/// 1.0 for equal strings down to 0.0, from the edit distance
+fn similarity(a: &[char], b: &[char]) -> f32 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    1.0 - row[b.len()] as f32 / longest as f32
+}
+
+/// Rough token count of `text`, assuming about four characters per token
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// At most `max_chars` characters of `text`, marking where it was cut
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}\n[truncated]", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// User message asking about clipboard contents
+fn with_clipboard_context(context: &str, text: &str) -> String {
+    format!("Context from my clipboard:\n```\n{}\n```\n\n{}", context, text)
+}
+
+/// Truncate history just before the last user message
+fn truncate_last_user_turn(history: &mut VecDeque<Message>) -> bool {
+    match history.iter().rposition(|m| m.role == "user") {
+        Some(pos) => {
+            history.truncate(pos);
+            true
         }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_last_user_turn() {
+        let mut history: VecDeque<Message> = vec![
+            Message::user("first"),
+            Message::assistant("reply"),
+            Message::user("secnod"),
+            Message::assistant("confused reply"),
+        ].into();
+
+        assert!(truncate_last_user_turn(&mut history));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].content, "reply");
+    }
+
+    #[test]
+    fn test_generated_title_keeps_changed_title() {
+        let title = RwLock::new(Some("Mount NFS".to_string()));
+        apply_generated_title(&title, Some("Mount NFS"), "Mounting NFS shares".to_string());
+        assert_eq!(title.read().as_deref(), Some("Mounting NFS shares"));
+
+        let title = RwLock::new(Some("My title".to_string()));
+        apply_generated_title(&title, Some("Mount NFS"), "Mounting NFS shares".to_string());
+        assert_eq!(title.read().as_deref(), Some("My title"));
+    }
+
+    #[test]
+    fn test_manual_switch_suspends_rules() {
+        let mut gate = RuleGate::default();
+        assert_eq!(gate.admit(Some("technical".to_string())), Some("technical".to_string()));
+
+        gate.suspend();
+        assert_eq!(gate.admit(Some("technical".to_string())), None);
+        assert_eq!(gate.admit(Some("technical".to_string())), None);
+
+        // A different rule holding ends the suspension
+        assert_eq!(gate.admit(Some("concise".to_string())), Some("concise".to_string()));
+    }
+
+    #[test]
+    fn test_initial_prompt_precedence() {
+        let mut config = Config::default();
+        let mut profile = ProfileManager::from_config(&config).active_profile().unwrap().clone();
+        assert_eq!(initial_prompt(&config, &profile), None);
+
+        config.audio.stt_initial_prompt = Some("NixOS, systemd".to_string());
+        assert_eq!(initial_prompt(&config, &profile).as_deref(), Some("NixOS, systemd"));
+
+        profile.stt_initial_prompt = Some("Rust, cargo".to_string());
+        assert_eq!(initial_prompt(&config, &profile).as_deref(), Some("Rust, cargo"));
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("héllo wörld", 5), "héllo\n[truncated]");
+        assert_eq!(truncate_chars("👋👋👋", 2), "👋👋\n[truncated]");
+    }
+
+    #[test]
+    fn test_recent_messages() {
+        let history: VecDeque<Message> = (0..5).map(|i| Message::user(i.to_string())).collect();
+
+        let window = recent_messages(&history, 2);
+        assert_eq!(window.len(), 2);
+        assert_eq!(window[0].content, "3");
+        assert_eq!(window[1].content, "4");
+
+        assert_eq!(recent_messages(&history, 20).len(), 5);
+    }
+
+    #[test]
+    fn test_display_limit() {
+        let mut config = Config::default();
+        assert_eq!(display_limit(&config), 500);
+
+        config.general.display_history_limit = 100;
+        assert_eq!(display_limit(&config), 100);
+        config.general.display_history_limit = 0;
+        assert_eq!(display_limit(&config), usize::MAX);
+    }
+
+    #[test]
+    fn test_coalesce_stream_chunks() {
+        let commands = vec![
+            UiCommand::SetThinking(true),
+            UiCommand::stream_chunk("Hel"),
+            UiCommand::stream_chunk("lo"),
+            UiCommand::SpectrogramFrame(vec![0.1]),
+            UiCommand::SpectrogramFrame(vec![0.2]),
+            UiCommand::stream_chunk(" there"),
+            UiCommand::Hide,
+        ];
+
+        let coalesced = coalesce_ui_commands(commands, true);
+        assert_eq!(coalesced.len(), 5);
+        assert!(matches!(&coalesced[0], UiCommand::SetThinking(true)));
+        assert!(matches!(&coalesced[1], UiCommand::StreamChunk { text, chunks: 2 } if text == "Hello"));
+        assert!(matches!(&coalesced[2], UiCommand::SpectrogramFrame(bands) if bands == &[0.2]));
+        // Chunks on either side of another command stay separate so order is kept
+        assert!(matches!(&coalesced[3], UiCommand::StreamChunk { text, chunks: 1 } if text == " there"));
+        assert!(matches!(&coalesced[4], UiCommand::Hide));
+    }
+
+    #[test]
+    fn test_paced_stream_chunks_stay_separate() {
+        let commands = vec![
+            UiCommand::stream_chunk("Hel"),
+            UiCommand::stream_chunk("lo"),
+            UiCommand::SpectrogramFrame(vec![0.1]),
+            UiCommand::SpectrogramFrame(vec![0.2]),
+        ];
+
+        let coalesced = coalesce_ui_commands(commands, false);
+        assert_eq!(coalesced.len(), 3);
+        assert!(matches!(&coalesced[0], UiCommand::StreamChunk { text, chunks: 1 } if text == "Hel"));
+        assert!(matches!(&coalesced[1], UiCommand::StreamChunk { text, chunks: 1 } if text == "lo"));
+        assert!(matches!(&coalesced[2], UiCommand::SpectrogramFrame(bands) if bands == &[0.2]));
+    }
+
+    #[tokio::test]
+    async fn test_undo_removed_message() {
+        let state = AppState::new(Config::default()).await.unwrap();
+        state.chat_history.write().extend([Message::user("hi"), Message::assistant("hello")]);
+
+        assert!(state.remove_last_user_message());
+        assert!(state.chat_history.read().is_empty());
+
+        assert!(state.undo_history());
+        assert_eq!(state.chat_history.read().len(), 2);
+        assert!(state.redo_history());
+        assert!(state.chat_history.read().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_session_keeps_full_history_and_profile() {
+        let mut config = Config::default();
+        config.audio.tts_backend = TtsBackend::Espeak;
+        config.general.display_history_limit = 2;
+        config.profiles.insert("w".to_string(), VoiceProfile::new("Work".to_string()).into());
+        let mut state = AppState::new(config).await.unwrap();
+        let dir = std::env::temp_dir().join(format!("blipply-state-sessions-{}", uuid::Uuid::new_v4()));
+        state.sessions = Some(Arc::new(SessionStore::new(&dir)));
+
+        let session = Session::new("w", chrono::Utc::now());
+        let id = session.id.clone();
+        *state.session.write() = Some(session);
+        state.chat_history.write().extend([
+            Message::user("one"), Message::assistant("1"), Message::user("two"), Message::assistant("2"),
+        ]);
+
+        // Removing a turn is saved, and older turns past the display limit stay in the session
+        assert!(state.remove_last_user_message());
+        assert_eq!(state.sessions.as_ref().unwrap().load(&id).unwrap().messages.len(), 2);
+        assert!(state.undo_history());
+        assert_eq!(state.sessions.as_ref().unwrap().load(&id).unwrap().messages.len(), 4);
+
+        state.chat_history.write().clear();
+        state.load_session(&id).unwrap();
+        assert_eq!(state.chat_messages().len(), 4);
+        assert_eq!(state.profiles.read().active, "w");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_input_device_change_reopens_microphone() {
+        let state = AppState::new(Config::default()).await.unwrap();
+        let mut ui_rx = state.take_ui_receiver().unwrap();
+        let (audio_tx, _audio_rx) = create_audio_channel();
+        *state.audio_tx.write() = Some(audio_tx);
+
+        let event = AudioEvent::InputDeviceChanged { new_device: Some("USB Headset".to_string()) };
+        state.handle_audio_event(event).await.unwrap();
+
+        // Whether or not this machine can capture audio, the user hears about it
+        assert!(matches!(ui_rx.try_recv(), Ok(UiCommand::ShowToast(_))));
+    }
+
+    #[tokio::test]
+    async fn test_transcript_answered_off_the_event_loop() {
+        let state = AppState::new(Config::default()).await.unwrap();
+        let mut ui_rx = state.take_ui_receiver().unwrap();
+
+        let transcript = |text: &str| AudioEvent::TranscriptFinal { text: text.to_string(), confidence: 1.0 };
+        state.handle_audio_event(transcript("what time is it")).await.unwrap();
+        // Returned before the reply, which is still generating
+        assert!(state.is_generating());
+
+        // A second question while busy is not sent
+        while ui_rx.try_recv().is_ok() {}
+        state.handle_audio_event(transcript("and the date")).await.unwrap();
+        assert!(ui_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_auto_hide_waits_for_generation() {
+        let state = AppState::new(Config::default()).await.unwrap();
+        *state.visible.write() = true;
+
+        state.set_generating(true);
+        state.handle_audio_event(AudioEvent::LongSilence).await.unwrap();
+        assert!(state.is_visible());
+        state.set_generating(false);
+        assert!(!state.is_visible());
+
+        // Typing in the meantime cancels the deferred hide
+        *state.visible.write() = true;
+        state.set_generating(true);
+        state.handle_audio_event(AudioEvent::LongSilence).await.unwrap();
+        state.note_input_activity();
+        state.set_generating(false);
+        assert!(state.is_visible());
+    }
+
+    #[test]
+    fn test_detected_language_addendum() {
+        let mut config = Config::default();
+        let mut profile = VoiceProfile::new("Polyglot".to_string());
+        let history = vec![Message::user("Bonjour, quelle heure est-il ?")];
+
+        profile.auto_detect_language = true;
+        let addendum = language_addendum(&profile, &config, Some("fr"));
+        let messages = assemble_messages("You are helpful.".to_string(), addendum, history.clone());
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].role, "system");
+        assert_eq!(messages[1].content, "The user is speaking French, please respond in the same language.");
+        assert_eq!(messages[2].content, history[0].content);
+
+        // Nothing detected yet, or the language is fixed
+        assert_eq!(language_addendum(&profile, &config, None), None);
+        config.audio.stt_language = Some("fr".to_string());
+        assert_eq!(language_addendum(&profile, &config, Some("fr")), None);
+        config.audio.stt_language = None;
+        profile.response_language = Some("English".to_string());
+        assert_eq!(language_addendum(&profile, &config, Some("fr")), None);
+
+        let messages = assemble_messages("You are helpful.".to_string(), None, history);
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stream() {
+        let chunks = ["Once", " upon", " a", " time"].map(|c| Ok(c.to_string()));
+        let cancelled = CancelFlag::default();
+        let mut received = Vec::new();
+
+        let (text, was_cancelled) = collect_response(futures::stream::iter(chunks), &[], &cancelled, |chunk| {
+            // Press Stop while the second chunk is on screen
+            if chunk == " upon" {
+                cancelled.set();
+            }
+            received.push(chunk);
+        }).await;
+
+        assert!(was_cancelled);
+        assert_eq!(text, "Once upon");
+        assert_eq!(received, ["Once", " upon"]);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stalled_stream() {
+        use futures::StreamExt;
+
+        // The model sent one chunk and then went quiet
+        let chunks = futures::stream::iter([Ok("Thinking".to_string())]).chain(futures::stream::pending());
+        let cancelled = Arc::new(CancelFlag::default());
+
+        let stop = cancelled.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            stop.set();
+        });
+
+        let collected = collect_response(chunks, &[], &cancelled, |_| {});
+        let (text, was_cancelled) = tokio::time::timeout(std::time::Duration::from_secs(5), collected)
+            .await
+            .expect("cancel should end a stalled stream");
+        assert!(was_cancelled);
+        assert_eq!(text, "Thinking");
+    }
+
+    #[tokio::test]
+    async fn test_stream_runs_to_stop_sequence() {
+        let chunks = ["Answer.", "\nUser:", " more"].map(|c| Ok(c.to_string()));
+        let stop = vec!["User:".to_string()];
+
+        let (text, was_cancelled) =
+            collect_response(futures::stream::iter(chunks), &stop, &CancelFlag::default(), |_| {}).await;
+
+        assert!(!was_cancelled);
+        assert_eq!(text, "Answer.\n");
+    }
+
+    #[tokio::test]
+    async fn test_split_stop_sequence_is_not_shown() {
+        let stop = vec!["User:".to_string()];
+        let collect = |chunks: Vec<&'static str>| {
+            let stop = stop.clone();
+            async move {
+                let mut shown = String::new();
+                let stream = futures::stream::iter(chunks.into_iter().map(|c| Ok(c.to_string())));
+                let (text, _) = collect_response(stream, &stop, &CancelFlag::default(), |c| shown.push_str(&c)).await;
+                (text, shown)
+            }
+        };
+
+        let (text, shown) = collect(vec!["Answer.\nUs", "er: more"]).await;
+        assert_eq!(text, "Answer.\n");
+        assert_eq!(shown, "Answer.\n");
+
+        // Held back text that doesn't complete a stop sequence is still shown
+        let (text, shown) = collect(vec!["Answer.\nUs", "e it"]).await;
+        assert_eq!(text, "Answer.\nUse it");
+        assert_eq!(shown, "Answer.\nUse it");
+        let (_, shown) = collect(vec!["Ends with Us"]).await;
+        assert_eq!(shown, "Ends with Us");
+    }
+
+    #[test]
+    fn test_profile_stop_sequences_sent() {
+        let mut config: crate::config::ProfileConfig = toml::from_str(r#"
+            name = "Terse"
+            model = "llama3.2:3b"
+            personality = "helpful"
+            avatar_path = ""
+            avatar_size_px = 96
+            voice_model = "en_US-lessac-medium"
+            tts_speed = 1.0
+            tts_enabled = true
+            llm_stop_sequences = ["---"]
+        "#).unwrap();
+        assert_eq!(config.stop, vec!["---"]);
+
+        let options = generation_options(&VoiceProfile::from(config.clone()), &HashMap::new()).unwrap();
+        assert_eq!(serde_json::to_value(&options).unwrap()["stop"], serde_json::json!(["---"]));
+
+        config.stop.clear();
+        let options = generation_options(&VoiceProfile::from(config), &HashMap::new()).unwrap();
+        assert!(serde_json::to_value(&options).unwrap().get("stop").is_none());
+    }
+
+    #[test]
+    fn test_preset_with_explicit_override() {
+        let mut profile = VoiceProfile::new("Default".to_string());
+        profile.preset = Some("precise".to_string());
+        let options = generation_options(&profile, &HashMap::new()).unwrap();
+        assert_eq!((options.temperature, options.top_p, options.top_k), (0.2, Some(0.5), Some(20)));
+
+        // The profile's own temperature beats the preset's
+        profile.temperature = Some(0.9);
+        let options = generation_options(&profile, &HashMap::new()).unwrap();
+        assert_eq!((options.temperature, options.top_p), (0.9, Some(0.5)));
+
+        // Unknown presets fall back to the defaults
+        profile.preset = Some("missing".to_string());
+        let options = generation_options(&profile, &HashMap::new()).unwrap();
+        assert_eq!(options.top_p, None);
+    }
+
+    #[test]
+    fn test_model_override() {
+        let mut profile = VoiceProfile::new("Default".to_string());
+        override_model(&mut profile, None);
+        assert_eq!(profile.model, "llama3.2:3b");
+        override_model(&mut profile, Some(" "));
+        assert_eq!(profile.model, "llama3.2:3b");
+        override_model(&mut profile, Some("llama3.1:70b"));
+        assert_eq!(profile.model, "llama3.1:70b");
+    }
+
+    #[test]
+    fn test_duplicate_transcript() {
+        use std::time::Duration;
+        let window = Duration::from_millis(3000);
+        let soon = Duration::from_millis(800);
+
+        assert!(is_duplicate_transcript("What's the weather?", soon, "what's the weather", window));
+        assert!(is_duplicate_transcript("Open the terminal please", soon, "Open the terminals please", window));
+        assert!(!is_duplicate_transcript("What's the weather?", Duration::from_millis(3500), "What's the weather?", window));
+        assert!(!is_duplicate_transcript("What's the weather?", soon, "What's the time?", window));
+        // A zero window turns the check off
+        assert!(!is_duplicate_transcript("Hi", soon, "Hi", Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_context_snapshot_counts_history() {
+        let state = AppState::new(Config::default()).await.unwrap();
+        let empty = state.get_context_snapshot();
+        assert!(empty.history.is_empty());
+
+        state.chat_history.write().extend([Message::user("How do I mount NFS?"), Message::assistant("Use fileSystems.")]);
+        state.set_clipboard_context("server:/export");
+        let snapshot = state.get_context_snapshot();
+        assert_eq!(snapshot.history.len(), 2);
+        assert_eq!(snapshot.injected_context.as_deref(), Some("server:/export"));
+        assert!(snapshot.total_estimated_tokens > empty.total_estimated_tokens);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_edit_without_user_message() {
+        let state = AppState::new(Config::default()).await.unwrap();
+        assert!(!state.remove_last_user_message());
+        assert!(state.edit_last_user_message("hello").await.is_err());
     }
 }
@@ -0,0 +1,53 @@
+// Blipply Assistant
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+//! systemd `Type=notify` integration. Every call is a no-op when the
+//! daemon isn't started by systemd.
+
+use sd_notify::NotifyState;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+pub fn notify_ready() {
+    if let Err(e) = sd_notify::notify(false, &[NotifyState::Ready]) {
+        warn!("Failed to notify systemd of readiness: {}", e);
+    }
+}
+
+pub fn notify_stopping() {
+    sd_notify::notify(false, &[NotifyState::Stopping]).ok();
+}
+
+/// Ping interval for the systemd watchdog, half of `WATCHDOG_USEC`
+fn watchdog_interval() -> Option<Duration> {
+    let mut usec = 0;
+    if !sd_notify::watchdog_enabled(false, &mut usec) || usec == 0 {
+        return None;
+    }
+    Some(Duration::from_micros(usec / 2))
+}
+
+/// Send `WATCHDOG=1` from the GTK main loop so a hung UI stops the pings
+pub fn start_watchdog() {
+    let Some(interval) = watchdog_interval() else {
+        return;
+    };
+
+    debug!("systemd watchdog enabled, pinging every {:?}", interval);
+    glib::timeout_add_local(interval, || {
+        sd_notify::notify(false, &[NotifyState::Watchdog]).ok();
+        glib::ControlFlow::Continue
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_disabled_without_env() {
+        std::env::remove_var("WATCHDOG_USEC");
+        assert!(watchdog_interval().is_none());
+    }
+}
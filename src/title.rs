@@ -0,0 +1,88 @@
+// Blipply Assistant - Conversation Titles
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use tracing::{debug, warn};
+
+use crate::ollama::{Message, OllamaClient};
+
+/// Longest title kept, in words
+const MAX_TITLE_WORDS: usize = 6;
+
+const TITLE_PROMPT: &str = "Write a title of 3 to 6 words for a conversation that starts with \
+the user's message. Reply with the title only, without quotes or punctuation at the end.";
+
+/// Title from the first words of the opening message, used when the model
+/// isn't asked for one
+pub fn heuristic_title(first_message: &str) -> String {
+    let words: Vec<&str> = first_message
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .take(MAX_TITLE_WORDS)
+        .collect();
+
+    let mut title = words.join(" ");
+    if let Some(first) = title.chars().next() {
+        title.replace_range(..first.len_utf8(), &first.to_uppercase().to_string());
+    }
+    title
+}
+
+/// Tidy a model-written title: first line only, no quotes or trailing
+/// punctuation, at most `MAX_TITLE_WORDS` words
+fn clean_title(raw: &str) -> String {
+    let line = raw.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+    let line = line.strip_prefix("Title:").unwrap_or(line);
+
+    line.split_whitespace()
+        .take(MAX_TITLE_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c: char| matches!(c, '"' | '\'' | '*' | '.' | '!' | '?' | ':'))
+        .to_string()
+}
+
+/// Ask `model` for a short title, falling back to the heuristic if the
+/// request fails or the answer is empty
+pub async fn generate_title(ollama: &OllamaClient, model: &str, first_message: &str) -> String {
+    let messages = vec![Message::system(TITLE_PROMPT), Message::user(first_message)];
+
+    match ollama.chat(model, messages).await {
+        Ok(reply) => {
+            let title = clean_title(&reply);
+            debug!("Model titled the conversation {:?}", title);
+            if title.is_empty() {
+                heuristic_title(first_message)
+            } else {
+                title
+            }
+        }
+        Err(e) => {
+            warn!("Failed to generate a conversation title: {}", e);
+            heuristic_title(first_message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heuristic_title() {
+        assert_eq!(
+            heuristic_title("how do I mount an NFS share at boot on NixOS?"),
+            "How do I mount an NFS"
+        );
+        assert_eq!(heuristic_title("  hi!  "), "Hi");
+        assert_eq!(heuristic_title("¿"), "");
+    }
+
+    #[test]
+    fn test_clean_title() {
+        assert_eq!(clean_title("\"Mounting NFS on NixOS.\"\n\nHope that helps!"), "Mounting NFS on NixOS");
+        assert_eq!(clean_title("Title: **Rust Lifetimes Explained**"), "Rust Lifetimes Explained");
+        assert_eq!(clean_title("one two three four five six seven"), "one two three four five six");
+    }
+}
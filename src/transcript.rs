@@ -0,0 +1,156 @@
+// Blipply Assistant - Standalone Transcription
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use anyhow::Result;
+
+use crate::audio::stt::{Transcription, WordTimestamp};
+
+/// Most words shown in one subtitle cue
+const MAX_CUE_WORDS: usize = 8;
+
+/// Longest a subtitle cue stays on screen
+const MAX_CUE_MS: u64 = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TranscriptFormat {
+    /// Just the text
+    Plain,
+    /// SubRip subtitles
+    Srt,
+    /// WebVTT subtitles
+    Vtt,
+    /// Text, confidence and word timings
+    Json,
+}
+
+impl TranscriptFormat {
+    /// Whether the format needs Whisper's word timings
+    pub fn needs_timestamps(self) -> bool {
+        !matches!(self, TranscriptFormat::Plain)
+    }
+}
+
+/// A subtitle line and when it is shown
+#[derive(Debug, Clone, PartialEq)]
+struct Cue {
+    text: String,
+    start_ms: u64,
+    end_ms: u64,
+}
+
+/// Render `transcription` in `format`
+pub fn render(transcription: &Transcription, format: TranscriptFormat) -> Result<String> {
+    Ok(match format {
+        TranscriptFormat::Plain => format!("{}\n", transcription.text),
+        TranscriptFormat::Srt => format_srt(&cues(&transcription.words)),
+        TranscriptFormat::Vtt => format_vtt(&cues(&transcription.words)),
+        TranscriptFormat::Json => format!("{}\n", serde_json::to_string_pretty(transcription)?),
+    })
+}
+
+/// Group words into cues, ending one after a sentence, or once it is too
+/// long to read comfortably
+fn cues(words: &[WordTimestamp]) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let mut current: Vec<&WordTimestamp> = Vec::new();
+
+    for word in words {
+        current.push(word);
+
+        let start_ms = current[0].start_ms;
+        let sentence_end = word.word.ends_with(['.', '!', '?']);
+        if sentence_end || current.len() >= MAX_CUE_WORDS || word.end_ms.saturating_sub(start_ms) >= MAX_CUE_MS {
+            cues.push(cue(&current));
+            current.clear();
+        }
+    }
+    if !current.is_empty() {
+        cues.push(cue(&current));
+    }
+
+    cues
+}
+
+fn cue(words: &[&WordTimestamp]) -> Cue {
+    Cue {
+        text: words.iter().map(|w| w.word.as_str()).collect::<Vec<_>>().join(" "),
+        start_ms: words[0].start_ms,
+        end_ms: words[words.len() - 1].end_ms,
+    }
+}
+
+fn format_srt(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1, timestamp(cue.start_ms, ','), timestamp(cue.end_ms, ','), cue.text
+        ));
+    }
+    out
+}
+
+fn format_vtt(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            timestamp(cue.start_ms, '.'), timestamp(cue.end_ms, '.'), cue.text
+        ));
+    }
+    out
+}
+
+/// `HH:MM:SS,mmm` for SRT, `HH:MM:SS.mmm` for WebVTT
+fn timestamp(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(word: &str, start_ms: u64, end_ms: u64) -> WordTimestamp {
+        WordTimestamp { word: word.to_string(), start_ms, end_ms }
+    }
+
+    fn transcription(words: Vec<WordTimestamp>) -> Transcription {
+        Transcription { text: "Hello world.".to_string(), confidence: 0.9, words, language: None }
+    }
+
+    #[test]
+    fn test_srt_output() {
+        let t = transcription(vec![word("Hello", 0, 500), word("world.", 600, 1200)]);
+        assert_eq!(
+            render(&t, TranscriptFormat::Srt).unwrap(),
+            "1\n00:00:00,000 --> 00:00:01,200\nHello world.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_vtt_output_splits_sentences() {
+        let t = transcription(vec![word("Hi.", 0, 400), word("Bye", 3_661_000, 3_661_250)]);
+        assert_eq!(
+            render(&t, TranscriptFormat::Vtt).unwrap(),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:00.400\nHi.\n\n01:01:01.000 --> 01:01:01.250\nBye\n\n"
+        );
+    }
+
+    #[test]
+    fn test_plain_and_json_output() {
+        let t = transcription(vec![word("Hello", 0, 500)]);
+        assert_eq!(render(&t, TranscriptFormat::Plain).unwrap(), "Hello world.\n");
+
+        let json: serde_json::Value = serde_json::from_str(&render(&t, TranscriptFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["words"][0]["start_ms"], 0);
+        assert_eq!(json["words"][0]["end_ms"], 500);
+    }
+}
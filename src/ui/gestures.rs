@@ -0,0 +1,90 @@
+// Blipply Assistant - User Interface
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use gtk::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::state::{AppState, UiCommand};
+
+/// Horizontal speed in px/s separating a fast swipe from a slow one
+const FAST_SWIPE_VELOCITY: f64 = 300.0;
+
+/// Slower horizontal movement than this is ignored as jitter
+const MIN_SWIPE_VELOCITY: f64 = 50.0;
+
+/// Smallest window size reachable by pinching
+const MIN_PINCH_SIZE: (i32, i32) = (240, 320);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeAction {
+    Hide,
+    OpenSettings,
+}
+
+/// Action for a swipe ending with velocity (`vx`, `vy`): a fast right-to-left
+/// swipe hides the window, a slow left-to-right one opens the settings
+pub fn swipe_action(vx: f64, vy: f64) -> Option<SwipeAction> {
+    if vx.abs() < vy.abs() {
+        return None;
+    }
+
+    if vx <= -FAST_SWIPE_VELOCITY {
+        Some(SwipeAction::Hide)
+    } else if (MIN_SWIPE_VELOCITY..FAST_SWIPE_VELOCITY).contains(&vx) {
+        Some(SwipeAction::OpenSettings)
+    } else {
+        None
+    }
+}
+
+/// Add touch swipe and pinch-to-resize gestures to the window
+pub fn attach(window: &gtk::Window, state: Arc<AppState>) {
+    let swipe = gtk::GestureSwipe::new();
+    swipe.set_touch_only(true);
+    swipe.connect_swipe(move |_, vx, vy| {
+        match swipe_action(vx, vy) {
+            Some(SwipeAction::Hide) => state.toggle_visibility(),
+            Some(SwipeAction::OpenSettings) => state.send_ui_command(UiCommand::OpenSettings(None)),
+            None => debug!("Ignoring swipe ({:.0}, {:.0}) px/s", vx, vy),
+        }
+    });
+    window.add_controller(swipe);
+
+    // Scale from the size the window had when the pinch began
+    let zoom = gtk::GestureZoom::new();
+    let start_size = Rc::new(Cell::new((0, 0)));
+    let window_clone = window.clone();
+    let start_clone = start_size.clone();
+    zoom.connect_begin(move |_, _| {
+        start_clone.set((window_clone.width(), window_clone.height()));
+    });
+    let window_clone = window.clone();
+    zoom.connect_scale_changed(move |_, scale| {
+        let (width, height) = start_size.get();
+        window_clone.set_default_size(
+            ((width as f64 * scale) as i32).max(MIN_PINCH_SIZE.0),
+            ((height as f64 * scale) as i32).max(MIN_PINCH_SIZE.1),
+        );
+    });
+    window.add_controller(zoom);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swipe_velocity_threshold() {
+        assert_eq!(swipe_action(-450.0, 20.0), Some(SwipeAction::Hide));
+        assert_eq!(swipe_action(-250.0, 0.0), None);
+        assert_eq!(swipe_action(150.0, 10.0), Some(SwipeAction::OpenSettings));
+        assert_eq!(swipe_action(500.0, 0.0), None);
+        assert_eq!(swipe_action(20.0, 0.0), None);
+        // Mostly vertical movement is scrolling, not a swipe
+        assert_eq!(swipe_action(-400.0, 900.0), None);
+    }
+}
@@ -0,0 +1,156 @@
+// Blipply Assistant - User Interface
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use gtk::gdk;
+use gtk::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+const RECORDING_LABEL: &str = "Press keys…";
+
+type HotkeyCallback = Box<dyn Fn(&str)>;
+
+/// Button that records a key combination when clicked and reports it in the
+/// config's hotkey format, e.g. "Super+Shift+A". Escape cancels recording.
+pub struct HotkeyRecorder {
+    button: gtk::Button,
+    callbacks: Rc<RefCell<Vec<HotkeyCallback>>>,
+}
+
+impl HotkeyRecorder {
+    pub fn new(current: &str) -> Self {
+        let button = gtk::Button::with_label(current);
+        button.set_tooltip_text(Some("Click, then press the new shortcut"));
+
+        let callbacks: Rc<RefCell<Vec<HotkeyCallback>>> = Rc::default();
+        let recording = Rc::new(Cell::new(false));
+        let hotkey = Rc::new(RefCell::new(current.to_string()));
+
+        let recording_clone = recording.clone();
+        button.connect_clicked(move |button| {
+            recording_clone.set(true);
+            button.set_label(RECORDING_LABEL);
+            button.grab_focus();
+        });
+
+        let keys = gtk::EventControllerKey::new();
+        let button_clone = button.clone();
+        let callbacks_clone = callbacks.clone();
+        keys.connect_key_pressed(move |_, key, _, modifiers| {
+            if !recording.get() {
+                return glib::Propagation::Proceed;
+            }
+
+            if key == gdk::Key::Escape && !modifiers.intersects(hotkey_modifiers()) {
+                recording.set(false);
+                button_clone.set_label(&hotkey.borrow());
+                return glib::Propagation::Stop;
+            }
+
+            // Keep listening until a non-modifier key completes the combination
+            let Some(recorded) = hotkey_string(modifiers, key) else {
+                return glib::Propagation::Stop;
+            };
+
+            recording.set(false);
+            button_clone.set_label(&recorded);
+            *hotkey.borrow_mut() = recorded.clone();
+            for callback in callbacks_clone.borrow().iter() {
+                callback(&recorded);
+            }
+            glib::Propagation::Stop
+        });
+        button.add_controller(keys);
+
+        Self { button, callbacks }
+    }
+
+    pub fn widget(&self) -> &gtk::Button {
+        &self.button
+    }
+
+    /// Called with the new hotkey string each time one is recorded
+    pub fn connect_hotkey_changed(&self, callback: impl Fn(&str) + 'static) {
+        self.callbacks.borrow_mut().push(Box::new(callback));
+    }
+}
+
+fn hotkey_modifiers() -> gdk::ModifierType {
+    gdk::ModifierType::SUPER_MASK
+        | gdk::ModifierType::CONTROL_MASK
+        | gdk::ModifierType::ALT_MASK
+        | gdk::ModifierType::SHIFT_MASK
+}
+
+/// Format a key press as a hotkey string. `None` for modifier keys on their
+/// own and for keys the hotkey listener can't bind.
+fn hotkey_string(modifiers: gdk::ModifierType, key: gdk::Key) -> Option<String> {
+    let key_name = key_name(key)?;
+
+    let mut parts = Vec::new();
+    for (mask, name) in [
+        (gdk::ModifierType::SUPER_MASK, "Super"),
+        (gdk::ModifierType::CONTROL_MASK, "Ctrl"),
+        (gdk::ModifierType::ALT_MASK, "Alt"),
+        (gdk::ModifierType::SHIFT_MASK, "Shift"),
+    ] {
+        if modifiers.contains(mask) {
+            parts.push(name.to_string());
+        }
+    }
+    parts.push(key_name);
+
+    Some(parts.join("+"))
+}
+
+/// Name of `key` as the hotkey parser spells it
+fn key_name(key: gdk::Key) -> Option<String> {
+    match key {
+        gdk::Key::space => return Some("Space".to_string()),
+        gdk::Key::Return | gdk::Key::KP_Enter => return Some("Enter".to_string()),
+        gdk::Key::Escape => return Some("Esc".to_string()),
+        _ => {}
+    }
+
+    // Shift turns letters upper case; the name is the same either way
+    let name = key.to_lower().name()?;
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase().to_string()),
+        (Some('F'), Some(_)) if name[1..].parse::<u8>().is_ok_and(|n| (1..=12).contains(&n)) => {
+            Some(name.to_string())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotkey_string() {
+        let shift = gdk::ModifierType::SHIFT_MASK;
+        let super_shift = gdk::ModifierType::SUPER_MASK | shift;
+        let ctrl_alt = gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK;
+
+        assert_eq!(hotkey_string(super_shift, gdk::Key::A).as_deref(), Some("Super+Shift+A"));
+        assert_eq!(hotkey_string(ctrl_alt, gdk::Key::c).as_deref(), Some("Ctrl+Alt+C"));
+        assert_eq!(hotkey_string(gdk::ModifierType::SUPER_MASK, gdk::Key::space).as_deref(), Some("Super+Space"));
+        assert_eq!(hotkey_string(ctrl_alt, gdk::Key::_5).as_deref(), Some("Ctrl+Alt+5"));
+        assert_eq!(hotkey_string(gdk::ModifierType::empty(), gdk::Key::F5).as_deref(), Some("F5"));
+        assert_eq!(hotkey_string(shift, gdk::Key::F12).as_deref(), Some("Shift+F12"));
+    }
+
+    #[test]
+    fn test_modifier_only_is_rejected() {
+        let super_mask = gdk::ModifierType::SUPER_MASK;
+        assert_eq!(hotkey_string(super_mask, gdk::Key::Super_L), None);
+        assert_eq!(hotkey_string(super_mask, gdk::Key::Shift_L), None);
+        assert_eq!(hotkey_string(gdk::ModifierType::CONTROL_MASK, gdk::Key::Control_R), None);
+        assert_eq!(hotkey_string(gdk::ModifierType::empty(), gdk::Key::Alt_L), None);
+        // Not bindable by the hotkey listener
+        assert_eq!(hotkey_string(super_mask, gdk::Key::F13), None);
+    }
+}
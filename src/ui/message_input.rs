@@ -0,0 +1,162 @@
+// Blipply Assistant - User Interface
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use gtk::gdk;
+use gtk::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+const PLACEHOLDER: &str = "Type a message... (Ctrl+Enter to send)";
+
+/// Tallest the input grows before it scrolls
+const MAX_HEIGHT: i32 = 120;
+
+type SubmitCallback = Box<dyn Fn()>;
+
+/// Multi-line message input. Enter starts a new line and Ctrl+Enter submits,
+/// so pasted text keeps its line breaks.
+#[derive(Clone)]
+pub struct MessageInput {
+    scroller: gtk::ScrolledWindow,
+    view: gtk::TextView,
+    /// Whether the buffer holds the grey placeholder instead of user text
+    placeholder_shown: Rc<Cell<bool>>,
+    callbacks: Rc<RefCell<Vec<SubmitCallback>>>,
+}
+
+impl MessageInput {
+    pub fn new() -> Self {
+        let view = gtk::TextView::new();
+        view.set_wrap_mode(gtk::WrapMode::WordChar);
+        view.set_accepts_tab(false);
+        view.set_hexpand(true);
+        let tag = gtk::TextTag::new(Some("placeholder"));
+        tag.set_foreground(Some("#888888"));
+        view.buffer().tag_table().add(&tag);
+
+        let scroller = gtk::ScrolledWindow::new();
+        scroller.set_child(Some(&view));
+        scroller.set_hexpand(true);
+        scroller.set_hscrollbar_policy(gtk::PolicyType::Never);
+        scroller.set_propagate_natural_height(true);
+        scroller.set_max_content_height(MAX_HEIGHT);
+
+        let input = Self {
+            scroller,
+            view,
+            placeholder_shown: Rc::default(),
+            callbacks: Rc::default(),
+        };
+
+        // GTK text views have no placeholder, so show one while empty and unfocused
+        let focus = gtk::EventControllerFocus::new();
+        let this = input.clone();
+        focus.connect_enter(move |_| this.hide_placeholder());
+        let this = input.clone();
+        focus.connect_leave(move |_| {
+            let this = this.clone();
+            glib::idle_add_local_once(move || this.show_placeholder());
+        });
+        input.view.add_controller(focus);
+
+        // Ahead of the text view, which would insert a newline
+        let keys = gtk::EventControllerKey::new();
+        keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let callbacks = input.callbacks.clone();
+        keys.connect_key_pressed(move |_, key, _, modifiers| {
+            let is_enter = matches!(key, gdk::Key::Return | gdk::Key::KP_Enter);
+            if !is_enter || !modifiers.contains(gdk::ModifierType::CONTROL_MASK) {
+                return glib::Propagation::Proceed;
+            }
+            for callback in callbacks.borrow().iter() {
+                callback();
+            }
+            glib::Propagation::Stop
+        });
+        input.view.add_controller(keys);
+
+        input.show_placeholder();
+        input
+    }
+
+    pub fn widget(&self) -> &gtk::ScrolledWindow {
+        &self.scroller
+    }
+
+    /// The typed text, trimmed; empty while the placeholder shows
+    pub fn text(&self) -> String {
+        let buffer = self.view.buffer();
+        let (start, end) = buffer.bounds();
+        submitted_text(&buffer.text(&start, &end, false), self.placeholder_shown.get())
+    }
+
+    pub fn set_text(&self, text: &str) {
+        self.placeholder_shown.set(false);
+        self.view.buffer().set_text(text);
+        if text.is_empty() {
+            self.show_placeholder();
+        }
+    }
+
+    pub fn grab_focus(&self) {
+        self.view.grab_focus();
+    }
+
+    /// Called whenever the user edits the text
+    pub fn connect_edited(&self, callback: impl Fn() + 'static) {
+        let placeholder_shown = self.placeholder_shown.clone();
+        self.view.buffer().connect_changed(move |_| {
+            if !placeholder_shown.get() {
+                callback();
+            }
+        });
+    }
+
+    /// Called on Ctrl+Enter
+    pub fn connect_submit(&self, callback: impl Fn() + 'static) {
+        self.callbacks.borrow_mut().push(Box::new(callback));
+    }
+
+    fn show_placeholder(&self) {
+        let buffer = self.view.buffer();
+        if self.placeholder_shown.get() || buffer.char_count() > 0 || self.view.has_focus() {
+            return;
+        }
+        self.placeholder_shown.set(true);
+        buffer.insert_with_tags_by_name(&mut buffer.start_iter(), PLACEHOLDER, &["placeholder"]);
+    }
+
+    fn hide_placeholder(&self) {
+        if self.placeholder_shown.replace(false) {
+            self.view.buffer().set_text("");
+        }
+    }
+}
+
+impl Default for MessageInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What gets sent for the input's `buffer_text`
+fn submitted_text(buffer_text: &str, placeholder_shown: bool) -> String {
+    if placeholder_shown {
+        return String::new();
+    }
+    buffer_text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_line_paste_is_sent_whole() {
+        let pasted = "first line\nsecond line\nthird line\n";
+        assert_eq!(submitted_text(pasted, false), "first line\nsecond line\nthird line");
+        assert_eq!(submitted_text("  \n ", false), "");
+        assert_eq!(submitted_text(PLACEHOLDER, true), "");
+    }
+}
@@ -4,6 +4,11 @@
 
 mod window;
 mod widgets;
+mod spectrogram;
+mod gestures;
+mod hotkey_recorder;
+mod message_input;
+mod print;
 
 pub use window::create_window;
 pub use widgets::*;
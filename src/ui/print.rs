@@ -0,0 +1,231 @@
+// Blipply Assistant - User Interface
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use gtk::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use tracing::error;
+
+use crate::ollama::Message;
+
+/// Message text, monospaced so line wrapping can be worked out up front
+const CONTENT_FONT: &str = "Monospace 10";
+/// Who sent each message
+const HEADER_FONT: &str = "Sans Bold 11";
+/// Extra space between lines, as a fraction of the font height
+const LINE_SPACING: f64 = 0.2;
+
+/// One printed line, already wrapped to the page width
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintLine {
+    pub text: String,
+    /// A role header such as "You:", printed in bold
+    pub header: bool,
+}
+
+impl PrintLine {
+    fn header(text: String) -> Self {
+        Self { text, header: true }
+    }
+
+    fn content(text: String) -> Self {
+        Self { text, header: false }
+    }
+}
+
+/// How much fits on a page, measured with the print fonts
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PageMetrics {
+    pub width: f64,
+    pub line_height: f64,
+    /// Monospace characters per line
+    pub columns: usize,
+    pub lines_per_page: usize,
+}
+
+impl PageMetrics {
+    pub fn measure(cr: &cairo::Context, width: f64, height: f64) -> Self {
+        let mut char_width: f64 = 1.0;
+        let mut line_height: f64 = 1.0;
+        for (font, sample) in [(CONTENT_FONT, "M"), (HEADER_FONT, "You:")] {
+            let layout = create_layout(cr, font);
+            layout.set_text(sample);
+            let (w, h) = layout.pixel_size();
+            if font == CONTENT_FONT {
+                char_width = (w as f64).max(1.0);
+            }
+            line_height = line_height.max(h as f64 * (1.0 + LINE_SPACING));
+        }
+
+        Self {
+            width,
+            line_height,
+            columns: ((width / char_width) as usize).max(1),
+            lines_per_page: ((height / line_height) as usize).max(1),
+        }
+    }
+}
+
+/// The conversation as printed lines: a header per message, its wrapped
+/// text, and a blank line between messages
+pub fn transcript_lines(messages: &[Message], assistant_name: &str, columns: usize) -> Vec<PrintLine> {
+    let mut lines = Vec::new();
+    for (i, message) in messages.iter().enumerate() {
+        if i > 0 {
+            lines.push(PrintLine::content(String::new()));
+        }
+        let role = match message.role.as_str() {
+            "user" => "You",
+            "assistant" => assistant_name,
+            "system" => "System",
+            _ => "Unknown",
+        };
+        lines.push(PrintLine::header(format!("{}:", role)));
+        lines.extend(wrap(&message.content, columns).into_iter().map(PrintLine::content));
+    }
+    lines
+}
+
+/// Split `lines` into pages, keeping headers off the bottom of a page
+pub fn paginate(lines: Vec<PrintLine>, lines_per_page: usize) -> Vec<Vec<PrintLine>> {
+    let mut pages: Vec<Vec<PrintLine>> = vec![Vec::new()];
+    for line in lines {
+        let page = pages.last_mut().unwrap();
+        let full = page.len() >= lines_per_page;
+        let orphan_header = lines_per_page > 1 && page.len() + 1 == lines_per_page && line.header;
+        if full || orphan_header {
+            pages.push(Vec::new());
+        }
+        let page = pages.last_mut().unwrap();
+        if !(page.is_empty() && line.text.is_empty() && !line.header) {
+            page.push(line);
+        }
+    }
+    pages
+}
+
+/// Word-wrap `text` to `columns` characters, splitting words that don't fit
+/// on a line of their own. Keeps the text's own line breaks.
+fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let line_len = line.chars().count();
+            let word_len = word.chars().count();
+            if line_len > 0 && line_len + 1 + word_len > columns {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+            while line.chars().count() > columns {
+                let split = line.char_indices().nth(columns).map_or(line.len(), |(i, _)| i);
+                let rest = line.split_off(split);
+                lines.push(std::mem::replace(&mut line, rest));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn create_layout(cr: &cairo::Context, font: &str) -> pango::Layout {
+    let layout = pangocairo::functions::create_layout(cr);
+    layout.set_font_description(Some(&pango::FontDescription::from_string(font)));
+    layout
+}
+
+/// Draw one page of lines at the top left of `cr`
+pub fn draw_page(cr: &cairo::Context, page: &[PrintLine], metrics: &PageMetrics) {
+    cr.set_source_rgb(0.0, 0.0, 0.0);
+    for (i, line) in page.iter().enumerate() {
+        let layout = create_layout(cr, if line.header { HEADER_FONT } else { CONTENT_FONT });
+        // Pango wraps anything the monospace estimate got wrong
+        layout.set_width((metrics.width * pango::SCALE as f64) as i32);
+        layout.set_wrap(pango::WrapMode::WordChar);
+        layout.set_text(&line.text);
+        cr.move_to(0.0, i as f64 * metrics.line_height);
+        pangocairo::functions::show_layout(cr, &layout);
+    }
+}
+
+/// Show the print dialog for `messages`
+pub fn print_conversation(parent: Option<&gtk::Window>, messages: Vec<Message>, assistant_name: String) {
+    let operation = gtk::PrintOperation::new();
+    operation.set_job_name("Blipply Conversation");
+    operation.set_embed_page_setup(true);
+    // Return to the UI command loop rather than nesting a main loop
+    operation.set_allow_async(true);
+
+    let pages: Rc<RefCell<(PageMetrics, Vec<Vec<PrintLine>>)>> = Default::default();
+
+    // Lay the text out once the paper size is known
+    let layout_pages = pages.clone();
+    operation.connect_begin_print(move |operation, context| {
+        let metrics = PageMetrics::measure(&context.cairo_context(), context.width(), context.height());
+        let lines = transcript_lines(&messages, &assistant_name, metrics.columns);
+        let paginated = paginate(lines, metrics.lines_per_page);
+        operation.set_n_pages(paginated.len() as i32);
+        *layout_pages.borrow_mut() = (metrics, paginated);
+    });
+
+    operation.connect_draw_page(move |_, context, page_nr| {
+        let pages = pages.borrow();
+        let (metrics, pages) = &*pages;
+        if let Some(page) = pages.get(page_nr as usize) {
+            draw_page(&context.cairo_context(), page, metrics);
+        }
+    });
+
+    if let Err(e) = operation.run(gtk::PrintOperationAction::PrintDialog, parent) {
+        error!("Failed to print conversation: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> Vec<Message> {
+        vec![
+            Message::user("What is the capital of France?"),
+            Message::assistant("The capital of France is Paris, which is also its largest city.\nIt sits on the Seine."),
+            Message::user("Thanks!"),
+        ]
+    }
+
+    #[test]
+    fn test_wrap_and_paginate() {
+        assert_eq!(wrap("one two three", 7), ["one two", "three"]);
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("first\n\nthird", 20), ["first", "", "third"]);
+
+        let lines = transcript_lines(&conversation(), "Blipply", 30);
+        assert_eq!(lines[0], PrintLine::header("You:".to_string()));
+        assert!(lines.iter().any(|l| l.header && l.text == "Blipply:"));
+        assert!(lines.iter().all(|l| l.text.chars().count() <= 30));
+
+        let pages = paginate(lines, 4);
+        assert!(pages.len() > 1);
+        assert!(pages.iter().all(|page| !page.is_empty() && page.len() <= 4));
+        // A header never ends a page
+        assert!(pages.iter().all(|page| !page.last().unwrap().header));
+    }
+
+    #[test]
+    fn test_three_message_page_draws() {
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 595, 842).unwrap();
+        let cr = cairo::Context::new(&surface).unwrap();
+
+        let metrics = PageMetrics::measure(&cr, 595.0, 842.0);
+        let pages = paginate(transcript_lines(&conversation(), "Blipply", metrics.columns), metrics.lines_per_page);
+        assert!(!pages.is_empty());
+
+        for page in &pages {
+            draw_page(&cr, page, &metrics);
+        }
+    }
+}
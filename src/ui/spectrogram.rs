@@ -0,0 +1,80 @@
+// Blipply Assistant - User Interface
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use gtk::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Fraction of the previous level kept each frame, so bars fall smoothly
+const DECAY: f32 = 0.85;
+/// Levels below this are drawn as empty bars
+const FLOOR_DB: f32 = -60.0;
+
+/// Bar display of microphone frequency bands
+pub struct Spectrogram {
+    area: gtk::DrawingArea,
+    levels: Rc<RefCell<Vec<f32>>>,
+}
+
+impl Spectrogram {
+    pub fn new() -> Self {
+        let area = gtk::DrawingArea::new();
+        area.set_content_height(48);
+        area.set_hexpand(true);
+
+        let levels: Rc<RefCell<Vec<f32>>> = Rc::new(RefCell::new(Vec::new()));
+        let draw_levels = levels.clone();
+
+        area.set_draw_func(move |_, cr, width, height| {
+            let levels = draw_levels.borrow();
+            if levels.is_empty() {
+                return;
+            }
+
+            let bar_width = width as f64 / levels.len() as f64;
+            cr.set_source_rgb(0.31, 0.78, 0.47);
+
+            for (i, level) in levels.iter().enumerate() {
+                let bar_height = *level as f64 * height as f64;
+                cr.rectangle(
+                    i as f64 * bar_width + 1.0,
+                    height as f64 - bar_height,
+                    (bar_width - 2.0).max(1.0),
+                    bar_height,
+                );
+            }
+            cr.fill().ok();
+        });
+
+        Self { area, levels }
+    }
+
+    pub fn widget(&self) -> &gtk::DrawingArea {
+        &self.area
+    }
+
+    /// Feed one frame of band magnitudes (linear, 0.0-1.0)
+    pub fn push_frame(&self, bands: &[f32]) {
+        {
+            let mut levels = self.levels.borrow_mut();
+            levels.resize(bands.len(), 0.0);
+            for (level, &magnitude) in levels.iter_mut().zip(bands) {
+                *level = normalize_level(magnitude).max(*level * DECAY);
+            }
+        }
+        self.area.queue_draw();
+    }
+}
+
+impl Default for Spectrogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Map a linear magnitude onto 0.0-1.0 over the displayed dB range
+fn normalize_level(magnitude: f32) -> f32 {
+    let db = 20.0 * magnitude.max(1e-6).log10();
+    ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+}
@@ -3,10 +3,28 @@
 // Licensed under the MIT License
 
 use gtk::prelude::*;
+use std::cell::RefCell;
 use std::sync::Arc;
 use tracing::error;
 
-use crate::state::AppState;
+use crate::config::InteractionMode;
+use crate::profiles::VoiceProfile;
+use crate::state::{AppState, UiCommand};
+
+thread_local! {
+    /// Open model dropdowns, refreshed when the installed models change, with
+    /// the label of their "profile's model" item if they have one
+    static MODEL_COMBOS: RefCell<Vec<(gtk::glib::WeakRef<gtk::ComboBoxText>, Option<&'static str>)>> = RefCell::new(Vec::new());
+}
+
+/// First item of the input box's model dropdown, sending with the profile's model
+const PROFILE_MODEL_LABEL: &str = "Profile model";
+
+/// First item of the preset dropdown, using the profile's own preset
+const PROFILE_PRESET_LABEL: &str = "Profile preset";
+
+/// Widget name of the push-to-talk button, so profile switches can find it
+const TALK_BUTTON_NAME: &str = "talk-button";
 
 pub fn create_avatar(path: &str, size: i32) -> gtk::Widget {
     // Try to load the image
@@ -43,45 +61,264 @@ pub fn create_chat_view() -> (gtk::ScrolledWindow, gtk::TextBuffer) {
     
     let buffer = text_view.buffer();
     
+    attach_drag_source(&text_view);
+    
     let scrolled = gtk::ScrolledWindow::new();
     scrolled.set_child(Some(&text_view));
     scrolled.set_vexpand(true);
     scrolled.set_min_content_height(300);
     
+    // Follow new text while scrolled to the bottom; scrolling up to read
+    // stops following until the bottom is reached again
+    let adjustment = scrolled.vadjustment();
+    let following = std::rc::Rc::new(std::cell::Cell::new(true));
+    let following_clone = following.clone();
+    adjustment.connect_value_changed(move |adj| {
+        following_clone.set(is_scrolled_to_bottom(adj.value(), adj.page_size(), adj.upper()));
+    });
+    adjustment.connect_upper_notify(move |adj| {
+        if following.get() {
+            adj.set_value(adj.upper() - adj.page_size());
+        }
+    });
+    
     (scrolled, buffer)
 }
 
-pub fn create_input_box(state: Arc<AppState>, buffer: gtk::TextBuffer) -> gtk::Box {
+/// Pixels from the bottom that still count as scrolled to the bottom
+const SCROLL_BOTTOM_SLACK: f64 = 4.0;
+
+fn is_scrolled_to_bottom(value: f64, page_size: f64, upper: f64) -> bool {
+    value + page_size >= upper - SCROLL_BOTTOM_SLACK
+}
+
+/// Side panel listing saved conversations; activating one reopens it.
+/// The list is reloaded each time the panel is revealed.
+pub fn create_history_panel(state: Arc<AppState>) -> gtk::Revealer {
+    let list = gtk::ListBox::new();
+    list.set_selection_mode(gtk::SelectionMode::None);
+    list.set_activate_on_single_click(true);
+    list.set_placeholder(Some(&gtk::Label::new(Some("No saved conversations"))));
+
+    let state_clone = state.clone();
+    list.connect_row_activated(move |_, row| {
+        if let Err(e) = state_clone.load_session(&row.widget_name()) {
+            error!("Failed to open conversation: {:#}", e);
+        }
+    });
+
+    let scrolled = gtk::ScrolledWindow::new();
+    scrolled.set_child(Some(&list));
+    scrolled.set_width_request(180);
+    scrolled.set_vexpand(true);
+
+    let revealer = gtk::Revealer::new();
+    revealer.set_transition_type(gtk::RevealerTransitionType::SlideRight);
+    revealer.set_child(Some(&scrolled));
+    revealer.connect_reveal_child_notify(move |revealer| {
+        if revealer.reveals_child() {
+            populate_history_list(&list, &state);
+        }
+    });
+
+    revealer
+}
+
+fn populate_history_list(list: &gtk::ListBox, state: &AppState) {
+    while let Some(row) = list.first_child() {
+        list.remove(&row);
+    }
+
+    let sessions = match state.list_sessions() {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            error!("Failed to list conversations: {:#}", e);
+            return;
+        }
+    };
+
+    for session in sessions {
+        let title = gtk::Label::new(Some(&session.display_title()));
+        title.set_halign(gtk::Align::Start);
+        title.set_ellipsize(gtk::pango::EllipsizeMode::End);
+
+        let details = gtk::Label::new(Some(&format!(
+            "{} · {} turns",
+            session.updated.with_timezone(&chrono::Local).format("%b %-d %H:%M"),
+            session.turn_count(),
+        )));
+        details.set_halign(gtk::Align::Start);
+        details.add_css_class("dim-label");
+
+        let row_box = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        row_box.append(&title);
+        row_box.append(&details);
+
+        let row = gtk::ListBoxRow::new();
+        row.set_widget_name(&session.id);
+        row.set_child(Some(&row_box));
+        list.append(&row);
+    }
+}
+
+/// Let the user drag message text out of the chat into other applications.
+/// Drags the selection if there is one, otherwise the message line under the pointer.
+fn attach_drag_source(text_view: &gtk::TextView) {
+    let drag_source = gtk::DragSource::new();
+    drag_source.set_actions(gtk::gdk::DragAction::COPY);
+
+    let view = text_view.clone();
+    drag_source.connect_prepare(move |_, x, y| {
+        let buffer = view.buffer();
+
+        let text = if let Some((start, end)) = buffer.selection_bounds() {
+            buffer.text(&start, &end, false).to_string()
+        } else {
+            let (bx, by) = view.window_to_buffer_coords(gtk::TextWindowType::Widget, x as i32, y as i32);
+            let mut start = view.iter_at_location(bx, by)?;
+            start.set_line_offset(0);
+            skip_message_label(&mut start);
+            let mut end = start.clone();
+            end.forward_to_line_end();
+            buffer.text(&start, &end, false).to_string()
+        };
+
+        if text.trim().is_empty() {
+            return None;
+        }
+
+        Some(message_content_provider(&text))
+    });
+
+    drag_source.connect_drag_begin(|source, _| {
+        let icon = gtk::IconTheme::for_display(&source.widget().display()).lookup_icon(
+            "insert-text-symbolic",
+            &[],
+            32,
+            1,
+            gtk::TextDirection::None,
+            gtk::IconLookupFlags::empty(),
+        );
+        source.set_icon(Some(&icon), 0, 0);
+    });
+
+    text_view.add_controller(drag_source);
+}
+
+/// Move `iter` past the timestamp and role label a message line starts with,
+/// whatever the assistant is called
+fn skip_message_label(iter: &mut gtk::TextIter) {
+    while !iter.ends_line() && iter.tags().iter().any(super::window::is_message_label) {
+        if !iter.forward_to_tag_toggle(None::<&gtk::TextTag>) {
+            break;
+        }
+    }
+}
+
+/// MIME type / bytes pairs offered when dragging message text
+fn drag_payloads(text: &str) -> Vec<(&'static str, Vec<u8>)> {
+    let mut payloads = vec![("text/plain;charset=utf-8", text.as_bytes().to_vec())];
+
+    let uris: String = text
+        .split_whitespace()
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|uri| format!("{}\r\n", uri.trim_end_matches(|c: char| ".,;:!?)]>\"'".contains(c))))
+        .collect();
+
+    if !uris.is_empty() {
+        payloads.push(("text/uri-list", uris.into_bytes()));
+    }
+
+    payloads
+}
+
+fn message_content_provider(text: &str) -> gtk::gdk::ContentProvider {
+    let providers: Vec<gtk::gdk::ContentProvider> = drag_payloads(text)
+        .into_iter()
+        .map(|(mime, bytes)| gtk::gdk::ContentProvider::for_bytes(mime, &glib::Bytes::from_owned(bytes)))
+        .collect();
+
+    gtk::gdk::ContentProvider::new_union(&providers)
+}
+
+/// The input row, and its message input for filling in text
+pub fn create_input_box(state: Arc<AppState>, buffer: gtk::TextBuffer) -> (gtk::Box, super::message_input::MessageInput) {
     let input_box = gtk::Box::new(gtk::Orientation::Horizontal, 8);
     
-    let entry = gtk::Entry::new();
-    entry.set_placeholder_text(Some("Type a message..."));
-    entry.set_hexpand(true);
+    let entry = super::message_input::MessageInput::new();
     
     let send_button = gtk::Button::with_label("Send");
     
+    // Model for the next message only, unless pinned; the first item keeps
+    // the profile's. Only that item is offered while Ollama can't be reached
+    let model_combo = create_model_selector(&state.models(), Some(PROFILE_MODEL_LABEL));
+    model_combo.set_tooltip_text(Some("Model for the next message"));
+    let pin_button = gtk::ToggleButton::with_label("📌");
+    pin_button.set_tooltip_text(Some("Keep using this model"));
+    
+    // Sampling preset for the rest of the session
+    let preset_combo = gtk::ComboBoxText::new();
+    preset_combo.append(Some(""), PROFILE_PRESET_LABEL);
+    for name in state.preset_names() {
+        preset_combo.append(Some(&name), &name);
+    }
+    preset_combo.set_active(Some(0));
+    preset_combo.set_tooltip_text(Some("Generation preset"));
+    let state_clone = state.clone();
+    preset_combo.connect_changed(move |combo| {
+        let preset = combo.active_id().filter(|id| !id.is_empty());
+        if let Err(e) = state_clone.set_preset(preset.as_deref()) {
+            error!("Failed to set preset: {}", e);
+        }
+    });
+    
+    // Image dropped onto the input, sent with the next message
+    let dropped_image: std::rc::Rc<RefCell<Option<std::path::PathBuf>>> = Default::default();
+    
     // Handle send action
     let entry_clone = entry.clone();
     let state_clone = state.clone();
     let buffer_clone = buffer.clone();
+    let dropped_clone = dropped_image.clone();
+    let model_clone = model_combo.clone();
+    let pin_clone = pin_button.clone();
     
     let send_action = move || {
+        if let Some(path) = dropped_clone.borrow_mut().take() {
+            submit_image(&state_clone, &entry_clone, path);
+            return;
+        }
+        
         let text = entry_clone.text();
         if !text.is_empty() {
             // Clear input
             entry_clone.set_text("");
             
-            // Add to chat
-            let message = crate::ollama::Message::user(text.as_str());
-            append_message(&buffer_clone, &message);
+            // An edited message replaces the last one, which redraws the chat
+            let editing = state_clone.take_editing_last();
+            if !editing {
+                let message = crate::ollama::Message::user(&text);
+                append_message(&buffer_clone, &message, &state_clone.assistant_name());
+            }
+            
+            let model = model_clone.active_id()
+                .filter(|id| !id.is_empty())
+                .map(|id| id.to_string());
+            if !pin_clone.is_active() {
+                model_clone.set_active(Some(0));
+            }
             
             // Process message
             let state = state_clone.clone();
-            let text = text.to_string();
-            glib::spawn_future_local(async move {
-                // In a real implementation, this would be handled by the audio pipeline
-                // For text-only mode, we'd need to trigger Ollama directly
-                // For now, we'll just add the message to the buffer
+            tokio::spawn(async move {
+                let sent = if editing {
+                    state.edit_last_user_message(&text).await
+                } else {
+                    state.process_user_message(&text, model.as_deref()).await
+                };
+                if let Err(e) = sent {
+                    error!("Failed to send message: {}", e);
+                }
             });
         }
     };
@@ -91,44 +328,175 @@ pub fn create_input_box(state: Arc<AppState>, buffer: gtk::TextBuffer) -> gtk::B
         send_action_clone();
     });
     
-    entry.connect_activate(move |_| {
-        send_action();
+    entry.connect_submit(send_action);
+    
+    let state_clone = state.clone();
+    entry.connect_edited(move || state_clone.note_input_activity());
+    
+    // Attach an image file
+    let attach_button = gtk::Button::with_label("📎 Attach");
+    let entry_clone = entry.clone();
+    let state_clone = state.clone();
+    attach_button.connect_clicked(move |button| {
+        let parent = button.root().and_downcast::<gtk::Window>();
+        let dialog = gtk::FileChooserNative::new(
+            Some("Attach Image"),
+            parent.as_ref(),
+            gtk::FileChooserAction::Open,
+            Some("Attach"),
+            Some("Cancel"),
+        );
+        let filter = gtk::FileFilter::new();
+        for mime in IMAGE_MIME_TYPES {
+            filter.add_mime_type(mime);
+        }
+        dialog.add_filter(&filter);
+
+        let entry = entry_clone.clone();
+        let state = state_clone.clone();
+        dialog.connect_response(move |dialog, response| {
+            if response == gtk::ResponseType::Accept {
+                if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                    submit_image(&state, &entry, path);
+                }
+            }
+            dialog.destroy();
+        });
+        dialog.show();
+    });
+    
+    // Ask about the current screen
+    let screenshot_button = gtk::Button::with_label("📷");
+    screenshot_button.set_tooltip_text(Some("Ask about a screenshot"));
+    let entry_clone = entry.clone();
+    let state_clone = state.clone();
+    screenshot_button.connect_clicked(move |_| {
+        let entry = entry_clone.clone();
+        let state = state_clone.clone();
+        glib::spawn_future_local(async move {
+            match crate::screenshot::capture().await {
+                Ok(path) => submit_image(&state, &entry, path),
+                Err(e) => error!("Screenshot failed: {}", e),
+            }
+        });
+    });
+    
+    // Speak while held, for push-to-talk profiles
+    let talk_button = gtk::Button::with_label("🎤 Hold to talk");
+    talk_button.set_widget_name(TALK_BUTTON_NAME);
+    talk_button.set_visible(state.interaction_mode() == InteractionMode::VoicePtt);
+    let talk = gtk::GestureClick::new();
+    let state_clone = state.clone();
+    talk.connect_pressed(move |_, _, _, _| state_clone.set_talking(true));
+    let state_clone = state.clone();
+    talk.connect_released(move |_, _, _, _| state_clone.set_talking(false));
+    let state_clone = state.clone();
+    talk.connect_cancel(move |_, _| state_clone.set_talking(false));
+    talk_button.add_controller(talk);
+    
+    // Drop an image file to ask about it
+    let drop_target = gtk::DropTarget::new(gtk::gio::File::static_type(), gtk::gdk::DragAction::COPY);
+    let entry_clone = entry.clone();
+    drop_target.connect_drop(move |_, value, _, _| {
+        let Some(path) = value.get::<gtk::gio::File>().ok().and_then(|file| file.path()) else {
+            return false;
+        };
+        if !is_supported_image(&path) {
+            return false;
+        }
+
+        *dropped_image.borrow_mut() = Some(path);
+        entry_clone.set_text("Describe this image");
+        entry_clone.grab_focus();
+        true
     });
+    input_box.add_controller(drop_target);
     
-    input_box.append(&entry);
+    input_box.append(entry.widget());
+    input_box.append(&model_combo);
+    input_box.append(&pin_button);
+    input_box.append(&preset_combo);
+    input_box.append(&attach_button);
+    input_box.append(&screenshot_button);
+    input_box.append(&talk_button);
     input_box.append(&send_button);
     
-    input_box
+    (input_box, entry)
 }
 
-pub fn create_profile_selector(state: Arc<AppState>) -> gtk::ComboBoxText {
-    let combo = gtk::ComboBoxText::new();
-    
-    // Populate with profiles
-    {
-        let profiles = state.profiles.read();
-        for (id, profile) in &profiles.profiles {
-            combo.append(Some(id), &profile.name);
+/// Show the input box's push-to-talk button only for push-to-talk profiles
+pub fn set_talk_button_visible(input_box: &gtk::Box, visible: bool) {
+    let mut child = input_box.first_child();
+    while let Some(widget) = child {
+        if widget.widget_name() == TALK_BUTTON_NAME {
+            widget.set_visible(visible);
         }
-        combo.set_active_id(Some(&profiles.active));
+        child = widget.next_sibling();
     }
-    
-    // Add "Create New..." option
-    combo.append(Some("__new__"), "➕ Create New");
+}
+
+/// Images the attach dialog and drop target accept
+const IMAGE_MIME_TYPES: &[&str] = &["image/png", "image/jpeg"];
+
+fn is_supported_image(path: &std::path::Path) -> bool {
+    let (content_type, _) = gtk::gio::content_type_guess(Some(path), &[]);
+    IMAGE_MIME_TYPES.iter().any(|mime| gtk::gio::content_type_is_mime_type(&content_type, mime))
+}
+
+/// Send the entry text, or a default question, together with an image
+fn submit_image(state: &Arc<AppState>, entry: &super::message_input::MessageInput, path: std::path::PathBuf) {
+    let text = match entry.text() {
+        text if text.is_empty() => "What's in this image?".to_string(),
+        text => text,
+    };
+    entry.set_text("");
+
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    state.send_ui_command(UiCommand::AppendMessage(crate::ollama::Message::user(
+        format!("{} 📎 {}", text, file_name),
+    )));
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = state.submit_with_image(&text, &path).await {
+            error!("Failed to send image: {}", e);
+        }
+    });
+}
+
+pub fn create_profile_selector(state: Arc<AppState>) -> gtk::ComboBoxText {
+    let combo = gtk::ComboBoxText::new();
+    populate_profile_selector(&combo, &state);
     
     // Handle selection
     combo.connect_changed(move |combo| {
-        if let Some(id) = combo.active_id() {
-            let id_str = id.as_str();
-            
-            if id_str == "__new__" {
-                // Show profile creation dialog
-                show_create_profile_dialog(state.clone());
+        let Some(id) = combo.active_id() else {
+            return;
+        };
+        let active = state.profiles.read().active.clone();
+        
+        match id.as_str() {
+            "__new__" | "__edit__" => {
+                let editing = (id == "__edit__").then_some(active.as_str());
+                let dialog = create_profile_editor_dialog(state.clone(), editing);
+                
+                // Show the saved profile in the list
+                let combo = combo.clone();
+                let state = state.clone();
+                dialog.connect_response(move |_, response| {
+                    if response == gtk::ResponseType::Accept {
+                        populate_profile_selector(&combo, &state);
+                    }
+                });
+                dialog.present();
                 
                 // Reset to current active profile
-                let profiles = state.profiles.read();
-                combo.set_active_id(Some(&profiles.active));
-            } else {
+                combo.set_active_id(Some(&active));
+            }
+            id_str if id_str == active => {}
+            id_str => {
                 // Switch profile
                 if let Err(e) = state.switch_profile(id_str) {
                     error!("Failed to switch profile: {}", e);
@@ -140,14 +508,129 @@ pub fn create_profile_selector(state: Arc<AppState>) -> gtk::ComboBoxText {
     combo
 }
 
-fn show_create_profile_dialog(state: Arc<AppState>) {
+fn populate_profile_selector(combo: &gtk::ComboBoxText, state: &AppState) {
+    let active = {
+        let profiles = state.profiles.read();
+        combo.remove_all();
+        for (id, profile) in &profiles.profiles {
+            combo.append(Some(id), &profile.name);
+        }
+        profiles.active.clone()
+    };
+    
+    combo.append(Some("__edit__"), "✏️ Edit Profile");
+    combo.append(Some("__new__"), "➕ Create New");
+    combo.set_active_id(Some(&active));
+}
+
+/// Personality presets offered in the profile editor
+const PERSONALITIES: &[(&str, &str)] = &[
+    ("helpful", "Helpful"),
+    ("sassy", "Sassy"),
+    ("technical", "Technical"),
+    ("concise", "Concise"),
+];
+
+/// `profile` with the editor's personality choice and custom prompt applied.
+/// A blank prompt falls back to the personality preset.
+fn with_prompt_fields(profile: &VoiceProfile, personality: Option<&str>, prompt: &str) -> VoiceProfile {
+    let mut profile = profile.clone();
+    if let Some(personality) = personality {
+        profile.personality = personality.to_string();
+    }
+    profile.system_prompt = Some(prompt.trim().to_string()).filter(|p| !p.is_empty());
+    profile
+}
+
+/// Why the profile form can't be saved, if it can't
+fn profile_form_error(name: &str, tts_speed: f64) -> Option<&'static str> {
+    if name.trim().is_empty() {
+        Some("Name can't be empty")
+    } else if !(0.1..=5.0).contains(&tts_speed) {
+        Some("Speech speed must be between 0.1 and 5.0")
+    } else {
+        None
+    }
+}
+
+/// Settings window. Only the General page is editable here; the rest is
+/// reached through the config file.
+pub fn create_settings_dialog(state: Arc<AppState>) -> gtk::Dialog {
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Settings"),
+        None::<&gtk::Window>,
+        gtk::DialogFlags::empty(),
+        &[("Close", gtk::ResponseType::Close)],
+    );
+    dialog.connect_response(|dialog, _| dialog.close());
+
+    let general = gtk::Grid::new();
+    general.set_row_spacing(8);
+    general.set_column_spacing(8);
+    general.set_margin_start(16);
+    general.set_margin_end(16);
+    general.set_margin_top(16);
+    general.set_margin_bottom(16);
+
+    let hotkey_label = gtk::Label::new(Some("Show/hide shortcut"));
+    hotkey_label.set_halign(gtk::Align::Start);
+    let recorder = super::hotkey_recorder::HotkeyRecorder::new(&state.config.read().general.hotkey.to_string());
+    recorder.widget().set_hexpand(true);
+    let state_clone = state.clone();
+    recorder.connect_hotkey_changed(move |hotkey| {
+        if let Err(e) = state_clone.set_hotkey(hotkey) {
+            error!("Failed to save hotkey: {}", e);
+        }
+    });
+    general.attach(&hotkey_label, 0, 0, 1, 1);
+    general.attach(recorder.widget(), 1, 0, 1, 1);
+
+    let hint = gtk::Label::new(Some("The new shortcut is used after Blipply restarts"));
+    hint.add_css_class("dim-label");
+    hint.set_halign(gtk::Align::Start);
+    general.attach(&hint, 0, 1, 2, 1);
+
+    let config_button = gtk::Button::with_label("Edit config file…");
+    config_button.connect_clicked(move |_| {
+        state.send_ui_command(UiCommand::OpenSettings(None));
+    });
+    general.attach(&config_button, 0, 2, 2, 1);
+
+    let notebook = gtk::Notebook::new();
+    notebook.append_page(&general, Some(&gtk::Label::new(Some("General"))));
+    dialog.content_area().append(&notebook);
+
+    dialog
+}
+
+/// Dialog for editing every field of profile `profile_name`, or for creating
+/// a new profile when it is `None`. Saving writes the config and switches to
+/// the profile.
+pub fn create_profile_editor_dialog(state: Arc<AppState>, profile_name: Option<&str>) -> gtk::Dialog {
+    let (profile, id) = {
+        let profiles = state.profiles.read();
+        match profile_name.and_then(|id| profiles.profiles.get(id).map(|p| (p.clone(), id.to_string()))) {
+            Some((profile, id)) => (profile, Some(id)),
+            None => {
+                // New profiles start from the active one
+                let mut base = profiles.active_profile()
+                    .cloned()
+                    .unwrap_or_else(|_| VoiceProfile::new(String::new()));
+                base.name.clear();
+                base.system_prompt = None;
+                (base, None)
+            }
+        }
+    };
+    
+    let title = if id.is_some() { "Edit Profile" } else { "Create New Profile" };
     let dialog = gtk::Dialog::with_buttons(
-        Some("Create New Profile"),
+        Some(title),
         None::<&gtk::Window>,
         gtk::DialogFlags::MODAL,
         &[
             ("Cancel", gtk::ResponseType::Cancel),
-            ("Create", gtk::ResponseType::Accept),
+            ("Save", gtk::ResponseType::Accept),
         ],
     );
     
@@ -160,59 +643,307 @@ fn show_create_profile_dialog(state: Arc<AppState>) {
     grid.set_margin_top(16);
     grid.set_margin_bottom(16);
     
-    // Profile name
-    let name_label = gtk::Label::new(Some("Profile Name:"));
-    name_label.set_halign(gtk::Align::Start);
-    grid.attach(&name_label, 0, 0, 1, 1);
+    let add_row = |row: i32, label: &str, widget: &gtk::Widget| {
+        let label = gtk::Label::new(Some(label));
+        label.set_halign(gtk::Align::Start);
+        label.set_valign(gtk::Align::Start);
+        grid.attach(&label, 0, row, 1, 1);
+        grid.attach(widget, 1, row, 1, 1);
+    };
     
+    // Profile name
     let name_entry = gtk::Entry::new();
     name_entry.set_placeholder_text(Some("My Profile"));
-    grid.attach(&name_entry, 1, 0, 1, 1);
+    name_entry.set_text(&profile.name);
+    add_row(0, "Profile Name:", name_entry.upcast_ref());
+    
+    // Model, keeping the current one even if Ollama doesn't list it
+    let mut models = state.models();
+    if !models.contains(&profile.model) {
+        models.insert(0, profile.model.clone());
+    }
+    let model_combo = create_model_selector(&models, None);
+    model_combo.set_active_id(Some(&profile.model));
+    add_row(1, "Model:", model_combo.upcast_ref());
+    
+    // Personality
+    let personality_combo = gtk::ComboBoxText::new();
+    for (id, label) in PERSONALITIES {
+        personality_combo.append(Some(id), label);
+    }
+    let prompt_files = state.profiles.read().prompts.names();
+    for name in prompt_files.iter().filter(|name| !PERSONALITIES.iter().any(|(id, _)| id == name)) {
+        personality_combo.append(Some(name), name);
+    }
+    if !PERSONALITIES.iter().any(|(id, _)| *id == profile.personality) && !prompt_files.contains(&profile.personality) {
+        personality_combo.append(Some(&profile.personality), &profile.personality);
+    }
+    personality_combo.set_active_id(Some(&profile.personality));
+    add_row(2, "Personality:", personality_combo.upcast_ref());
     
-    // Base profile
-    let base_label = gtk::Label::new(Some("Base Template:"));
-    base_label.set_halign(gtk::Align::Start);
-    grid.attach(&base_label, 0, 1, 1, 1);
+    // Custom system prompt
+    let prompt_view = gtk::TextView::new();
+    prompt_view.set_wrap_mode(gtk::WrapMode::WordChar);
+    prompt_view.buffer().set_text(profile.system_prompt.as_deref().unwrap_or(""));
+    let prompt_scroll = gtk::ScrolledWindow::new();
+    prompt_scroll.set_min_content_height(80);
+    prompt_scroll.set_min_content_width(280);
+    prompt_scroll.set_child(Some(&prompt_view));
+    prompt_scroll.set_tooltip_text(Some("Replaces the personality preset when set"));
+    add_row(3, "System Prompt:", prompt_scroll.upcast_ref());
     
-    let base_combo = gtk::ComboBoxText::new();
-    base_combo.append(Some("default"), "Default");
-    base_combo.append(Some("none"), "From Scratch");
-    base_combo.set_active(Some(0));
-    grid.attach(&base_combo, 1, 1, 1, 1);
+    // What the model will actually be told, kept in sync with the fields above
+    let preview_view = gtk::TextView::new();
+    preview_view.set_editable(false);
+    preview_view.set_cursor_visible(false);
+    preview_view.set_wrap_mode(gtk::WrapMode::WordChar);
+    let preview_scroll = gtk::ScrolledWindow::new();
+    preview_scroll.set_min_content_height(80);
+    preview_scroll.set_min_content_width(280);
+    preview_scroll.set_child(Some(&preview_view));
+    add_row(4, "Effective System Prompt:", preview_scroll.upcast_ref());
+    
+    let char_count = gtk::Label::new(None);
+    char_count.add_css_class("dim-label");
+    char_count.set_halign(gtk::Align::End);
+    grid.attach(&char_count, 1, 5, 1, 1);
+    
+    let update_preview = {
+        let state = state.clone();
+        let profile = profile.clone();
+        let personality_combo = personality_combo.clone();
+        let prompt_buffer = prompt_view.buffer();
+        let preview_buffer = preview_view.buffer();
+        let char_count = char_count.clone();
+        move || {
+            let prompt = prompt_buffer.text(&prompt_buffer.start_iter(), &prompt_buffer.end_iter(), false);
+            let personality = personality_combo.active_id();
+            let draft = with_prompt_fields(&profile, personality.as_deref(), &prompt);
+            let effective = state.profiles.read().get_system_prompt(&draft);
+            preview_buffer.set_text(&effective);
+            char_count.set_text(&format!("{} characters", effective.chars().count()));
+        }
+    };
+    update_preview();
+    let update_preview_clone = update_preview.clone();
+    personality_combo.connect_changed(move |_| update_preview_clone());
+    prompt_view.buffer().connect_changed(move |_| update_preview());
+    
+    // Avatar
+    let avatar_box = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+    let avatar_entry = gtk::Entry::new();
+    avatar_entry.set_text(&profile.avatar_path);
+    avatar_entry.set_hexpand(true);
+    let browse_button = gtk::Button::with_label("Browse…");
+    let avatar_clone = avatar_entry.clone();
+    browse_button.connect_clicked(move |button| {
+        let parent = button.root().and_downcast::<gtk::Window>();
+        let chooser = gtk::FileChooserNative::new(
+            Some("Choose Avatar"),
+            parent.as_ref(),
+            gtk::FileChooserAction::Open,
+            Some("Choose"),
+            Some("Cancel"),
+        );
+        let filter = gtk::FileFilter::new();
+        filter.add_mime_type("image/*");
+        chooser.add_filter(&filter);
+        
+        let entry = avatar_clone.clone();
+        chooser.connect_response(move |chooser, response| {
+            if response == gtk::ResponseType::Accept {
+                if let Some(path) = chooser.file().and_then(|file| file.path()) {
+                    entry.set_text(&path.to_string_lossy());
+                }
+            }
+            chooser.destroy();
+        });
+        chooser.show();
+    });
+    avatar_box.append(&avatar_entry);
+    avatar_box.append(&browse_button);
+    add_row(6, "Avatar:", avatar_box.upcast_ref());
+    
+    // Speech
+    let speed_spin = gtk::SpinButton::with_range(0.1, 5.0, 0.05);
+    speed_spin.set_digits(2);
+    speed_spin.set_value(profile.tts_speed as f64);
+    add_row(7, "Speech Speed:", speed_spin.upcast_ref());
+    
+    let tts_switch = gtk::Switch::new();
+    tts_switch.set_active(profile.tts_enabled);
+    tts_switch.set_halign(gtk::Align::Start);
+    add_row(8, "Speak Replies:", tts_switch.upcast_ref());
+    
+    // Only multi-speaker Piper voices get a speaker choice
+    let speaker_combo = gtk::ComboBoxText::new();
+    let num_speakers = state.voice_speakers(&profile.voice_model);
+    for speaker in 0..num_speakers {
+        speaker_combo.append(Some(&speaker.to_string()), &format!("Speaker {}", speaker));
+    }
+    speaker_combo.set_active_id(Some(&profile.tts_speaker_id.unwrap_or(0).to_string()));
+    if num_speakers > 1 {
+        add_row(9, "Speaker:", speaker_combo.upcast_ref());
+    }
+    
+    let error_label = gtk::Label::new(None);
+    error_label.add_css_class("error");
+    error_label.set_halign(gtk::Align::Start);
+    grid.attach(&error_label, 0, 10, 2, 1);
     
     content.append(&grid);
     
-    let state_clone = state.clone();
+    // Only allow saving a valid form
+    let validate = {
+        let dialog = dialog.clone();
+        let name_entry = name_entry.clone();
+        let speed_spin = speed_spin.clone();
+        let error_label = error_label.clone();
+        move || {
+            let error = profile_form_error(&name_entry.text(), speed_spin.value());
+            error_label.set_text(error.unwrap_or(""));
+            dialog.set_response_sensitive(gtk::ResponseType::Accept, error.is_none());
+        }
+    };
+    validate();
+    let validate_clone = validate.clone();
+    name_entry.connect_changed(move |_| validate_clone());
+    speed_spin.connect_value_changed(move |_| validate());
+    
     dialog.connect_response(move |dialog, response| {
         if response == gtk::ResponseType::Accept {
-            let name = name_entry.text().to_string();
-            let base = base_combo.active_id()
-                .and_then(|id| if id == "none" { None } else { Some(id.to_string()) });
+            let buffer = prompt_view.buffer();
+            let prompt = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
             
-            // Create profile via state
-            // In a real implementation, this would update the ProfileManager
+            let personality = personality_combo.active_id();
+            let mut profile = with_prompt_fields(&profile, personality.as_deref(), &prompt);
+            profile.name = name_entry.text().trim().to_string();
+            if let Some(model) = model_combo.active_id() {
+                profile.model = model.to_string();
+            }
+            profile.avatar_path = avatar_entry.text().to_string();
+            profile.tts_speed = speed_spin.value() as f32;
+            profile.tts_enabled = tts_switch.is_active();
+            if num_speakers > 1 {
+                profile.tts_speaker_id = speaker_combo.active_id().and_then(|id| id.parse().ok());
+            }
             
-            dialog.close();
-        } else {
-            dialog.close();
+            match state.save_profile(id.as_deref(), profile) {
+                Ok(saved_id) => {
+                    if let Err(e) = state.switch_profile(&saved_id) {
+                        error!("Failed to switch profile: {}", e);
+                    }
+                }
+                Err(e) => {
+                    // Keep the dialog open so the edits aren't lost
+                    error!("Failed to save profile: {}", e);
+                    error_label.set_text(&format!("Couldn't save: {}", e));
+                    return;
+                }
+            }
         }
+        dialog.close();
     });
     
-    dialog.present();
+    dialog
 }
 
-fn append_message(buffer: &gtk::TextBuffer, message: &crate::ollama::Message) {
-    let mut end_iter = buffer.end_iter();
-    
-    buffer.insert(&mut end_iter, "\n");
-    
-    let role_text = match message.role.as_str() {
-        "user" => "You: ",
-        "assistant" => "Assistant: ",
-        _ => "",
-    };
-    
-    buffer.insert(&mut end_iter, role_text);
-    buffer.insert(&mut end_iter, &message.content);
-    buffer.insert(&mut end_iter, "\n");
+/// Dropdown of installed models that stays current while open. With a
+/// `default_label`, the first item has an empty id and stands for the
+/// profile's model.
+fn create_model_selector(models: &[String], default_label: Option<&'static str>) -> gtk::ComboBoxText {
+    let combo = gtk::ComboBoxText::new();
+    set_model_items(&combo, models, default_label);
+    combo.set_active(Some(0));
+
+    MODEL_COMBOS.with(|combos| {
+        let mut combos = combos.borrow_mut();
+        combos.retain(|(weak, _)| weak.upgrade().is_some());
+        combos.push((combo.downgrade(), default_label));
+    });
+    combo
+}
+
+/// Replace the items of every open model dropdown, keeping the selection
+pub fn refresh_model_selectors(models: &[String]) {
+    MODEL_COMBOS.with(|combos| {
+        combos.borrow_mut().retain(|(weak, default_label)| match weak.upgrade() {
+            Some(combo) => {
+                let active = combo.active_id();
+                set_model_items(&combo, models, *default_label);
+                let restored = active.is_some_and(|id| combo.set_active_id(Some(&id)));
+                if !restored {
+                    combo.set_active(Some(0));
+                }
+                true
+            }
+            None => false,
+        });
+    });
+}
+
+fn set_model_items(combo: &gtk::ComboBoxText, models: &[String], default_label: Option<&str>) {
+    combo.remove_all();
+    if let Some(label) = default_label {
+        combo.append(Some(""), label);
+    }
+    for model in models {
+        combo.append(Some(model), model);
+    }
+}
+
+fn append_message(buffer: &gtk::TextBuffer, message: &crate::ollama::Message, assistant_name: &str) {
+    super::window::append_message_to_buffer(buffer, message, assistant_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrolled_to_bottom() {
+        assert!(is_scrolled_to_bottom(700.0, 300.0, 1000.0));
+        assert!(is_scrolled_to_bottom(698.0, 300.0, 1000.0));
+        assert!(!is_scrolled_to_bottom(400.0, 300.0, 1000.0));
+        // Nothing to scroll yet
+        assert!(is_scrolled_to_bottom(0.0, 300.0, 120.0));
+    }
+
+    #[test]
+    fn test_drag_payload_utf8() {
+        let payloads = drag_payloads("héllo 👋");
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].0, "text/plain;charset=utf-8");
+        assert_eq!(payloads[0].1, "héllo 👋".as_bytes());
+    }
+
+    #[test]
+    fn test_drag_payload_uri_list() {
+        let payloads = drag_payloads("See https://nixos.org/manual.");
+        assert_eq!(payloads[1].0, "text/uri-list");
+        assert_eq!(payloads[1].1, b"https://nixos.org/manual\r\n");
+    }
+
+    #[test]
+    fn test_prompt_preview_follows_personality() {
+        let manager = crate::profiles::ProfileManager::from_config(&crate::config::Config::default());
+        let profile = VoiceProfile::new("Work".to_string());
+
+        let helpful = manager.get_system_prompt(&with_prompt_fields(&profile, Some("helpful"), "  "));
+        assert!(helpful.starts_with("You are Blipply"));
+        let custom = manager.get_system_prompt(&with_prompt_fields(&profile, Some("custom"), ""));
+        assert_ne!(custom, helpful);
+        assert_eq!(custom, "You are a helpful desktop assistant.");
+        // A written prompt wins over any personality
+        let written = manager.get_system_prompt(&with_prompt_fields(&profile, Some("custom"), " Be brief. "));
+        assert_eq!(written, "Be brief.");
+    }
+
+    #[test]
+    fn test_profile_form_validation() {
+        assert_eq!(profile_form_error("Work", 1.0), None);
+        assert!(profile_form_error("  ", 1.0).is_some());
+        assert!(profile_form_error("Work", 0.05).is_some());
+        assert!(profile_form_error("Work", 5.5).is_some());
+    }
 }
@@ -5,12 +5,23 @@
 use anyhow::Result;
 use gtk::prelude::*;
 use gtk4_layer_shell::{Edge, Layer, LayerShell};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, error};
 
-use crate::state::{AppState, UiCommand};
+use crate::accessibility::AnnouncementPriority;
+use crate::config::InteractionMode;
+use crate::state::{coalesce_ui_commands, AppState, UiCommand};
 use crate::ollama::Message;
-use super::widgets::{create_avatar, create_chat_view, create_input_box, create_profile_selector};
+use crate::profiles::VoiceProfile;
+use super::widgets::{
+    create_avatar, create_chat_view, create_history_panel, create_input_box, create_profile_selector,
+    set_talk_button_visible,
+};
+use super::spectrogram::Spectrogram;
 
 pub fn create_window(state: Arc<AppState>) -> Result<gtk::Window> {
     let window = gtk::Window::new();
@@ -31,7 +42,8 @@ pub fn create_window(state: Arc<AppState>) -> Result<gtk::Window> {
     window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::OnDemand);
     
     // Set size
-    window.set_default_size(400, 600);
+    let (width, height) = state.profiles.read().active_profile().map_or(DEFAULT_WINDOW_SIZE, window_size);
+    window.set_default_size(width, height);
     window.set_title(Some("Blipply Assistant"));
     
     // Create main layout
@@ -57,6 +69,26 @@ pub fn create_window(state: Arc<AppState>) -> Result<gtk::Window> {
     let profile_selector = create_profile_selector(state.clone());
     header_box.append(&profile_selector);
     
+    // Settings
+    let settings_button = gtk::Button::with_label("⚙");
+    settings_button.set_tooltip_text(Some("Settings"));
+    let state_clone = state.clone();
+    settings_button.connect_clicked(move |_| {
+        state_clone.send_ui_command(UiCommand::OpenSettings(Some("general".to_string())));
+    });
+    header_box.append(&settings_button);
+    
+    // Saved conversations
+    let history_button = gtk::ToggleButton::with_label("🕘");
+    history_button.set_tooltip_text(Some("Past conversations"));
+    header_box.append(&history_button);
+    
+    // Collapse the chat to keep the window small
+    let chat_open = state.config.read().general.chat_panel_open;
+    let chat_toggle = gtk::ToggleButton::with_label(chat_toggle_label(chat_open));
+    chat_toggle.set_active(chat_open);
+    header_box.append(&chat_toggle);
+    
     // Close button
     let close_button = gtk::Button::with_label("✕");
     close_button.add_css_class("circular");
@@ -70,102 +102,681 @@ pub fn create_window(state: Arc<AppState>) -> Result<gtk::Window> {
     
     // Chat view
     let (chat_scroll, chat_buffer) = create_chat_view();
-    main_box.append(&chat_scroll);
+    chat_scroll.set_hexpand(true);
+    let history_panel = create_history_panel(state.clone());
+    let panel_clone = history_panel.clone();
+    history_button.connect_toggled(move |button| {
+        panel_clone.set_reveal_child(button.is_active());
+    });
+    let chat_revealer = gtk::Revealer::new();
+    chat_revealer.set_transition_type(gtk::RevealerTransitionType::SlideDown);
+    chat_revealer.set_child(Some(&chat_scroll));
+    chat_revealer.set_reveal_child(chat_open);
+    chat_revealer.set_hexpand(true);
+    let revealer_clone = chat_revealer.clone();
+    let state_clone = state.clone();
+    chat_toggle.connect_toggled(move |button| {
+        let open = button.is_active();
+        button.set_label(chat_toggle_label(open));
+        revealer_clone.set_reveal_child(open);
+        if let Err(e) = state_clone.set_chat_panel_open(open) {
+            error!("Failed to save chat panel state: {}", e);
+        }
+    });
+    let content_box = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+    content_box.append(&history_panel);
+    content_box.append(&chat_revealer);
+    main_box.append(&content_box);
     
     // Input box
-    let input_box = create_input_box(state.clone(), chat_buffer.clone());
+    let (input_box, message_input) = create_input_box(state.clone(), chat_buffer.clone());
     main_box.append(&input_box);
     
+    // Right-click menu of the chat
+    if let Some(chat_view) = chat_scroll.child().and_downcast::<gtk::TextView>() {
+        let print_state = state.clone();
+        let edit_state = state.clone();
+        add_chat_menu(&chat_view, vec![
+            ("Print Conversation", "print", Box::new(move || print_state.send_ui_command(UiCommand::Print))),
+            ("Edit Last Message", "edit-last", Box::new(move || {
+                // Sending it from the input box replaces the old one
+                match edit_state.start_editing_last() {
+                    Some(text) => {
+                        message_input.set_text(&text);
+                        message_input.grab_focus();
+                    }
+                    None => edit_state.send_ui_command(UiCommand::ShowToast("No message to edit".to_string())),
+                }
+            })),
+        ]);
+    }
+    
+    // Stop a response while it streams in
+    let stop_button = gtk::Button::with_label("⏹ Stop");
+    stop_button.add_css_class("destructive-action");
+    stop_button.set_visible(false);
+    let state_clone = state.clone();
+    stop_button.connect_clicked(move |_| {
+        state_clone.send_ui_command(UiCommand::CancelResponse);
+    });
+    input_box.append(&stop_button);
+    
+    // Microphone spectrogram
+    let spectrogram = state.config.read().audio.show_spectrogram.then(Spectrogram::new);
+    if let Some(spectrogram) = &spectrogram {
+        main_box.append(spectrogram.widget());
+    }
+    
     // Status indicators
     let status_box = gtk::Box::new(gtk::Orientation::Horizontal, 8);
     let listening_indicator = gtk::Label::new(None);
     listening_indicator.set_visible(false);
     let speaking_indicator = gtk::Label::new(None);
     speaking_indicator.set_visible(false);
+    let language_indicator = gtk::Label::new(None);
+    language_indicator.add_css_class("detected-language");
+    language_indicator.set_tooltip_text(Some("Detected speech language"));
+    language_indicator.set_visible(false);
     status_box.append(&listening_indicator);
+    status_box.append(&language_indicator);
     status_box.append(&speaking_indicator);
     main_box.append(&status_box);
     
+    // Models Ollama holds in memory, hidden while none are loaded
+    let loaded_models_list = gtk::Box::new(gtk::Orientation::Vertical, 4);
+    let loaded_models_popover = gtk::Popover::new();
+    loaded_models_popover.set_child(Some(&loaded_models_list));
+    let loaded_models_button = gtk::MenuButton::new();
+    loaded_models_button.set_label("Loaded Models");
+    loaded_models_button.set_popover(Some(&loaded_models_popover));
+    loaded_models_button.set_visible(false);
+    status_box.append(&loaded_models_button);
+    
+    let toast = gtk::Label::new(None);
+    toast.add_css_class("toast");
+    toast.set_visible(false);
+    status_box.append(&toast);
+    
+    // Always-visible one-line status in HUD mode
+    let persistent_hud = state.config.read().general.persistent_hud;
+    let hud_status = persistent_hud.then(|| {
+        let label = gtk::Label::new(Some(Activity::default().status_text()));
+        label.set_halign(gtk::Align::Start);
+        label.add_css_class("hud-status");
+        status_box.prepend(&label);
+        label
+    });
+    
+    // Running word count while a reply streams in
+    let progress_label = state.config.read().general.show_generation_progress.then(|| {
+        let label = gtk::Label::new(None);
+        label.add_css_class("generation-progress");
+        label.set_visible(false);
+        status_box.append(&label);
+        label
+    });
+    
     window.set_child(Some(&main_box));
     
+    // Undo/redo chat history changes, except while typing, where the
+    // input keeps its own text undo
+    let shortcuts = gtk::ShortcutController::new();
+    shortcuts.set_propagation_phase(gtk::PropagationPhase::Capture);
+    for (accelerator, command) in [
+        ("<Control>z", UiCommand::Undo),
+        ("<Control><Shift>z", UiCommand::Redo),
+    ] {
+        let state = state.clone();
+        let action = gtk::CallbackAction::new(move |widget, _| {
+            if editing_text(widget) {
+                return glib::Propagation::Proceed;
+            }
+            state.send_ui_command(command.clone());
+            glib::Propagation::Stop
+        });
+        shortcuts.add_shortcut(gtk::Shortcut::new(
+            gtk::ShortcutTrigger::parse_string(accelerator),
+            Some(action),
+        ));
+    }
+    // Ctrl+C stops a streaming response, and copies as usual otherwise
+    let state_clone = state.clone();
+    let cancel = gtk::CallbackAction::new(move |_, _| {
+        if !state_clone.is_generating() {
+            return glib::Propagation::Proceed;
+        }
+        state_clone.send_ui_command(UiCommand::CancelResponse);
+        glib::Propagation::Stop
+    });
+    shortcuts.add_shortcut(gtk::Shortcut::new(
+        gtk::ShortcutTrigger::parse_string("<Control>c"),
+        Some(cancel),
+    ));
+    window.add_controller(shortcuts);
+
+    // Touch gestures
+    if state.config.read().general.swipe_gestures_enabled {
+        super::gestures::attach(&window, state.clone());
+    }
+    
     // Handle UI commands
     let mut ui_rx = state.take_ui_receiver().expect("UI receiver already taken");
     let window_clone = window.clone();
     let buffer_clone = chat_buffer.clone();
     let listening_clone = listening_indicator.clone();
+    let language_clone = language_indicator.clone();
     let speaking_clone = speaking_indicator.clone();
+    let loaded_models_button_clone = loaded_models_button.clone();
+    let command_state = state.clone();
+    let input_clone = input_box.clone();
+    let stop_clone = stop_button.clone();
+    let chat_toggle_clone = chat_toggle.clone();
     
-    glib::spawn_future_local(async move {
-        while let Some(cmd) = ui_rx.recv().await {
-            match cmd {
-                UiCommand::Show => {
-                    debug!("Showing window");
-                    window_clone.present();
+    // Draws a streamed chunk and counts it towards the progress label
+    let progress = Rc::new(RefCell::new(GenerationProgress::default()));
+    let draw_chunk: Rc<dyn Fn(&str, usize)> = {
+        let buffer = chat_buffer.clone();
+        let progress = progress.clone();
+        let progress_label = progress_label.clone();
+        Rc::new(move |chunk: &str, chunks: usize| {
+            append_chunk_to_buffer(&buffer, chunk);
+            if let Some(label) = &progress_label {
+                let mut progress = progress.borrow_mut();
+                if progress.push(chunk, chunks, Instant::now()) {
+                    label.set_text(&progress.status_text());
+                    label.set_visible(true);
                 }
-                UiCommand::Hide => {
-                    debug!("Hiding window");
-                    window_clone.hide();
+            }
+        })
+    };
+    let paced = Rc::new(RefCell::new(PacedChunks::default()));
+
+    glib::spawn_future_local(async move {
+        let mut activity = Activity::default();
+        let mut toast_serial = 0u32;
+        let toast_shown = std::rc::Rc::new(std::cell::Cell::new(0u32));
+        while let Some(first) = ui_rx.recv().await {
+            // Handle everything already queued in one go, merging streamed text
+            let mut batch = vec![first];
+            while batch.len() < MAX_UI_BATCH {
+                match ui_rx.try_recv() {
+                    Ok(cmd) => batch.push(cmd),
+                    Err(_) => break,
                 }
-                UiCommand::Toggle => {
-                    if window_clone.is_visible() {
-                        window_clone.hide();
-                    } else {
+            }
+
+            // Paced chunks are drawn one at a time, so keep them apart
+            let pacing = command_state.config.read().general.stream_pacing_ms
+                .filter(|ms| *ms > 0)
+                .map(|ms| Duration::from_millis(ms.into()));
+            let (scrollback_limit, message_limit) = {
+                let config = command_state.config.read();
+                (config.general.chat_scrollback_limit, config.general.display_history_limit)
+            };
+
+            for cmd in coalesce_ui_commands(batch, pacing.is_none()) {
+                match cmd {
+                    UiCommand::Show => {
+                        debug!("Showing window");
                         window_clone.present();
                     }
-                }
-                UiCommand::AppendMessage(msg) => {
-                    append_message_to_buffer(&buffer_clone, &msg);
-                }
-                UiCommand::StreamChunk(chunk) => {
-                    append_chunk_to_buffer(&buffer_clone, &chunk);
-                }
-                UiCommand::SetListening(listening) => {
-                    if listening {
-                        listening_clone.set_text("🎤 Listening...");
-                        listening_clone.add_css_class("listening");
-                    } else {
-                        listening_clone.set_text("");
-                        listening_clone.remove_css_class("listening");
-                    }
-                    listening_clone.set_visible(listening);
-                }
-                UiCommand::SetSpeaking(speaking) => {
-                    if speaking {
-                        speaking_clone.set_text("🔊 Speaking...");
-                        speaking_clone.add_css_class("speaking");
-                    } else {
-                        speaking_clone.set_text("");
-                        speaking_clone.remove_css_class("speaking");
-                    }
-                    speaking_clone.set_visible(speaking);
-                }
-                UiCommand::SwitchProfile(profile_name) => {
-                    debug!("Switched to profile: {}", profile_name);
-                    // Update avatar and other UI elements
-                }
-                UiCommand::UpdateAvatar(path) => {
-                    debug!("Update avatar: {}", path);
+                    UiCommand::Hide => {
+                        debug!("Hiding window");
+                        window_clone.hide();
+                    }
+                    UiCommand::Toggle => {
+                        if window_clone.is_visible() {
+                            window_clone.hide();
+                        } else {
+                            window_clone.present();
+                        }
+                    }
+                    UiCommand::AppendMessage(msg) => {
+                        PacedChunks::flush(&paced, draw_chunk.as_ref());
+                        // New messages open a collapsed chat, which stays open
+                        // next time too
+                        if !chat_toggle_clone.is_active() {
+                            chat_toggle_clone.set_active(true);
+                        }
+                        let assistant_name = command_state.assistant_name();
+                        append_message_to_buffer(&buffer_clone, &msg, &assistant_name);
+                        match msg.role.as_str() {
+                            "assistant" => command_state.announce(
+                                &format!("{}: {}", assistant_name, msg.content),
+                                AnnouncementPriority::Medium,
+                            ),
+                            "system" => command_state.announce(&msg.content, AnnouncementPriority::High),
+                            _ => {}
+                        }
+                    }
+                    UiCommand::SetHistory(messages) => {
+                        PacedChunks::clear(&paced);
+                        buffer_clone.set_text("");
+                        let assistant_name = command_state.assistant_name();
+                        for msg in &messages {
+                            append_message_to_buffer(&buffer_clone, msg, &assistant_name);
+                        }
+                    }
+                    UiCommand::StreamChunk { text, chunks } => match pacing {
+                        Some(pacing) => PacedChunks::push(&paced, text, chunks, pacing, draw_chunk.clone()),
+                        None => draw_chunk(&text, chunks),
+                    },
+                    UiCommand::SetListening(listening) => {
+                        if listening {
+                            listening_clone.set_text("🎤 Listening...");
+                            listening_clone.add_css_class("listening");
+                        } else {
+                            listening_clone.set_text("");
+                            listening_clone.remove_css_class("listening");
+                        }
+                        listening_clone.set_visible(listening);
+                        activity.listening = listening;
+                        update_hud_status(hud_status.as_ref(), activity);
+                    }
+                    UiCommand::SetLanguage(language) => {
+                        language_clone.set_text(&language.to_uppercase());
+                        language_clone.set_visible(true);
+                    }
+                    UiCommand::SetTranscribing(transcribing) => {
+                        // Shares the listening label, which SpeechEnd just cleared
+                        if transcribing {
+                            let queued = command_state.transcription_queue_depth().unwrap_or(0);
+                            listening_clone.set_text(&transcribing_text(queued));
+                            listening_clone.add_css_class("transcribing");
+                        } else {
+                            listening_clone.set_text("");
+                            listening_clone.remove_css_class("transcribing");
+                        }
+                        listening_clone.set_visible(transcribing);
+                        activity.transcribing = transcribing;
+                        update_hud_status(hud_status.as_ref(), activity);
+                    }
+                    UiCommand::SetThinking(thinking) => {
+                        // The reply is complete; show whatever is still paced
+                        PacedChunks::flush(&paced, draw_chunk.as_ref());
+                        activity.thinking = thinking;
+                        *progress.borrow_mut() = GenerationProgress::default();
+                        if let Some(label) = &progress_label {
+                            label.set_visible(false);
+                        }
+                        update_hud_status(hud_status.as_ref(), activity);
+                    }
+                    UiCommand::SetStopButtonVisible(visible) => {
+                        stop_clone.set_visible(visible);
+                    }
+                    UiCommand::CancelResponse => {
+                        PacedChunks::flush(&paced, draw_chunk.as_ref());
+                        command_state.cancel_response();
+                    }
+                    UiCommand::SetSpeaking(speaking) => {
+                        if speaking {
+                            speaking_clone.set_text("🔊 Speaking...");
+                            speaking_clone.add_css_class("speaking");
+                        } else {
+                            speaking_clone.set_text("");
+                            speaking_clone.remove_css_class("speaking");
+                        }
+                        speaking_clone.set_visible(speaking);
+                        activity.speaking = speaking;
+                        update_hud_status(hud_status.as_ref(), activity);
+                    }
+                    UiCommand::SpectrogramFrame(bands) => {
+                        if let Some(spectrogram) = &spectrogram {
+                            spectrogram.push_frame(&bands);
+                        }
+                    }
+                    UiCommand::JsonParseError(error) => {
+                        PacedChunks::flush(&paced, draw_chunk.as_ref());
+                        let msg = Message::system(format!("Response is not valid JSON: {}", error));
+                        append_message_to_buffer(&buffer_clone, &msg, &command_state.assistant_name());
+                        command_state.announce(&msg.content, AnnouncementPriority::High);
+                    }
+                    UiCommand::AskAboutClipboard => {
+                        let clipboard = window_clone.clipboard();
+                        let text = clipboard.read_text_future().await.ok().flatten();
+                        let msg = match text.as_deref().map(str::trim) {
+                            Some(text) if !text.is_empty() => {
+                                let chars = command_state.set_clipboard_context(text);
+                                Message::system(format!("📋 Clipboard attached ({} characters). Ask your question.", chars))
+                            }
+                            _ => Message::system("The clipboard has no text to ask about"),
+                        };
+                        append_message_to_buffer(&buffer_clone, &msg, &command_state.assistant_name());
+                        if let Some(entry) = input_clone.first_child() {
+                            entry.grab_focus();
+                        }
+                    }
+                    UiCommand::OpenSettings(section) => {
+                        debug!("Opening settings {:?}", section);
+                        match section.as_deref() {
+                            Some("general") => {
+                                super::widgets::create_settings_dialog(command_state.clone()).present();
+                            }
+                            _ => {
+                                if let Err(e) = open_config_file() {
+                                    error!("Failed to open settings: {}", e);
+                                }
+                            }
+                        }
+                    }
+                    UiCommand::Print => {
+                        super::print::print_conversation(
+                            Some(&window_clone),
+                            command_state.chat_messages(),
+                            command_state.assistant_name(),
+                        );
+                    }
+                    UiCommand::ModelsUpdated(models) => {
+                        super::widgets::refresh_model_selectors(&models);
+                    }
+                    UiCommand::ShowToast(text) => {
+                        toast.set_text(&text);
+                        toast.set_visible(true);
+                        command_state.announce(&text, AnnouncementPriority::Low);
+                        // Only the newest toast's timer hides it
+                        toast_serial += 1;
+                        toast_shown.set(toast_serial);
+                        let serial = toast_serial;
+                        let toast = toast.clone();
+                        let shown = toast_shown.clone();
+                        glib::timeout_add_local_once(TOAST_DURATION, move || {
+                            if shown.get() == serial {
+                                toast.set_visible(false);
+                            }
+                        });
+                    }
+                    UiCommand::LoadedModelsUpdated(models) => {
+                        while let Some(child) = loaded_models_list.first_child() {
+                            loaded_models_list.remove(&child);
+                        }
+                        for model in &models {
+                            let label = gtk::Label::new(Some(&model.label()));
+                            label.set_halign(gtk::Align::Start);
+                            loaded_models_list.append(&label);
+                        }
+                        loaded_models_button_clone.set_visible(!models.is_empty());
+                    }
+                    UiCommand::Undo => {
+                        if !command_state.undo_history() {
+                            debug!("Nothing to undo");
+                        }
+                    }
+                    UiCommand::Redo => {
+                        if !command_state.redo_history() {
+                            debug!("Nothing to redo");
+                        }
+                    }
+                    UiCommand::SwitchProfile(profile_name) => {
+                        debug!("Switched to profile: {}", profile_name);
+                        // Update avatar and other UI elements
+                        let size = command_state.profiles.read().active_profile().map(window_size);
+                        if let Ok(size) = size {
+                            resize_window(&window_clone, size);
+                        }
+                        let push_to_talk = command_state.interaction_mode() == InteractionMode::VoicePtt;
+                        set_talk_button_visible(&input_clone, push_to_talk);
+                    }
+                    UiCommand::UpdateAvatar(path) => {
+                        debug!("Update avatar: {}", path);
+                    }
                 }
             }
+            trim_scrollback(&buffer_clone, scrollback_limit, message_limit);
         }
     });
     
+    if persistent_hud {
+        state.toggle_visibility();
+    }
+    
     Ok(window)
 }
 
-fn append_message_to_buffer(buffer: &gtk::TextBuffer, message: &Message) {
+/// A right-click menu item: label, action name and what it does
+type ChatMenuItem = (&'static str, &'static str, Box<dyn Fn()>);
+
+/// Add `items` to the right-click menu of the chat view
+fn add_chat_menu(view: &gtk::TextView, items: Vec<ChatMenuItem>) {
+    let actions = gtk::gio::SimpleActionGroup::new();
+    let menu = gtk::gio::Menu::new();
+    for (label, name, activate) in items {
+        let action = gtk::gio::SimpleAction::new(name, None);
+        action.connect_activate(move |_, _| activate());
+        actions.add_action(&action);
+        menu.append(Some(label), Some(&format!("chat.{}", name)));
+    }
+    view.insert_action_group("chat", Some(&actions));
+    view.set_extra_menu(Some(&menu));
+}
+
+/// Whether keyboard focus is in an editable text field of `widget`'s window
+fn editing_text(widget: &gtk::Widget) -> bool {
+    let Some(focus) = widget.root().and_then(|root| root.focus()) else {
+        return false;
+    };
+    match focus.downcast_ref::<gtk::TextView>() {
+        Some(view) => view.is_editable(),
+        None => focus.is::<gtk::Text>(),
+    }
+}
+
+fn chat_toggle_label(open: bool) -> &'static str {
+    if open {
+        "Chat History ▲"
+    } else {
+        "Chat History ▼"
+    }
+}
+
+/// What the assistant is doing, for the HUD status line
+#[derive(Debug, Default, Clone, Copy)]
+struct Activity {
+    listening: bool,
+    transcribing: bool,
+    thinking: bool,
+    speaking: bool,
+}
+
+impl Activity {
+    fn status_text(self) -> &'static str {
+        if self.speaking {
+            "🔊 Speaking"
+        } else if self.thinking {
+            "💭 Thinking"
+        } else if self.transcribing {
+            "⏳ Transcribing"
+        } else if self.listening {
+            "🎤 Listening"
+        } else {
+            "💤 Idle"
+        }
+    }
+}
+
+/// Listening label while Whisper runs, with the utterances waiting behind it
+fn transcribing_text(queued: usize) -> String {
+    match queued {
+        0 => "⏳ Transcribing…".to_string(),
+        n => format!("⏳ Transcribing… ({} more queued)", n),
+    }
+}
+
+/// Window size for profiles that don't set their own
+const DEFAULT_WINDOW_SIZE: (i32, i32) = (400, 600);
+/// Smallest window size a profile can ask for
+const MIN_WINDOW_SIZE: (i32, i32) = (240, 320);
+/// Space kept between the window and the screen edges it is anchored to
+const WINDOW_MARGINS: (i32, i32) = (16, 32);
+
+/// The window size `profile` asks for, clamped to the minimum
+fn window_size(profile: &VoiceProfile) -> (i32, i32) {
+    let width = profile.window_width.map_or(DEFAULT_WINDOW_SIZE.0, |w| w.min(i32::MAX as u32) as i32);
+    let height = profile.window_height.map_or(DEFAULT_WINDOW_SIZE.1, |h| h.min(i32::MAX as u32) as i32);
+    (width.max(MIN_WINDOW_SIZE.0), height.max(MIN_WINDOW_SIZE.1))
+}
+
+/// Resize the layer-shell window, keeping it on its monitor inside the
+/// margins it is anchored with
+fn resize_window(window: &gtk::Window, (width, height): (i32, i32)) {
+    let monitor = window.surface().and_then(|surface| window.display().monitor_at_surface(&surface));
+    let (width, height) = match monitor {
+        Some(monitor) => {
+            let area = monitor.geometry();
+            (
+                width.min(area.width() - WINDOW_MARGINS.0).max(MIN_WINDOW_SIZE.0),
+                height.min(area.height() - WINDOW_MARGINS.1).max(MIN_WINDOW_SIZE.1),
+            )
+        }
+        None => (width, height),
+    };
+    window.set_default_size(width, height);
+}
+
+/// Most queued UI commands handled before yielding back to GTK
+const MAX_UI_BATCH: usize = 256;
+
+/// How long a toast stays in the status bar
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// Shortest time between progress label updates, to keep redraws cheap
+const PROGRESS_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Words and tokens streamed so far in the current reply
+#[derive(Debug, Default)]
+struct GenerationProgress {
+    words: usize,
+    /// Ollama streams roughly one token per chunk; merged chunks carry
+    /// how many they hold
+    tokens: usize,
+    /// Whether the last chunk ended inside a word
+    in_word: bool,
+    last_update: Option<Instant>,
+}
+
+impl GenerationProgress {
+    /// Count `chunk`, which merges `chunks` streamed chunks. Returns true
+    /// when the label is due for an update.
+    fn push(&mut self, chunk: &str, chunks: usize, now: Instant) -> bool {
+        self.tokens += chunks;
+        for c in chunk.chars() {
+            let word_char = !c.is_whitespace();
+            if word_char && !self.in_word {
+                self.words += 1;
+            }
+            self.in_word = word_char;
+        }
+
+        let due = self.last_update
+            .map_or(true, |last| now.duration_since(last) >= PROGRESS_UPDATE_INTERVAL);
+        if due {
+            self.last_update = Some(now);
+        }
+        due
+    }
+
+    fn status_text(&self) -> String {
+        format!("{} words · {} tokens", self.words, self.tokens)
+    }
+}
+
+/// Streamed chunks waiting to be drawn one per `stream_pacing_ms` tick.
+/// They are drawn by a timer, not the command loop, so other commands don't
+/// wait behind them.
+#[derive(Default)]
+struct PacedChunks {
+    /// Text with the number of model chunks it holds
+    pending: VecDeque<(String, usize)>,
+    timer: Option<glib::SourceId>,
+}
+
+impl PacedChunks {
+    /// Queue `chunk`, starting the timer that draws queued chunks if it isn't running
+    fn push(paced: &Rc<RefCell<Self>>, chunk: String, chunks: usize, pacing: Duration, draw: Rc<dyn Fn(&str, usize)>) {
+        let mut this = paced.borrow_mut();
+        this.pending.push_back((chunk, chunks));
+        if this.timer.is_some() {
+            return;
+        }
+
+        let weak = Rc::downgrade(paced);
+        this.timer = Some(glib::timeout_add_local(pacing, move || {
+            let Some(paced) = weak.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            let next = paced.borrow_mut().pending.pop_front();
+            match next {
+                Some((chunk, chunks)) => {
+                    draw(&chunk, chunks);
+                    glib::ControlFlow::Continue
+                }
+                None => {
+                    paced.borrow_mut().timer = None;
+                    glib::ControlFlow::Break
+                }
+            }
+        }));
+    }
+
+    /// Draw everything still queued at once and stop the timer
+    fn flush(paced: &RefCell<Self>, draw: &dyn Fn(&str, usize)) {
+        let pending = Self::clear(paced);
+        for (chunk, chunks) in &pending {
+            draw(chunk, *chunks);
+        }
+    }
+
+    /// Stop the timer, returning the chunks it hadn't drawn yet
+    fn clear(paced: &RefCell<Self>) -> VecDeque<(String, usize)> {
+        let mut this = paced.borrow_mut();
+        if let Some(timer) = this.timer.take() {
+            timer.remove();
+        }
+        std::mem::take(&mut this.pending)
+    }
+}
+
+fn update_hud_status(label: Option<&gtk::Label>, activity: Activity) {
+    if let Some(label) = label {
+        label.set_text(activity.status_text());
+    }
+}
+
+/// Open config.toml in the user's default editor
+fn open_config_file() -> Result<()> {
+    let uri = gtk::gio::File::for_path(crate::config::Config::config_path()?).uri();
+    gtk::gio::AppInfo::launch_default_for_uri(&uri, None::<&gtk::gio::AppLaunchContext>)?;
+    Ok(())
+}
+
+/// Append `message` under its role label; assistant replies are labelled
+/// with `assistant_name`
+pub(super) fn append_message_to_buffer(buffer: &gtk::TextBuffer, message: &Message, assistant_name: &str) {
     let mut end_iter = buffer.end_iter();
     
     // Add role label
     let role_text = match message.role.as_str() {
-        "user" => "You: ",
-        "assistant" => "Assistant: ",
-        "system" => "System: ",
-        _ => "Unknown: ",
+        "user" => "You: ".to_string(),
+        "assistant" => format!("{}: ", assistant_name),
+        "system" => "System: ".to_string(),
+        _ => "Unknown: ".to_string(),
     };
     
     buffer.insert(&mut end_iter, "\n");
-    buffer.insert(&mut end_iter, role_text);
+    
+    // Small grey time before the role label
+    if let Some(timestamp) = message.timestamp {
+        if buffer.tag_table().lookup("timestamp").is_none() {
+            let tag = gtk::TextTag::new(Some("timestamp"));
+            tag.set_foreground(Some("#888888"));
+            tag.set_scale(0.8);
+            buffer.tag_table().add(&tag);
+        }
+        
+        let start = end_iter.offset();
+        let label = timestamp_label(&timestamp.with_timezone(&chrono::Local), &chrono::Local::now());
+        buffer.insert(&mut end_iter, &format!("{} ", label));
+        buffer.apply_tag_by_name("timestamp", &buffer.iter_at_offset(start), &end_iter);
+    }
     
     // Create tag for role
     let tag_name = format!("{}-role", message.role);
@@ -184,19 +795,23 @@ fn append_message_to_buffer(buffer: &gtk::TextBuffer, message: &Message) {
     }
     
     let start = end_iter;
-    buffer.insert(&mut end_iter, role_text);
+    buffer.insert(&mut end_iter, &role_text);
     buffer.apply_tag_by_name(&tag_name, &start, &end_iter);
     
     // Add message content
     buffer.insert(&mut end_iter, &message.content);
     buffer.insert(&mut end_iter, "\n");
-    
-    // Auto-scroll to bottom
-    if let Some(mark) = buffer.get_insert() {
-        if let Some(view) = buffer.get_property("view") {
-            // This would need a reference to the TextView
-            // For now, we'll just insert the text
-        }
+}
+
+/// Time of a message, with the date too if it wasn't today
+fn timestamp_label<Tz: chrono::TimeZone>(timestamp: &chrono::DateTime<Tz>, now: &chrono::DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    if timestamp.date_naive() == now.date_naive() {
+        timestamp.format("%H:%M").to_string()
+    } else {
+        timestamp.format("%b %-d %H:%M").to_string()
     }
 }
 
@@ -205,6 +820,75 @@ fn append_chunk_to_buffer(buffer: &gtk::TextBuffer, chunk: &str) {
     buffer.insert(&mut end_iter, chunk);
 }
 
+/// Lines to drop from the top of a `line_count` line buffer to get within
+/// `limit`, if any; a limit of 0 keeps everything
+fn scrollback_excess(line_count: i32, limit: usize) -> Option<i32> {
+    let limit = i32::try_from(limit).unwrap_or(i32::MAX);
+    (limit > 0 && line_count > limit).then(|| line_count - limit)
+}
+
+/// Line to cut the chat at so it is within `line_limit` lines and
+/// `message_limit` messages, given the line each message starts on. Whole
+/// messages go where possible, so none is left without its role label and
+/// tags; a single message longer than `line_limit` is cut mid-way instead.
+/// A limit of 0 doesn't apply.
+fn scrollback_cut(message_lines: &[i32], line_count: i32, line_limit: usize, message_limit: usize) -> Option<i32> {
+    let by_lines = scrollback_excess(line_count, line_limit).map(|excess| {
+        message_lines.iter().copied().find(|line| *line >= excess).unwrap_or(excess)
+    });
+    let by_messages = (message_limit > 0 && message_lines.len() > message_limit)
+        .then(|| message_lines[message_lines.len() - message_limit]);
+    by_lines.max(by_messages)
+}
+
+/// Drop the oldest messages once the chat is over `line_limit` lines or
+/// `message_limit` messages
+fn trim_scrollback(buffer: &gtk::TextBuffer, line_limit: usize, message_limit: usize) {
+    let line_count = buffer.line_count();
+    // Every message takes at least two lines, so there's no need to count
+    // them while the buffer is short
+    let over_lines = scrollback_excess(line_count, line_limit).is_some();
+    let may_be_over_messages = message_limit > 0 && (line_count as usize).saturating_sub(1) / 2 > message_limit;
+    if !over_lines && !may_be_over_messages {
+        return;
+    }
+
+    let Some(cut_line) = scrollback_cut(&message_lines(buffer), line_count, line_limit, message_limit) else {
+        return;
+    };
+    let Some(mut cut) = buffer.iter_at_line(cut_line) else {
+        return;
+    };
+    let mut start = buffer.start_iter();
+    buffer.delete(&mut start, &mut cut);
+}
+
+/// The line each message in the chat starts on, oldest first
+fn message_lines(buffer: &gtk::TextBuffer) -> Vec<i32> {
+    let mut lines = Vec::new();
+    let mut iter = buffer.start_iter();
+    loop {
+        // A timestamp is directly followed by the role label on the same line
+        if iter.starts_line() && starts_message(&iter) {
+            lines.push(iter.line());
+        }
+        if !iter.forward_to_tag_toggle(None::<&gtk::TextTag>) {
+            break;
+        }
+    }
+    lines
+}
+
+/// Whether a message's timestamp or role label starts at `iter`
+fn starts_message(iter: &gtk::TextIter) -> bool {
+    iter.toggled_tags(true).iter().any(is_message_label)
+}
+
+/// Whether `tag` marks a message's timestamp or role label
+pub(super) fn is_message_label(tag: &gtk::TextTag) -> bool {
+    tag.name().is_some_and(|name| name == "timestamp" || name.ends_with("-role"))
+}
+
 fn apply_css() {
     let provider = gtk::CssProvider::new();
     provider.load_from_string(
@@ -219,6 +903,16 @@ fn apply_css() {
             font-weight: bold;
         }
         
+        .transcribing {
+            color: #E2A84A;
+            font-weight: bold;
+        }
+        
+        .generation-progress {
+            opacity: 0.6;
+            font-size: smaller;
+        }
+        
         .circular {
             border-radius: 50%;
             min-width: 32px;
@@ -233,3 +927,90 @@ fn apply_css() {
         gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_window_size() {
+        let mut profile = VoiceProfile::new("Compact".to_string());
+        assert_eq!(window_size(&profile), DEFAULT_WINDOW_SIZE);
+
+        profile.window_width = Some(300);
+        assert_eq!(window_size(&profile), (300, DEFAULT_WINDOW_SIZE.1));
+
+        profile.window_width = Some(10);
+        profile.window_height = Some(5000);
+        assert_eq!(window_size(&profile), (MIN_WINDOW_SIZE.0, 5000));
+    }
+
+    #[test]
+    fn test_scrollback_excess() {
+        assert_eq!(scrollback_excess(100, 5000), None);
+        assert_eq!(scrollback_excess(5000, 5000), None);
+        assert_eq!(scrollback_excess(5300, 5000), Some(300));
+        // 0 keeps everything
+        assert_eq!(scrollback_excess(1_000_000, 0), None);
+    }
+
+    #[test]
+    fn test_scrollback_cut() {
+        // Messages starting on lines 1, 3, 5 and 9 of a 12 line chat
+        let messages = [1, 3, 5, 9];
+        assert_eq!(scrollback_cut(&messages, 12, 0, 0), None);
+        assert_eq!(scrollback_cut(&messages, 12, 20, 4), None);
+
+        // Cut at the message that starts after the excess lines
+        assert_eq!(scrollback_cut(&messages, 12, 10, 0), Some(3));
+        // Keep only the newest messages
+        assert_eq!(scrollback_cut(&messages, 12, 0, 2), Some(5));
+        // Whichever limit drops more wins
+        assert_eq!(scrollback_cut(&messages, 12, 10, 3), Some(3));
+        assert_eq!(scrollback_cut(&messages, 12, 10, 1), Some(9));
+        // The last message alone is over the line limit
+        assert_eq!(scrollback_cut(&messages, 12, 2, 0), Some(10));
+    }
+
+    #[test]
+    fn test_timestamp_label() {
+        use chrono::TimeZone;
+
+        let tz = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        let now = tz.with_ymd_and_hms(2026, 3, 5, 18, 0, 0).unwrap();
+        // 23:30 UTC the day before is 01:30 on the same local day
+        let earlier = chrono::Utc.with_ymd_and_hms(2026, 3, 4, 23, 30, 0).unwrap().with_timezone(&tz);
+
+        assert_eq!(timestamp_label(&earlier, &now), "01:30");
+        assert_eq!(timestamp_label(&(earlier - chrono::Duration::hours(2)), &now), "Mar 4 23:30");
+    }
+
+    #[test]
+    fn test_transcribing_text() {
+        assert_eq!(transcribing_text(0), "⏳ Transcribing…");
+        assert_eq!(transcribing_text(2), "⏳ Transcribing… (2 more queued)");
+    }
+
+    #[test]
+    fn test_generation_progress() {
+        let start = Instant::now();
+        let mut progress = GenerationProgress::default();
+
+        assert!(progress.push("Hel", 1, start));
+        // A word split across chunks counts once, and updates are throttled
+        assert!(!progress.push("lo wor", 1, start + Duration::from_millis(50)));
+        assert!(!progress.push("ld", 1, start + Duration::from_millis(100)));
+        // Merged chunks still count each token
+        assert!(progress.push(" again\n", 3, start + PROGRESS_UPDATE_INTERVAL));
+        assert_eq!(progress.status_text(), "3 words · 6 tokens");
+    }
+
+    #[test]
+    fn test_hud_status_precedence() {
+        assert_eq!(Activity::default().status_text(), "💤 Idle");
+        assert_eq!(Activity { listening: true, ..Default::default() }.status_text(), "🎤 Listening");
+        assert_eq!(Activity { listening: true, thinking: true, ..Default::default() }.status_text(), "💭 Thinking");
+        assert_eq!(Activity { thinking: true, speaking: true, ..Default::default() }.status_text(), "🔊 Speaking");
+        assert_eq!(Activity { listening: true, transcribing: true, ..Default::default() }.status_text(), "⏳ Transcribing");
+    }
+}
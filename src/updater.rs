@@ -0,0 +1,327 @@
+// Blipply Assistant - Updates
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+//! Checks the latest GitHub release against the running version and can
+//! replace this executable with the release's binary. The binary is the
+//! release asset named `blipply-assistant-<arch>-linux` for this machine's
+//! architecture, e.g. `blipply-assistant-x86_64-linux`, verified against the
+//! SHA-256 digest GitHub lists for it or a `<asset>.sha256` file next to it.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{debug, info};
+
+pub const DEFAULT_UPDATE_URL: &str = "https://api.github.com/repos/ALH477/blipply-assistant/releases/latest";
+
+/// The version of this build
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest any one request may take, including downloading the binary
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    html_url: Option<String>,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+    /// e.g. "sha256:9f86d0…"
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+/// A release newer than the running version
+#[derive(Debug, Clone)]
+pub struct UpdateInfo {
+    /// The release tag, e.g. "v1.4.0"
+    pub version: String,
+    /// Release notes page
+    pub url: Option<String>,
+    /// Download URL of the binary for this machine, if the release has one
+    binary_url: Option<String>,
+    /// Expected SHA-256 of the binary, lowercase hex
+    sha256: Option<String>,
+    /// Download URL of a `<asset>.sha256` file when GitHub lists no digest
+    checksum_url: Option<String>,
+}
+
+pub struct UpdateChecker {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl UpdateChecker {
+    /// Check `url`, a GitHub "latest release" API URL, or the project's
+    /// releases when unset
+    pub fn new(url: Option<String>) -> Self {
+        let client = reqwest::Client::builder()
+            // GitHub's API turns away requests without one
+            .user_agent(concat!("blipply-assistant/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            client,
+            url: url.unwrap_or_else(|| DEFAULT_UPDATE_URL.to_string()),
+        }
+    }
+
+    /// The latest release, if it is newer than this build
+    pub async fn check(&self) -> Result<Option<UpdateInfo>> {
+        let release: Release = self.client
+            .get(&self.url)
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", self.url))?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Unexpected release information")?;
+
+        debug!("Latest release is {}, running {}", release.tag_name, CURRENT_VERSION);
+        if !is_newer(&release.tag_name, CURRENT_VERSION) {
+            return Ok(None);
+        }
+
+        let binary = binary_asset(&release.assets, std::env::consts::ARCH).cloned();
+        let checksum_url = binary.as_ref().and_then(|binary| {
+            let checksum_name = format!("{}.sha256", binary.name);
+            release.assets.iter()
+                .find(|asset| asset.name == checksum_name)
+                .map(|asset| asset.browser_download_url.clone())
+        });
+
+        Ok(Some(UpdateInfo {
+            version: release.tag_name,
+            url: release.html_url,
+            sha256: binary.as_ref().and_then(|binary| binary.digest.as_deref()).and_then(parse_digest),
+            binary_url: binary.map(|binary| binary.browser_download_url),
+            checksum_url,
+        }))
+    }
+
+    /// Download the update, check it against its digest and move it over the
+    /// running executable. Returns the executable's path.
+    pub async fn apply(&self, update: &UpdateInfo) -> Result<PathBuf> {
+        let Some(binary_url) = &update.binary_url else {
+            bail!("Release {} has no binary for {}", update.version, std::env::consts::ARCH);
+        };
+
+        let expected = match (&update.sha256, &update.checksum_url) {
+            (Some(sha256), _) => sha256.clone(),
+            (None, Some(checksum_url)) => {
+                let checksum = self.client.get(checksum_url).send().await?.error_for_status()?.text().await?;
+                parse_digest(&checksum).context("Unreadable checksum file")?
+            }
+            (None, None) => bail!("Release {} has no SHA-256 digest to verify against", update.version),
+        };
+
+        info!("Downloading {}", binary_url);
+        let bytes = self.client.get(binary_url).send().await?.error_for_status()?.bytes().await?;
+        let actual: String = Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect();
+        if actual != expected {
+            bail!("Downloaded binary doesn't match its digest (expected {}, got {})", expected, actual);
+        }
+
+        let exe = std::env::current_exe().context("Failed to find the running executable")?;
+        // Next to the executable, so the rename stays on one filesystem
+        let file_name = exe.file_name().context("Executable has no file name")?.to_string_lossy();
+        let partial = exe.with_file_name(format!(".{}.update", file_name));
+        let replaced = write_synced(&partial, &bytes).and_then(|()| set_executable(&partial)).and_then(|()| {
+            std::fs::rename(&partial, &exe).with_context(|| format!("Failed to replace {:?}", exe))
+        });
+        if replaced.is_err() {
+            std::fs::remove_file(&partial).ok();
+        }
+        replaced?;
+
+        info!("Updated {:?} to {}", exe, update.version);
+        Ok(exe)
+    }
+}
+
+/// Write `bytes` to `path` and flush them to disk, so a crash after the
+/// rename can't leave a truncated executable
+fn write_synced(path: &std::path::Path, bytes: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut file = std::fs::File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    file.write_all(bytes).with_context(|| format!("Failed to write {:?}", path))?;
+    file.sync_all().with_context(|| format!("Failed to sync {:?}", path))
+}
+
+fn set_executable(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to make {:?} executable", path))
+}
+
+/// Name of the release binary for `arch`, e.g. "blipply-assistant-x86_64-linux"
+fn binary_asset_name(arch: &str) -> String {
+    format!("blipply-assistant-{}-linux", arch)
+}
+
+/// The release asset to run on a machine of architecture `arch`. Only the
+/// exact name counts, so packages and archives are never installed.
+fn binary_asset<'a>(assets: &'a [ReleaseAsset], arch: &str) -> Option<&'a ReleaseAsset> {
+    let name = binary_asset_name(arch);
+    assets.iter().find(|asset| asset.name == name)
+}
+
+/// Lowercase hex SHA-256 from "sha256:<hex>" or a `sha256sum` line
+fn parse_digest(digest: &str) -> Option<String> {
+    let hex = digest.trim().strip_prefix("sha256:").unwrap_or(digest.trim());
+    let hex = hex.split_whitespace().next()?;
+    (hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit())).then(|| hex.to_ascii_lowercase())
+}
+
+/// A semantic version such as "1.4.0-rc.1"; build metadata is ignored
+#[derive(Debug, PartialEq, Eq)]
+struct Version {
+    core: [u64; 3],
+    pre: Vec<String>,
+}
+
+impl Version {
+    /// Parse a version or release tag, with or without a leading "v".
+    /// Missing minor and patch numbers count as 0.
+    fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+        let version = version.split('+').next()?;
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, pre.split('.').map(str::to_string).collect()),
+            None => (version, Vec::new()),
+        };
+
+        let mut numbers = [0; 3];
+        let mut parts = core.split('.');
+        for number in numbers.iter_mut() {
+            if let Some(part) = parts.next() {
+                *number = part.parse().ok()?;
+            }
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self { core: numbers, pre })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.core.cmp(&other.core).then_with(|| {
+            // A pre-release comes before its release
+            match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => {
+                    for (a, b) in self.pre.iter().zip(&other.pre) {
+                        let order = match (a.parse::<u64>(), b.parse::<u64>()) {
+                            (Ok(a), Ok(b)) => a.cmp(&b),
+                            // Numeric identifiers sort before alphanumeric ones
+                            (Ok(_), Err(_)) => Ordering::Less,
+                            (Err(_), Ok(_)) => Ordering::Greater,
+                            (Err(_), Err(_)) => a.cmp(b),
+                        };
+                        if order != Ordering::Equal {
+                            return order;
+                        }
+                    }
+                    self.pre.len().cmp(&other.pre.len())
+                }
+            }
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Whether `latest` is a later version than `current`. Versions that don't
+/// parse are never newer.
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (Version::parse(latest), Version::parse(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_comparison() {
+        assert!(is_newer("v1.4.0", "1.3.9"));
+        assert!(is_newer("1.10.0", "1.9.0"));
+        assert!(is_newer("v2", "1.9.9"));
+        assert!(!is_newer("v1.3.0", "1.3.0"));
+        assert!(!is_newer("1.2.9", "1.3.0"));
+
+        // Pre-releases
+        assert!(is_newer("1.4.0", "1.4.0-rc.1"));
+        assert!(!is_newer("1.4.0-rc.1", "1.4.0"));
+        assert!(is_newer("1.4.0-rc.2", "1.4.0-rc.1"));
+        assert!(is_newer("1.4.0-rc.10", "1.4.0-rc.9"));
+        assert!(is_newer("1.4.0-beta", "1.4.0-alpha.5"));
+        assert!(is_newer("1.4.0-alpha.1", "1.4.0-alpha"));
+        assert!(is_newer("1.4.0-alpha.beta", "1.4.0-alpha.1"));
+
+        // Build metadata doesn't count
+        assert!(!is_newer("1.3.0+build.7", "1.3.0"));
+
+        assert!(!is_newer("nightly", "1.3.0"));
+        assert!(!is_newer("1.3.0.1", "1.3.0"));
+    }
+
+    #[test]
+    fn test_binary_asset_exact_name() {
+        let asset = |name: &str| ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.com/{}", name),
+            digest: None,
+        };
+        let assets = [
+            asset("blipply-assistant-x86_64.deb"),
+            asset("blipply-assistant-x86_64.AppImage"),
+            asset("blipply-assistant-x86_64-darwin"),
+            asset("blipply-assistant-x86_64-linux.sha256"),
+            asset("blipply-assistant-x86_64-linux"),
+            asset("blipply-assistant-aarch64-linux"),
+        ];
+
+        assert_eq!(binary_asset(&assets, "x86_64").unwrap().name, "blipply-assistant-x86_64-linux");
+        assert_eq!(binary_asset(&assets, "aarch64").unwrap().name, "blipply-assistant-aarch64-linux");
+        assert!(binary_asset(&assets[..4], "x86_64").is_none());
+    }
+
+    #[test]
+    fn test_parse_digest() {
+        let hex = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        assert_eq!(parse_digest(&format!("sha256:{}", hex)).as_deref(), Some(hex));
+        assert_eq!(parse_digest(&format!("{}  blipply-assistant\n", hex.to_uppercase())).as_deref(), Some(hex));
+        assert_eq!(parse_digest("sha256:abc"), None);
+    }
+}
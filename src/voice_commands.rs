@@ -0,0 +1,181 @@
+// Blipply Assistant - Spoken Commands
+// Copyright (c) 2026 DeMoD LLC
+// Licensed under the MIT License
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Optional wake word before a command, e.g. "Blipply, clear conversation"
+const WAKE_PREFIX: &str = r"^(?:blipply[,.!]?\s+)?";
+
+/// Trailing punctuation Whisper tends to add
+const END_SUFFIX: &str = r"[.!?]*$";
+
+/// Every pattern seen so far, compiled once instead of for each transcript.
+/// `None` marks a pattern that doesn't compile, so it's only reported once.
+static COMPILED_PATTERNS: Lazy<Mutex<HashMap<String, Option<regex::Regex>>>> = Lazy::new(Default::default);
+
+/// What a spoken command does instead of being sent to the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum VoiceAction {
+    /// Switch to `profile`, or to the one named by the pattern's `profile` group
+    SwitchProfile {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<String>,
+    },
+    ClearConversation,
+    StopSpeaking,
+    Hide,
+    Undo,
+}
+
+/// Transcripts matching `pattern` (case-insensitive regex) run `action`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoiceCommand {
+    pub pattern: String,
+    #[serde(flatten)]
+    pub action: VoiceAction,
+}
+
+impl VoiceCommand {
+    fn new(pattern: &str, action: VoiceAction) -> Self {
+        Self {
+            pattern: format!("{}{}{}", WAKE_PREFIX, pattern, END_SUFFIX),
+            action,
+        }
+    }
+
+    /// The action to run if `text` matches, with the profile filled in from
+    /// the `profile` capture group when the command doesn't name one
+    fn matches(&self, text: &str) -> Option<VoiceAction> {
+        let re = self.compiled()?;
+        let captures = re.captures(text)?;
+
+        match &self.action {
+            VoiceAction::SwitchProfile { profile: None } => {
+                let profile = captures.name("profile")?.as_str().trim().to_string();
+                Some(VoiceAction::SwitchProfile { profile: Some(profile) })
+            }
+            action => Some(action.clone()),
+        }
+    }
+
+    fn compiled(&self) -> Option<regex::Regex> {
+        let mut patterns = COMPILED_PATTERNS.lock();
+        if let Some(re) = patterns.get(&self.pattern) {
+            return re.clone();
+        }
+
+        let re = match regex::RegexBuilder::new(&self.pattern).case_insensitive(true).build() {
+            Ok(re) => Some(re),
+            Err(e) => {
+                warn!("Invalid voice command pattern {:?}: {}", self.pattern, e);
+                None
+            }
+        };
+        patterns.insert(self.pattern.clone(), re.clone());
+        re
+    }
+}
+
+/// Built-in commands: "switch to <profile>", "clear conversation", "stop talking"
+pub fn default_commands() -> Vec<VoiceCommand> {
+    vec![
+        VoiceCommand::new(r"switch to (?P<profile>[\w -]+?)", VoiceAction::SwitchProfile { profile: None }),
+        VoiceCommand::new(r"clear (?:the )?conversation", VoiceAction::ClearConversation),
+        VoiceCommand::new(r"stop talking", VoiceAction::StopSpeaking),
+    ]
+}
+
+/// First command matching `text`. Profile commands are checked before the
+/// global ones so a profile can redefine a phrase.
+pub fn match_command(
+    profile_commands: &[VoiceCommand],
+    global_commands: &[VoiceCommand],
+    text: &str,
+) -> Option<VoiceAction> {
+    let text = text.trim();
+    profile_commands.iter()
+        .chain(global_commands)
+        .find_map(|command| command.matches(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_commands() {
+        let commands = default_commands();
+
+        assert_eq!(
+            match_command(&[], &commands, " Blipply, switch to Sassy."),
+            Some(VoiceAction::SwitchProfile { profile: Some("Sassy".to_string()) })
+        );
+        assert_eq!(match_command(&[], &commands, "CLEAR THE CONVERSATION"), Some(VoiceAction::ClearConversation));
+        assert_eq!(match_command(&[], &commands, "Stop talking!"), Some(VoiceAction::StopSpeaking));
+        // Only whole utterances count, not phrases inside a question
+        assert_eq!(match_command(&[], &commands, "How do I stop talking so much?"), None);
+    }
+
+    #[test]
+    fn test_profile_commands_take_precedence() {
+        let profile = vec![VoiceCommand {
+            pattern: "^stop talking$".to_string(),
+            action: VoiceAction::Hide,
+        }];
+
+        assert_eq!(match_command(&profile, &default_commands(), "stop talking"), Some(VoiceAction::Hide));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_skipped() {
+        let commands = vec![
+            VoiceCommand { pattern: "(".to_string(), action: VoiceAction::Hide },
+            VoiceCommand { pattern: "^undo$".to_string(), action: VoiceAction::Undo },
+        ];
+
+        assert_eq!(match_command(&[], &commands, "undo"), Some(VoiceAction::Undo));
+        assert_eq!(COMPILED_PATTERNS.lock().get("("), Some(&None));
+    }
+
+    #[test]
+    fn test_patterns_compiled_once() {
+        let command = VoiceCommand { pattern: "^compiled once$".to_string(), action: VoiceAction::Hide };
+        assert!(command.matches("compiled once").is_some());
+
+        let first = command.compiled().unwrap();
+        let second = command.compiled().unwrap();
+        assert_eq!(first.as_str(), second.as_str());
+        assert!(COMPILED_PATTERNS.lock().contains_key("^compiled once$"));
+    }
+
+    #[test]
+    fn test_toml_format() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            voice_commands: Vec<VoiceCommand>,
+        }
+
+        let wrapper: Wrapper = toml::from_str(r#"
+            [[voice_commands]]
+            pattern = "^go away$"
+            action = "hide"
+
+            [[voice_commands]]
+            pattern = "^work mode$"
+            action = "switch_profile"
+            profile = "work"
+        "#).unwrap();
+
+        assert_eq!(wrapper.voice_commands[0].action, VoiceAction::Hide);
+        assert_eq!(
+            wrapper.voice_commands[1].action,
+            VoiceAction::SwitchProfile { profile: Some("work".to_string()) }
+        );
+    }
+}